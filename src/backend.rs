@@ -0,0 +1,458 @@
+use crate::{
+    matrix::{
+        checked_multiply, dims, multiply_matrices, multiply_matrices_threaded, Matrix, MatrixError,
+    },
+    typed::{multiply_i32, narrow},
+};
+
+/// An implementation of matrix multiplication. Every backend here runs on
+/// the CPU; a wgpu compute backend behind a `gpu` feature is left for a
+/// follow-up, as the crate doesn't depend on wgpu yet
+pub trait MultiplyBackend {
+    fn name(&self) -> &'static str;
+
+    /// Number of threads the backend computes on
+    fn threads(&self) -> usize {
+        1
+    }
+
+    /// Multiplies the already shape-checked operands
+    fn multiply_unchecked(&self, a: &Matrix, b: &Matrix) -> Result<Matrix, MatrixError>;
+
+    fn multiply(&self, a: &Matrix, b: &Matrix) -> Result<Matrix, MatrixError> {
+        check_multiply_dims(a, b)?;
+        self.multiply_unchecked(a, b)
+    }
+}
+
+/// Names of the backends `find_backend` knows, default first
+pub const ENGINES: [&str; 5] = ["narrow", "naive", "threaded", "blocked", "strassen"];
+
+/// The backend used when none is asked for: the i32 kernel, which is the
+/// fastest on a single thread and falls back to i64 when needed
+pub const DEFAULT_ENGINE: &str = "narrow";
+
+/// Whether the named engine spreads its work over `--threads`
+pub fn is_multithreaded(name: &str) -> bool {
+    name == "threaded"
+}
+
+/// Number of threads to use when none is asked for
+pub fn default_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Looks up a backend by name, giving multithreaded ones `threads` threads
+pub fn find_backend(name: &str, threads: usize) -> Option<Box<dyn MultiplyBackend>> {
+    Some(match name {
+        "narrow" => Box::new(Narrow),
+        "naive" => Box::new(Naive),
+        "threaded" => Box::new(Threaded {
+            threads: threads.max(1),
+        }),
+        "blocked" => Box::new(Blocked),
+        "strassen" => Box::new(Strassen),
+        _ => return None,
+    })
+}
+
+/// Checks that `a` and `b` are rectangular and `a`'s columns match `b`'s rows
+pub fn check_multiply_dims<T>(a: &[Vec<T>], b: &[Vec<T>]) -> Result<(), MatrixError> {
+    let (left, right) = (dims(a), dims(b));
+    let ragged = |m: &[Vec<T>], cols: usize| m.iter().any(|row| row.len() != cols);
+    if left.0 == 0 || right.0 == 0 || left.1 == 0 || right.1 == 0 {
+        return Err(MatrixError::Empty);
+    }
+    if left.1 != right.0 || ragged(a, left.1) || ragged(b, right.1) {
+        return Err(MatrixError::DimensionMismatch { left, right });
+    }
+    Ok(())
+}
+
+/// The textbook triple loop
+pub struct Naive;
+
+impl MultiplyBackend for Naive {
+    fn name(&self) -> &'static str {
+        "naive"
+    }
+
+    fn multiply_unchecked(&self, a: &Matrix, b: &Matrix) -> Result<Matrix, MatrixError> {
+        multiply_matrices(a, b)
+    }
+}
+
+/// The i32 kernel when both operands fit, otherwise the naive loop
+pub struct Narrow;
+
+impl MultiplyBackend for Narrow {
+    fn name(&self) -> &'static str {
+        "narrow"
+    }
+
+    fn multiply_unchecked(&self, a: &Matrix, b: &Matrix) -> Result<Matrix, MatrixError> {
+        Ok(match (narrow(a), narrow(b)) {
+            (Some(a), Some(b)) => multiply_i32(&a, &b)?,
+            _ => checked_multiply(a, b).ok_or(MatrixError::Overflow { bits: 64 })?,
+        })
+    }
+}
+
+/// Bands of rows computed on separate threads
+pub struct Threaded {
+    pub threads: usize,
+}
+
+impl MultiplyBackend for Threaded {
+    fn name(&self) -> &'static str {
+        "threaded"
+    }
+
+    fn threads(&self) -> usize {
+        self.threads
+    }
+
+    fn multiply_unchecked(&self, a: &Matrix, b: &Matrix) -> Result<Matrix, MatrixError> {
+        multiply_matrices_threaded(a, b, self.threads)
+    }
+}
+
+/// Edge length of the tiles `Blocked` works on
+const BLOCK: usize = 64;
+
+/// The triple loop over cache-sized tiles, in i-k-j order so the innermost
+/// loop walks rows of both `b` and the result
+pub struct Blocked;
+
+impl MultiplyBackend for Blocked {
+    fn name(&self) -> &'static str {
+        "blocked"
+    }
+
+    fn multiply_unchecked(&self, a: &Matrix, b: &Matrix) -> Result<Matrix, MatrixError> {
+        let (rows, inner) = dims(a);
+        let cols = dims(b).1;
+        let mut result = vec![vec![0i64; cols]; rows];
+
+        for ii in (0..rows).step_by(BLOCK) {
+            for kk in (0..inner).step_by(BLOCK) {
+                for jj in (0..cols).step_by(BLOCK) {
+                    for i in ii..(ii + BLOCK).min(rows) {
+                        for k in kk..(kk + BLOCK).min(inner) {
+                            let x = a[i][k];
+                            for j in jj..(jj + BLOCK).min(cols) {
+                                result[i][j] = x
+                                    .checked_mul(b[k][j])
+                                    .and_then(|y| result[i][j].checked_add(y))
+                                    .ok_or(MatrixError::Overflow { bits: 64 })?;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// Below this size `Strassen` switches to the triple loop, which is faster
+/// than recursing further
+const STRASSEN_CUTOFF: usize = 64;
+
+/// Strassen's algorithm: seven half-size products instead of eight. Operands
+/// are zero-padded to a square, and each level of the recursion pads an odd
+/// size by one more row and column. Its intermediate sums can overflow i64
+/// even when the product fits, so a block whose sums overflow is multiplied
+/// with the triple loop instead, giving the same results as `Naive`
+pub struct Strassen;
+
+/// Combines the elements at the same positions with `f`, failing if it
+/// overflows
+fn combine(a: &Matrix, b: &Matrix, f: fn(i64, i64) -> Option<i64>) -> Result<Matrix, MatrixError> {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| {
+            x.iter()
+                .zip(y)
+                .map(|(&p, &q)| f(p, q).ok_or(MatrixError::Overflow { bits: 64 }))
+                .collect()
+        })
+        .collect()
+}
+
+fn add(a: &Matrix, b: &Matrix) -> Result<Matrix, MatrixError> {
+    combine(a, b, i64::checked_add)
+}
+
+fn sub(a: &Matrix, b: &Matrix) -> Result<Matrix, MatrixError> {
+    combine(a, b, i64::checked_sub)
+}
+
+/// `m` with zeros added to make it `n`×`n`
+fn pad(m: &Matrix, n: usize) -> Matrix {
+    let mut padded: Matrix = m
+        .iter()
+        .map(|row| {
+            let mut row = row.clone();
+            row.resize(n, 0);
+            row
+        })
+        .collect();
+    padded.resize(n, vec![0; n]);
+    padded
+}
+
+/// Quadrant `(qi, qj)` of a square matrix of even size
+fn quadrant(m: &Matrix, qi: usize, qj: usize) -> Matrix {
+    let h = m.len() / 2;
+    m[qi * h..(qi + 1) * h]
+        .iter()
+        .map(|row| row[qj * h..(qj + 1) * h].to_vec())
+        .collect()
+}
+
+fn strassen_square(a: &Matrix, b: &Matrix) -> Result<Matrix, MatrixError> {
+    let n = a.len();
+    if n <= STRASSEN_CUTOFF {
+        return multiply_matrices(a, b);
+    }
+    if n % 2 == 1 {
+        let mut c = strassen_square(&pad(a, n + 1), &pad(b, n + 1))?;
+        c.truncate(n);
+        c.iter_mut().for_each(|row| row.truncate(n));
+        return Ok(c);
+    }
+    match strassen_halves(a, b) {
+        // the sums of quadrants overflowed, which the product itself may not
+        Err(MatrixError::Overflow { .. }) => multiply_matrices(a, b),
+        product => product,
+    }
+}
+
+/// One level of Strassen's algorithm on square matrices of even size
+fn strassen_halves(a: &Matrix, b: &Matrix) -> Result<Matrix, MatrixError> {
+    let n = a.len();
+    let (a11, a12, a21, a22) = (
+        quadrant(a, 0, 0),
+        quadrant(a, 0, 1),
+        quadrant(a, 1, 0),
+        quadrant(a, 1, 1),
+    );
+    let (b11, b12, b21, b22) = (
+        quadrant(b, 0, 0),
+        quadrant(b, 0, 1),
+        quadrant(b, 1, 0),
+        quadrant(b, 1, 1),
+    );
+
+    let m1 = strassen_square(&add(&a11, &a22)?, &add(&b11, &b22)?)?;
+    let m2 = strassen_square(&add(&a21, &a22)?, &b11)?;
+    let m3 = strassen_square(&a11, &sub(&b12, &b22)?)?;
+    let m4 = strassen_square(&a22, &sub(&b21, &b11)?)?;
+    let m5 = strassen_square(&add(&a11, &a12)?, &b22)?;
+    let m6 = strassen_square(&sub(&a21, &a11)?, &add(&b11, &b12)?)?;
+    let m7 = strassen_square(&sub(&a12, &a22)?, &add(&b21, &b22)?)?;
+
+    let c11 = add(&sub(&add(&m1, &m4)?, &m5)?, &m7)?;
+    let c12 = add(&m3, &m5)?;
+    let c21 = add(&m2, &m4)?;
+    let c22 = add(&add(&sub(&m1, &m2)?, &m3)?, &m6)?;
+
+    let mut c = Vec::with_capacity(n);
+    for (left, right) in c11.into_iter().zip(c12) {
+        c.push([left, right].concat());
+    }
+    for (left, right) in c21.into_iter().zip(c22) {
+        c.push([left, right].concat());
+    }
+    Ok(c)
+}
+
+impl MultiplyBackend for Strassen {
+    fn name(&self) -> &'static str {
+        "strassen"
+    }
+
+    fn multiply_unchecked(&self, a: &Matrix, b: &Matrix) -> Result<Matrix, MatrixError> {
+        let (rows, inner) = dims(a);
+        let cols = dims(b).1;
+        let n = rows.max(inner).max(cols);
+
+        let mut c = strassen_square(&pad(a, n), &pad(b, n))?;
+        c.truncate(rows);
+        c.iter_mut().for_each(|row| row.truncate(cols));
+        Ok(c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_engine_computes_the_same_product() {
+        let a: Matrix = (0..70)
+            .map(|i| (0..70).map(|j| (i * 7 + j) % 11 - 5).collect())
+            .collect();
+        let b: Matrix = (0..70)
+            .map(|i| (0..3).map(|j| (i + j * 3) % 7 - 3).collect())
+            .collect();
+        let expected = multiply_matrices(&a, &b).unwrap();
+        for name in ENGINES {
+            let backend = find_backend(name, 4).unwrap();
+            assert_eq!(backend.multiply(&a, &b).unwrap(), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn every_engine_reports_overflow() {
+        let a = vec![vec![i32::MAX as i64; 3]];
+        let b = vec![vec![i32::MAX as i64; 2]; 3];
+        for name in ENGINES {
+            let backend = find_backend(name, 2).unwrap();
+            assert_eq!(
+                backend.multiply(&a, &b),
+                Err(MatrixError::Overflow { bits: 64 }),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn multiply_checks_shapes() {
+        let a = vec![vec![1, 2]];
+        assert_eq!(
+            Naive.multiply(&a, &a),
+            Err(MatrixError::DimensionMismatch {
+                left: (1, 2),
+                right: (1, 2)
+            })
+        );
+        assert_eq!(Naive.multiply(&vec![], &a), Err(MatrixError::Empty));
+    }
+
+    #[test]
+    fn find_backend_rejects_unknown_names() {
+        assert!(find_backend("rayon", 1).is_none());
+        assert_eq!(
+            find_backend(DEFAULT_ENGINE, 1).unwrap().name(),
+            DEFAULT_ENGINE
+        );
+        assert_eq!(find_backend("threaded", 0).unwrap().threads(), 1);
+    }
+
+    #[test]
+    fn every_engine_rejects_empty_operands() {
+        let no_cols: Matrix = vec![vec![], vec![]];
+        for (a, b) in [(vec![], vec![]), (no_cols, vec![])] {
+            assert_eq!(multiply_matrices(&a, &b), Err(MatrixError::Empty));
+            for name in ENGINES {
+                let backend = find_backend(name, 2).unwrap();
+                assert_eq!(
+                    backend.multiply(&a, &b),
+                    Err(MatrixError::Empty),
+                    "{}",
+                    name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn narrow_widens_operands_that_dont_fit_i32() {
+        // one element past i32 sends the product down the i64 loop, which
+        // still gets it right
+        let big = i32::MAX as i64 + 1;
+        let a = vec![vec![big, 1], vec![-3, 2]];
+        let b = vec![vec![2, 0], vec![5, -1]];
+        assert!(narrow(&a).is_none());
+        let expected = vec![vec![2 * big + 5, -1], vec![4, -2]];
+        assert_eq!(Narrow.multiply(&a, &b), Ok(expected.clone()));
+        assert_eq!(Narrow.multiply(&a, &b), Naive.multiply(&a, &b));
+        assert_eq!(
+            Narrow.multiply(&b, &vec![vec![big], vec![0]]),
+            Ok(vec![vec![2 * big], vec![5 * big]])
+        );
+    }
+
+    /// A backend outside this module, which only gets operands whose shapes
+    /// multiply
+    struct Recording(std::cell::Cell<usize>);
+
+    impl MultiplyBackend for Recording {
+        fn name(&self) -> &'static str {
+            "recording"
+        }
+
+        fn multiply_unchecked(&self, a: &Matrix, b: &Matrix) -> Result<Matrix, MatrixError> {
+            self.0.set(self.0.get() + 1);
+            Ok(vec![vec![0; dims(b).1]; a.len()])
+        }
+    }
+
+    #[test]
+    fn other_backends_get_the_shape_checks_and_defaults() {
+        let backend = Recording(std::cell::Cell::new(0));
+        assert_eq!(backend.threads(), 1);
+        let (row, col) = (vec![vec![1, 2]], vec![vec![3], vec![4]]);
+        assert!(backend.multiply(&row, &row).is_err());
+        assert!(backend.multiply(&vec![], &col).is_err());
+        assert_eq!(backend.0.get(), 0);
+        assert_eq!(backend.multiply(&col, &row), Ok(vec![vec![0, 0]; 2]));
+        assert_eq!(backend.0.get(), 1);
+    }
+
+    #[test]
+    fn every_engine_is_found_by_its_own_name() {
+        for name in ENGINES {
+            let backend = find_backend(name, 3).unwrap();
+            assert_eq!(backend.name(), name);
+            let threads = if is_multithreaded(name) { 3 } else { 1 };
+            assert_eq!(backend.threads(), threads, "{}", name);
+        }
+        assert!(find_backend("gpu", 1).is_none());
+    }
+
+    #[test]
+    fn strassen_handles_sizes_that_arent_powers_of_two() {
+        let shape = |rows: usize, cols: usize, seed: i64| -> Matrix {
+            (0..rows as i64)
+                .map(|i| {
+                    (0..cols as i64)
+                        .map(|j| (i * 13 + j * 7 + seed) % 19 - 9)
+                        .collect()
+                })
+                .collect()
+        };
+        for (rows, inner, cols) in [(65, 65, 65), (67, 67, 67), (130, 129, 131), (100, 65, 3)] {
+            let (a, b) = (shape(rows, inner, 1), shape(inner, cols, 2));
+            assert_eq!(
+                Strassen.multiply(&a, &b),
+                multiply_matrices(&a, &b),
+                "{}x{} by {}x{}",
+                rows,
+                inner,
+                inner,
+                cols
+            );
+        }
+    }
+
+    #[test]
+    fn strassen_falls_back_when_only_its_sums_overflow() {
+        // the product of A and the identity is A, but any sum of two of its
+        // quadrants, as A11 + A22, overflows
+        let n = STRASSEN_CUTOFF + 2;
+        let a = vec![vec![1i64 << 62; n]; n];
+        let identity: Matrix = (0..n)
+            .map(|i| (0..n).map(|j| (i == j) as i64).collect())
+            .collect();
+        assert!(add(&quadrant(&a, 0, 0), &quadrant(&a, 1, 1)).is_err());
+        assert_eq!(Strassen.multiply(&a, &identity), Ok(a.clone()));
+        assert_eq!(
+            Strassen.multiply(&a, &identity),
+            Naive.multiply(&a, &identity)
+        );
+    }
+}
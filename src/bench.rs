@@ -0,0 +1,496 @@
+use crate::{
+    backend::{default_threads, find_backend, MultiplyBackend, ENGINES},
+    binary::canonical_checksum,
+    matrix::{Answer, Matrix, MatrixError},
+};
+use rand::{rngs::StdRng, RngExt, SeedableRng};
+use std::{
+    error::Error,
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// What `run_bench` measures
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchOptions {
+    /// Side lengths of the square operands
+    pub sizes: Vec<usize>,
+    /// Engines to time, the first being the baseline speedups are relative to
+    pub engines: Vec<String>,
+    pub seed: u64,
+    /// Untimed runs before the timed ones, per size and engine
+    pub warmup: usize,
+    /// Timed runs, whose median is reported
+    pub iterations: usize,
+    /// Threads given to multithreaded engines
+    pub threads: usize,
+}
+
+impl Default for BenchOptions {
+    fn default() -> BenchOptions {
+        BenchOptions {
+            sizes: vec![128, 256, 512],
+            engines: ENGINES.iter().map(|name| name.to_string()).collect(),
+            seed: 0,
+            warmup: 1,
+            iterations: 5,
+            threads: default_threads(),
+        }
+    }
+}
+
+/// The timing of one engine at one size
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+    pub size: usize,
+    pub engine: String,
+    pub median: Duration,
+    /// Median of the baseline engine divided by this engine's
+    pub speedup: f64,
+}
+
+/// The first cell where an engine's product differs from the baseline's
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    pub engine: String,
+    pub baseline: String,
+    pub size: usize,
+    /// Seed of the operands, which `random_operands` reproduces
+    pub seed: u64,
+    pub row: usize,
+    pub col: usize,
+    pub expected: i64,
+    pub actual: i64,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} differs from {} at row {}, column {} (expected {}, got {}) for size {}, seed {}",
+            self.engine,
+            self.baseline,
+            self.row,
+            self.col,
+            self.expected,
+            self.actual,
+            self.size,
+            self.seed
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BenchError {
+    UnknownEngine(String),
+    /// An engine's product differs from the baseline engine's
+    Disagree(Box<Mismatch>),
+    Matrix(MatrixError),
+}
+
+impl fmt::Display for BenchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BenchError::UnknownEngine(name) => write!(f, "unknown engine {:?}", name),
+            BenchError::Disagree(mismatch) => write!(f, "{}", mismatch),
+            BenchError::Matrix(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for BenchError {}
+
+impl From<MatrixError> for BenchError {
+    fn from(err: MatrixError) -> BenchError {
+        BenchError::Matrix(err)
+    }
+}
+
+/// Two random `size`×`size` operands with elements in -100..=100, small
+/// enough that no engine overflows
+pub fn random_operands(size: usize, seed: u64) -> (Matrix, Matrix) {
+    random_shaped_operands(Shape([size; 3]), seed)
+}
+
+/// Random operands like `random_operands`, of the sizes `shape` gives
+pub fn random_shaped_operands(shape: Shape, seed: u64) -> (Matrix, Matrix) {
+    let Shape([m, n, p]) = shape;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut random = |rows: usize, cols: usize| -> Matrix {
+        (0..rows)
+            .map(|_| (0..cols).map(|_| rng.random_range(-100..=100)).collect())
+            .collect()
+    };
+    let a = random(m, n);
+    (a, random(n, p))
+}
+
+/// The sizes of an `m`×`n` times `n`×`p` product, as `[m, n, p]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shape(pub [usize; 3]);
+
+impl Shape {
+    /// Parses `n` (two n×n operands), `mxn` (m×n times n×n) or `mxnxp`
+    /// (m×n times n×p). Every size must be positive
+    pub fn parse(text: &str) -> Option<Shape> {
+        let sizes: Vec<usize> = text
+            .split('x')
+            .map(|size| size.trim().parse().ok().filter(|&n| n > 0))
+            .collect::<Option<_>>()?;
+        match sizes[..] {
+            [n] => Some(Shape([n; 3])),
+            [m, n] => Some(Shape([m, n, n])),
+            [m, n, p] => Some(Shape([m, n, p])),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Shape([m, n, p]) = *self;
+        write!(f, "{}x{} * {}x{}", m, n, n, p)
+    }
+}
+
+/// The outcome of `time_product`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timing {
+    pub engine: &'static str,
+    pub threads: usize,
+    pub elapsed: Duration,
+    /// `binary::canonical_checksum` of the product
+    pub checksum: String,
+}
+
+/// Multiplies random operands of `shape` once with `engine`, timing only
+/// the multiplication
+pub fn time_product(
+    shape: Shape,
+    engine: &str,
+    threads: usize,
+    seed: u64,
+) -> Result<Timing, BenchError> {
+    let backend = find_backend(engine, threads)
+        .ok_or_else(|| BenchError::UnknownEngine(String::from(engine)))?;
+    let (a, b) = random_shaped_operands(shape, seed);
+    let start = Instant::now();
+    let product = backend.multiply(&a, &b)?;
+    let elapsed = start.elapsed();
+    Ok(Timing {
+        engine: backend.name(),
+        threads: backend.threads(),
+        elapsed,
+        checksum: canonical_checksum(&Answer::Int(product))?,
+    })
+}
+
+/// Compares `product` with the baseline's equally shaped `expected`,
+/// describing the first differing cell
+fn compare(
+    expected: &Matrix,
+    product: &Matrix,
+    engine: &str,
+    baseline: &str,
+    seed: u64,
+) -> Result<(), BenchError> {
+    let differing = expected
+        .iter()
+        .zip(product)
+        .enumerate()
+        .find_map(|(i, (x, y))| x.iter().zip(y).position(|(p, q)| p != q).map(|j| (i, j)));
+    let Some((row, col)) = differing else {
+        return Ok(());
+    };
+    Err(BenchError::Disagree(Box::new(Mismatch {
+        engine: engine.to_string(),
+        baseline: baseline.to_string(),
+        size: expected.len(),
+        seed,
+        row,
+        col,
+        expected: expected[row][col],
+        actual: product[row][col],
+    })))
+}
+
+fn median(mut times: Vec<Duration>) -> Duration {
+    times.sort();
+    times.get(times.len() / 2).copied().unwrap_or_default()
+}
+
+fn find_backends(
+    engines: &[String],
+    threads: usize,
+) -> Result<Vec<Box<dyn MultiplyBackend>>, BenchError> {
+    engines
+        .iter()
+        .map(|name| {
+            find_backend(name, threads).ok_or_else(|| BenchError::UnknownEngine(name.clone()))
+        })
+        .collect()
+}
+
+/// Times every engine at every size on the same random operands, checking
+/// that all engines compute the same product
+pub fn run_bench(options: &BenchOptions) -> Result<Vec<BenchResult>, BenchError> {
+    let backends = find_backends(&options.engines, options.threads)?;
+
+    let mut results = vec![];
+    for &size in &options.sizes {
+        let (a, b) = random_operands(size, options.seed);
+        let mut baseline: Option<(Matrix, Duration)> = None;
+        for (backend, name) in backends.iter().zip(&options.engines) {
+            for _ in 0..options.warmup {
+                backend.multiply(&a, &b)?;
+            }
+            let mut product = None;
+            let mut times = vec![];
+            for _ in 0..options.iterations.max(1) {
+                let start = Instant::now();
+                let result = backend.multiply(&a, &b)?;
+                times.push(start.elapsed());
+                product = Some(result);
+            }
+            let product = product.unwrap();
+            let median = median(times);
+
+            let base_median = match &baseline {
+                Some((expected, base_median)) => {
+                    compare(expected, &product, name, &options.engines[0], options.seed)?;
+                    *base_median
+                }
+                None => {
+                    baseline = Some((product, median));
+                    median
+                }
+            };
+            results.push(BenchResult {
+                size,
+                engine: name.clone(),
+                median,
+                speedup: if median.is_zero() {
+                    1.0
+                } else {
+                    base_median.as_secs_f64() / median.as_secs_f64()
+                },
+            });
+        }
+    }
+    Ok(results)
+}
+
+fn millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+/// Formats results as an aligned table with one row per size and engine
+pub fn format_bench_table(results: &[BenchResult]) -> String {
+    let width = results
+        .iter()
+        .map(|r| r.engine.len())
+        .chain(["engine".len()])
+        .max()
+        .unwrap_or(0);
+    let mut text = format!(
+        "{:>6}  {:<width$}  {:>12}  {:>8}\n",
+        "size",
+        "engine",
+        "median (ms)",
+        "speedup",
+        width = width
+    );
+    for r in results {
+        text.push_str(&format!(
+            "{:>6}  {:<width$}  {:>12.3}  {:>7.2}x\n",
+            r.size,
+            r.engine,
+            millis(r.median),
+            r.speedup,
+            width = width
+        ));
+    }
+    text
+}
+
+/// Formats results as `{"seed": s, "results": [{"size": n, "engine": ...,
+/// "median_ms": t, "speedup": x}, ...]}`
+pub fn format_bench_json(results: &[BenchResult], seed: u64) -> String {
+    let entries: Vec<String> = results
+        .iter()
+        .map(|r| {
+            format!(
+                "{{\"size\": {}, \"engine\": \"{}\", \"median_ms\": {:?}, \"speedup\": {:?}}}",
+                r.size,
+                r.engine,
+                millis(r.median),
+                r.speedup
+            )
+        })
+        .collect();
+    format!(
+        "{{\"seed\": {}, \"results\": [{}]}}\n",
+        seed,
+        entries.join(", ")
+    )
+}
+
+/// What `verify_backends` checks
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyOptions {
+    /// Side length of the square operands
+    pub size: usize,
+    /// Operand pairs to check, seeded `seed`, `seed + 1`, ...
+    pub iterations: usize,
+    pub seed: u64,
+    /// Engines to check, each against the first
+    pub engines: Vec<String>,
+    pub threads: usize,
+}
+
+impl Default for VerifyOptions {
+    fn default() -> VerifyOptions {
+        // the textbook loop is the reference the others are checked against
+        let mut engines: Vec<String> = ENGINES.iter().map(|name| name.to_string()).collect();
+        engines.sort_by_key(|name| name != "naive");
+        VerifyOptions {
+            size: 100,
+            iterations: 10,
+            seed: 0,
+            engines,
+            threads: default_threads(),
+        }
+    }
+}
+
+/// Multiplies random operand pairs with every engine, failing with the
+/// first cell where one differs from the first engine
+pub fn verify_backends(options: &VerifyOptions) -> Result<(), BenchError> {
+    let backends = find_backends(&options.engines, options.threads)?;
+    let Some((baseline, others)) = backends.split_first() else {
+        return Ok(());
+    };
+    for i in 0..options.iterations {
+        let seed = options.seed.wrapping_add(i as u64);
+        let (a, b) = random_operands(options.size, seed);
+        let expected = baseline.multiply(&a, &b)?;
+        for (backend, name) in others.iter().zip(&options.engines[1..]) {
+            let product = backend.multiply(&a, &b)?;
+            compare(&expected, &product, name, &options.engines[0], seed)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::parse_json;
+
+    fn small_bench() -> BenchOptions {
+        BenchOptions {
+            sizes: vec![3, 8],
+            warmup: 0,
+            iterations: 3,
+            threads: 2,
+            ..BenchOptions::default()
+        }
+    }
+
+    #[test]
+    fn bench_times_every_engine_at_every_size() {
+        let options = small_bench();
+        let results = run_bench(&options).unwrap();
+        assert_eq!(results.len(), 2 * ENGINES.len());
+        assert_eq!(results[0].speedup, 1.0);
+        assert_eq!(
+            results
+                .iter()
+                .map(|r| (r.size, r.engine.as_str()))
+                .collect::<Vec<_>>()[..2],
+            [(3, ENGINES[0]), (3, ENGINES[1])]
+        );
+
+        let table = format_bench_table(&results);
+        assert_eq!(table.lines().count(), results.len() + 1);
+        assert!(table.starts_with("  size  engine"), "{}", table);
+        parse_json(&format_bench_json(&results, options.seed)).unwrap();
+    }
+
+    #[test]
+    fn bench_rejects_unknown_engines() {
+        let options = BenchOptions {
+            engines: vec![String::from("naive"), String::from("gpu")],
+            ..small_bench()
+        };
+        assert!(matches!(
+            run_bench(&options),
+            Err(BenchError::UnknownEngine(name)) if name == "gpu"
+        ));
+    }
+
+    #[test]
+    fn medians_pick_the_middle_time() {
+        let ms = Duration::from_millis;
+        assert_eq!(median(vec![ms(9), ms(1), ms(5)]), ms(5));
+        assert_eq!(median(vec![]), Duration::ZERO);
+    }
+
+    #[test]
+    fn every_engine_agrees_with_the_naive_loop() {
+        let options = VerifyOptions {
+            size: 17,
+            iterations: 3,
+            threads: 3,
+            ..VerifyOptions::default()
+        };
+        assert_eq!(options.engines[0], "naive");
+        assert_eq!(verify_backends(&options), Ok(()));
+    }
+
+    #[test]
+    fn a_disagreement_names_the_first_differing_cell() {
+        let expected = vec![vec![1, 2], vec![3, 4]];
+        let product = vec![vec![1, 2], vec![3, 5]];
+        let Err(BenchError::Disagree(mismatch)) = compare(&expected, &product, "fast", "naive", 9)
+        else {
+            panic!("expected a mismatch");
+        };
+        assert_eq!(
+            mismatch.to_string(),
+            "fast differs from naive at row 1, column 1 (expected 4, got 5) for size 2, seed 9"
+        );
+        assert_eq!(compare(&expected, &expected, "fast", "naive", 9), Ok(()));
+    }
+
+    #[test]
+    fn shapes_parse_one_to_three_sizes() {
+        assert_eq!(Shape::parse("4"), Some(Shape([4, 4, 4])));
+        assert_eq!(Shape::parse("4x2"), Some(Shape([4, 2, 2])));
+        assert_eq!(Shape::parse("4x2x7"), Some(Shape([4, 2, 7])));
+        for bad in ["", "0", "4x0", "4x2x7x1", "4by2", "-3"] {
+            assert_eq!(Shape::parse(bad), None, "{:?}", bad);
+        }
+        assert_eq!(Shape([4, 2, 7]).to_string(), "4x2 * 2x7");
+
+        let (a, b) = random_shaped_operands(Shape([4, 2, 7]), 9);
+        assert_eq!((a.len(), a[0].len(), b.len(), b[0].len()), (4, 2, 2, 7));
+        assert_eq!(random_shaped_operands(Shape([4, 2, 7]), 9), (a, b));
+    }
+
+    #[test]
+    fn time_product_reports_the_engine_used() {
+        let timing = time_product(Shape([5, 3, 4]), "threaded", 2, 0).unwrap();
+        assert_eq!((timing.engine, timing.threads), ("threaded", 2));
+        assert_eq!(
+            time_product(Shape([5, 3, 4]), "naive", 2, 0)
+                .unwrap()
+                .threads,
+            1
+        );
+        assert!(matches!(
+            time_product(Shape([5, 3, 4]), "gpu", 2, 0),
+            Err(BenchError::UnknownEngine(_))
+        ));
+    }
+}
@@ -0,0 +1,409 @@
+use crate::{
+    matrix::{dims, Answer, Matrix, MatrixError, MatrixF64},
+    sha256::{sha256, to_hex},
+};
+use std::{
+    error::Error,
+    fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+/// Binary matrix container:
+///
+/// | bytes  | field                          |
+/// |--------|--------------------------------|
+/// | 0..4   | magic `MMB\0`                  |
+/// | 4..6   | format version, u16 LE         |
+/// | 6      | element type tag               |
+/// | 7      | reserved, zero                 |
+/// | 8..16  | rows, u64 LE                   |
+/// | 16..24 | cols, u64 LE                   |
+/// | 24..   | elements, row-major, LE        |
+///
+/// The fixed-size header and row-major data mean element `(i, j)` lives at
+/// `HEADER_LEN + (i * cols + j) * element_size`, so readers can seek straight
+/// to any tile
+pub const MAGIC: [u8; 4] = *b"MMB\0";
+pub const VERSION: u16 = 1;
+pub const HEADER_LEN: u64 = 24;
+
+/// Element type of a binary matrix file. Both types are 8 bytes wide
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElementType {
+    I64,
+    F64,
+}
+
+impl ElementType {
+    pub fn tag(self) -> u8 {
+        match self {
+            ElementType::I64 => 1,
+            ElementType::F64 => 2,
+        }
+    }
+
+    pub fn from_tag(tag: u8) -> Option<ElementType> {
+        match tag {
+            1 => Some(ElementType::I64),
+            2 => Some(ElementType::F64),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ElementType::I64 => "i64",
+            ElementType::F64 => "f64",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub rows: usize,
+    pub cols: usize,
+    pub element: ElementType,
+}
+
+impl Header {
+    pub fn to_bytes(self) -> [u8; HEADER_LEN as usize] {
+        let mut bytes = [0; HEADER_LEN as usize];
+        bytes[0..4].copy_from_slice(&MAGIC);
+        bytes[4..6].copy_from_slice(&VERSION.to_le_bytes());
+        bytes[6] = self.element.tag();
+        bytes[8..16].copy_from_slice(&(self.rows as u64).to_le_bytes());
+        bytes[16..24].copy_from_slice(&(self.cols as u64).to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; HEADER_LEN as usize]) -> Result<Header, BinaryError> {
+        if bytes[0..4] != MAGIC {
+            return Err(BinaryError::BadMagic);
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version != VERSION {
+            return Err(BinaryError::UnsupportedVersion(version));
+        }
+        let element =
+            ElementType::from_tag(bytes[6]).ok_or(BinaryError::UnsupportedElementType(bytes[6]))?;
+        if bytes[7] != 0 {
+            return Err(BinaryError::BadHeader(format!(
+                "reserved byte 7 is {}, expected 0",
+                bytes[7]
+            )));
+        }
+        let field = |range: std::ops::Range<usize>| {
+            u64::from_le_bytes(bytes[range].try_into().unwrap()) as usize
+        };
+        Ok(Header {
+            rows: field(8..16),
+            cols: field(16..24),
+            element,
+        })
+    }
+
+    /// Total file length implied by the header, saturating for headers
+    /// too large for any file
+    pub fn file_len(self) -> u64 {
+        (self.rows as u64)
+            .saturating_mul(self.cols as u64)
+            .saturating_mul(8)
+            .saturating_add(HEADER_LEN)
+    }
+
+    /// Fails unless the elements are of type `expected`
+    pub fn expect_element(self, expected: ElementType) -> Result<Header, BinaryError> {
+        if self.element != expected {
+            return Err(BinaryError::ElementTypeMismatch {
+                expected,
+                found: self.element,
+            });
+        }
+        Ok(self)
+    }
+}
+
+#[derive(Debug)]
+pub enum BinaryError {
+    Io(io::Error),
+    /// The file doesn't start with the magic bytes
+    BadMagic,
+    UnsupportedVersion(u16),
+    UnsupportedElementType(u8),
+    /// The elements aren't of the type the reader needs, e.g. f64 elements
+    /// read as integer operands
+    ElementTypeMismatch {
+        expected: ElementType,
+        found: ElementType,
+    },
+    /// A header field holds an impossible value
+    BadHeader(String),
+    /// The file is shorter than its header says
+    Truncated {
+        expected: u64,
+        actual: u64,
+    },
+    Matrix(MatrixError),
+}
+
+impl fmt::Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryError::Io(err) => write!(f, "{}", err),
+            BinaryError::BadMagic => write!(f, "not a binary matrix file (bad magic bytes)"),
+            BinaryError::UnsupportedVersion(v) => write!(f, "unsupported format version {}", v),
+            BinaryError::UnsupportedElementType(t) => {
+                write!(f, "unsupported element type tag {}", t)
+            }
+            BinaryError::ElementTypeMismatch { expected, found } => write!(
+                f,
+                "elements are {}, expected {}",
+                found.name(),
+                expected.name()
+            ),
+            BinaryError::BadHeader(msg) => write!(f, "bad header: {}", msg),
+            BinaryError::Truncated { expected, actual } => write!(
+                f,
+                "file is truncated: expected {} bytes, found {}",
+                expected, actual
+            ),
+            BinaryError::Matrix(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for BinaryError {}
+
+impl From<io::Error> for BinaryError {
+    fn from(err: io::Error) -> BinaryError {
+        BinaryError::Io(err)
+    }
+}
+
+impl From<MatrixError> for BinaryError {
+    fn from(err: MatrixError) -> BinaryError {
+        BinaryError::Matrix(err)
+    }
+}
+
+/// Opens a binary matrix file of i64 elements and validates its header
+/// against its length
+pub fn open_binary(path: &Path) -> Result<(File, Header), BinaryError> {
+    let mut file = File::open(path)?;
+    let mut bytes = [0; HEADER_LEN as usize];
+    let actual = file.metadata()?.len();
+    if actual < HEADER_LEN {
+        return Err(BinaryError::Truncated {
+            expected: HEADER_LEN,
+            actual,
+        });
+    }
+    file.read_exact(&mut bytes)?;
+    let header = Header::from_bytes(&bytes)?.expect_element(ElementType::I64)?;
+    if actual < header.file_len() {
+        return Err(BinaryError::Truncated {
+            expected: header.file_len(),
+            actual,
+        });
+    }
+    Ok((file, header))
+}
+
+pub fn read_binary(path: &Path) -> Result<Matrix, BinaryError> {
+    let (file, header) = open_binary(path)?;
+    let mut reader = BufReader::new(file);
+    let mut buf = [0; 8];
+    let mut m = Vec::with_capacity(header.rows);
+    for _ in 0..header.rows {
+        let mut row = Vec::with_capacity(header.cols);
+        for _ in 0..header.cols {
+            reader.read_exact(&mut buf)?;
+            row.push(i64::from_le_bytes(buf));
+        }
+        m.push(row);
+    }
+    Ok(m)
+}
+
+/// Parses the contents of a binary matrix file of i64 elements
+pub fn parse_binary(bytes: &[u8]) -> Result<Matrix, BinaryError> {
+    let truncated = |expected| BinaryError::Truncated {
+        expected,
+        actual: bytes.len() as u64,
+    };
+    let header_bytes = bytes
+        .get(..HEADER_LEN as usize)
+        .ok_or_else(|| truncated(HEADER_LEN))?;
+    let header =
+        Header::from_bytes(header_bytes.try_into().unwrap())?.expect_element(ElementType::I64)?;
+    if (bytes.len() as u64) < header.file_len() {
+        return Err(truncated(header.file_len()));
+    }
+    let data = &bytes[HEADER_LEN as usize..header.file_len() as usize];
+    let mut elements = data
+        .chunks_exact(8)
+        .map(|chunk| i64::from_le_bytes(chunk.try_into().unwrap()));
+    Ok((0..header.rows)
+        .map(|_| elements.by_ref().take(header.cols).collect())
+        .collect())
+}
+
+/// The contents of a binary matrix file holding `answer`, with i64 or f64
+/// elements to match it
+pub fn format_binary(answer: &Answer) -> Result<Vec<u8>, BinaryError> {
+    let (rows, cols) = answer.dims();
+    let ragged = match answer {
+        Answer::Int(m) => m.iter().position(|row| row.len() != cols),
+        Answer::Float(m) => m.iter().position(|row| row.len() != cols),
+    };
+    if let Some(row) = ragged {
+        return Err(MatrixError::Ragged { row }.into());
+    }
+    let element = match answer {
+        Answer::Int(_) => ElementType::I64,
+        Answer::Float(_) => ElementType::F64,
+    };
+    let header = Header {
+        rows,
+        cols,
+        element,
+    };
+    let mut bytes = Vec::with_capacity(header.file_len() as usize);
+    bytes.extend_from_slice(&header.to_bytes());
+    match answer {
+        Answer::Int(m) => m
+            .iter()
+            .flatten()
+            .for_each(|x| bytes.extend_from_slice(&x.to_le_bytes())),
+        Answer::Float(m) => m
+            .iter()
+            .flatten()
+            .for_each(|x| bytes.extend_from_slice(&x.to_le_bytes())),
+    }
+    Ok(bytes)
+}
+
+/// The SHA-256 digest, in hex, of `answer`'s canonical encoding: its
+/// `format_binary` bytes, with floats first made canonical by turning -0.0
+/// into 0.0 and every NaN into the quiet NaN `0x7ff8000000000000`. The
+/// digest depends only on the shape, the element type and the values, not
+/// on output settings or the engine that computed it
+pub fn canonical_checksum(answer: &Answer) -> Result<String, MatrixError> {
+    // a ragged matrix is the only thing `format_binary` rejects
+    let encode = |answer: &Answer| {
+        format_binary(answer).map_err(|err| match err {
+            BinaryError::Matrix(err) => err,
+            err => MatrixError::InvalidArgument(err.to_string()),
+        })
+    };
+    let canonical = match answer {
+        Answer::Int(_) => encode(answer)?,
+        Answer::Float(m) => {
+            let m: MatrixF64 = m
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|&x| match x {
+                            _ if x.is_nan() => f64::from_bits(0x7ff8_0000_0000_0000),
+                            _ if x == 0.0 => 0.0,
+                            _ => x,
+                        })
+                        .collect()
+                })
+                .collect();
+            encode(&Answer::Float(m))?
+        }
+    };
+    Ok(to_hex(&sha256(&canonical)))
+}
+
+pub fn write_binary(path: &Path, m: &Matrix) -> Result<(), BinaryError> {
+    let (rows, cols) = dims(m);
+    if let Some(row) = m.iter().position(|row| row.len() != cols) {
+        return Err(MatrixError::Ragged { row }.into());
+    }
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    let header = Header {
+        rows,
+        cols,
+        element: ElementType::I64,
+    };
+    writer.write_all(&header.to_bytes())?;
+    for x in m.iter().flatten() {
+        writer.write_all(&x.to_le_bytes())?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_files_round_trip_with_a_fixed_header() {
+        let m = vec![vec![1, -2, 3], vec![i64::MIN, 0, i64::MAX]];
+        let bytes = format_binary(&Answer::Int(m.clone())).unwrap();
+        assert_eq!(bytes.len() as u64, HEADER_LEN + 6 * 8);
+        assert_eq!(&bytes[..8], b"MMB\0\x01\x00\x01\x00");
+        assert_eq!(parse_binary(&bytes).unwrap(), m);
+
+        let path =
+            std::env::temp_dir().join(format!("matrixmult-{}-binary.mmb", std::process::id()));
+        write_binary(&path, &m).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), bytes);
+        assert_eq!(read_binary(&path).unwrap(), m);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn malformed_binary_files_are_rejected() {
+        let bytes = format_binary(&Answer::Int(vec![vec![1, 2]])).unwrap();
+        let patched = |at: usize, value: u8| {
+            let mut bytes = bytes.clone();
+            bytes[at] = value;
+            parse_binary(&bytes)
+        };
+        assert!(matches!(patched(0, b'X'), Err(BinaryError::BadMagic)));
+        assert!(matches!(
+            patched(4, 2),
+            Err(BinaryError::UnsupportedVersion(2))
+        ));
+        assert!(matches!(
+            patched(6, 9),
+            Err(BinaryError::UnsupportedElementType(9))
+        ));
+        assert!(matches!(patched(7, 1), Err(BinaryError::BadHeader(_))));
+        assert!(matches!(
+            patched(6, ElementType::F64.tag()),
+            Err(BinaryError::ElementTypeMismatch {
+                expected: ElementType::I64,
+                found: ElementType::F64
+            })
+        ));
+        assert!(matches!(
+            parse_binary(&bytes[..bytes.len() - 1]),
+            Err(BinaryError::Truncated {
+                expected: 40,
+                actual: 39
+            })
+        ));
+        assert!(matches!(
+            format_binary(&Answer::Int(vec![vec![1], vec![]])),
+            Err(BinaryError::Matrix(MatrixError::Ragged { row: 1 }))
+        ));
+    }
+
+    #[test]
+    fn checksums_ignore_the_sign_of_zero_and_nan_payloads() {
+        let a = Answer::Float(vec![vec![0.0, f64::NAN]]);
+        let b = Answer::Float(vec![vec![-0.0, f64::from_bits(0x7ff8_0000_0000_0001)]]);
+        assert_eq!(canonical_checksum(&a), canonical_checksum(&b));
+        let int = Answer::Int(vec![vec![0, 0]]);
+        assert_ne!(canonical_checksum(&int), canonical_checksum(&a));
+        assert_eq!(canonical_checksum(&int).unwrap().len(), 64);
+    }
+}
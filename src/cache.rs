@@ -0,0 +1,100 @@
+/// A small least-recently-used cache. Lookups compare whole keys, so a key
+/// made of a computation's inputs never returns a result for other inputs
+#[derive(Debug, Clone)]
+pub struct LruCache<K, V> {
+    capacity: usize,
+    /// Entries from least to most recently used
+    entries: Vec<(K, V)>,
+    hits: usize,
+    misses: usize,
+}
+
+impl<K: PartialEq, V> LruCache<K, V> {
+    /// A cache holding at most `capacity` entries, at least one
+    pub fn new(capacity: usize) -> LruCache<K, V> {
+        LruCache {
+            capacity: capacity.max(1),
+            entries: vec![],
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// The value stored for `key`, which becomes the most recently used
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let Some(index) = self.entries.iter().position(|(k, _)| k == key) else {
+            self.misses += 1;
+            return None;
+        };
+        self.hits += 1;
+        let entry = self.entries.remove(index);
+        self.entries.push(entry);
+        self.entries.last().map(|(_, v)| v)
+    }
+
+    /// Stores `value` for `key`, dropping the least recently used entry if
+    /// the cache is full
+    pub fn insert(&mut self, key: K, value: V) {
+        self.entries.retain(|(k, _)| k != &key);
+        if self.entries.len() == self.capacity {
+            self.entries.remove(0);
+        }
+        self.entries.push((key, value));
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Lookups that found a value
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Lookups that didn't
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_least_recently_used_entry_is_dropped() {
+        let mut cache = LruCache::new(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        assert_eq!(cache.get(&"a"), Some(&1));
+        // "b" is now the least recently used
+        cache.insert("c", 3);
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(cache.get(&"c"), Some(&3));
+        assert_eq!((cache.hits(), cache.misses()), (3, 1));
+
+        cache.insert("c", 4);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&"c"), Some(&4));
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn capacity_is_at_least_one() {
+        let mut cache = LruCache::new(0);
+        cache.insert(1, "one");
+        assert_eq!(cache.get(&1), Some(&"one"));
+        cache.insert(2, "two");
+        assert_eq!((cache.len(), cache.get(&1)), (1, None));
+    }
+}
@@ -0,0 +1,2501 @@
+use crate::{
+    backend::{default_threads, find_backend, is_multithreaded, DEFAULT_ENGINE, ENGINES},
+    bench::{
+        format_bench_json, format_bench_table, run_bench, time_product, verify_backends,
+        BenchError, BenchOptions, Shape, VerifyOptions,
+    },
+    binary::{canonical_checksum, parse_binary, BinaryError},
+    commands::SHELLS,
+    completions::{format_completions, Shell},
+    csv::parse_csv,
+    envelope::Envelope,
+    export::{export_answer, render_answer, write_atomic, ExportError, WriteOptions},
+    files::FileFormat,
+    format::{answer_cells, format_answer_localized, NumberLocale},
+    gzip::{gzip, is_gzip_path, GzDecoder, MAX_LEVEL},
+    jobs::{format_job_summary, parse_manifest, run_jobs},
+    json::{parse_json_matrix, quote},
+    latex::WideMatrix,
+    log::{self, Level, LOG_ENV},
+    manpage::format_manpage,
+    market::parse_matrix_market,
+    matrix::{Answer, Matrix, MatrixError},
+    npy::{parse_npy_matrix, NpyError},
+    out_of_core::{multiply_files, parse_memory_limit},
+    parse::{parse_text_matrix, FormatError},
+    pretty::{format_pretty, PrettyOptions},
+    random::{random_int_matrix, MatrixKind},
+    repl::run_repl,
+    session::{
+        format_session, parse_session, sanitize_name, unique_name, unique_path, NamedMatrices,
+    },
+    watch::{time_of_day, watch_paths, DEFAULT_DEBOUNCE, DEFAULT_INTERVAL},
+};
+use std::{
+    error::Error,
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Memory used for matrix tiles by `--out-of-core` unless `--memory-limit` is given
+pub const DEFAULT_MEMORY_LIMIT: usize = 1 << 30;
+
+/// Largest input read from a file or stdin unless `--max-input-size` is given
+pub const DEFAULT_MAX_INPUT_SIZE: usize = 256 << 20;
+
+/// How input files are read
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadOptions {
+    pub max_size: usize,
+    /// Overrides the format inferred from the file extension
+    pub format: Option<FileFormat>,
+    /// Skip the first row of CSV input
+    pub csv_header: bool,
+    /// Field separator of CSV and TSV input, overriding the format's
+    /// default
+    pub delimiter: Option<char>,
+    /// Decompress every input, not just `.gz` files
+    pub compressed: bool,
+}
+
+impl Default for ReadOptions {
+    fn default() -> ReadOptions {
+        ReadOptions {
+            max_size: DEFAULT_MAX_INPUT_SIZE,
+            format: None,
+            csv_header: false,
+            delimiter: None,
+            compressed: false,
+        }
+    }
+}
+
+/// Command line options. With no input files the TUI is started
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// Directory every result computed in the TUI is written to
+    pub autosave_dir: Option<PathBuf>,
+    pub out_of_core: bool,
+    pub memory_limit: usize,
+    /// Read both operands from stdin, separated by a blank line
+    pub stdin: bool,
+    pub read: ReadOptions,
+    /// Write the result to this file instead of stdout
+    pub output: Option<PathBuf>,
+    pub write: WriteOptions,
+    /// Decimal point and digit grouping of results printed to stdout or
+    /// shown in the TUI
+    pub locale: NumberLocale,
+    /// Print text results to stdout as an aligned table
+    pub pretty: bool,
+    /// Layout of `--pretty` tables
+    pub layout: PrettyOptions,
+    /// Name of the multiplication backend, one of `backend::ENGINES`
+    pub engine: String,
+    /// Threads for multithreaded engines, defaulting to the available cores
+    pub threads: Option<usize>,
+    /// How much is reported besides the result
+    pub verbosity: Verbosity,
+    /// Print an `Envelope` describing the run instead of the bare result
+    pub json: bool,
+    /// File diagnostics are appended to, at the level in `MATRIXMULT_LOG`
+    pub log: Option<PathBuf>,
+    /// Input files, where `-` means stdin
+    pub paths: Vec<PathBuf>,
+    /// The `bench` subcommand, if given
+    pub bench: Option<BenchCommand>,
+    /// The `batch` subcommand, if given
+    pub batch: Option<BatchCommand>,
+    /// The `gen` subcommand, if given
+    pub gen: Option<GenCommand>,
+    /// The `watch` subcommand, if given
+    pub watch: Option<WatchCommand>,
+    /// The `session` subcommand, if given
+    pub session: Option<SessionCommand>,
+    /// Session file the TUI starts from and saves to when quitting
+    pub resume: Option<PathBuf>,
+    /// Run the TUI without loading or saving any file
+    pub safe: bool,
+    /// The `verify` subcommand, if given
+    pub verify: Option<VerifyOptions>,
+    /// Print the completion script for this shell
+    pub completions: Option<Shell>,
+    /// Print the man page
+    pub manpage: bool,
+    /// Run the line-oriented `repl` subcommand
+    pub repl: bool,
+    /// Time one product of random operands of this shape instead of
+    /// multiplying files
+    pub size: Option<Shape>,
+    /// Seed of the `--size` operands
+    pub seed: u64,
+    /// Print `binary::canonical_checksum` of the product instead of the
+    /// product, or besides writing it to `--output`
+    pub checksum: bool,
+}
+
+/// How much the non-interactive modes report besides their result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verbosity {
+    /// Only the result or checksum, set by `-q`
+    Quiet,
+    /// Also where the result was written
+    Normal,
+    /// Also the operand sizes, engine, timing and warnings on stderr, set by
+    /// `-v`
+    Verbose,
+}
+
+/// Options of `matrixmult bench`
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchCommand {
+    pub options: BenchOptions,
+    /// Print the results as JSON instead of a table
+    pub json: bool,
+}
+
+/// Options of `matrixmult gen`. The result is written like a product, to
+/// `Options::output` or stdout
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenCommand {
+    pub rows: usize,
+    pub cols: usize,
+    pub min: i64,
+    pub max: i64,
+    pub kind: MatrixKind,
+    pub seed: u64,
+}
+
+/// Options of `matrixmult batch`
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchCommand {
+    pub manifest: PathBuf,
+    /// Jobs run at the same time
+    pub jobs: usize,
+    /// Replace existing output files
+    pub overwrite: bool,
+}
+
+/// `matrixmult session export` or `import`
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionCommand {
+    /// Write every matrix of a session file to its own file in `dir`
+    Export { session: PathBuf, dir: PathBuf },
+    /// Make a session file of matrix files, each named after its file
+    Import {
+        session: PathBuf,
+        files: Vec<PathBuf>,
+    },
+}
+
+/// Options of `matrixmult watch`. The product is written to
+/// `Options::output`, which is required
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchCommand {
+    pub a: PathBuf,
+    pub b: PathBuf,
+    /// How often the inputs are checked for changes
+    pub interval: Duration,
+    /// How long the inputs must stay unchanged before they're read again
+    pub debounce: Duration,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            autosave_dir: None,
+            out_of_core: false,
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+            stdin: false,
+            read: ReadOptions::default(),
+            output: None,
+            write: WriteOptions::default(),
+            locale: NumberLocale::default(),
+            pretty: false,
+            layout: PrettyOptions::default(),
+            engine: String::from(DEFAULT_ENGINE),
+            threads: None,
+            verbosity: Verbosity::Normal,
+            json: false,
+            log: None,
+            paths: vec![],
+            bench: None,
+            batch: None,
+            gen: None,
+            watch: None,
+            session: None,
+            resume: None,
+            safe: false,
+            verify: None,
+            completions: None,
+            manpage: false,
+            repl: false,
+            size: None,
+            seed: 0,
+            checksum: false,
+        }
+    }
+}
+
+impl Options {
+    /// Whether to run without the TUI
+    pub fn is_batch(&self) -> bool {
+        self.out_of_core
+            || self.stdin
+            || !self.paths.is_empty()
+            || self.bench.is_some()
+            || self.batch.is_some()
+            || self.gen.is_some()
+            || self.watch.is_some()
+            || self.session.is_some()
+            || self.verify.is_some()
+            || self.completions.is_some()
+            || self.manpage
+            || self.repl
+            || self.size.is_some()
+    }
+}
+
+#[derive(Debug)]
+pub enum CliError {
+    /// The command line itself is wrong
+    Usage(String),
+    Io {
+        path: PathBuf,
+        err: io::Error,
+    },
+    /// An input is larger than `--max-input-size`
+    InputTooLarge {
+        path: PathBuf,
+        limit: usize,
+    },
+    /// Stdin didn't hold exactly two blank-line separated matrices
+    StdinMatrices {
+        found: usize,
+    },
+    /// An input file in a structured format is malformed
+    Format {
+        path: PathBuf,
+        err: FormatError,
+    },
+    /// An input file couldn't be parsed
+    Parse {
+        path: PathBuf,
+        err: MatrixError,
+    },
+    /// An input `.npy` file is malformed or unsupported
+    Npy {
+        path: PathBuf,
+        err: NpyError,
+    },
+    /// An input `.mmb` file is malformed or unsupported
+    BinaryFile {
+        path: PathBuf,
+        err: BinaryError,
+    },
+    Matrix(MatrixError),
+    Binary(BinaryError),
+    Export(ExportError),
+    Bench(BenchError),
+    /// Some jobs of a batch manifest failed
+    JobsFailed {
+        failed: usize,
+        total: usize,
+    },
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(msg) => write!(f, "{}", msg),
+            CliError::Io { path, err } => write!(f, "{}: {}", path.display(), err),
+            CliError::InputTooLarge { path, limit } => write!(
+                f,
+                "{}: input is larger than the {} byte limit (see --max-input-size)",
+                path.display(),
+                limit
+            ),
+            CliError::StdinMatrices { found } => write!(
+                f,
+                "expected two matrices on stdin separated by a blank line, found {}",
+                found
+            ),
+            CliError::Format { path, err } => write!(f, "{}: {}", path.display(), err),
+            CliError::Parse { path, err } => write!(f, "{}: {}", path.display(), err),
+            CliError::Npy { path, err } => write!(f, "{}: {}", path.display(), err),
+            CliError::BinaryFile { path, err } => write!(f, "{}: {}", path.display(), err),
+            CliError::Matrix(err) => write!(f, "{}", err),
+            CliError::Binary(err) => write!(f, "{}", err),
+            CliError::Export(ExportError::Exists(path)) => write!(
+                f,
+                "{} already exists (pass --force to replace it)",
+                path.display()
+            ),
+            CliError::Export(err) => write!(f, "{}", err),
+            CliError::Bench(err) => write!(f, "{}", err),
+            CliError::JobsFailed { failed, total } => {
+                write!(f, "{} of {} jobs failed", failed, total)
+            }
+        }
+    }
+}
+
+impl Error for CliError {}
+
+impl From<MatrixError> for CliError {
+    fn from(err: MatrixError) -> CliError {
+        CliError::Matrix(err)
+    }
+}
+
+impl From<ExportError> for CliError {
+    fn from(err: ExportError) -> CliError {
+        CliError::Export(err)
+    }
+}
+
+impl From<BenchError> for CliError {
+    fn from(err: BenchError) -> CliError {
+        CliError::Bench(err)
+    }
+}
+
+impl From<BinaryError> for CliError {
+    fn from(err: BinaryError) -> CliError {
+        CliError::Binary(err)
+    }
+}
+
+/// Exit status of a successful run
+pub const EXIT_OK: i32 = 0;
+/// Exit status of failures without a more specific code, e.g. failed batch jobs
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+/// An input couldn't be parsed
+pub const EXIT_PARSE: i32 = 3;
+/// The operands' shapes don't suit the operation
+pub const EXIT_SHAPE: i32 = 4;
+/// An element of the result doesn't fit in an i64
+pub const EXIT_OVERFLOW: i32 = 5;
+/// A file or stream couldn't be read or written
+pub const EXIT_IO: i32 = 6;
+
+fn matrix_exit_code(err: &MatrixError) -> i32 {
+    match err {
+        MatrixError::Parse { .. }
+        | MatrixError::ZeroDenominator { .. }
+        | MatrixError::EmptyRow { .. } => EXIT_PARSE,
+        MatrixError::NotSquare { .. }
+        | MatrixError::DimensionMismatch { .. }
+        | MatrixError::Empty
+        | MatrixError::Ragged { .. } => EXIT_SHAPE,
+        MatrixError::Overflow { .. } => EXIT_OVERFLOW,
+        _ => EXIT_FAILURE,
+    }
+}
+
+impl CliError {
+    /// The process exit status for this error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Usage(_) => EXIT_USAGE,
+            CliError::Io { .. } | CliError::InputTooLarge { .. } => EXIT_IO,
+            CliError::StdinMatrices { .. } | CliError::Format { .. } | CliError::Npy { .. } => {
+                EXIT_PARSE
+            }
+            CliError::Parse { err, .. } | CliError::Matrix(err) => matrix_exit_code(err),
+            CliError::Binary(err) | CliError::BinaryFile { err, .. } => match err {
+                BinaryError::Io(_) => EXIT_IO,
+                BinaryError::Matrix(err) => matrix_exit_code(err),
+                _ => EXIT_PARSE,
+            },
+            CliError::Export(ExportError::Io(_) | ExportError::Exists(_)) => EXIT_IO,
+            CliError::Export(_) => EXIT_FAILURE,
+            CliError::Bench(BenchError::Matrix(err)) => matrix_exit_code(err),
+            CliError::Bench(BenchError::UnknownEngine(_)) => EXIT_USAGE,
+            CliError::Bench(_) | CliError::JobsFailed { .. } => EXIT_FAILURE,
+        }
+    }
+
+    /// Name of the variant, naming the matrix error inside it if there is one
+    pub fn variant(&self) -> &'static str {
+        match self {
+            CliError::Usage(_) => "Usage",
+            CliError::Io { .. } => "Io",
+            CliError::InputTooLarge { .. } => "InputTooLarge",
+            CliError::StdinMatrices { .. } => "StdinMatrices",
+            CliError::Format { .. } => "Format",
+            CliError::Parse { err, .. } | CliError::Matrix(err) => err.name(),
+            CliError::Npy { .. } => "Npy",
+            CliError::Binary(BinaryError::Matrix(err))
+            | CliError::BinaryFile {
+                err: BinaryError::Matrix(err),
+                ..
+            } => err.name(),
+            CliError::Binary(_) | CliError::BinaryFile { .. } => "Binary",
+            CliError::Export(_) => "Export",
+            CliError::Bench(BenchError::Matrix(err)) => err.name(),
+            CliError::Bench(_) => "Bench",
+            CliError::JobsFailed { .. } => "JobsFailed",
+        }
+    }
+}
+
+/// How errors are reported on stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    /// `error: <message>`
+    #[default]
+    Text,
+    /// One JSON object per error, see `format_error`
+    Json,
+}
+
+/// Takes `--error-format text|json` out of `args`, wherever it is, so it
+/// applies to errors in the rest of the command line too
+pub fn split_error_format(
+    args: impl IntoIterator<Item = String>,
+) -> Result<(ErrorFormat, Vec<String>), CliError> {
+    let mut format = ErrorFormat::Text;
+    let mut rest = vec![];
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg != "--error-format" {
+            rest.push(arg);
+            continue;
+        }
+        format = match args.next().as_deref() {
+            Some("text") => ErrorFormat::Text,
+            Some("json") => ErrorFormat::Json,
+            Some(other) => {
+                return Err(usage(format!(
+                    "--error-format must be text or json, not {:?}",
+                    other
+                )))
+            }
+            None => return Err(usage("--error-format needs a value")),
+        };
+    }
+    Ok((format, rest))
+}
+
+/// Where in the input a matrix error occurred, as JSON fields
+fn matrix_position(err: &MatrixError) -> Vec<(&'static str, String)> {
+    let pair = |(rows, cols): (usize, usize)| format!("[{}, {}]", rows, cols);
+    match err {
+        MatrixError::Parse { row, col, token }
+        | MatrixError::ZeroDenominator { row, col, token } => vec![
+            ("row", row.to_string()),
+            ("col", col.to_string()),
+            ("token", quote(token)),
+        ],
+        MatrixError::DimensionMismatch { left, right } => {
+            vec![("left", pair(*left)), ("right", pair(*right))]
+        }
+        MatrixError::NotSquare { rows, cols } => vec![("shape", pair((*rows, *cols)))],
+        MatrixError::Ragged { row } | MatrixError::EmptyRow { row } => {
+            vec![("row", row.to_string())]
+        }
+        MatrixError::LinearlyDependent { col } => vec![("col", col.to_string())],
+        _ => vec![],
+    }
+}
+
+/// `err` on one line: `error: <message>`, or for `ErrorFormat::Json` an
+/// object with the variant, message, exit code and, where known, the path
+/// and position (the 1-based `line` and `column` in the file, or the `row`
+/// and `col` of the matrix as numbered in the message) of the error
+pub fn format_error(err: &CliError, format: ErrorFormat) -> String {
+    if format == ErrorFormat::Text {
+        return format!("error: {}", err);
+    }
+    let mut fields = vec![
+        ("variant", quote(err.variant())),
+        ("message", quote(&err.to_string())),
+        ("exit_code", err.exit_code().to_string()),
+    ];
+    match err {
+        CliError::Io { path, .. }
+        | CliError::InputTooLarge { path, .. }
+        | CliError::Npy { path, .. }
+        | CliError::BinaryFile { path, .. } => {
+            fields.push(("path", quote(&path.display().to_string())))
+        }
+        CliError::Format { path, err } => {
+            fields.push(("path", quote(&path.display().to_string())));
+            fields.push(("line", err.line.to_string()));
+            if let Some(column) = err.column {
+                fields.push(("column", column.to_string()));
+            }
+        }
+        CliError::Parse { path, err } => {
+            fields.push(("path", quote(&path.display().to_string())));
+            fields.extend(matrix_position(err));
+        }
+        CliError::Matrix(err)
+        | CliError::Binary(BinaryError::Matrix(err))
+        | CliError::Bench(BenchError::Matrix(err)) => fields.extend(matrix_position(err)),
+        CliError::Export(ExportError::Exists(path)) => {
+            fields.push(("path", quote(&path.display().to_string())))
+        }
+        _ => {}
+    }
+    let body: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("\"{}\": {}", key, value))
+        .collect();
+    format!("{{{}}}", body.join(", "))
+}
+
+fn usage(msg: impl Into<String>) -> CliError {
+    CliError::Usage(msg.into())
+}
+
+/// Checks that `name` is one of `backend::ENGINES`
+fn check_engine(name: &str) -> Result<(), CliError> {
+    if name == "rayon" {
+        return Err(usage(
+            "the rayon engine isn't available in this build, use threaded",
+        ));
+    }
+    if name == "gpu" {
+        return Err(usage(
+            "there is no GPU engine yet, every engine runs on the CPU",
+        ));
+    }
+    if !ENGINES.contains(&name) {
+        return Err(usage(format!(
+            "unknown engine {:?}, expected one of {}",
+            name,
+            ENGINES.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Parses the value of `--delimiter`: one character or `tab`. Characters
+/// that can be part of a number or a quoted field aren't delimiters
+pub fn parse_delimiter(text: &str) -> Result<char, CliError> {
+    let invalid = |why: &str| usage(format!("invalid delimiter {:?}: {}", text, why));
+    if text == "tab" {
+        return Ok('\t');
+    }
+    let mut chars = text.chars();
+    let (Some(c), None) = (chars.next(), chars.next()) else {
+        return Err(invalid("expected one character or tab"));
+    };
+    match c {
+        '0'..='9' | '-' | '+' | '.' => Err(invalid("it can be part of a number")),
+        '"' | '\n' | '\r' => Err(invalid("it can't separate fields")),
+        _ => Ok(c),
+    }
+}
+
+/// Parses a comma separated list of positive counts, e.g. `--sizes 128,512`
+fn parse_counts(flag: &str, list: &str) -> Result<Vec<usize>, CliError> {
+    list.split(',')
+        .map(|item| {
+            item.trim()
+                .parse()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| usage(format!("{}: invalid count {:?}", flag, item)))
+        })
+        .collect()
+}
+
+/// Parses the arguments after `session`, setting the format and `--force`
+/// in `options`
+fn parse_session_args(
+    mut args: impl Iterator<Item = String>,
+    options: &mut Options,
+) -> Result<SessionCommand, CliError> {
+    let action = args.next().unwrap_or_default();
+    let mut paths = vec![];
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                let name = args.next().ok_or_else(|| usage("--format needs a value"))?;
+                options.write.format = Some(
+                    FileFormat::from_name(&name)
+                        .ok_or_else(|| usage(format!("unknown format {:?}", name)))?,
+                );
+            }
+            "--force" => options.write.overwrite = true,
+            _ if !arg.starts_with("--") => paths.push(PathBuf::from(arg)),
+            _ => return Err(usage(format!("unknown session argument {:?}", arg))),
+        }
+    }
+    let mut paths = paths.into_iter();
+    let session = paths
+        .next()
+        .ok_or_else(|| usage("session needs a session file"))?;
+    match action.as_str() {
+        "export" => {
+            let (Some(dir), None) = (paths.next(), paths.next()) else {
+                return Err(usage("session export needs a session file and a directory"));
+            };
+            if options.write.overwrite {
+                return Err(usage(
+                    "session export never replaces files, --force doesn't apply",
+                ));
+            }
+            Ok(SessionCommand::Export { session, dir })
+        }
+        "import" => {
+            let files: Vec<PathBuf> = paths.collect();
+            if files.is_empty() {
+                return Err(usage("session import needs matrix files to import"));
+            }
+            if options.write.format.is_some() {
+                return Err(usage(
+                    "session import writes a session file, --format doesn't apply",
+                ));
+            }
+            Ok(SessionCommand::Import { session, files })
+        }
+        _ => Err(usage(format!(
+            "session needs export or import, not {:?}",
+            action
+        ))),
+    }
+}
+
+/// Reads a session file written by `session::format_session`
+pub fn read_session_file(path: &Path) -> Result<NamedMatrices, CliError> {
+    let text = std::fs::read_to_string(path).map_err(io_error(path))?;
+    parse_session(&text).map_err(|err| CliError::Format {
+        path: path.to_path_buf(),
+        err,
+    })
+}
+
+/// Runs `session export` or `session import`, writing a line to `out` for
+/// every file written
+fn run_session(
+    command: &SessionCommand,
+    options: &Options,
+    out: &mut impl Write,
+) -> Result<(), CliError> {
+    let mut lines = String::new();
+    match command {
+        SessionCommand::Export { session, dir } => {
+            let matrices = read_session_file(session)?;
+            std::fs::create_dir_all(dir).map_err(io_error(dir))?;
+            let extension = options
+                .write
+                .format
+                .map_or("txt", |format| format.extension());
+            for (name, m) in &matrices {
+                let path = unique_path(dir, name, extension);
+                let format = export_answer(&path, Some(&Answer::Int(m.clone())), &options.write)?;
+                lines.push_str(&format!("wrote {} as {}\n", path.display(), format.name()));
+            }
+        }
+        SessionCommand::Import { session, files } => {
+            let mut matrices = NamedMatrices::new();
+            for path in files {
+                let m = read_matrix_file(path, &options.read)?;
+                // `a` for `a.csv` and `a.csv.gz`
+                let mut stem = path.file_stem().map(Path::new);
+                if is_gzip_path(path) {
+                    stem = stem.and_then(|stem| stem.file_stem().map(Path::new));
+                }
+                let stem =
+                    stem.map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+                let name = unique_name(&sanitize_name(&stem), |name| matrices.contains_key(name));
+                lines.push_str(&format!("imported {} as {}\n", path.display(), name));
+                matrices.insert(name, m);
+            }
+            write_atomic(
+                session,
+                format_session(&matrices).as_bytes(),
+                options.write.overwrite,
+            )?;
+            lines.push_str(&format!("wrote {}\n", session.display()));
+        }
+    }
+    out.write_all(lines.as_bytes()).map_err(|err| CliError::Io {
+        path: PathBuf::from("<stdout>"),
+        err,
+    })
+}
+
+/// Parses the arguments after `watch`, setting the output, format and
+/// engine in `options`
+fn parse_watch_args(
+    mut args: impl Iterator<Item = String>,
+    options: &mut Options,
+) -> Result<WatchCommand, CliError> {
+    let mut paths = vec![];
+    let (mut interval, mut debounce) = (DEFAULT_INTERVAL, DEFAULT_DEBOUNCE);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| usage(format!("{} needs a value", arg)))
+        };
+        match arg.as_str() {
+            "--interval" => {
+                interval = Duration::from_millis(parse_counts(&arg, &value()?)?[0] as u64)
+            }
+            "--debounce" => {
+                let v = value()?;
+                debounce = Duration::from_millis(
+                    v.parse()
+                        .map_err(|_| usage(format!("--debounce: invalid value {:?}", v)))?,
+                );
+            }
+            "--output" => options.output = Some(PathBuf::from(value()?)),
+            "--format" => {
+                let name = value()?;
+                options.write.format = Some(
+                    FileFormat::from_name(&name)
+                        .ok_or_else(|| usage(format!("unknown format {:?}", name)))?,
+                );
+            }
+            "--engine" => {
+                let name = value()?;
+                check_engine(&name)?;
+                options.engine = name;
+            }
+            "--threads" => options.threads = Some(parse_counts(&arg, &value()?)?[0]),
+            _ if !arg.starts_with("--") => paths.push(PathBuf::from(arg)),
+            _ => return Err(usage(format!("unknown watch argument {:?}", arg))),
+        }
+    }
+    let [a, b]: [PathBuf; 2] = paths
+        .try_into()
+        .map_err(|_| usage("watch needs two input files"))?;
+    if [&a, &b].iter().any(|path| path.as_os_str() == STDIN_PATH) {
+        return Err(usage("watch can't read its inputs from stdin"));
+    }
+    if options.output.is_none() {
+        return Err(usage("watch needs --output"));
+    }
+    if options.threads.is_some() && !is_multithreaded(&options.engine) {
+        return Err(usage(format!(
+            "--threads only applies to the threaded engine, not {}",
+            options.engine
+        )));
+    }
+    Ok(WatchCommand {
+        a,
+        b,
+        interval,
+        debounce,
+    })
+}
+
+/// Parses the arguments after `bench`
+fn parse_bench_args(mut args: impl Iterator<Item = String>) -> Result<BenchCommand, CliError> {
+    let mut bench = BenchCommand {
+        options: BenchOptions::default(),
+        json: false,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| usage(format!("{} needs a value", arg)))
+        };
+        match arg.as_str() {
+            "--sizes" => bench.options.sizes = parse_counts(&arg, &value()?)?,
+            "--engines" => {
+                let engines: Vec<String> = value()?.split(',').map(str::to_string).collect();
+                engines.iter().try_for_each(|name| check_engine(name))?;
+                bench.options.engines = engines;
+            }
+            "--seed" => {
+                let seed = value()?;
+                bench.options.seed = seed
+                    .parse()
+                    .map_err(|_| usage(format!("invalid seed {:?}", seed)))?;
+            }
+            "--warmup" => {
+                let warmup = value()?;
+                bench.options.warmup = warmup
+                    .parse()
+                    .map_err(|_| usage(format!("invalid warmup count {:?}", warmup)))?;
+            }
+            "--iterations" => bench.options.iterations = parse_counts(&arg, &value()?)?[0],
+            "--threads" => bench.options.threads = parse_counts(&arg, &value()?)?[0],
+            "--json" => bench.json = true,
+            _ => return Err(usage(format!("unknown bench argument {:?}", arg))),
+        }
+    }
+    Ok(bench)
+}
+
+/// Parses the arguments after `verify`
+fn parse_verify_args(mut args: impl Iterator<Item = String>) -> Result<VerifyOptions, CliError> {
+    let mut verify = VerifyOptions::default();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| usage(format!("{} needs a value", arg)))
+        };
+        match arg.as_str() {
+            "--size" => verify.size = parse_counts(&arg, &value()?)?[0],
+            "--iterations" => verify.iterations = parse_counts(&arg, &value()?)?[0],
+            "--seed" => {
+                let seed = value()?;
+                verify.seed = seed
+                    .parse()
+                    .map_err(|_| usage(format!("invalid seed {:?}", seed)))?;
+            }
+            "--engines" => {
+                let engines: Vec<String> = value()?.split(',').map(str::to_string).collect();
+                engines.iter().try_for_each(|name| check_engine(name))?;
+                verify.engines = engines;
+            }
+            "--threads" => verify.threads = parse_counts(&arg, &value()?)?[0],
+            _ => return Err(usage(format!("unknown verify argument {:?}", arg))),
+        }
+    }
+    Ok(verify)
+}
+
+/// Parses the arguments after `batch`
+fn parse_batch_args(mut args: impl Iterator<Item = String>) -> Result<BatchCommand, CliError> {
+    let mut manifest = None;
+    let mut batch = BatchCommand {
+        manifest: PathBuf::new(),
+        jobs: 1,
+        overwrite: false,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--jobs" => {
+                let jobs = args.next().ok_or_else(|| usage("--jobs needs a count"))?;
+                batch.jobs = parse_counts(&arg, &jobs)?[0];
+            }
+            "--force" => batch.overwrite = true,
+            _ if !arg.starts_with("--") && manifest.is_none() => {
+                manifest = Some(PathBuf::from(arg))
+            }
+            _ => return Err(usage(format!("unknown batch argument {:?}", arg))),
+        }
+    }
+    batch.manifest = manifest.ok_or_else(|| usage("batch needs a manifest file"))?;
+    Ok(batch)
+}
+
+/// Parses the arguments after `gen`, setting the output options of `options`
+fn parse_gen_args(
+    mut args: impl Iterator<Item = String>,
+    options: &mut Options,
+) -> Result<GenCommand, CliError> {
+    let (mut rows, mut cols, mut kind, mut density) = (None, None, String::from("dense"), 0.1);
+    let mut gen = GenCommand {
+        rows: 0,
+        cols: 0,
+        min: -9,
+        max: 9,
+        kind: MatrixKind::Dense,
+        seed: 0,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| usage(format!("{} needs a value", arg)))
+        };
+        let invalid = |value: &str| usage(format!("{}: invalid value {:?}", arg, value));
+        match arg.as_str() {
+            "--rows" => rows = Some(parse_counts(&arg, &value()?)?[0]),
+            "--cols" => cols = Some(parse_counts(&arg, &value()?)?[0]),
+            "--min" | "--max" | "--seed" => {
+                let v = value()?;
+                match arg.as_str() {
+                    "--min" => gen.min = v.parse().map_err(|_| invalid(&v))?,
+                    "--max" => gen.max = v.parse().map_err(|_| invalid(&v))?,
+                    _ => gen.seed = v.parse().map_err(|_| invalid(&v))?,
+                }
+            }
+            "--kind" => kind = value()?,
+            "--density" => {
+                let v = value()?;
+                density = v.parse().map_err(|_| invalid(&v))?;
+            }
+            "--output" => options.output = Some(PathBuf::from(value()?)),
+            "--format" => {
+                let name = value()?;
+                options.write.format = Some(
+                    FileFormat::from_name(&name)
+                        .ok_or_else(|| usage(format!("unknown format {:?}", name)))?,
+                );
+            }
+            "--force" => options.write.overwrite = true,
+            _ => return Err(usage(format!("unknown gen argument {:?}", arg))),
+        }
+    }
+    gen.rows = rows.ok_or_else(|| usage("gen needs --rows"))?;
+    gen.cols = cols.unwrap_or(gen.rows);
+    gen.kind = MatrixKind::from_name(&kind, density).ok_or_else(|| {
+        usage(format!(
+            "unknown kind {:?}, expected dense, identity, diagonal, symmetric or sparse",
+            kind
+        ))
+    })?;
+    Ok(gen)
+}
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, CliError> {
+    let mut options = Options::default();
+    let mut args = args.into_iter().peekable();
+    if args.peek().is_some_and(|arg| arg == "bench") {
+        args.next();
+        options.bench = Some(parse_bench_args(args)?);
+        return Ok(options);
+    }
+    if args.peek().is_some_and(|arg| arg == "batch") {
+        args.next();
+        options.batch = Some(parse_batch_args(args)?);
+        return Ok(options);
+    }
+    if args.peek().is_some_and(|arg| arg == "completions") {
+        args.next();
+        let shell = args.next().unwrap_or_default();
+        options.completions = Some(Shell::from_name(&shell).ok_or_else(|| {
+            usage(format!(
+                "completions needs a shell, one of {}",
+                SHELLS.join(", ")
+            ))
+        })?);
+        if let Some(arg) = args.next() {
+            return Err(usage(format!("unknown completions argument {:?}", arg)));
+        }
+        return Ok(options);
+    }
+    if args.peek().is_some_and(|arg| arg == "manpage") {
+        args.next();
+        if let Some(arg) = args.next() {
+            return Err(usage(format!("unknown manpage argument {:?}", arg)));
+        }
+        options.manpage = true;
+        return Ok(options);
+    }
+    if args.peek().is_some_and(|arg| arg == "repl") {
+        args.next();
+        options.repl = true;
+    }
+    if args.peek().is_some_and(|arg| arg == "verify") {
+        args.next();
+        options.verify = Some(parse_verify_args(args)?);
+        return Ok(options);
+    }
+    if args.peek().is_some_and(|arg| arg == "session") {
+        args.next();
+        options.session = Some(parse_session_args(args, &mut options)?);
+        return Ok(options);
+    }
+    if args.peek().is_some_and(|arg| arg == "watch") {
+        args.next();
+        options.watch = Some(parse_watch_args(args, &mut options)?);
+        return Ok(options);
+    }
+    if args.peek().is_some_and(|arg| arg == "gen") {
+        args.next();
+        options.gen = Some(parse_gen_args(args, &mut options)?);
+        return Ok(options);
+    }
+    let mut seed_given = false;
+    let mut quiet = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--autosave" => {
+                let dir = args
+                    .next()
+                    .ok_or_else(|| usage("--autosave needs a directory"))?;
+                options.autosave_dir = Some(PathBuf::from(dir));
+            }
+            "--out-of-core" => options.out_of_core = true,
+            "--memory-limit" => {
+                let limit = args
+                    .next()
+                    .ok_or_else(|| usage("--memory-limit needs a size"))?;
+                options.memory_limit = parse_memory_limit(&limit)
+                    .ok_or_else(|| usage(format!("invalid memory limit {:?}", limit)))?;
+            }
+            "--stdin" => options.stdin = true,
+            "--max-input-size" => {
+                let limit = args
+                    .next()
+                    .ok_or_else(|| usage("--max-input-size needs a size"))?;
+                options.read.max_size = parse_memory_limit(&limit)
+                    .ok_or_else(|| usage(format!("invalid input size {:?}", limit)))?;
+            }
+            "--format" => {
+                let name = args
+                    .next()
+                    .ok_or_else(|| usage("--format needs a format"))?;
+                let format = FileFormat::from_name(&name)
+                    .ok_or_else(|| usage(format!("unknown format {:?}", name)))?;
+                // output-only formats leave the inputs' formats to their extensions
+                if format.is_readable() {
+                    options.read.format = Some(format);
+                }
+                options.write.format = Some(format);
+            }
+            "--csv-header" => options.read.csv_header = true,
+            "--compress" => {
+                options.read.compressed = true;
+                options.write.compress = true;
+            }
+            "--compression-level" => {
+                let level = args
+                    .next()
+                    .ok_or_else(|| usage("--compression-level needs a level"))?;
+                options.write.compression_level = level
+                    .parse()
+                    .ok()
+                    .filter(|&n| n <= MAX_LEVEL)
+                    .ok_or_else(|| {
+                        usage(format!(
+                            "--compression-level must be 0 to {}, not {:?}",
+                            MAX_LEVEL, level
+                        ))
+                    })?;
+            }
+            "--delimiter" => {
+                let delimiter = args
+                    .next()
+                    .ok_or_else(|| usage("--delimiter needs a character"))?;
+                let c = parse_delimiter(&delimiter)?;
+                options.read.delimiter = Some(c);
+                options.write.delimiter = Some(c);
+            }
+            "--latex-env" => {
+                let env = args
+                    .next()
+                    .ok_or_else(|| usage("--latex-env needs an environment name"))?;
+                if env.is_empty() || !env.chars().all(|c| c.is_ascii_alphabetic() || c == '*') {
+                    return Err(usage(format!("invalid LaTeX environment {:?}", env)));
+                }
+                options.write.latex.environment = env;
+            }
+            "--latex-brackets" => options.write.latex.brackets = true,
+            "--latex-max-cols" => {
+                let max = args
+                    .next()
+                    .ok_or_else(|| usage("--latex-max-cols needs a column count"))?;
+                options.write.latex.max_cols = Some(
+                    max.parse()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .ok_or_else(|| usage(format!("invalid column count {:?}", max)))?,
+                );
+            }
+            "--latex-transpose" => options.write.latex.wide = WideMatrix::Transpose,
+            "--locale" => {
+                let name = args
+                    .next()
+                    .ok_or_else(|| usage("--locale needs a locale name"))?;
+                options.locale = NumberLocale::from_name(&name)
+                    .ok_or_else(|| usage(format!("unknown locale {:?}", name)))?;
+            }
+            "--markdown-max-rows" | "--markdown-max-cols" => {
+                let max = args
+                    .next()
+                    .ok_or_else(|| usage(format!("{} needs a count", arg)))?;
+                let max = max
+                    .parse()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| usage(format!("invalid count {:?}", max)))?;
+                if arg == "--markdown-max-rows" {
+                    options.write.markdown.max_rows = Some(max);
+                } else {
+                    options.write.markdown.max_cols = Some(max);
+                }
+            }
+            "--force" => options.write.overwrite = true,
+            "--engine" => {
+                let name = args
+                    .next()
+                    .ok_or_else(|| usage("--engine needs an engine name"))?;
+                check_engine(&name)?;
+                options.engine = name;
+            }
+            "--threads" => {
+                let threads = args
+                    .next()
+                    .ok_or_else(|| usage("--threads needs a thread count"))?;
+                options.threads = Some(
+                    threads
+                        .parse()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .ok_or_else(|| usage(format!("invalid thread count {:?}", threads)))?,
+                );
+            }
+            "--pretty" => options.pretty = true,
+            "--borders" => options.layout.borders = true,
+            "--headers" => options.layout.headers = true,
+            "--max-width" => {
+                let max = args
+                    .next()
+                    .ok_or_else(|| usage("--max-width needs a width"))?;
+                options.layout.max_width = Some(
+                    max.parse()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .ok_or_else(|| usage(format!("invalid width {:?}", max)))?,
+                );
+            }
+            "-q" | "--quiet" => quiet = true,
+            "-v" | "--verbose" => options.verbosity = Verbosity::Verbose,
+            "--json" => options.json = true,
+            "--safe" => options.safe = true,
+            "--resume" => {
+                let path = args.next().ok_or_else(|| usage("--resume needs a path"))?;
+                options.resume = Some(PathBuf::from(path));
+            }
+            "--size" => {
+                let size = args.next().ok_or_else(|| usage("--size needs a size"))?;
+                options.size = Some(Shape::parse(&size).ok_or_else(|| {
+                    usage(format!(
+                        "--size: invalid size {:?}, expected n, mxn or mxnxp",
+                        size
+                    ))
+                })?);
+            }
+            "--seed" => {
+                let seed = args.next().ok_or_else(|| usage("--seed needs a seed"))?;
+                options.seed = seed
+                    .parse()
+                    .map_err(|_| usage(format!("--seed: invalid seed {:?}", seed)))?;
+                seed_given = true;
+            }
+            "--checksum" => options.checksum = true,
+            "--log" => {
+                let path = args.next().ok_or_else(|| usage("--log needs a path"))?;
+                options.log = Some(PathBuf::from(path));
+            }
+            "--output" => {
+                let path = args.next().ok_or_else(|| usage("--output needs a path"))?;
+                options.output = Some(PathBuf::from(path));
+            }
+            _ if !arg.starts_with("--") => options.paths.push(PathBuf::from(arg)),
+            _ => return Err(usage(format!("unknown argument {:?}", arg))),
+        }
+    }
+    if options.threads.is_some() && !is_multithreaded(&options.engine) {
+        return Err(usage(format!(
+            "--threads only applies to the threaded engine, not {}",
+            options.engine
+        )));
+    }
+    if !options.pretty && options.layout != PrettyOptions::default() {
+        return Err(usage(
+            "--borders, --headers and --max-width only apply with --pretty",
+        ));
+    }
+    if options.pretty
+        && options
+            .write
+            .format
+            .is_some_and(|format| format != FileFormat::Text)
+    {
+        return Err(usage("--pretty only applies to text output"));
+    }
+    if options.resume.is_some() && options.is_batch() {
+        return Err(usage("--resume only applies to the TUI"));
+    }
+    if options.safe && options.is_batch() {
+        return Err(usage("--safe only applies to the TUI"));
+    }
+    if options.safe
+        && (options.autosave_dir.is_some() || options.resume.is_some() || options.log.is_some())
+    {
+        return Err(usage(
+            "--autosave, --resume and --log write files, which --safe disables",
+        ));
+    }
+    if quiet {
+        if options.verbosity == Verbosity::Verbose {
+            return Err(usage("--quiet and --verbose can't be combined"));
+        }
+        options.verbosity = Verbosity::Quiet;
+    }
+    if options.json {
+        let multiplies = (!options.paths.is_empty() || options.stdin) && !options.out_of_core;
+        if !(multiplies || options.size.is_some())
+            || options.gen.is_some()
+            || options.batch.is_some()
+            || options.watch.is_some()
+            || options.session.is_some()
+            || options.verify.is_some()
+        {
+            return Err(usage("--json only applies to multiplying files or --size"));
+        }
+        if options.output.is_none()
+            && (options.pretty || options.write.format.is_some() || options.write.compress)
+        {
+            return Err(usage(
+                "--json prints the product itself, so without --output it takes no --pretty, --format or --compress",
+            ));
+        }
+    }
+    if seed_given && options.size.is_none() {
+        return Err(usage("--seed only applies with --size"));
+    }
+    if options.checksum && (options.out_of_core || options.repl) {
+        return Err(usage("--checksum doesn't apply to --out-of-core or repl"));
+    }
+    if options.size.is_some()
+        && (!options.paths.is_empty()
+            || options.stdin
+            || options.out_of_core
+            || options.output.is_some()
+            || options.repl)
+    {
+        return Err(usage(
+            "--size multiplies random operands and takes no paths, --stdin, --out-of-core or --output",
+        ));
+    }
+    if options.repl
+        && (!options.paths.is_empty()
+            || options.stdin
+            || options.out_of_core
+            || options.output.is_some())
+    {
+        return Err(usage(
+            "repl reads commands from stdin and takes no paths, --stdin, --out-of-core or --output",
+        ));
+    }
+    Ok(options)
+}
+
+/// Starts logging to `--log`, if given, at the level named by `level`
+/// (the value of `MATRIXMULT_LOG`, `info` if unset)
+pub fn init_log(options: &Options, level: Option<&str>) -> Result<(), CliError> {
+    let Some(path) = &options.log else {
+        return Ok(());
+    };
+    let level = match level {
+        Some(name) => Level::from_name(name).ok_or_else(|| {
+            usage(format!(
+                "{} must be error, warn, info, debug or trace, not {:?}",
+                LOG_ENV, name
+            ))
+        })?,
+        None => Level::Info,
+    };
+    log::init(path, level).map_err(io_error(path))
+}
+
+/// The path that stands for stdin
+const STDIN_PATH: &str = "-";
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> CliError + '_ {
+    move |err| CliError::Io {
+        path: path.to_path_buf(),
+        err,
+    }
+}
+
+/// Reads all of `reader`, failing once more than `limit` bytes have been
+/// read rather than buffering an unbounded input
+fn read_limited(reader: impl Read, path: &Path, limit: usize) -> Result<Vec<u8>, CliError> {
+    let mut bytes = vec![];
+    reader
+        .take(limit as u64 + 1)
+        .read_to_end(&mut bytes)
+        .map_err(io_error(path))?;
+    if bytes.len() > limit {
+        return Err(CliError::InputTooLarge {
+            path: path.to_path_buf(),
+            limit,
+        });
+    }
+    Ok(bytes)
+}
+
+/// Parses the contents of an input in the given format
+fn parse_input(
+    bytes: &[u8],
+    path: &Path,
+    format: FileFormat,
+    options: &ReadOptions,
+) -> Result<Matrix, CliError> {
+    if format == FileFormat::Npy {
+        return parse_npy_matrix(bytes).map_err(|err| CliError::Npy {
+            path: path.to_path_buf(),
+            err,
+        });
+    }
+    if format == FileFormat::Binary {
+        return parse_binary(bytes).map_err(|err| CliError::BinaryFile {
+            path: path.to_path_buf(),
+            err,
+        });
+    }
+    let text = std::str::from_utf8(bytes).map_err(|_| CliError::Io {
+        path: path.to_path_buf(),
+        err: io::Error::new(io::ErrorKind::InvalidData, "input is not valid UTF-8"),
+    })?;
+    let format_error = |err| CliError::Format {
+        path: path.to_path_buf(),
+        err,
+    };
+    match format {
+        FileFormat::Text => parse_text_matrix(text).map_err(|err| CliError::Parse {
+            path: path.to_path_buf(),
+            err,
+        }),
+        FileFormat::Csv | FileFormat::Tsv => {
+            let delimiter = options.delimiter.unwrap_or(format.default_delimiter());
+            parse_csv(text, options.csv_header, delimiter).map_err(format_error)
+        }
+        FileFormat::MatrixMarket => parse_matrix_market(text).map_err(format_error),
+        FileFormat::Json => parse_json_matrix(text).map_err(format_error),
+        FileFormat::Npy | FileFormat::Binary => unreachable!("handled above"),
+        FileFormat::Latex | FileFormat::Markdown => Err(usage(format!(
+            "{}: {} is an output-only format",
+            path.display(),
+            format.name()
+        ))),
+    }
+}
+
+/// Reads and parses a matrix file, or stdin if `path` is `-`. The format is
+/// inferred from the extension unless `options` sets one
+pub fn read_matrix_file(path: &Path, options: &ReadOptions) -> Result<Matrix, CliError> {
+    let _span = log::span(
+        Level::Debug,
+        "parse",
+        format_args!("path={}", path.display()),
+    );
+    let limit = options.max_size;
+    let input: Box<dyn Read> = if path == Path::new(STDIN_PATH) {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(File::open(path).map_err(io_error(path))?)
+    };
+    // the limit applies to the decompressed size
+    let bytes = if options.compressed || is_gzip_path(path) {
+        read_limited(GzDecoder::new(input), path, limit)?
+    } else {
+        read_limited(input, path, limit)?
+    };
+    let format = options
+        .format
+        .unwrap_or_else(|| FileFormat::from_path(path));
+    parse_input(&bytes, path, format, options)
+}
+
+/// Reads two matrices separated by one or more blank lines, stopping as soon
+/// as more than the size limit has been read
+pub fn read_matrix_pair(
+    reader: impl BufRead,
+    options: &ReadOptions,
+) -> Result<(Matrix, Matrix), CliError> {
+    let path = Path::new(STDIN_PATH);
+    if let Some(format @ (FileFormat::Npy | FileFormat::Binary)) = options.format {
+        return Err(usage(format!(
+            "--stdin can't read {} input, use - for one operand instead",
+            format.name()
+        )));
+    }
+    let limit = options.max_size;
+    let mut blocks: Vec<String> = vec![];
+    let mut in_block = false;
+    let mut read = 0;
+
+    for line in reader.lines() {
+        let line = line.map_err(io_error(path))?;
+        read += line.len() + 1;
+        if read > limit {
+            return Err(CliError::InputTooLarge {
+                path: path.to_path_buf(),
+                limit,
+            });
+        }
+        if line.trim().is_empty() {
+            in_block = false;
+            continue;
+        }
+        if !in_block {
+            blocks.push(String::new());
+            in_block = true;
+        }
+        let block = blocks.last_mut().unwrap();
+        block.push_str(&line);
+        block.push('\n');
+    }
+
+    let [a, b] = blocks.as_slice() else {
+        return Err(CliError::StdinMatrices {
+            found: blocks.len(),
+        });
+    };
+    let format = options.format.unwrap_or(FileFormat::Text);
+    Ok((
+        parse_input(a.as_bytes(), path, format, options)?,
+        parse_input(b.as_bytes(), path, format, options)?,
+    ))
+}
+
+/// Runs the non-interactive modes, writing results to `out`
+pub fn run(options: &Options, out: &mut impl Write) -> Result<(), CliError> {
+    let write_out = |out: &mut dyn Write, bytes: &[u8]| {
+        log::debug_assert_stdout_free();
+        out.write_all(bytes).map_err(|err| CliError::Io {
+            path: PathBuf::from("<stdout>"),
+            err,
+        })
+    };
+    if let Some(bench) = &options.bench {
+        let results = run_bench(&bench.options)?;
+        let text = if bench.json {
+            format_bench_json(&results, bench.options.seed)
+        } else {
+            format_bench_table(&results)
+        };
+        return write_out(out, text.as_bytes());
+    }
+    if let Some(shell) = options.completions {
+        return write_out(out, format_completions(shell).as_bytes());
+    }
+    if options.manpage {
+        return write_out(out, format_manpage().as_bytes());
+    }
+    if options.repl {
+        return run_repl(options, out);
+    }
+    if let Some(shape) = options.size {
+        let threads = threads(options);
+        let timing = time_product(shape, &options.engine, threads, options.seed)?;
+        let warnings = thread_warnings(timing.threads);
+        if options.json {
+            let envelope = Envelope {
+                result: None,
+                output: None,
+                format: None,
+                rows: shape.0[0],
+                cols: shape.0[2],
+                engine: timing.engine,
+                threads: timing.threads,
+                elapsed: timing.elapsed,
+                checksum: options.checksum.then(|| timing.checksum.clone()),
+                seed: Some(options.seed),
+                warnings,
+            };
+            return write_out(out, envelope.to_json().as_bytes());
+        }
+        if options.verbosity == Verbosity::Verbose {
+            for warning in &warnings {
+                eprintln!("warning: {}", warning);
+            }
+        }
+        if options.checksum && options.verbosity == Verbosity::Quiet {
+            return write_out(out, format!("{}\n", timing.checksum).as_bytes());
+        }
+        let engine = if is_multithreaded(timing.engine) {
+            format!("{} ({} threads)", timing.engine, timing.threads)
+        } else {
+            String::from(timing.engine)
+        };
+        let mut text = format!(
+            "{} with {}, seed {}: {:.3} ms\n",
+            shape,
+            engine,
+            options.seed,
+            timing.elapsed.as_secs_f64() * 1000.0
+        );
+        if options.checksum {
+            text.push_str(&format!("checksum: {}\n", timing.checksum));
+        }
+        return write_out(out, text.as_bytes());
+    }
+    if let Some(verify) = &options.verify {
+        verify_backends(verify)?;
+        let text = format!(
+            "{} engines agree on {} pairs of {}x{} matrices (seeds {} to {})\n",
+            verify.engines.len(),
+            verify.iterations,
+            verify.size,
+            verify.size,
+            verify.seed,
+            verify.seed.wrapping_add(verify.iterations as u64 - 1)
+        );
+        return write_out(out, text.as_bytes());
+    }
+    if let Some(gen) = &options.gen {
+        let m = random_int_matrix(gen.rows, gen.cols, (gen.min, gen.max), gen.kind, gen.seed)?;
+        return write_answer(options, &Answer::Int(m), out);
+    }
+    if let Some(session) = &options.session {
+        return run_session(session, options, out);
+    }
+    if let Some(watch) = &options.watch {
+        let mut result = Ok(());
+        watch_paths(
+            &[&watch.a, &watch.b],
+            watch.interval,
+            watch.debounce,
+            || {
+                let line = match recompute(watch, options) {
+                    Ok(format) => format!(
+                        "{} wrote {} as {}\n",
+                        time_of_day(),
+                        options.output.as_deref().unwrap_or(Path::new("")).display(),
+                        format.name()
+                    ),
+                    Err(err) => {
+                        format!("{} error: {} (waiting for a change)\n", time_of_day(), err)
+                    }
+                };
+                result = write_out(out, line.as_bytes()).and_then(|()| {
+                    out.flush().map_err(|err| CliError::Io {
+                        path: PathBuf::from("<stdout>"),
+                        err,
+                    })
+                });
+                result.is_ok()
+            },
+        );
+        return result;
+    }
+    if let Some(batch) = &options.batch {
+        let path = &batch.manifest;
+        let text = std::fs::read_to_string(path).map_err(io_error(path))?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        let jobs = parse_manifest(&text, base).map_err(|err| CliError::Format {
+            path: path.clone(),
+            err,
+        })?;
+        let write = WriteOptions {
+            overwrite: batch.overwrite,
+            ..options.write.clone()
+        };
+        let reports = run_jobs(&jobs, batch.jobs, &options.read, &write);
+        write_out(out, format_job_summary(&reports).as_bytes())?;
+        let failed = reports.iter().filter(|r| r.result.is_err()).count();
+        if failed > 0 {
+            return Err(CliError::JobsFailed {
+                failed,
+                total: reports.len(),
+            });
+        }
+        return Ok(());
+    }
+    if options.out_of_core {
+        let (a, b, result) = match (options.paths.as_slice(), &options.output) {
+            ([a, b, result], None) | ([a, b], Some(result)) => (a, b, result),
+            _ => {
+                return Err(usage(
+                    "--out-of-core needs two input files and an output file",
+                ))
+            }
+        };
+        if options.read.compressed || [a, b, result].iter().any(|path| is_gzip_path(path)) {
+            return Err(usage("--out-of-core only works on uncompressed files"));
+        }
+        if is_same_file(result, a) || is_same_file(result, b) {
+            return Err(usage(format!(
+                "{} is an input, it can't also be the output",
+                result.display()
+            )));
+        }
+        if !options.write.overwrite && result.exists() {
+            return Err(ExportError::Exists(result.clone()).into());
+        }
+        return Ok(multiply_files(a, b, result, options.memory_limit)?);
+    }
+
+    let (a, b) = if options.stdin {
+        if !options.paths.is_empty() {
+            return Err(usage("--stdin doesn't take input files"));
+        }
+        if options.read.compressed {
+            read_matrix_pair(BufReader::new(GzDecoder::new(io::stdin())), &options.read)?
+        } else {
+            read_matrix_pair(io::stdin().lock(), &options.read)?
+        }
+    } else {
+        let [a, b] = options.paths.as_slice() else {
+            return Err(usage("expected two input files"));
+        };
+        if a == b && a == Path::new(STDIN_PATH) {
+            return Err(usage("only one operand can be read from stdin"));
+        }
+        read_operands(a, b, &options.read, threads(options))?
+    };
+    let threads = threads(options);
+    let backend = find_backend(&options.engine, threads)
+        .ok_or_else(|| usage(format!("unknown engine {:?}", options.engine)))?;
+    let verbose = options.verbosity == Verbosity::Verbose;
+    if verbose {
+        eprintln!(
+            "a: {}x{}, b: {}x{}",
+            a.len(),
+            a.first().map_or(0, |row| row.len()),
+            b.len(),
+            b.first().map_or(0, |row| row.len())
+        );
+        eprintln!("engine: {}, threads: {}", backend.name(), backend.threads());
+    }
+    log::log(
+        Level::Info,
+        "engine",
+        format_args!("engine={} threads={}", backend.name(), backend.threads()),
+    );
+    let (product, elapsed) = {
+        let _span = log::span(
+            Level::Info,
+            "computation",
+            format_args!(
+                "operation=multiply a={}x{} b={}x{}",
+                a.len(),
+                a.first().map_or(0, |row| row.len()),
+                b.len(),
+                b.first().map_or(0, |row| row.len())
+            ),
+        );
+        let started = Instant::now();
+        (backend.multiply(&a, &b)?, started.elapsed())
+    };
+    let (rows, cols) = (product.len(), product.first().map_or(0, |row| row.len()));
+    let warnings = thread_warnings(backend.threads());
+    if verbose {
+        eprintln!(
+            "product: {}x{} in {:.3} ms",
+            rows,
+            cols,
+            elapsed.as_secs_f64() * 1000.0
+        );
+        for warning in &warnings {
+            eprintln!("warning: {}", warning);
+        }
+    }
+    let answer = Answer::Int(product);
+    let checksum = if options.checksum {
+        Some(canonical_checksum(&answer)?)
+    } else {
+        None
+    };
+    if options.json {
+        let format = match &options.output {
+            Some(path) => Some(export_answer(path, Some(&answer), &options.write)?),
+            None => None,
+        };
+        let envelope = Envelope {
+            result: options.output.is_none().then_some(&answer),
+            output: options.output.as_deref(),
+            format,
+            rows,
+            cols,
+            engine: backend.name(),
+            threads: backend.threads(),
+            elapsed,
+            checksum,
+            seed: None,
+            warnings,
+        };
+        return write_out(out, envelope.to_json().as_bytes());
+    }
+    if let Some(checksum) = checksum {
+        if options.output.is_some() {
+            write_answer(options, &answer, out)?;
+        }
+        return write_out(out, format!("{}\n", checksum).as_bytes());
+    }
+    write_answer(options, &answer, out)
+}
+
+/// Whether `a` and `b` name the same existing file
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Things worth knowing about a run with `threads` threads that don't stop
+/// it
+fn thread_warnings(threads: usize) -> Vec<String> {
+    let cores = default_threads();
+    if threads > cores {
+        let available = if cores == 1 { "core is" } else { "cores are" };
+        vec![format!(
+            "{} threads but only {} {} available",
+            threads, cores, available
+        )]
+    } else {
+        vec![]
+    }
+}
+
+/// The threads the CLI computes on: `--threads`, or one per core
+fn threads(options: &Options) -> usize {
+    options.threads.unwrap_or_else(default_threads)
+}
+
+/// Reads the two operand files, at the same time unless there's only one
+/// thread to read them on, from `--threads 1` or a single core. An error
+/// reading `a` is reported before one reading `b`, as when they're read one
+/// after the other
+fn read_operands(
+    a: &Path,
+    b: &Path,
+    read: &ReadOptions,
+    threads: usize,
+) -> Result<(Matrix, Matrix), CliError> {
+    if threads <= 1 {
+        return Ok((read_matrix_file(a, read)?, read_matrix_file(b, read)?));
+    }
+    let (a, b) = thread::scope(|scope| {
+        let reader = scope.spawn(|| read_matrix_file(b, read));
+        let a = read_matrix_file(a, read);
+        let b = reader.join().unwrap_or_else(|_| {
+            Err(CliError::Io {
+                path: b.to_path_buf(),
+                err: io::Error::other("the thread reading it panicked"),
+            })
+        });
+        (a, b)
+    });
+    Ok((a?, b?))
+}
+
+/// Multiplies the inputs of `watch` and replaces `--output` with the product
+fn recompute(watch: &WatchCommand, options: &Options) -> Result<FileFormat, CliError> {
+    let a = read_matrix_file(&watch.a, &options.read)?;
+    let b = read_matrix_file(&watch.b, &options.read)?;
+    let threads = threads(options);
+    let product = find_backend(&options.engine, threads)
+        .ok_or_else(|| usage(format!("unknown engine {:?}", options.engine)))?
+        .multiply(&a, &b)?;
+    let output = options
+        .output
+        .as_deref()
+        .ok_or_else(|| usage("watch needs --output"))?;
+    let write = WriteOptions {
+        overwrite: true,
+        ..options.write.clone()
+    };
+    Ok(export_answer(output, Some(&Answer::Int(product)), &write)?)
+}
+
+/// `answer` as text for stdout, localized and laid out by `--pretty`
+pub fn format_stdout(options: &Options, answer: &Answer) -> String {
+    if options.pretty {
+        let cells: Vec<Vec<String>> = answer_cells(answer)
+            .iter()
+            .map(|row| row.iter().map(|x| options.locale.localize(x)).collect())
+            .collect();
+        format_pretty(&cells, &options.layout)
+    } else {
+        format_answer_localized(answer, &options.locale)
+    }
+}
+
+/// Writes a result to `--output`, or to `out` in the `--format` and
+/// `--locale` given
+fn write_answer(options: &Options, answer: &Answer, out: &mut impl Write) -> Result<(), CliError> {
+    if let Some(path) = &options.output {
+        let format = export_answer(path, Some(answer), &options.write)?;
+        if options.verbosity != Verbosity::Quiet {
+            eprintln!("wrote {} as {}", path.display(), format.name());
+        }
+        return Ok(());
+    }
+    let mut bytes = match options.write.format {
+        Some(format) if format != FileFormat::Text => {
+            render_answer(answer, format, &options.write)?
+        }
+        _ => format_stdout(options, answer).into_bytes(),
+    };
+    if options.write.compress {
+        bytes = gzip(&bytes, options.write.compression_level).map_err(|err| CliError::Io {
+            path: PathBuf::from("<stdout>"),
+            err,
+        })?;
+    }
+    log::debug_assert_stdout_free();
+    out.write_all(&bytes).map_err(|err| CliError::Io {
+        path: PathBuf::from("<stdout>"),
+        err,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::{parse_json, JsonValue};
+    use std::fs;
+
+    /// Writes `contents` to a file in the temp directory, named after the
+    /// test so parallel tests don't share files
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let path = temp_path(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("matrixmult-{}-{}", std::process::id(), name))
+    }
+
+    /// Exports `answer` to a fresh file called `name` and reads it back in
+    /// the format its extension names
+    fn round_trip(name: &str, answer: &Answer, write: &WriteOptions) -> Matrix {
+        let path = temp_path(name);
+        let _ = fs::remove_file(&path);
+        export_answer(&path, Some(answer), write).unwrap();
+        let m = read_matrix_file(&path, &ReadOptions::default()).unwrap();
+        fs::remove_file(&path).unwrap();
+        m
+    }
+
+    fn sample() -> Matrix {
+        vec![vec![1, -2, 3], vec![0, 40, i64::MIN], vec![7, 0, i64::MAX]]
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn operands_read_the_same_on_one_or_two_threads() {
+        let a = temp_file("operands-a.txt", &"1 2 3\n".repeat(200));
+        let b = temp_file("operands-b.csv", "4,5\n6,7\n8,9\n");
+        let read = ReadOptions::default();
+        let sequential = read_operands(&a, &b, &read, 1).unwrap();
+        assert_eq!(read_operands(&a, &b, &read, 2).unwrap(), sequential);
+        assert_eq!(sequential.0.len(), 200);
+        assert_eq!(sequential.1, vec![vec![4, 5], vec![6, 7], vec![8, 9]]);
+    }
+
+    #[test]
+    fn operand_errors_name_the_file_that_failed() {
+        let good = temp_file("operands-good.txt", "1 2\n");
+        let bad = temp_file("operands-bad.txt", "1 x\n");
+        for threads in [1, 2] {
+            let err = read_operands(&good, &bad, &ReadOptions::default(), threads).unwrap_err();
+            assert!(err.to_string().contains("operands-bad.txt"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn the_gpu_engine_is_rejected_as_not_there_yet() {
+        let err = parse_args(args(&["--engine", "gpu", "a.txt", "b.txt"])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "there is no GPU engine yet, every engine runs on the CPU"
+        );
+    }
+
+    #[test]
+    fn unavailable_engines_are_usage_errors() {
+        for engine in ["rayon", "gpu", "fast"] {
+            let err = parse_args(args(&["--engine", engine, "a.txt", "b.txt"])).unwrap_err();
+            assert_eq!(err.exit_code(), EXIT_USAGE, "{}", engine);
+        }
+    }
+
+    #[test]
+    fn overflowing_products_exit_with_the_overflow_code() {
+        let a = temp_file("overflow-a.txt", "2147483647 2147483647 2147483647\n");
+        let b = temp_file("overflow-b.txt", &"2147483647 2147483647\n".repeat(3));
+        for engine in ENGINES {
+            let options = parse_args(args(&[
+                "--engine",
+                engine,
+                a.to_str().unwrap(),
+                b.to_str().unwrap(),
+            ]))
+            .unwrap();
+            let err = run(&options, &mut vec![]).unwrap_err();
+            assert_eq!(err.exit_code(), EXIT_OVERFLOW, "{}", engine);
+        }
+    }
+
+    #[test]
+    fn two_files_print_their_product() {
+        let a = temp_file("product-a.txt", "1 2\n3 4\n");
+        let b = temp_file("product-b.txt", "5 6\n7 8\n");
+        let options = parse_args(args(&[a.to_str().unwrap(), b.to_str().unwrap()])).unwrap();
+        let mut out = vec![];
+        run(&options, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "19 22\n43 50\n");
+    }
+
+    #[test]
+    fn file_arguments_must_come_in_pairs_that_exist() {
+        let a = temp_file("pair-a.txt", "1 2\n");
+        let options = parse_args(args(&[a.to_str().unwrap()])).unwrap();
+        let err = run(&options, &mut vec![]).unwrap_err();
+        assert_eq!(err.exit_code(), EXIT_USAGE);
+
+        let missing = std::env::temp_dir().join("matrixmult-no-such-file.txt");
+        let options = parse_args(args(&[a.to_str().unwrap(), missing.to_str().unwrap()])).unwrap();
+        let err = run(&options, &mut vec![]).unwrap_err();
+        assert_eq!(err.exit_code(), EXIT_IO);
+    }
+
+    #[test]
+    fn stdin_holds_two_matrices_separated_by_blank_lines() {
+        let input = "1 2\n3 4\n\n\n5 6\n7 8\n";
+        let (a, b) = read_matrix_pair(input.as_bytes(), &ReadOptions::default()).unwrap();
+        assert_eq!(a, vec![vec![1, 2], vec![3, 4]]);
+        assert_eq!(b, vec![vec![5, 6], vec![7, 8]]);
+
+        for input in ["1 2\n", "1\n\n2\n\n3\n"] {
+            let err = read_matrix_pair(input.as_bytes(), &ReadOptions::default()).unwrap_err();
+            assert!(matches!(err, CliError::StdinMatrices { .. }), "{}", err);
+        }
+    }
+
+    #[test]
+    fn stdin_stops_reading_past_the_size_limit() {
+        let read = ReadOptions {
+            max_size: 8,
+            ..ReadOptions::default()
+        };
+        let err = read_matrix_pair("1 2 3 4 5\n\n1\n".as_bytes(), &read).unwrap_err();
+        assert!(
+            matches!(err, CliError::InputTooLarge { limit: 8, .. }),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn csv_exports_read_back_unchanged() {
+        let answer = Answer::Int(sample());
+        assert_eq!(
+            round_trip("export.csv", &answer, &WriteOptions::default()),
+            sample()
+        );
+        let path = temp_path("export-exists.csv");
+        fs::write(&path, "1\n").unwrap();
+        assert!(matches!(
+            export_answer(&path, Some(&answer), &WriteOptions::default()),
+            Err(ExportError::Exists(_))
+        ));
+        assert!(matches!(
+            export_answer(&path, None, &WriteOptions::default()),
+            Err(ExportError::NothingToExport)
+        ));
+    }
+
+    #[test]
+    fn json_exports_read_back_unchanged() {
+        let answer = Answer::Int(sample());
+        assert_eq!(
+            round_trip("export.json", &answer, &WriteOptions::default()),
+            sample()
+        );
+    }
+
+    #[test]
+    fn matrix_market_exports_read_back_unchanged() {
+        let answer = Answer::Int(sample());
+        assert_eq!(
+            round_trip("export.mtx", &answer, &WriteOptions::default()),
+            sample()
+        );
+    }
+
+    #[test]
+    fn gzip_exports_read_back_unchanged() {
+        let answer = Answer::Int(sample());
+        assert_eq!(
+            round_trip("export.csv.gz", &answer, &WriteOptions::default()),
+            sample()
+        );
+        let write = WriteOptions {
+            compress: true,
+            compression_level: 0,
+            ..WriteOptions::default()
+        };
+        let path = temp_path("export-compressed.txt");
+        let _ = fs::remove_file(&path);
+        export_answer(&path, Some(&answer), &write).unwrap();
+        assert_eq!(&fs::read(&path).unwrap()[..2], [0x1f, 0x8b]);
+        let read = ReadOptions {
+            compressed: true,
+            ..ReadOptions::default()
+        };
+        assert_eq!(read_matrix_file(&path, &read).unwrap(), sample());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn npy_exports_read_back_unchanged() {
+        let answer = Answer::Int(sample());
+        assert_eq!(
+            round_trip("export.npy", &answer, &WriteOptions::default()),
+            sample()
+        );
+    }
+
+    #[test]
+    fn output_files_get_the_format_of_their_extension() {
+        let a = temp_file("output-a.txt", "1 2\n3 4\n");
+        let b = temp_file("output-b.txt", "5 6\n7 8\n");
+        let out = temp_path("output.md");
+        let _ = fs::remove_file(&out);
+        let options = parse_args(args(&[
+            "--output",
+            out.to_str().unwrap(),
+            a.to_str().unwrap(),
+            b.to_str().unwrap(),
+        ]))
+        .unwrap();
+        let mut stdout = vec![];
+        run(&options, &mut stdout).unwrap();
+        assert_eq!(
+            fs::read_to_string(&out).unwrap(),
+            "| 1 | 2 |\n| ---: | ---: |\n| 19 | 22 |\n| 43 | 50 |\n"
+        );
+        fs::remove_file(&out).unwrap();
+    }
+
+    #[test]
+    fn threads_need_a_positive_count_and_the_threaded_engine() {
+        for count in ["0", "-1", "many"] {
+            let err = parse_args(args(&[
+                "--engine",
+                "threaded",
+                "--threads",
+                count,
+                "a",
+                "b",
+            ]))
+            .unwrap_err();
+            assert_eq!(err.exit_code(), EXIT_USAGE, "{}", count);
+        }
+        let err = parse_args(args(&["--threads", "2", "a", "b"])).unwrap_err();
+        assert!(err.to_string().contains("not narrow"), "{}", err);
+
+        let options =
+            parse_args(args(&["--engine", "threaded", "--threads", "3", "a", "b"])).unwrap();
+        assert_eq!(
+            (options.engine.as_str(), threads(&options)),
+            ("threaded", 3)
+        );
+        let options = parse_args(args(&["a", "b"])).unwrap();
+        assert_eq!(
+            (options.engine.as_str(), threads(&options)),
+            (DEFAULT_ENGINE, default_threads())
+        );
+    }
+
+    #[test]
+    fn more_threads_than_cores_is_a_warning() {
+        assert!(thread_warnings(1).is_empty());
+        let warnings = thread_warnings(default_threads() + 1);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("available"), "{:?}", warnings);
+    }
+
+    /// Runs the command line `argv`, returning the error it fails with
+    fn run_err(argv: &[&str]) -> CliError {
+        match parse_args(args(argv)) {
+            Ok(options) => run(&options, &mut vec![]).unwrap_err(),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn failures_exit_with_codes_by_kind() {
+        let a = temp_file("codes-a.txt", "1 2\n3 4\n");
+        let bad = temp_file("codes-bad.txt", "1 x\n");
+        let tall = temp_file("codes-tall.txt", "1\n2\n3\n");
+        let (a, bad, tall) = (
+            a.to_str().unwrap(),
+            bad.to_str().unwrap(),
+            tall.to_str().unwrap(),
+        );
+        assert_eq!(run_err(&["--bogus"]).exit_code(), EXIT_USAGE);
+        assert_eq!(run_err(&[a, bad]).exit_code(), EXIT_PARSE);
+        assert_eq!(run_err(&[a, tall]).exit_code(), EXIT_SHAPE);
+        assert_eq!(run_err(&[a, "/nonexistent/b.txt"]).exit_code(), EXIT_IO);
+    }
+
+    #[test]
+    fn json_errors_carry_the_variant_code_and_position() {
+        let bad = temp_file("json-error-bad.txt", "1 2\n3 x\n");
+        let a = temp_file("json-error-a.txt", "1\n");
+        let err = run_err(&[a.to_str().unwrap(), bad.to_str().unwrap()]);
+        let text = format_error(&err, ErrorFormat::Json);
+        let json = parse_json(&text).unwrap();
+        let field = |key: &str| json.get(key).map(|value| value.value.clone());
+        assert_eq!(
+            field("variant"),
+            Some(JsonValue::String(String::from("Parse")))
+        );
+        assert_eq!(
+            field("exit_code"),
+            Some(JsonValue::Number(String::from("3")))
+        );
+        assert_eq!(field("row"), Some(JsonValue::Number(String::from("1"))));
+        assert_eq!(field("col"), Some(JsonValue::Number(String::from("1"))));
+        assert_eq!(field("token"), Some(JsonValue::String(String::from("x"))));
+        assert!(
+            matches!(field("path"), Some(JsonValue::String(path)) if path.ends_with("json-error-bad.txt"))
+        );
+        assert_eq!(
+            format_error(&err, ErrorFormat::Text),
+            format!("error: {}", err)
+        );
+    }
+
+    #[test]
+    fn error_format_is_taken_from_anywhere_in_the_arguments() {
+        let (format, rest) =
+            split_error_format(args(&["a", "--error-format", "json", "b"])).unwrap();
+        assert_eq!((format, rest), (ErrorFormat::Json, args(&["a", "b"])));
+        assert!(split_error_format(args(&["--error-format", "yaml"])).is_err());
+        assert!(split_error_format(args(&["--error-format"])).is_err());
+    }
+
+    #[test]
+    fn binary_exports_read_back_unchanged() {
+        let answer = Answer::Int(sample());
+        assert_eq!(
+            round_trip("export.mmb", &answer, &WriteOptions::default()),
+            sample()
+        );
+    }
+
+    #[test]
+    fn watch_arguments() {
+        let options = parse_args(args(&[
+            "watch",
+            "a.txt",
+            "b.txt",
+            "--output",
+            "c.csv",
+            "--interval",
+            "50",
+            "--debounce",
+            "0",
+        ]))
+        .unwrap();
+        assert_eq!(
+            options.watch,
+            Some(WatchCommand {
+                a: PathBuf::from("a.txt"),
+                b: PathBuf::from("b.txt"),
+                interval: Duration::from_millis(50),
+                debounce: Duration::ZERO,
+            })
+        );
+        assert_eq!(options.output, Some(PathBuf::from("c.csv")));
+        for (argv, message) in [
+            (
+                &["watch", "a.txt", "--output", "c"][..],
+                "watch needs two input files",
+            ),
+            (
+                &["watch", "-", "b.txt", "--output", "c"],
+                "watch can't read its inputs from stdin",
+            ),
+            (&["watch", "a.txt", "b.txt"], "watch needs --output"),
+            (
+                &["watch", "a.txt", "b.txt", "--output", "c", "--bogus"],
+                "unknown watch argument \"--bogus\"",
+            ),
+        ] {
+            match parse_args(args(argv)) {
+                Err(CliError::Usage(msg)) => assert_eq!(msg, message),
+                other => panic!("{:?} gave {:?}", argv, other),
+            }
+        }
+    }
+
+    #[test]
+    fn size_times_a_random_product() {
+        let options = parse_args(args(&["--size", "2x3x4", "--seed", "5"])).unwrap();
+        let mut out = vec![];
+        run(&options, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(
+            text.starts_with("2x3 * 3x4 with narrow, seed 5: "),
+            "{}",
+            text
+        );
+        assert!(text.ends_with(" ms\n"), "{}", text);
+
+        for (argv, message) in [
+            (&["--seed", "5", "a.txt", "b.txt"][..], "--seed only applies with --size"),
+            (&["--size", "0"], "--size: invalid size \"0\", expected n, mxn or mxnxp"),
+            (
+                &["--size", "3", "a.txt", "b.txt"],
+                "--size multiplies random operands and takes no paths, --stdin, --out-of-core or --output",
+            ),
+        ] {
+            match parse_args(args(argv)) {
+                Err(CliError::Usage(msg)) => assert_eq!(msg, message),
+                other => panic!("{:?} gave {:?}", argv, other),
+            }
+        }
+    }
+
+    #[test]
+    fn checksums_are_the_same_for_every_engine() {
+        let a = temp_file("checksum-a.txt", "1 2\n3 4\n");
+        let b = temp_file("checksum-b.txt", "5 6\n7 8\n");
+        let expected = format!(
+            "{}\n",
+            canonical_checksum(&Answer::Int(vec![vec![19, 22], vec![43, 50]])).unwrap()
+        );
+        for engine in ENGINES {
+            let options = parse_args(args(&[
+                "--checksum",
+                "--engine",
+                engine,
+                a.to_str().unwrap(),
+                b.to_str().unwrap(),
+            ]))
+            .unwrap();
+            let mut out = vec![];
+            run(&options, &mut out).unwrap();
+            assert_eq!(String::from_utf8(out).unwrap(), expected, "{}", engine);
+        }
+
+        let sized = |engine: &str| {
+            let options = parse_args(args(&[
+                "--size",
+                "6x5x4",
+                "--checksum",
+                "-q",
+                "--engine",
+                engine,
+            ]))
+            .unwrap();
+            let mut out = vec![];
+            run(&options, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(sized("naive").len(), 65);
+        assert_eq!(sized("naive"), sized("threaded"));
+        assert!(matches!(
+            parse_args(args(&["--checksum", "--out-of-core", "a", "b", "c"])),
+            Err(CliError::Usage(_))
+        ));
+    }
+
+    #[test]
+    fn sessions_import_and_export_matrix_files() {
+        let a = temp_file("session-a.csv", "1,2\n3,4\n");
+        let b = temp_file("session-b.txt", "5 6\n");
+        let session = temp_path("session.session");
+        let dir = temp_path("session-export");
+        let _ = fs::remove_file(&session);
+        let _ = fs::remove_dir_all(&dir);
+
+        let session_arg = session.to_str().unwrap();
+        let options = parse_args(args(&[
+            "session",
+            "import",
+            session_arg,
+            a.to_str().unwrap(),
+            b.to_str().unwrap(),
+            a.to_str().unwrap(),
+        ]))
+        .unwrap();
+        run(&options, &mut vec![]).unwrap();
+        let stem = |path: &Path| sanitize_name(&path.file_stem().unwrap().to_string_lossy());
+        let (a_name, b_name) = (stem(&a), stem(&b));
+        let matrices = read_session_file(&session).unwrap();
+        assert_eq!(matrices[&a_name], vec![vec![1, 2], vec![3, 4]]);
+        assert_eq!(matrices[&format!("{}-2", a_name)], matrices[&a_name]);
+        assert_eq!(matrices[&b_name], vec![vec![5, 6]]);
+
+        let options = parse_args(args(&[
+            "session",
+            "export",
+            session_arg,
+            dir.to_str().unwrap(),
+            "--format",
+            "csv",
+        ]))
+        .unwrap();
+        run(&options, &mut vec![]).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join(format!("{}.csv", b_name))).unwrap(),
+            "5,6\n"
+        );
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+
+        assert!(matches!(
+            parse_args(args(&["session", "export", session_arg, "out", "--force"])),
+            Err(CliError::Usage(_))
+        ));
+        assert!(matches!(
+            parse_args(args(&[
+                "--resume",
+                session_arg,
+                a.to_str().unwrap(),
+                b.to_str().unwrap()
+            ])),
+            Err(CliError::Usage(_))
+        ));
+        fs::remove_file(&session).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn safe_mode_only_runs_the_tui_without_files() {
+        assert!(parse_args(args(&["--safe"])).unwrap().safe);
+        for (argv, message) in [
+            (
+                &["--safe", "a.txt", "b.txt"][..],
+                "--safe only applies to the TUI",
+            ),
+            (
+                &["--safe", "--log", "log.txt"],
+                "--autosave, --resume and --log write files, which --safe disables",
+            ),
+            (
+                &["--safe", "--resume", "s.session"],
+                "--autosave, --resume and --log write files, which --safe disables",
+            ),
+        ] {
+            match parse_args(args(argv)) {
+                Err(CliError::Usage(msg)) => assert_eq!(msg, message),
+                other => panic!("{:?} gave {:?}", argv, other),
+            }
+        }
+    }
+
+    #[test]
+    fn tsv_and_delimited_csv_exports_read_back_unchanged() {
+        let answer = Answer::Int(sample());
+        assert_eq!(
+            round_trip("export.tsv", &answer, &WriteOptions::default()),
+            sample()
+        );
+        let path = temp_path("export-delimited.csv");
+        let _ = fs::remove_file(&path);
+        let write = WriteOptions {
+            delimiter: Some(';'),
+            ..WriteOptions::default()
+        };
+        export_answer(&path, Some(&answer), &write).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().starts_with("1;-2;3\n"));
+        let read = ReadOptions {
+            delimiter: Some(';'),
+            ..ReadOptions::default()
+        };
+        assert_eq!(read_matrix_file(&path, &read).unwrap(), sample());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn delimiters_are_one_character_that_isnt_part_of_a_number() {
+        assert_eq!(parse_delimiter("tab").unwrap(), '\t');
+        assert_eq!(parse_delimiter(";").unwrap(), ';');
+        for (text, why) in [
+            ("", "expected one character or tab"),
+            (";;", "expected one character or tab"),
+            ("-", "it can be part of a number"),
+            ("7", "it can be part of a number"),
+            ("\"", "it can't separate fields"),
+        ] {
+            match parse_delimiter(text) {
+                Err(CliError::Usage(msg)) => {
+                    assert_eq!(msg, format!("invalid delimiter {:?}: {}", text, why))
+                }
+                other => panic!("{:?} gave {:?}", text, other),
+            }
+        }
+        let options = parse_args(args(&["--delimiter", "tab", "a.csv", "b.csv"])).unwrap();
+        assert_eq!(
+            (options.read.delimiter, options.write.delimiter),
+            (Some('\t'), Some('\t'))
+        );
+    }
+
+    #[test]
+    fn json_prints_an_envelope_and_quiet_only_the_result() {
+        let a = temp_file("envelope-a.txt", "1 2\n3 4\n");
+        let b = temp_file("envelope-b.txt", "5 6\n7 8\n");
+        let (a, b) = (a.to_str().unwrap(), b.to_str().unwrap());
+        let output = |argv: &[&str]| {
+            let mut out = vec![];
+            run(&parse_args(args(argv)).unwrap(), &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let json = parse_json(&output(&["--json", "--engine", "naive", a, b])).unwrap();
+        let field = |key: &str| json.get(key).map(|value| value.value.clone());
+        assert_eq!(field("rows"), Some(JsonValue::Number(String::from("2"))));
+        assert_eq!(
+            field("engine"),
+            Some(JsonValue::String(String::from("naive")))
+        );
+        let Some(JsonValue::Object(result)) = field("result") else {
+            panic!("{:?}", json);
+        };
+        assert!(result.iter().any(|(key, _)| key == "data"));
+        assert_eq!(output(&["-q", a, b]), "19 22\n43 50\n");
+
+        for (argv, message) in [
+            (&["-q", "--verbose", a, b][..], "--quiet and --verbose can't be combined"),
+            (&["--json"], "--json only applies to multiplying files or --size"),
+            (
+                &["--json", "--pretty", a, b],
+                "--json prints the product itself, so without --output it takes no --pretty, --format or --compress",
+            ),
+        ] {
+            match parse_args(args(argv)) {
+                Err(CliError::Usage(msg)) => assert_eq!(msg, message),
+                other => panic!("{:?} gave {:?}", argv, other),
+            }
+        }
+    }
+
+    #[test]
+    fn out_of_core_output_is_never_an_operand_or_replaced_unasked() {
+        let (a, b, out) = (
+            temp_path("ooc-cli-a.mmb"),
+            temp_path("ooc-cli-b.mmb"),
+            temp_path("ooc-cli-out.mmb"),
+        );
+        let m = vec![vec![1, 2], vec![3, 4]];
+        crate::binary::write_binary(&a, &m).unwrap();
+        crate::binary::write_binary(&b, &m).unwrap();
+        let _ = fs::remove_file(&out);
+        let run_with = |extra: &[&str]| {
+            let mut all = vec!["--out-of-core", a.to_str().unwrap(), b.to_str().unwrap()];
+            all.extend(extra);
+            run(&parse_args(args(&all)).unwrap(), &mut vec![])
+        };
+
+        // the same file through a different path is still the same file
+        let a_again = a.parent().unwrap().join(".").join(a.file_name().unwrap());
+        let err = run_with(&[a_again.to_str().unwrap()]).unwrap_err();
+        assert_eq!(err.exit_code(), EXIT_USAGE);
+        assert_eq!(crate::binary::read_binary(&a).unwrap(), m);
+
+        run_with(&["--output", out.to_str().unwrap()]).unwrap();
+        let product = vec![vec![7, 10], vec![15, 22]];
+        assert_eq!(crate::binary::read_binary(&out).unwrap(), product);
+
+        crate::binary::write_binary(&out, &m).unwrap();
+        let err = run_with(&[out.to_str().unwrap()]).unwrap_err();
+        assert!(
+            matches!(err, CliError::Export(ExportError::Exists(_))),
+            "{}",
+            err
+        );
+        assert_eq!(crate::binary::read_binary(&out).unwrap(), m);
+        run_with(&[out.to_str().unwrap(), "--force"]).unwrap();
+        assert_eq!(crate::binary::read_binary(&out).unwrap(), product);
+
+        let err =
+            run_with(&[out.to_str().unwrap(), "--output", out.to_str().unwrap()]).unwrap_err();
+        assert_eq!(err.exit_code(), EXIT_USAGE);
+        for path in [a, b, out] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+}
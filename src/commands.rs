@@ -0,0 +1,321 @@
+use crate::backend::ENGINES;
+
+/// Help of `--engine`. There's no rayon engine, as the crate doesn't depend
+/// on rayon
+const ENGINE_HELP: &str =
+    "multiplication engine, by default narrow, which runs on one thread (no rayon engine in this build)";
+
+/// A command line flag, for shell completions and the man page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Flag {
+    /// The flag without its leading dashes
+    pub name: &'static str,
+    /// Name of the flag's value, or `None` for a switch
+    pub value: Option<&'static str>,
+    /// The values the flag accepts, if it has a fixed set
+    pub choices: &'static [&'static str],
+    /// Whether the value is a path
+    pub path: bool,
+    pub help: &'static str,
+}
+
+/// The top-level command or a subcommand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Command {
+    pub name: &'static str,
+    /// Positional arguments, as shown in the synopsis
+    pub args: &'static str,
+    pub about: &'static str,
+    pub flags: &'static [Flag],
+}
+
+const fn switch(name: &'static str, help: &'static str) -> Flag {
+    Flag {
+        name,
+        value: None,
+        choices: &[],
+        path: false,
+        help,
+    }
+}
+
+const fn value(name: &'static str, value: &'static str, help: &'static str) -> Flag {
+    Flag {
+        name,
+        value: Some(value),
+        choices: &[],
+        path: false,
+        help,
+    }
+}
+
+const fn path(name: &'static str, value: &'static str, help: &'static str) -> Flag {
+    Flag {
+        name,
+        value: Some(value),
+        choices: &[],
+        path: true,
+        help,
+    }
+}
+
+const fn choice(
+    name: &'static str,
+    value: &'static str,
+    choices: &'static [&'static str],
+    help: &'static str,
+) -> Flag {
+    Flag {
+        name,
+        value: Some(value),
+        choices,
+        path: false,
+        help,
+    }
+}
+
+pub const FORMATS: [&str; 9] = [
+    "text", "csv", "tsv", "mtx", "json", "npy", "latex", "markdown", "binary",
+];
+pub const LOCALES: [&str; 5] = ["neutral", "en", "de", "fr", "ch"];
+pub const KINDS: [&str; 5] = ["dense", "identity", "diagonal", "symmetric", "sparse"];
+pub const SHELLS: [&str; 4] = ["bash", "zsh", "fish", "powershell"];
+pub const ERROR_FORMATS: [&str; 2] = ["text", "json"];
+
+/// Every flag `cli::parse_args` accepts. Keep in sync with it and the
+/// subcommand parsers, which the completions and man page are made from
+pub const MAIN: Command = Command {
+    name: "matrixmult",
+    args: "[a b]",
+    about: "Multiply two matrices, in a TUI or from files",
+    flags: &[
+        path(
+            "autosave",
+            "dir",
+            "write every result computed in the TUI to dir",
+        ),
+        switch(
+            "out-of-core",
+            "multiply two binary matrix files tile by tile",
+        ),
+        value(
+            "memory-limit",
+            "size",
+            "memory used for tiles by --out-of-core",
+        ),
+        switch(
+            "stdin",
+            "read both operands from stdin, separated by a blank line",
+        ),
+        value(
+            "max-input-size",
+            "size",
+            "largest input read from a file or stdin",
+        ),
+        choice(
+            "format",
+            "format",
+            &FORMATS,
+            "format of the inputs and output",
+        ),
+        switch("csv-header", "skip the header row of CSV inputs"),
+        switch("compress", "gzip the output and decompress every input"),
+        value(
+            "compression-level",
+            "0-9",
+            "gzip compression level of the output",
+        ),
+        value(
+            "delimiter",
+            "char",
+            "field separator of CSV input and output",
+        ),
+        value("latex-env", "name", "matrix environment of LaTeX output"),
+        switch(
+            "latex-brackets",
+            "wrap LaTeX output in \\left[ ... \\right]",
+        ),
+        value("latex-max-cols", "n", "truncate LaTeX output to n columns"),
+        switch(
+            "latex-transpose",
+            "transpose wide LaTeX output if that makes it fit",
+        ),
+        value("markdown-max-rows", "n", "rows of Markdown tables shown"),
+        value("markdown-max-cols", "n", "columns of Markdown tables shown"),
+        choice(
+            "locale",
+            "locale",
+            &LOCALES,
+            "decimal point and digit grouping of results",
+        ),
+        switch("pretty", "print results as a table with aligned columns"),
+        switch("borders", "draw box borders around --pretty tables"),
+        switch("headers", "number the rows and columns of --pretty tables"),
+        value(
+            "max-width",
+            "n",
+            "leave out the columns of --pretty tables past n characters",
+        ),
+        switch("force", "let --output replace an existing file"),
+        choice("engine", "engine", &ENGINES, ENGINE_HELP),
+        value("threads", "n", "threads for the threaded engine"),
+        switch("quiet", "print only the result or checksum, also -q"),
+        switch(
+            "verbose",
+            "report sizes, engine, timing and warnings on stderr, also -v",
+        ),
+        switch("json", "print one JSON object describing the run"),
+        value(
+            "size",
+            "n|mxn|mxnxp",
+            "time one product of random operands of this size",
+        ),
+        value("seed", "seed", "seed of the --size operands"),
+        switch(
+            "checksum",
+            "print a SHA-256 digest of the product instead of the product",
+        ),
+        path(
+            "log",
+            "path",
+            "append diagnostics to a file, at the level in MATRIXMULT_LOG",
+        ),
+        path("output", "path", "write the result to a file"),
+        switch("safe", "run the TUI without loading or saving files"),
+        path(
+            "resume",
+            "session",
+            "start the TUI from a session file, saving to it on quit",
+        ),
+        choice(
+            "error-format",
+            "format",
+            &ERROR_FORMATS,
+            "print errors as text or as JSON objects",
+        ),
+    ],
+};
+
+pub const SUBCOMMANDS: [Command; 9] = [
+    Command {
+        name: "bench",
+        args: "",
+        about: "Time the engines on random matrices",
+        flags: &[
+            value("sizes", "n,...", "side lengths of the operands"),
+            value(
+                "engines",
+                "engine,...",
+                "engines to time, the first the baseline",
+            ),
+            value("seed", "seed", "seed of the operands"),
+            value("warmup", "n", "untimed runs per size and engine"),
+            value("iterations", "n", "timed runs per size and engine"),
+            value("threads", "n", "threads for the threaded engine"),
+            switch("json", "print the results as JSON"),
+        ],
+    },
+    Command {
+        name: "batch",
+        args: "manifest",
+        about: "Run the jobs listed in a manifest",
+        flags: &[
+            value("jobs", "n", "jobs run at the same time"),
+            switch("force", "replace existing output files"),
+        ],
+    },
+    Command {
+        name: "gen",
+        args: "",
+        about: "Write a reproducible random matrix",
+        flags: &[
+            value("rows", "n", "rows of the matrix"),
+            value("cols", "n", "columns of the matrix, --rows by default"),
+            value("min", "n", "smallest element"),
+            value("max", "n", "largest element"),
+            value("seed", "seed", "seed of the generator"),
+            choice("kind", "kind", &KINDS, "structure of the matrix"),
+            value("density", "p", "fraction of nonzeros of sparse matrices"),
+            path("output", "path", "write the matrix to a file"),
+            choice("format", "format", &FORMATS, "format of the output"),
+            switch("force", "let --output replace an existing file"),
+        ],
+    },
+    Command {
+        name: "session",
+        args: "export|import session [dir|files...]",
+        about: "Convert a session file to and from matrix files",
+        flags: &[
+            choice("format", "format", &FORMATS, "format of exported files"),
+            switch("force", "let import replace an existing session file"),
+        ],
+    },
+    Command {
+        name: "watch",
+        args: "a b",
+        about: "Multiply two files again whenever either changes",
+        flags: &[
+            path("output", "path", "file the product is written to"),
+            choice("format", "format", &FORMATS, "format of the output"),
+            value("interval", "ms", "how often the inputs are checked"),
+            value(
+                "debounce",
+                "ms",
+                "how long the inputs must stay unchanged before they're read",
+            ),
+            choice("engine", "engine", &ENGINES, ENGINE_HELP),
+            value("threads", "n", "threads for the threaded engine"),
+        ],
+    },
+    Command {
+        name: "verify",
+        args: "",
+        about: "Check that the engines agree on random operands",
+        flags: &[
+            value("size", "n", "side length of the operands"),
+            value("iterations", "n", "operand pairs to check"),
+            value("seed", "seed", "seed of the first pair"),
+            value(
+                "engines",
+                "engine,...",
+                "engines to check, the first the reference",
+            ),
+            value("threads", "n", "threads for the threaded engine"),
+        ],
+    },
+    Command {
+        name: "repl",
+        args: "",
+        about: "Read matrix commands from stdin, one per line",
+        flags: &[
+            choice(
+                "locale",
+                "locale",
+                &LOCALES,
+                "decimal point and digit grouping of results",
+            ),
+            switch("pretty", "print results as a table with aligned columns"),
+            switch("borders", "draw box borders around --pretty tables"),
+            switch("headers", "number the rows and columns of --pretty tables"),
+            switch("csv-header", "skip the header row of CSV inputs"),
+            value(
+                "delimiter",
+                "char",
+                "field separator of CSV input and output",
+            ),
+            switch("force", "let export replace an existing file"),
+        ],
+    },
+    Command {
+        name: "completions",
+        args: "bash|zsh|fish|powershell",
+        about: "Print a shell completion script",
+        flags: &[],
+    },
+    Command {
+        name: "manpage",
+        args: "",
+        about: "Print the man page",
+        flags: &[],
+    },
+];
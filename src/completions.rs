@@ -0,0 +1,336 @@
+use crate::commands::{Command, Flag, MAIN, SHELLS, SUBCOMMANDS};
+
+/// A shell `format_completions` writes a script for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+}
+
+impl Shell {
+    pub fn from_name(name: &str) -> Option<Shell> {
+        Some(match name {
+            "bash" => Shell::Bash,
+            "zsh" => Shell::Zsh,
+            "fish" => Shell::Fish,
+            "powershell" => Shell::PowerShell,
+            _ => return None,
+        })
+    }
+}
+
+/// The completion script for `shell`, made from the flag definitions in
+/// `commands`
+pub fn format_completions(shell: Shell) -> String {
+    match shell {
+        Shell::Bash => bash(),
+        Shell::Zsh => zsh(),
+        Shell::Fish => fish(),
+        Shell::PowerShell => powershell(),
+    }
+}
+
+/// Words completed where no flag value is expected: the flags, plus the
+/// subcommand names at the top level and the shells after `completions`
+fn words(command: &Command, top_level: bool) -> Vec<String> {
+    let mut words: Vec<String> = command
+        .flags
+        .iter()
+        .map(|flag| format!("--{}", flag.name))
+        .collect();
+    if top_level {
+        words.extend(SUBCOMMANDS.iter().map(|sub| sub.name.to_string()));
+    }
+    if command.name == "completions" {
+        words.extend(SHELLS.iter().map(|shell| shell.to_string()));
+    }
+    words
+}
+
+/// Whether the positional arguments of `command` are files
+fn takes_files(command: &Command) -> bool {
+    command.name == MAIN.name || command.name == "batch"
+}
+
+/// Every command with the key scripts select it by, empty for the top level
+fn commands() -> impl Iterator<Item = (&'static str, &'static Command)> {
+    std::iter::once(("", &MAIN)).chain(SUBCOMMANDS.iter().map(|sub| (sub.name, sub)))
+}
+
+fn subcommand_names() -> Vec<&'static str> {
+    SUBCOMMANDS.iter().map(|sub| sub.name).collect()
+}
+
+fn bash() -> String {
+    let mut values = String::new();
+    for (key, command) in commands() {
+        for flag in command.flags.iter().filter(|flag| flag.value.is_some()) {
+            let action = if !flag.choices.is_empty() {
+                format!(
+                    "COMPREPLY=($(compgen -W \"{}\" -- \"${{cur}}\"))",
+                    flag.choices.join(" ")
+                )
+            } else if flag.path {
+                String::from("COMPREPLY=($(compgen -f -- \"${cur}\"))")
+            } else {
+                String::from("COMPREPLY=()")
+            };
+            values.push_str(&format!(
+                "        {}:--{}) {}; return ;;\n",
+                key, flag.name, action
+            ));
+        }
+    }
+
+    let mut words_of = String::new();
+    for (key, command) in commands() {
+        words_of.push_str(&format!(
+            "        {}) opts=\"{}\"; files={} ;;\n",
+            if key.is_empty() { "\"\"" } else { key },
+            words(command, key.is_empty()).join(" "),
+            takes_files(command) as u8
+        ));
+    }
+
+    format!(
+        r#"_matrixmult() {{
+    local cur prev cmd opts files
+    cur="${{COMP_WORDS[COMP_CWORD]}}"
+    prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    cmd=""
+    if [[ ${{COMP_CWORD}} -gt 1 ]]; then
+        case "${{COMP_WORDS[1]}}" in
+            {subcommands}) cmd="${{COMP_WORDS[1]}}" ;;
+        esac
+    fi
+    case "${{cmd}}:${{prev}}" in
+{values}    esac
+    case "${{cmd}}" in
+{words_of}    esac
+    COMPREPLY=($(compgen -W "${{opts}}" -- "${{cur}}"))
+    if [[ ${{files}} == 1 && ${{cur}} != -* ]]; then
+        COMPREPLY+=($(compgen -f -- "${{cur}}"))
+    fi
+}}
+complete -F _matrixmult matrixmult
+"#,
+        subcommands = subcommand_names().join("|"),
+        values = values,
+        words_of = words_of
+    )
+}
+
+/// Escapes text for a single-quoted `_arguments` spec
+fn zsh_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+        .replace('\'', "'\\''")
+}
+
+fn zsh_flag(flag: &Flag) -> String {
+    let value = match flag.value {
+        None => String::new(),
+        Some(name) if !flag.choices.is_empty() => {
+            format!(":{}:({})", zsh_escape(name), flag.choices.join(" "))
+        }
+        Some(name) if flag.path => format!(":{}:_files", zsh_escape(name)),
+        Some(name) => format!(":{}: ", zsh_escape(name)),
+    };
+    format!("'--{}[{}]{}'", flag.name, zsh_escape(flag.help), value)
+}
+
+fn zsh_arguments(command: &Command, indent: &str) -> String {
+    command
+        .flags
+        .iter()
+        .map(|flag| format!(" \\\n{}{}", indent, zsh_flag(flag)))
+        .collect()
+}
+
+fn zsh() -> String {
+    let mut branches = String::new();
+    for sub in &SUBCOMMANDS {
+        let positional = match sub.name {
+            "completions" => format!(" \\\n                '1:shell:({})'", SHELLS.join(" ")),
+            "batch" => String::from(" \\\n                '1:manifest:_files'"),
+            _ => String::new(),
+        };
+        branches.push_str(&format!(
+            "            {})\n                shift words; (( CURRENT-- ))\n                _arguments{}{}\n                return ;;\n",
+            sub.name,
+            zsh_arguments(sub, "                "),
+            positional
+        ));
+    }
+    let described: Vec<String> = SUBCOMMANDS
+        .iter()
+        .map(|sub| format!("{}\\:\"{}\"", sub.name, zsh_escape(sub.about)))
+        .collect();
+
+    format!(
+        r#"#compdef matrixmult
+
+_matrixmult() {{
+    local state
+    if (( CURRENT > 2 )); then
+        case $words[2] in
+{branches}        esac
+    fi
+    _arguments{flags} \
+        '1: :->first' \
+        '*:file:_files'
+    if [[ $state == first ]]; then
+        _alternative 'commands:command:(({described}))' 'files:file:_files'
+    fi
+}}
+
+_matrixmult "$@"
+"#,
+        branches = branches,
+        flags = zsh_arguments(&MAIN, "        "),
+        described = described.join(" ")
+    )
+}
+
+/// Escapes text for a single-quoted fish string
+fn fish_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+fn fish() -> String {
+    let mut text = String::from("complete -c matrixmult -f\n");
+    text.push_str("complete -c matrixmult -n __fish_use_subcommand -F\n");
+    for sub in &SUBCOMMANDS {
+        text.push_str(&format!(
+            "complete -c matrixmult -n __fish_use_subcommand -a {} -d '{}'\n",
+            sub.name,
+            fish_escape(sub.about)
+        ));
+    }
+    for (key, command) in commands() {
+        let condition = if key.is_empty() {
+            String::from("__fish_use_subcommand")
+        } else {
+            format!("'__fish_seen_subcommand_from {}'", key)
+        };
+        for flag in command.flags {
+            let value = match flag.value {
+                None => String::new(),
+                Some(_) if !flag.choices.is_empty() => {
+                    format!(" -x -a '{}'", flag.choices.join(" "))
+                }
+                Some(_) if flag.path => String::from(" -r -F"),
+                Some(_) => String::from(" -x"),
+            };
+            text.push_str(&format!(
+                "complete -c matrixmult -n {} -l {}{} -d '{}'\n",
+                condition,
+                flag.name,
+                value,
+                fish_escape(flag.help)
+            ));
+        }
+    }
+    text.push_str(&format!(
+        "complete -c matrixmult -n '__fish_seen_subcommand_from completions' -x -a '{}'\n",
+        SHELLS.join(" ")
+    ));
+    text.push_str("complete -c matrixmult -n '__fish_seen_subcommand_from batch' -F\n");
+    text
+}
+
+fn powershell_list<S: AsRef<str>>(items: &[S]) -> String {
+    let quoted: Vec<String> = items
+        .iter()
+        .map(|item| format!("'{}'", item.as_ref().replace('\'', "''")))
+        .collect();
+    format!("@({})", quoted.join(", "))
+}
+
+fn powershell() -> String {
+    let mut choices = String::new();
+    for (key, command) in commands() {
+        for flag in command.flags.iter().filter(|flag| !flag.choices.is_empty()) {
+            choices.push_str(&format!(
+                "        '{}:--{}' = {}\n",
+                key,
+                flag.name,
+                powershell_list(flag.choices)
+            ));
+        }
+    }
+    let mut words_of = String::new();
+    for (key, command) in commands() {
+        words_of.push_str(&format!(
+            "        '{}' = {}\n",
+            key,
+            powershell_list(&words(command, key.is_empty()))
+        ));
+    }
+
+    format!(
+        r#"Register-ArgumentCompleter -Native -CommandName matrixmult -ScriptBlock {{
+    param($wordToComplete, $commandAst, $cursorPosition)
+    $words = @($commandAst.CommandElements | ForEach-Object {{ $_.ToString() }})
+    $command = ''
+    if ($words.Count -gt 1 -and {subcommands} -contains $words[1]) {{
+        $command = $words[1]
+    }}
+    $previous = if ($wordToComplete) {{ $words[-2] }} else {{ $words[-1] }}
+    $choices = @{{
+{choices}    }}
+    $words_of = @{{
+{words_of}    }}
+    $key = "${{command}}:${{previous}}"
+    $candidates = if ($choices.ContainsKey($key)) {{ $choices[$key] }} else {{ $words_of[$command] }}
+    $candidates | Where-Object {{ $_ -like "$wordToComplete*" }} | ForEach-Object {{
+        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+    }}
+}}
+"#,
+        subcommands = powershell_list(&subcommand_names()),
+        choices = choices,
+        words_of = words_of
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_script_completes_every_flag_and_subcommand() {
+        for name in SHELLS {
+            let shell = Shell::from_name(name).unwrap();
+            let script = format_completions(shell);
+            for (_, command) in commands() {
+                for flag in command.flags {
+                    let completed = match shell {
+                        Shell::Fish => format!("-l {}", flag.name),
+                        _ => format!("--{}", flag.name),
+                    };
+                    assert!(
+                        script.contains(&completed),
+                        "{} is missing {}",
+                        name,
+                        flag.name
+                    );
+                }
+            }
+            for sub in subcommand_names() {
+                assert!(script.contains(sub), "{} is missing {}", name, sub);
+            }
+        }
+        assert_eq!(Shell::from_name("tcsh"), None);
+    }
+
+    #[test]
+    fn zsh_and_fish_help_is_escaped() {
+        assert_eq!(zsh_escape("a [b]: 'c'"), "a \\[b\\]\\: '\\''c'\\''");
+        assert_eq!(fish_escape("it's"), "it\\'s");
+    }
+}
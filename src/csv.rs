@@ -0,0 +1,123 @@
+use crate::{matrix::Matrix, parse::FormatError};
+use std::fmt::Display;
+
+/// Splits one CSV record into its fields. Fields may be wrapped in double
+/// quotes, inside which `""` stands for a literal quote
+pub fn split_record(line: &str, delimiter: char) -> Result<Vec<String>, String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            c if c == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(String::from("unterminated quoted field"));
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+/// Parses `delimiter` separated text, skipping the first record if `has_header`.
+/// Blank lines and trailing empty fields (as spreadsheet exports often leave)
+/// are ignored, and both LF and CRLF line endings are accepted
+pub fn parse_csv(text: &str, has_header: bool, delimiter: char) -> Result<Matrix, FormatError> {
+    let mut m: Matrix = vec![];
+
+    for (i, line) in text.lines().enumerate().skip(has_header as usize) {
+        let line_no = i + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut fields =
+            split_record(line, delimiter).map_err(|msg| FormatError::new(line_no, msg))?;
+        while fields.len() > 1 && fields.last().is_some_and(|f| f.trim().is_empty()) {
+            fields.pop();
+        }
+
+        let row = fields
+            .iter()
+            .enumerate()
+            .map(|(j, field)| {
+                field.trim().parse::<i64>().map_err(|_| {
+                    FormatError::at(line_no, j + 1, format!("invalid number {:?}", field))
+                })
+            })
+            .collect::<Result<Vec<i64>, FormatError>>()?;
+
+        if let Some(first) = m.first() {
+            if row.len() != first.len() {
+                return Err(FormatError::new(
+                    line_no,
+                    format!("expected {} fields, found {}", first.len(), row.len()),
+                ));
+            }
+        }
+        m.push(row);
+    }
+    Ok(m)
+}
+
+/// Quotes a field if it contains the delimiter, a quote or a line break
+pub fn escape_field(field: &str, delimiter: char) -> String {
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Formats a matrix as `delimiter` separated records, one per row
+pub fn format_csv<T: Display>(m: &[Vec<T>], delimiter: char) -> String {
+    let mut text = String::new();
+    for row in m {
+        let fields: Vec<String> = row
+            .iter()
+            .map(|x| escape_field(&x.to_string(), delimiter))
+            .collect();
+        text.push_str(&fields.join(&delimiter.to_string()));
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_import_handles_headers_quotes_and_crlf() {
+        let text = "a,b,c\r\n1, 2 ,\"3\"\r\n\r\n-4,5,6,,\r\n";
+        assert_eq!(
+            parse_csv(text, true, ',').unwrap(),
+            vec![vec![1, 2, 3], vec![-4, 5, 6]]
+        );
+        assert_eq!(
+            split_record("\"x,\"\"y\"\"\",z", ',').unwrap(),
+            vec!["x,\"y\"", "z"]
+        );
+    }
+
+    #[test]
+    fn csv_import_errors_point_at_the_field() {
+        let err = parse_csv("1,2\n3,x\n", false, ',').unwrap_err();
+        assert_eq!((err.line, err.column), (2, Some(2)));
+        let err = parse_csv("1,2\n3\n", false, ',').unwrap_err();
+        assert_eq!((err.line, err.column), (2, None));
+        let err = parse_csv("1,\"2\n", false, ',').unwrap_err();
+        assert_eq!(err.line, 1);
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{
+    files::FileFormat,
+    json::{format_json, quote},
+    matrix::Answer,
+};
+use std::{path::Path, time::Duration};
+
+/// Version of the `--json` envelope, raised whenever a field is renamed,
+/// removed or changes meaning. Adding fields doesn't raise it
+pub const ENVELOPE_VERSION: u32 = 1;
+
+/// Everything `--json` reports about a run, printed as one object:
+///
+/// ```text
+/// {"version": 1,
+///  "result": {"rows": 2, "cols": 2, "data": [[19, 22], [43, 50]]},
+///  "output": null, "format": null, "rows": 2, "cols": 2,
+///  "engine": "naive", "threads": 1, "elapsed_ms": 0.002,
+///  "checksum": null, "seed": null, "warnings": []}
+/// ```
+///
+/// Every field is always present, `null` when it doesn't apply
+#[derive(Debug, Clone, PartialEq)]
+pub struct Envelope<'a> {
+    /// The product as `json::format_json` writes it, when it went to stdout.
+    /// `null` if it was written to `output` or holds a NaN or infinity
+    pub result: Option<&'a Answer>,
+    /// The file the product was written to
+    pub output: Option<&'a Path>,
+    /// The format of `output`
+    pub format: Option<FileFormat>,
+    /// Size of the product
+    pub rows: usize,
+    pub cols: usize,
+    pub engine: &'a str,
+    pub threads: usize,
+    /// Time spent multiplying, without reading or writing files
+    pub elapsed: Duration,
+    /// The `--checksum` digest
+    pub checksum: Option<String>,
+    /// Seed of the `--size` operands
+    pub seed: Option<u64>,
+    pub warnings: Vec<String>,
+}
+
+impl Envelope<'_> {
+    /// The envelope as one line of JSON
+    pub fn to_json(&self) -> String {
+        let null = || String::from("null");
+        let result = self
+            .result
+            .and_then(format_json)
+            .map_or_else(null, |json| String::from(json.trim_end()));
+        let output = self
+            .output
+            .map_or_else(null, |path| quote(&path.display().to_string()));
+        let format = self.format.map_or_else(null, |format| quote(format.name()));
+        let checksum = self.checksum.as_deref().map_or_else(null, quote);
+        let seed = self.seed.map_or_else(null, |seed| seed.to_string());
+        let warnings: Vec<String> = self.warnings.iter().map(|w| quote(w)).collect();
+        format!(
+            "{{\"version\": {}, \"result\": {}, \"output\": {}, \"format\": {}, \
+             \"rows\": {}, \"cols\": {}, \"engine\": {}, \"threads\": {}, \
+             \"elapsed_ms\": {:?}, \"checksum\": {}, \"seed\": {}, \"warnings\": [{}]}}\n",
+            ENVELOPE_VERSION,
+            result,
+            output,
+            format,
+            self.rows,
+            self.cols,
+            quote(self.engine),
+            self.threads,
+            self.elapsed.as_secs_f64() * 1000.0,
+            checksum,
+            seed,
+            warnings.join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::{parse_json, JsonValue};
+
+    fn envelope(answer: &Answer) -> Envelope<'_> {
+        Envelope {
+            result: Some(answer),
+            output: None,
+            format: None,
+            rows: 2,
+            cols: 2,
+            engine: "naive",
+            threads: 1,
+            elapsed: Duration::from_micros(1500),
+            checksum: None,
+            seed: None,
+            warnings: vec![String::from("a \"quoted\" warning")],
+        }
+    }
+
+    #[test]
+    fn every_field_is_present() {
+        let answer = Answer::Int(vec![vec![19, 22], vec![43, 50]]);
+        let text = envelope(&answer).to_json();
+        assert!(
+            text.ends_with("}\n") && text.lines().count() == 1,
+            "{}",
+            text
+        );
+        let json = parse_json(&text).unwrap();
+        let field = |key: &str| json.get(key).map(|value| value.value.clone());
+        assert_eq!(field("version"), Some(JsonValue::Number(String::from("1"))));
+        assert_eq!(
+            field("elapsed_ms"),
+            Some(JsonValue::Number(String::from("1.5")))
+        );
+        assert_eq!(
+            field("engine"),
+            Some(JsonValue::String(String::from("naive")))
+        );
+        for key in ["output", "format", "checksum", "seed"] {
+            assert_eq!(field(key), Some(JsonValue::Null), "{}", key);
+        }
+        let Some(JsonValue::Array(warnings)) = field("warnings") else {
+            panic!("{}", text);
+        };
+        assert_eq!(
+            warnings[0].value,
+            JsonValue::String(String::from("a \"quoted\" warning"))
+        );
+        assert!(matches!(field("result"), Some(JsonValue::Object(_))));
+    }
+
+    #[test]
+    fn results_json_cant_hold_are_null() {
+        let answer = Answer::Float(vec![vec![f64::NAN]]);
+        let json = parse_json(&envelope(&answer).to_json()).unwrap();
+        assert_eq!(json.get("result").unwrap().value, JsonValue::Null);
+    }
+}
@@ -0,0 +1,286 @@
+use crate::{
+    binary::{format_binary, BinaryError},
+    csv::format_csv,
+    files::FileFormat,
+    format::format_answer,
+    gzip::{gzip, is_gzip_path, DEFAULT_LEVEL},
+    json::format_json,
+    latex::{format_latex, LatexOptions},
+    markdown::{format_markdown, MarkdownOptions},
+    market::format_matrix_market,
+    matrix::Answer,
+    npy::{format_npy, NpyError},
+};
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Writes `answer` to a new file in `dir`, named after the current time, the
+/// operation and the result's dimensions, e.g. `1700000000123-multiply-2x3.txt`
+pub fn autosave_result(dir: &Path, operation: &str, answer: &Answer) -> io::Result<PathBuf> {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis());
+    let (rows, cols) = answer.dims();
+    let path = dir.join(format!("{}-{}-{}x{}.txt", millis, operation, rows, cols));
+
+    fs::create_dir_all(dir)?;
+    fs::write(&path, format_answer(answer))?;
+    Ok(path)
+}
+
+/// How results are written to files
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteOptions {
+    /// Overrides the format inferred from the file extension
+    pub format: Option<FileFormat>,
+    /// Field separator of CSV and TSV output, overriding the format's
+    /// default
+    pub delimiter: Option<char>,
+    pub latex: LatexOptions,
+    pub markdown: MarkdownOptions,
+    /// Replace the file if it already exists
+    pub overwrite: bool,
+    /// Gzip the output, as is done anyway for `.gz` paths
+    pub compress: bool,
+    /// Gzip compression level, from 0 to `gzip::MAX_LEVEL`
+    pub compression_level: u32,
+}
+
+impl Default for WriteOptions {
+    fn default() -> WriteOptions {
+        WriteOptions {
+            format: None,
+            delimiter: None,
+            latex: LatexOptions::default(),
+            markdown: MarkdownOptions::default(),
+            overwrite: false,
+            compress: false,
+            compression_level: DEFAULT_LEVEL,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(io::Error),
+    /// There is no result (or only an empty one) to write
+    NothingToExport,
+    /// The result holds a NaN or infinity the format can't represent
+    NotFinite(FileFormat),
+    Npy(NpyError),
+    Binary(BinaryError),
+    /// The file exists and `overwrite` isn't set
+    Exists(PathBuf),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Io(err) => write!(f, "{}", err),
+            ExportError::NothingToExport => write!(f, "there is no result to export"),
+            ExportError::NotFinite(format) => write!(
+                f,
+                "the result holds values {} can't represent",
+                format.name()
+            ),
+            ExportError::Npy(err) => write!(f, "{}", err),
+            ExportError::Binary(err) => write!(f, "{}", err),
+            ExportError::Exists(path) => write!(f, "{} already exists", path.display()),
+        }
+    }
+}
+
+impl Error for ExportError {}
+
+impl From<NpyError> for ExportError {
+    fn from(err: NpyError) -> ExportError {
+        ExportError::Npy(err)
+    }
+}
+
+impl From<BinaryError> for ExportError {
+    fn from(err: BinaryError) -> ExportError {
+        ExportError::Binary(err)
+    }
+}
+
+impl From<io::Error> for ExportError {
+    fn from(err: io::Error) -> ExportError {
+        ExportError::Io(err)
+    }
+}
+
+/// Renders `answer` as the contents of a file in the given format
+pub fn render_answer(
+    answer: &Answer,
+    format: FileFormat,
+    options: &WriteOptions,
+) -> Result<Vec<u8>, ExportError> {
+    let text = match (format, answer) {
+        (FileFormat::Text, _) => format_answer(answer),
+        (FileFormat::Csv | FileFormat::Tsv, _) => {
+            let delimiter = options.delimiter.unwrap_or(format.default_delimiter());
+            match answer {
+                Answer::Int(m) => format_csv(m, delimiter),
+                Answer::Float(m) => format_csv(m, delimiter),
+            }
+        }
+        (FileFormat::Json, _) => format_json(answer).ok_or(ExportError::NotFinite(format))?,
+        (FileFormat::MatrixMarket, _) => {
+            format_matrix_market(answer).ok_or(ExportError::NotFinite(format))?
+        }
+        (FileFormat::Npy, _) => return Ok(format_npy(answer)?),
+        (FileFormat::Binary, _) => return Ok(format_binary(answer)?),
+        (FileFormat::Latex, _) => format_latex(answer, &options.latex),
+        (FileFormat::Markdown, _) => format_markdown(answer, &options.markdown),
+    };
+    Ok(text.into_bytes())
+}
+
+/// The hidden file next to `path` that a write goes to before it's renamed
+/// into place
+pub fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map_or_else(
+        || String::from("result"),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()))
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it into
+/// place, so readers never see a partly written file
+pub fn write_atomic(path: &Path, contents: &[u8], overwrite: bool) -> Result<(), ExportError> {
+    if !overwrite && path.exists() {
+        return Err(ExportError::Exists(path.to_path_buf()));
+    }
+    let tmp = temp_path(path);
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path).map_err(|err| {
+        let _ = fs::remove_file(&tmp);
+        ExportError::Io(err)
+    })
+}
+
+/// Paths of `count` numbered frames made from `path`: `anim.csv` gives
+/// `anim-0.csv`, `anim-1.csv`, ..., zero-padded to the same width
+pub fn frame_paths(path: &Path, count: usize) -> Vec<PathBuf> {
+    let stem = path.file_stem().map_or_else(
+        || String::from("frame"),
+        |stem| stem.to_string_lossy().into_owned(),
+    );
+    let extension = path
+        .extension()
+        .map_or_else(String::new, |ext| format!(".{}", ext.to_string_lossy()));
+    let width = count.saturating_sub(1).to_string().len();
+    (0..count)
+        .map(|i| path.with_file_name(format!("{}-{:0width$}{}", stem, i, extension)))
+        .collect()
+}
+
+/// Writes each of `frames` to its own file named by `frame_paths`, in the
+/// format of `path`, returning the paths written. Nothing is written if any
+/// of them exists and `options.overwrite` isn't set
+pub fn export_frames(
+    path: &Path,
+    frames: &[Answer],
+    options: &WriteOptions,
+) -> Result<Vec<PathBuf>, ExportError> {
+    let paths = frame_paths(path, frames.len());
+    if let Some(path) = paths
+        .iter()
+        .find(|path| !options.overwrite && path.exists())
+    {
+        return Err(ExportError::Exists(path.clone()));
+    }
+    let options = WriteOptions {
+        format: Some(
+            options
+                .format
+                .unwrap_or_else(|| FileFormat::from_path(path)),
+        ),
+        ..options.clone()
+    };
+    for (frame, path) in frames.iter().zip(&paths) {
+        export_answer(path, Some(frame), &options)?;
+    }
+    Ok(paths)
+}
+
+/// Writes `answer` to `path` in the format set in `options` or inferred from
+/// the extension, returning the format used. A missing or empty answer is an
+/// error rather than an empty file, and an existing file is only replaced if
+/// `options.overwrite` is set
+pub fn export_answer(
+    path: &Path,
+    answer: Option<&Answer>,
+    options: &WriteOptions,
+) -> Result<FileFormat, ExportError> {
+    let answer = answer
+        .filter(|answer| answer.dims().0 > 0)
+        .ok_or(ExportError::NothingToExport)?;
+    let format = options
+        .format
+        .unwrap_or_else(|| FileFormat::from_path(path));
+    let mut contents = render_answer(answer, format, options)?;
+    if options.compress || is_gzip_path(path) {
+        contents = gzip(&contents, options.compression_level)?;
+    }
+    write_atomic(path, &contents, options.overwrite)?;
+    Ok(format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("matrixmult-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn autosave_writes_the_answer_into_a_new_directory() {
+        let dir = temp_dir("autosave");
+        let answer = Answer::Int(vec![vec![1, 2, 3], vec![4, 5, 6]]);
+        let path = autosave_result(&dir, "multiply", &answer).unwrap();
+
+        assert_eq!(path.parent(), Some(dir.as_path()));
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        assert!(name.ends_with("-multiply-2x3.txt"), "{}", name);
+        assert_eq!(fs::read_to_string(&path).unwrap(), format_answer(&answer));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn frames_are_numbered_to_the_same_width() {
+        let paths = frame_paths(Path::new("out/anim.csv"), 11);
+        assert_eq!(paths[0], Path::new("out/anim-00.csv"));
+        assert_eq!(paths[10], Path::new("out/anim-10.csv"));
+        assert_eq!(frame_paths(Path::new("anim"), 2)[1], Path::new("anim-1"));
+    }
+
+    #[test]
+    fn frames_are_written_only_if_none_exist() {
+        let dir = temp_dir("frames");
+        fs::create_dir_all(&dir).unwrap();
+        let frames = [Answer::Int(vec![vec![1]]), Answer::Float(vec![vec![1.5]])];
+        let paths =
+            export_frames(&dir.join("anim.csv"), &frames, &WriteOptions::default()).unwrap();
+        assert_eq!(fs::read_to_string(&paths[0]).unwrap(), "1\n");
+        assert_eq!(fs::read_to_string(&paths[1]).unwrap(), "1.5\n");
+
+        fs::remove_file(&paths[0]).unwrap();
+        assert!(matches!(
+            export_frames(&dir.join("anim.csv"), &frames, &WriteOptions::default()),
+            Err(ExportError::Exists(path)) if path == paths[1]
+        ));
+        // the missing first frame wasn't written either
+        assert!(!paths[0].exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,139 @@
+use crate::gzip::is_gzip_path;
+use std::path::Path;
+
+/// File formats matrices can be read from or written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    /// Whitespace (or, when reading, comma) separated rows
+    Text,
+    Csv,
+    /// CSV separated by tabs unless another delimiter is given
+    Tsv,
+    MatrixMarket,
+    /// `{"rows": r, "cols": c, "data": [[...], ...]}`
+    Json,
+    /// NumPy `.npy`
+    Npy,
+    /// A LaTeX matrix environment, output only
+    Latex,
+    /// A GitHub-flavored Markdown table, output only
+    Markdown,
+    /// The `.mmb` container of `binary`
+    Binary,
+}
+
+impl FileFormat {
+    pub fn from_name(name: &str) -> Option<FileFormat> {
+        match name {
+            "text" | "txt" => Some(FileFormat::Text),
+            "csv" => Some(FileFormat::Csv),
+            "tsv" => Some(FileFormat::Tsv),
+            "mtx" | "matrixmarket" => Some(FileFormat::MatrixMarket),
+            "json" => Some(FileFormat::Json),
+            "npy" => Some(FileFormat::Npy),
+            "latex" | "tex" => Some(FileFormat::Latex),
+            "markdown" | "md" => Some(FileFormat::Markdown),
+            "binary" | "mmb" => Some(FileFormat::Binary),
+            _ => None,
+        }
+    }
+
+    /// The usual extension of files in this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            FileFormat::Text => "txt",
+            FileFormat::Csv => "csv",
+            FileFormat::Tsv => "tsv",
+            FileFormat::MatrixMarket => "mtx",
+            FileFormat::Json => "json",
+            FileFormat::Npy => "npy",
+            FileFormat::Latex => "tex",
+            FileFormat::Markdown => "md",
+            FileFormat::Binary => "mmb",
+        }
+    }
+
+    /// Infers the format from a file's extension, defaulting to text. The
+    /// extension before a `.gz` is used for gzip files, e.g. `csv` for
+    /// `a.csv.gz`
+    pub fn from_path(path: &Path) -> FileFormat {
+        let path = match path.file_stem() {
+            Some(stem) if is_gzip_path(path) => Path::new(stem),
+            _ => path,
+        };
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| FileFormat::from_name(&ext.to_lowercase()))
+            .unwrap_or(FileFormat::Text)
+    }
+
+    /// Field separator of CSV and TSV files when `--delimiter` isn't given
+    pub fn default_delimiter(self) -> char {
+        match self {
+            FileFormat::Tsv => '\t',
+            _ => ',',
+        }
+    }
+
+    /// Whether matrices can be read from this format, not just written
+    pub fn is_readable(self) -> bool {
+        !matches!(self, FileFormat::Latex | FileFormat::Markdown)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FileFormat::Text => "text",
+            FileFormat::Csv => "csv",
+            FileFormat::Tsv => "tsv",
+            FileFormat::MatrixMarket => "mtx",
+            FileFormat::Json => "json",
+            FileFormat::Npy => "npy",
+            FileFormat::Latex => "latex",
+            FileFormat::Markdown => "markdown",
+            FileFormat::Binary => "binary",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_are_inferred_from_the_extension() {
+        assert_eq!(FileFormat::from_path(Path::new("out.CSV")), FileFormat::Csv);
+        assert_eq!(
+            FileFormat::from_path(Path::new("out.tex")),
+            FileFormat::Latex
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("a.mtx.gz")),
+            FileFormat::MatrixMarket
+        );
+        assert_eq!(FileFormat::from_path(Path::new("out.gz")), FileFormat::Text);
+        assert_eq!(
+            FileFormat::from_path(Path::new("out.xyz")),
+            FileFormat::Text
+        );
+        assert_eq!(FileFormat::from_path(Path::new("out")), FileFormat::Text);
+    }
+
+    #[test]
+    fn names_and_extensions_lead_back_to_the_format() {
+        let all = [
+            FileFormat::Text,
+            FileFormat::Csv,
+            FileFormat::Tsv,
+            FileFormat::MatrixMarket,
+            FileFormat::Json,
+            FileFormat::Npy,
+            FileFormat::Latex,
+            FileFormat::Markdown,
+            FileFormat::Binary,
+        ];
+        for format in all {
+            assert_eq!(FileFormat::from_name(format.name()), Some(format));
+            assert_eq!(FileFormat::from_name(format.extension()), Some(format));
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{
+    backend::check_multiply_dims,
+    matrix::{dims, Matrix, MatrixError},
+    operation::{elementwise, Operation},
+};
+
+/// Bit width of the integers a fixed-width computation emulates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IntWidth {
+    I8,
+    I16,
+    I32,
+    I64,
+}
+
+impl IntWidth {
+    pub const ALL: [IntWidth; 4] = [IntWidth::I8, IntWidth::I16, IntWidth::I32, IntWidth::I64];
+
+    pub fn bits(self) -> u32 {
+        match self {
+            IntWidth::I8 => 8,
+            IntWidth::I16 => 16,
+            IntWidth::I32 => 32,
+            IntWidth::I64 => 64,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            IntWidth::I8 => "i8",
+            IntWidth::I16 => "i16",
+            IntWidth::I32 => "i32",
+            IntWidth::I64 => "i64",
+        }
+    }
+
+    /// The width after this one, wrapping around
+    pub fn next(self) -> IntWidth {
+        let index = IntWidth::ALL.iter().position(|&w| w == self).unwrap();
+        IntWidth::ALL[(index + 1) % IntWidth::ALL.len()]
+    }
+
+    fn min(self) -> i128 {
+        -(1 << (self.bits() - 1))
+    }
+
+    fn max(self) -> i128 {
+        (1 << (self.bits() - 1)) - 1
+    }
+}
+
+/// What a fixed-width computation does with a value that doesn't fit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OverflowMode {
+    /// Keep the low bits, like two's complement hardware
+    Wrap,
+    /// Fail with `MatrixError::Overflow`
+    Check,
+    /// Clamp to the smallest or largest value of the width
+    Saturate,
+}
+
+impl OverflowMode {
+    pub const ALL: [OverflowMode; 3] = [
+        OverflowMode::Wrap,
+        OverflowMode::Check,
+        OverflowMode::Saturate,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            OverflowMode::Wrap => "wrap",
+            OverflowMode::Check => "check",
+            OverflowMode::Saturate => "saturate",
+        }
+    }
+
+    /// The mode after this one, wrapping around
+    pub fn next(self) -> OverflowMode {
+        let index = OverflowMode::ALL.iter().position(|&m| m == self).unwrap();
+        OverflowMode::ALL[(index + 1) % OverflowMode::ALL.len()]
+    }
+}
+
+/// Integer arithmetic at a fixed width. Every input, product and partial
+/// sum is brought into the width's range by the overflow mode, so the
+/// result is what the same computation on that integer type gives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FixedWidth {
+    pub width: IntWidth,
+    pub mode: OverflowMode,
+}
+
+impl Default for FixedWidth {
+    /// Checked i64 arithmetic. `Operation::apply` computes the same results
+    /// without widening to i128, so callers use it for this mode
+    fn default() -> FixedWidth {
+        FixedWidth {
+            width: IntWidth::I64,
+            mode: OverflowMode::Check,
+        }
+    }
+}
+
+impl FixedWidth {
+    /// `value` brought into the width's range
+    pub fn reduce(self, value: i128) -> Result<i64, MatrixError> {
+        let (min, max) = (self.width.min(), self.width.max());
+        let reduced = match self.mode {
+            _ if (min..=max).contains(&value) => value,
+            OverflowMode::Wrap => {
+                let shift = 128 - self.width.bits();
+                (value << shift) >> shift
+            }
+            OverflowMode::Check => {
+                return Err(MatrixError::Overflow {
+                    bits: self.width.bits(),
+                })
+            }
+            OverflowMode::Saturate => value.clamp(min, max),
+        };
+        Ok(reduced as i64)
+    }
+
+    fn reduce_matrix(self, m: &Matrix) -> Result<Matrix, MatrixError> {
+        m.iter()
+            .map(|row| row.iter().map(|&x| self.reduce(x as i128)).collect())
+            .collect()
+    }
+
+    /// Computes `operation` at this width
+    pub fn apply(
+        self,
+        operation: Operation,
+        a: &Matrix,
+        b: &Matrix,
+    ) -> Result<Matrix, MatrixError> {
+        let (a, b) = (self.reduce_matrix(a)?, self.reduce_matrix(b)?);
+        let wide = |m: &Matrix| -> Vec<Vec<i128>> {
+            m.iter()
+                .map(|row| row.iter().map(|&x| x as i128).collect())
+                .collect()
+        };
+        let combine = |f: fn(i128, i128) -> i128| -> Result<Matrix, MatrixError> {
+            elementwise(&wide(&a), &wide(&b), f)?
+                .iter()
+                .map(|row| row.iter().map(|&x| self.reduce(x)).collect())
+                .collect()
+        };
+        match operation {
+            Operation::Multiply => self.multiply(&a, &b),
+            Operation::Add => combine(|x, y| x + y),
+            Operation::Subtract => combine(|x, y| x - y),
+            Operation::Hadamard => combine(|x, y| x * y),
+        }
+    }
+
+    fn multiply(self, a: &Matrix, b: &Matrix) -> Result<Matrix, MatrixError> {
+        check_multiply_dims(a, b)?;
+        let (n, p) = dims(b);
+        let mut product = vec![vec![0; p]; a.len()];
+        for i in 0..a.len() {
+            for j in 0..p {
+                let mut sum = 0;
+                for k in 0..n {
+                    let term = self.reduce(a[i][k] as i128 * b[k][j] as i128)?;
+                    sum = self.reduce(sum as i128 + term as i128)?;
+                }
+                product[i][j] = sum;
+            }
+        }
+        Ok(product)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(width: IntWidth, mode: OverflowMode) -> FixedWidth {
+        FixedWidth { width, mode }
+    }
+
+    #[test]
+    fn reduce_wraps_checks_and_saturates() {
+        assert_eq!(at(IntWidth::I8, OverflowMode::Wrap).reduce(200), Ok(-56));
+        assert_eq!(
+            at(IntWidth::I8, OverflowMode::Check).reduce(200),
+            Err(MatrixError::Overflow { bits: 8 })
+        );
+        assert_eq!(
+            at(IntWidth::I8, OverflowMode::Saturate).reduce(-200),
+            Ok(-128)
+        );
+        assert_eq!(
+            at(IntWidth::I16, OverflowMode::Check).reduce(-32768),
+            Ok(-32768)
+        );
+    }
+
+    #[test]
+    fn multiply_reduces_every_partial_sum() {
+        let a = vec![vec![100, 100]];
+        let b = vec![vec![1], vec![1]];
+        assert_eq!(
+            at(IntWidth::I8, OverflowMode::Wrap).apply(Operation::Multiply, &a, &b),
+            Ok(vec![vec![-56]])
+        );
+        assert_eq!(
+            at(IntWidth::I8, OverflowMode::Saturate).apply(Operation::Multiply, &a, &b),
+            Ok(vec![vec![127]])
+        );
+    }
+
+    #[test]
+    fn default_matches_operation_apply() {
+        let a = vec![vec![3, -4], vec![5, 6]];
+        let b = vec![vec![-7, 8], vec![9, 10]];
+        for operation in Operation::ALL {
+            assert_eq!(
+                FixedWidth::default().apply(operation, &a, &b),
+                operation.apply(&a, &b)
+            );
+        }
+        let big = vec![vec![i64::MAX]];
+        for operation in Operation::ALL {
+            assert_eq!(
+                FixedWidth::default().apply(operation, &big, &big),
+                operation.apply(&big, &big)
+            );
+        }
+    }
+}
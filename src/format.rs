@@ -0,0 +1,262 @@
+use crate::matrix::{Answer, Matrix, MatrixF64};
+
+/// Formats a matrix as space-separated rows, one row per line
+pub fn format_matrix(m: &Matrix) -> String {
+    let mut text = String::new();
+    for row in m {
+        let cells: Vec<String> = row.iter().map(|x| x.to_string()).collect();
+        text.push_str(&cells.join(" "));
+        text.push('\n');
+    }
+    text
+}
+
+pub fn format_matrix_f64(m: &MatrixF64) -> String {
+    let mut text = String::new();
+    for row in m {
+        let cells: Vec<String> = row.iter().map(|x| x.to_string()).collect();
+        text.push_str(&cells.join(" "));
+        text.push('\n');
+    }
+    text
+}
+
+/// Each element of a result formatted the way `format_answer` prints it
+pub fn answer_cells(answer: &Answer) -> Vec<Vec<String>> {
+    fn cells<T: ToString>(m: &[Vec<T>]) -> Vec<Vec<String>> {
+        m.iter()
+            .map(|row| row.iter().map(|x| x.to_string()).collect())
+            .collect()
+    }
+    match answer {
+        Answer::Int(m) => cells(m),
+        Answer::Float(m) => cells(m),
+    }
+}
+
+pub fn format_answer(answer: &Answer) -> String {
+    match answer {
+        Answer::Int(m) => format_matrix(m),
+        Answer::Float(m) => format_matrix_f64(m),
+    }
+}
+
+/// Decimal point and digit grouping characters for displayed numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberLocale {
+    pub decimal: char,
+    /// Separator between groups of three integer digits, if any
+    pub grouping: Option<char>,
+}
+
+impl Default for NumberLocale {
+    /// `1234.5`, the way Rust formats numbers
+    fn default() -> NumberLocale {
+        NumberLocale {
+            decimal: '.',
+            grouping: None,
+        }
+    }
+}
+
+impl NumberLocale {
+    /// Looks up a named locale: `neutral` (`1234.5`), `en` (`1,234.5`), `de`
+    /// (`1.234,5`), `fr` (`1 234,5` with a narrow no-break space) or `ch` (`1'234.5`)
+    pub fn from_name(name: &str) -> Option<NumberLocale> {
+        let (decimal, grouping) = match name {
+            "neutral" => ('.', None),
+            "en" => ('.', Some(',')),
+            "de" => (',', Some('.')),
+            "fr" => (',', Some('\u{202f}')),
+            "ch" => ('.', Some('\'')),
+            _ => return None,
+        };
+        Some(NumberLocale { decimal, grouping })
+    }
+
+    /// Rewrites a number formatted by Rust (`-1234.5`, `1e-7`, `NaN`) with
+    /// this locale's decimal point and grouping
+    pub fn localize(&self, number: &str) -> String {
+        let (mantissa, exponent) = match number.find(['e', 'E']) {
+            Some(i) => number.split_at(i),
+            None => (number, ""),
+        };
+        let (sign, digits) = match mantissa.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", mantissa),
+        };
+        if !digits.starts_with(|c: char| c.is_ascii_digit()) {
+            return number.to_string();
+        }
+        let (int, frac) = match digits.split_once('.') {
+            Some((int, frac)) => (int, Some(frac)),
+            None => (digits, None),
+        };
+
+        let mut text = String::from(sign);
+        for (i, c) in int.chars().enumerate() {
+            if i > 0 && (int.len() - i) % 3 == 0 {
+                if let Some(sep) = self.grouping {
+                    text.push(sep);
+                }
+            }
+            text.push(c);
+        }
+        if let Some(frac) = frac {
+            text.push(self.decimal);
+            text.push_str(frac);
+        }
+        text.push_str(exponent);
+        text
+    }
+}
+
+/// Formats a result like `format_answer`, with numbers in the given locale
+pub fn format_answer_localized(answer: &Answer, locale: &NumberLocale) -> String {
+    let mut text = String::new();
+    for row in answer_cells(answer) {
+        let cells: Vec<String> = row.iter().map(|x| locale.localize(x)).collect();
+        text.push_str(&cells.join(" "));
+        text.push('\n');
+    }
+    text
+}
+
+/// What `format_answer_zeros_as` shows zero elements as in the TUI
+pub const ZERO_PLACEHOLDER: &str = ".";
+
+/// Formats a result like `format_answer_localized`, with each zero element
+/// (`-0.0` included) shown as `placeholder` so the nonzero structure of a
+/// sparse matrix stands out
+pub fn format_answer_zeros_as(answer: &Answer, locale: &NumberLocale, placeholder: &str) -> String {
+    let zeros: Vec<Vec<bool>> = match answer {
+        Answer::Int(m) => m
+            .iter()
+            .map(|row| row.iter().map(|&x| x == 0).collect())
+            .collect(),
+        Answer::Float(m) => m
+            .iter()
+            .map(|row| row.iter().map(|&x| x == 0.0).collect())
+            .collect(),
+    };
+    let mut text = String::new();
+    for (row, zeros) in answer_cells(answer).iter().zip(zeros) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(zeros)
+            .map(|(x, zero)| {
+                if zero {
+                    String::from(placeholder)
+                } else {
+                    locale.localize(x)
+                }
+            })
+            .collect();
+        text.push_str(&cells.join(" "));
+        text.push('\n');
+    }
+    text
+}
+
+/// Shades of a heatmap, from the smallest element to the largest
+pub const HEATMAP_RAMP: [char; 10] = [' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'];
+
+/// The shade of `value` in a heatmap of elements from `min` to `max`. A
+/// constant matrix, where `min == max`, is shaded with the middle of the
+/// ramp, and values that aren't numbers with `?`
+pub fn heat_char(value: f64, min: f64, max: f64) -> char {
+    if value.is_nan() {
+        return '?';
+    }
+    let last = HEATMAP_RAMP.len() - 1;
+    if max <= min {
+        return HEATMAP_RAMP[last / 2];
+    }
+    let scaled = ((value - min) / (max - min)).clamp(0.0, 1.0);
+    HEATMAP_RAMP[(scaled * last as f64).round() as usize]
+}
+
+/// A matrix as a heatmap, one shade per element and one line per row
+pub fn format_heatmap(m: &MatrixF64) -> String {
+    let finite = m.iter().flatten().copied().filter(|x| x.is_finite());
+    let (min, max) = finite.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), x| {
+        (min.min(x), max.max(x))
+    });
+    let mut text = String::new();
+    for row in m {
+        text.extend(row.iter().map(|&x| heat_char(x, min, max)));
+        text.push('\n');
+    }
+    text
+}
+
+/// Formats a matrix the way it is typed into a panel: underscores between
+/// elements and newlines between rows
+pub fn matrix_to_text(m: &Matrix) -> String {
+    m.iter()
+        .map(|row| {
+            row.iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+                .join("_")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locales_rewrite_the_decimal_point_and_grouping() {
+        let de = NumberLocale::from_name("de").unwrap();
+        assert_eq!(de.localize("-1234567.25"), "-1.234.567,25");
+        assert_eq!(de.localize("123"), "123");
+        assert_eq!(de.localize("1234.5e-7"), "1.234,5e-7");
+        assert_eq!(de.localize("NaN"), "NaN");
+        assert_eq!(de.localize("-inf"), "-inf");
+        let ch = NumberLocale::from_name("ch").unwrap();
+        assert_eq!(ch.localize("1000"), "1'000");
+        assert_eq!(NumberLocale::default().localize("1234.5"), "1234.5");
+        assert_eq!(NumberLocale::from_name("xx"), None);
+    }
+
+    #[test]
+    fn localized_answers_keep_their_layout() {
+        let answer = Answer::Float(vec![vec![1234.5, -0.25], vec![2.0, 1e6]]);
+        let en = NumberLocale::from_name("en").unwrap();
+        assert_eq!(
+            format_answer_localized(&answer, &en),
+            "1,234.5 -0.25\n2 1,000,000\n"
+        );
+    }
+
+    #[test]
+    fn heatmaps_shade_from_the_smallest_to_the_largest_element() {
+        let m = vec![
+            vec![0.0, 9.0],
+            vec![4.5, f64::NAN],
+            vec![f64::INFINITY, -1.0],
+        ];
+        // infinities are clamped to the ends of the finite range
+        assert_eq!(format_heatmap(&m), ".@\n+?\n@ \n");
+        assert_eq!(heat_char(3.0, 3.0, 3.0), '=');
+        assert_eq!(format_heatmap(&vec![vec![2.0, 2.0]]), "==\n");
+    }
+
+    #[test]
+    fn zeros_show_as_the_placeholder() {
+        let locale = NumberLocale::from_name("en").unwrap();
+        let int = Answer::Int(vec![vec![1000, 0], vec![0, -2]]);
+        assert_eq!(
+            format_answer_zeros_as(&int, &locale, ZERO_PLACEHOLDER),
+            "1,000 .\n. -2\n"
+        );
+        let float = Answer::Float(vec![vec![-0.0, 0.5, 1e-300]]);
+        assert_eq!(
+            format_answer_zeros_as(&float, &locale, "0"),
+            format!("0 0.5 {}\n", 1e-300)
+        );
+    }
+}
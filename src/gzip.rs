@@ -0,0 +1,825 @@
+use std::{
+    error::Error,
+    fmt,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+/// Compression level used unless `--compression-level` is given
+pub const DEFAULT_LEVEL: u32 = 6;
+/// Highest compression level, the slowest and smallest
+pub const MAX_LEVEL: u32 = 9;
+
+const MAGIC: [u8; 2] = [0x1f, 0x8b];
+const DEFLATE: u8 = 8;
+/// How far back a match can reach
+const WINDOW: usize = 1 << 15;
+/// Input compressed per deflate block
+const BLOCK: usize = 1 << 16;
+/// Output decompressed per `GzDecoder::step`
+const CHUNK: usize = 1 << 14;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+
+const FEXTRA: u8 = 4;
+const FNAME: u8 = 8;
+const FCOMMENT: u8 = 16;
+const FHCRC: u8 = 2;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// Order the code length code lengths of a dynamic block are stored in
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+/// Longest hash chain searched for a match, by compression level
+const MAX_CHAIN: [usize; 10] = [0, 4, 8, 16, 32, 64, 128, 256, 1024, 4096];
+
+/// Why gzip data couldn't be decompressed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GzipError {
+    /// The data doesn't start with the gzip magic bytes
+    NotGzip,
+    UnsupportedMethod(u8),
+    /// The data is gzip but is damaged
+    Corrupt(&'static str),
+    /// The data ends in the middle of the stream
+    Truncated,
+}
+
+impl fmt::Display for GzipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GzipError::NotGzip => write!(f, "not gzip data (bad magic bytes)"),
+            GzipError::UnsupportedMethod(method) => {
+                write!(f, "unsupported gzip compression method {}", method)
+            }
+            GzipError::Corrupt(msg) => write!(f, "corrupt gzip data: {}", msg),
+            GzipError::Truncated => write!(f, "corrupt gzip data: unexpected end of file"),
+        }
+    }
+}
+
+impl Error for GzipError {}
+
+impl From<GzipError> for io::Error {
+    fn from(err: GzipError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// Whether `path` names a gzip file, by its `.gz` extension
+pub fn is_gzip_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gz"))
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+};
+
+fn crc32(crc: u32, bytes: &[u8]) -> u32 {
+    let mut c = !crc;
+    for &b in bytes {
+        c = CRC_TABLE[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8);
+    }
+    !c
+}
+
+/// A canonical Huffman code, as the number of codes of each length and the
+/// symbols in code order
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Huffman, GzipError> {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(GzipError::Corrupt("over-subscribed Huffman code"));
+            }
+        }
+        let mut offsets = [0u16; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+        Ok(Huffman { counts, symbols })
+    }
+
+    /// The tables of a fixed Huffman block
+    fn fixed() -> (Huffman, Huffman) {
+        let mut lengths = [8; 288];
+        lengths[144..256].fill(9);
+        lengths[256..280].fill(7);
+        let literals = Huffman::new(&lengths).expect("the fixed code is complete");
+        let distances = Huffman::new(&[5; 30]).expect("the fixed code is complete");
+        (literals, distances)
+    }
+}
+
+/// Reads the bits of a deflate stream, least significant first
+struct BitReader<R> {
+    inner: R,
+    buf: Vec<u8>,
+    start: usize,
+    end: usize,
+    bits: u32,
+    count: u32,
+}
+
+impl<R: Read> BitReader<R> {
+    fn new(inner: R) -> BitReader<R> {
+        BitReader {
+            inner,
+            buf: vec![0; 1 << 13],
+            start: 0,
+            end: 0,
+            bits: 0,
+            count: 0,
+        }
+    }
+
+    /// Whether the input is used up, reading more of it to find out
+    fn at_end(&mut self) -> io::Result<bool> {
+        if self.start == self.end {
+            self.start = 0;
+            self.end = loop {
+                match self.inner.read(&mut self.buf) {
+                    Ok(n) => break n,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => return Err(err),
+                }
+            };
+        }
+        Ok(self.start == self.end)
+    }
+
+    /// The next whole byte, which must only be read between bit fields
+    fn byte(&mut self) -> io::Result<Option<u8>> {
+        if self.at_end()? {
+            return Ok(None);
+        }
+        self.start += 1;
+        Ok(Some(self.buf[self.start - 1]))
+    }
+
+    fn require_byte(&mut self) -> io::Result<u8> {
+        Ok(self.byte()?.ok_or(GzipError::Truncated)?)
+    }
+
+    fn u16_le(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes([
+            self.require_byte()?,
+            self.require_byte()?,
+        ]))
+    }
+
+    fn u32_le(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes([
+            self.require_byte()?,
+            self.require_byte()?,
+            self.require_byte()?,
+            self.require_byte()?,
+        ]))
+    }
+
+    /// The next `n` bits, `n` at most 16
+    fn bits(&mut self, n: u32) -> io::Result<u32> {
+        while self.count < n {
+            self.bits |= (self.require_byte()? as u32) << self.count;
+            self.count += 8;
+        }
+        let value = self.bits & ((1 << n) - 1);
+        self.bits >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    /// Skips to the next byte boundary
+    fn align(&mut self) {
+        self.bits = 0;
+        self.count = 0;
+    }
+
+    fn decode(&mut self, code: &Huffman) -> io::Result<u16> {
+        let (mut bits, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &code.counts[1..] {
+            bits |= self.bits(1)? as i32;
+            let count = count as i32;
+            if bits - first < count {
+                return Ok(code.symbols[(index + bits - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            bits <<= 1;
+        }
+        Err(GzipError::Corrupt("invalid Huffman code").into())
+    }
+}
+
+enum State {
+    Header,
+    /// Between blocks
+    Block,
+    /// In a stored block, with this many bytes left
+    Stored(usize),
+    /// In a compressed block, with its literal/length and distance codes
+    Codes(Box<(Huffman, Huffman)>),
+    Trailer,
+    Done,
+}
+
+/// Decompresses gzip data as it's read. Concatenated gzip members are read
+/// as one stream, like `gzip -d` does
+pub struct GzDecoder<R> {
+    input: BitReader<R>,
+    state: State,
+    last_block: bool,
+    /// The last `WINDOW` bytes of output, which matches copy from
+    window: Vec<u8>,
+    window_pos: usize,
+    /// Output of the current member so far, capped at `WINDOW`
+    seen: usize,
+    out: Vec<u8>,
+    out_pos: usize,
+    crc: u32,
+    size: u32,
+    /// Whether a whole member has been read, after which more data must be
+    /// another member
+    read_member: bool,
+}
+
+impl<R: Read> GzDecoder<R> {
+    pub fn new(inner: R) -> GzDecoder<R> {
+        GzDecoder {
+            input: BitReader::new(inner),
+            state: State::Header,
+            last_block: false,
+            window: vec![0; WINDOW],
+            window_pos: 0,
+            seen: 0,
+            out: Vec::with_capacity(CHUNK + MAX_MATCH),
+            out_pos: 0,
+            crc: 0,
+            size: 0,
+            read_member: false,
+        }
+    }
+
+    fn emit(&mut self, byte: u8) {
+        self.window[self.window_pos] = byte;
+        self.window_pos = (self.window_pos + 1) % WINDOW;
+        self.seen = (self.seen + 1).min(WINDOW);
+        self.out.push(byte);
+    }
+
+    fn read_header(&mut self) -> io::Result<()> {
+        let magic = [self.input.byte()?, self.input.byte()?];
+        if magic != [Some(MAGIC[0]), Some(MAGIC[1])] {
+            if self.read_member {
+                return Err(GzipError::Corrupt("trailing data after the stream").into());
+            }
+            return Err(GzipError::NotGzip.into());
+        }
+        let method = self.input.require_byte()?;
+        if method != DEFLATE {
+            return Err(GzipError::UnsupportedMethod(method).into());
+        }
+        let flags = self.input.require_byte()?;
+        if flags & 0xe0 != 0 {
+            return Err(GzipError::Corrupt("reserved header flags are set").into());
+        }
+        // modification time, extra flags and operating system
+        for _ in 0..6 {
+            self.input.require_byte()?;
+        }
+        if flags & FEXTRA != 0 {
+            for _ in 0..self.input.u16_le()? {
+                self.input.require_byte()?;
+            }
+        }
+        for flag in [FNAME, FCOMMENT] {
+            if flags & flag != 0 {
+                while self.input.require_byte()? != 0 {}
+            }
+        }
+        if flags & FHCRC != 0 {
+            self.input.u16_le()?;
+        }
+        self.last_block = false;
+        self.seen = 0;
+        self.crc = 0;
+        self.size = 0;
+        Ok(())
+    }
+
+    fn read_dynamic_codes(&mut self) -> io::Result<(Huffman, Huffman)> {
+        let literals = self.input.bits(5)? as usize + 257;
+        let distances = self.input.bits(5)? as usize + 1;
+        let code_lengths = self.input.bits(4)? as usize + 4;
+        if literals > 286 || distances > 30 {
+            return Err(GzipError::Corrupt("too many length or distance codes").into());
+        }
+        let mut lengths = [0u8; 19];
+        for &i in &CODE_LENGTH_ORDER[..code_lengths] {
+            lengths[i] = self.input.bits(3)? as u8;
+        }
+        let code = Huffman::new(&lengths)?;
+
+        let mut lengths = vec![0u8; literals + distances];
+        let mut i = 0;
+        while i < lengths.len() {
+            let symbol = self.input.decode(&code)?;
+            let (value, repeat) = match symbol {
+                0..=15 => (symbol as u8, 1),
+                16 if i == 0 => {
+                    return Err(GzipError::Corrupt("repeated length with no first length").into())
+                }
+                16 => (lengths[i - 1], 3 + self.input.bits(2)? as usize),
+                17 => (0, 3 + self.input.bits(3)? as usize),
+                _ => (0, 11 + self.input.bits(7)? as usize),
+            };
+            if i + repeat > lengths.len() {
+                return Err(GzipError::Corrupt("too many code lengths").into());
+            }
+            lengths[i..i + repeat].fill(value);
+            i += repeat;
+        }
+        if lengths[256] == 0 {
+            return Err(GzipError::Corrupt("missing end-of-block code").into());
+        }
+        Ok((
+            Huffman::new(&lengths[..literals])?,
+            Huffman::new(&lengths[literals..])?,
+        ))
+    }
+
+    /// Decodes up to about `CHUNK` bytes of output into `out`
+    fn step(&mut self) -> io::Result<()> {
+        match std::mem::replace(&mut self.state, State::Done) {
+            State::Header => {
+                self.read_header()?;
+                self.state = State::Block;
+            }
+            State::Block if self.last_block => self.state = State::Trailer,
+            State::Block => {
+                self.last_block = self.input.bits(1)? == 1;
+                self.state = match self.input.bits(2)? {
+                    0 => {
+                        self.input.align();
+                        let len = self.input.u16_le()?;
+                        if self.input.u16_le()? != !len {
+                            return Err(
+                                GzipError::Corrupt("stored block length check failed").into()
+                            );
+                        }
+                        State::Stored(len as usize)
+                    }
+                    1 => State::Codes(Box::new(Huffman::fixed())),
+                    2 => State::Codes(Box::new(self.read_dynamic_codes()?)),
+                    _ => return Err(GzipError::Corrupt("invalid block type").into()),
+                };
+            }
+            State::Stored(left) => {
+                let n = left.min(CHUNK);
+                for _ in 0..n {
+                    let byte = self.input.require_byte()?;
+                    self.emit(byte);
+                }
+                self.state = if n == left {
+                    State::Block
+                } else {
+                    State::Stored(left - n)
+                };
+            }
+            State::Codes(codes) => {
+                let (literals, distances) = &*codes;
+                while self.out.len() < CHUNK {
+                    let symbol = self.input.decode(literals)? as usize;
+                    if symbol < 256 {
+                        self.emit(symbol as u8);
+                        continue;
+                    }
+                    if symbol == 256 {
+                        self.state = State::Block;
+                        return Ok(());
+                    }
+                    let index = symbol - 257;
+                    if index >= LENGTH_BASE.len() {
+                        return Err(GzipError::Corrupt("invalid length code").into());
+                    }
+                    let len = LENGTH_BASE[index] as usize
+                        + self.input.bits(LENGTH_EXTRA[index] as u32)? as usize;
+                    let index = self.input.decode(distances)? as usize;
+                    if index >= DIST_BASE.len() {
+                        return Err(GzipError::Corrupt("invalid distance code").into());
+                    }
+                    let dist = DIST_BASE[index] as usize
+                        + self.input.bits(DIST_EXTRA[index] as u32)? as usize;
+                    if dist > self.seen {
+                        return Err(GzipError::Corrupt("distance too far back").into());
+                    }
+                    for _ in 0..len {
+                        let byte = self.window[(self.window_pos + WINDOW - dist) % WINDOW];
+                        self.emit(byte);
+                    }
+                }
+                self.state = State::Codes(codes);
+            }
+            State::Trailer => {
+                self.input.align();
+                if self.input.u32_le()? != self.crc {
+                    return Err(GzipError::Corrupt("CRC mismatch").into());
+                }
+                if self.input.u32_le()? != self.size {
+                    return Err(GzipError::Corrupt("length mismatch").into());
+                }
+                self.read_member = true;
+                self.state = if self.input.at_end()? {
+                    State::Done
+                } else {
+                    State::Header
+                };
+            }
+            State::Done => {}
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for GzDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.out_pos == self.out.len() {
+            if matches!(self.state, State::Done) {
+                return Ok(0);
+            }
+            self.out.clear();
+            self.out_pos = 0;
+            self.step()?;
+            self.crc = crc32(self.crc, &self.out);
+            self.size = self.size.wrapping_add(self.out.len() as u32);
+        }
+        let n = buf.len().min(self.out.len() - self.out_pos);
+        buf[..n].copy_from_slice(&self.out[self.out_pos..self.out_pos + n]);
+        self.out_pos += n;
+        Ok(n)
+    }
+}
+
+/// Writes the bits of a deflate stream, least significant first
+struct BitWriter<W> {
+    inner: W,
+    buf: Vec<u8>,
+    bits: u64,
+    count: u32,
+}
+
+impl<W: Write> BitWriter<W> {
+    fn bits(&mut self, value: u32, n: u32) {
+        self.bits |= (value as u64) << self.count;
+        self.count += n;
+        while self.count >= 8 {
+            self.buf.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Writes a Huffman code, which is stored most significant bit first
+    fn code(&mut self, code: u32, len: u32) {
+        self.bits(code.reverse_bits() >> (32 - len), len);
+    }
+
+    /// Pads to the next byte boundary
+    fn align(&mut self) {
+        if self.count > 0 {
+            self.bits(0, 8 - self.count);
+        }
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Writes the buffered whole bytes to `inner`
+    fn drain(&mut self) -> io::Result<()> {
+        self.inner.write_all(&self.buf)?;
+        self.buf.clear();
+        Ok(())
+    }
+}
+
+/// Writes a literal/length symbol in the fixed Huffman code
+fn fixed_literal(out: &mut BitWriter<impl Write>, symbol: u32) {
+    match symbol {
+        0..=143 => out.code(0x30 + symbol, 8),
+        144..=255 => out.code(0x190 + symbol - 144, 9),
+        256..=279 => out.code(symbol - 256, 7),
+        _ => out.code(0xc0 + symbol - 280, 8),
+    }
+}
+
+/// Index of the last entry of `bases` at most `value`
+fn base_index(bases: &[u16], value: usize) -> usize {
+    bases
+        .iter()
+        .rposition(|&base| base as usize <= value)
+        .unwrap_or(0)
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    ((bytes[0] as usize) << 10 ^ (bytes[1] as usize) << 5 ^ bytes[2] as usize) % WINDOW
+}
+
+/// Compresses data into gzip as it's written, in deflate blocks with fixed
+/// Huffman codes and matches found by hash chains searched as deeply as the
+/// level says. Level 0 stores the data uncompressed. `finish` must be
+/// called to write the end of the stream
+pub struct GzEncoder<W: Write> {
+    out: BitWriter<W>,
+    level: u32,
+    pending: Vec<u8>,
+    /// The last `WINDOW` bytes compressed, which matches can reach into
+    window: Vec<u8>,
+    crc: u32,
+    size: u32,
+}
+
+impl<W: Write> GzEncoder<W> {
+    /// Starts a gzip stream at `level`, from 0 for none to `MAX_LEVEL`
+    pub fn new(inner: W, level: u32) -> GzEncoder<W> {
+        let level = level.min(MAX_LEVEL);
+        let mut out = BitWriter {
+            inner,
+            buf: Vec::with_capacity(BLOCK),
+            bits: 0,
+            count: 0,
+        };
+        let extra_flags = match level {
+            MAX_LEVEL => 2,
+            1 => 4,
+            _ => 0,
+        };
+        // no flags, no modification time, unknown operating system
+        out.bytes(&[MAGIC[0], MAGIC[1], DEFLATE, 0, 0, 0, 0, 0, extra_flags, 255]);
+        GzEncoder {
+            out,
+            level,
+            pending: Vec::with_capacity(BLOCK),
+            window: vec![],
+            crc: 0,
+            size: 0,
+        }
+    }
+
+    fn stored_block(&mut self, block: &[u8], last: bool) {
+        let mut chunks: Vec<&[u8]> = block.chunks(u16::MAX as usize).collect();
+        if chunks.is_empty() {
+            chunks.push(&[]);
+        }
+        let count = chunks.len();
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            self.out.bits((last && i + 1 == count) as u32, 1);
+            self.out.bits(0, 2);
+            self.out.align();
+            let len = chunk.len() as u16;
+            self.out.bytes(&len.to_le_bytes());
+            self.out.bytes(&(!len).to_le_bytes());
+            self.out.bytes(chunk);
+        }
+    }
+
+    fn fixed_block(&mut self, block: &[u8], last: bool) {
+        self.out.bits(last as u32, 1);
+        self.out.bits(1, 2);
+
+        let start = self.window.len();
+        let mut data = std::mem::take(&mut self.window);
+        data.extend_from_slice(block);
+        // most recent position of each hash plus one, and the previous
+        // position with the same hash of each position
+        let mut head = vec![0usize; WINDOW];
+        let mut prev = vec![0usize; data.len()];
+        let insert = |pos: usize, head: &mut [usize], prev: &mut [usize]| {
+            if pos + MIN_MATCH <= data.len() {
+                let h = hash(&data[pos..]);
+                prev[pos] = head[h];
+                head[h] = pos + 1;
+            }
+        };
+        for pos in 0..start {
+            insert(pos, &mut head, &mut prev);
+        }
+
+        let max_chain = MAX_CHAIN[self.level as usize];
+        let mut pos = start;
+        while pos < data.len() {
+            let max_len = (data.len() - pos).min(MAX_MATCH);
+            let (mut best_len, mut best_dist) = (0, 0);
+            if max_len >= MIN_MATCH {
+                let mut candidate = head[hash(&data[pos..])];
+                let mut chain = 0;
+                while candidate > 0 && chain < max_chain {
+                    let at = candidate - 1;
+                    if pos - at > WINDOW {
+                        break;
+                    }
+                    let len = data[at..]
+                        .iter()
+                        .zip(&data[pos..pos + max_len])
+                        .take_while(|(x, y)| x == y)
+                        .count();
+                    if len > best_len {
+                        (best_len, best_dist) = (len, pos - at);
+                        if len == max_len {
+                            break;
+                        }
+                    }
+                    candidate = prev[at];
+                    chain += 1;
+                }
+            }
+
+            if best_len >= MIN_MATCH {
+                let index = base_index(&LENGTH_BASE, best_len);
+                fixed_literal(&mut self.out, 257 + index as u32);
+                self.out.bits(
+                    (best_len - LENGTH_BASE[index] as usize) as u32,
+                    LENGTH_EXTRA[index] as u32,
+                );
+                let index = base_index(&DIST_BASE, best_dist);
+                self.out.code(index as u32, 5);
+                self.out.bits(
+                    (best_dist - DIST_BASE[index] as usize) as u32,
+                    DIST_EXTRA[index] as u32,
+                );
+                for at in pos..pos + best_len {
+                    insert(at, &mut head, &mut prev);
+                }
+                pos += best_len;
+            } else {
+                fixed_literal(&mut self.out, data[pos] as u32);
+                insert(pos, &mut head, &mut prev);
+                pos += 1;
+            }
+        }
+        fixed_literal(&mut self.out, 256);
+
+        let keep = data.len().saturating_sub(WINDOW);
+        data.drain(..keep);
+        self.window = data;
+    }
+
+    fn block(&mut self, block: &[u8], last: bool) -> io::Result<()> {
+        if self.level == 0 {
+            self.stored_block(block, last);
+        } else {
+            self.fixed_block(block, last);
+        }
+        self.out.drain()
+    }
+
+    /// Writes the rest of the stream and returns the inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        let pending = std::mem::take(&mut self.pending);
+        self.block(&pending, true)?;
+        self.out.align();
+        self.out.bytes(&self.crc.to_le_bytes());
+        self.out.bytes(&self.size.to_le_bytes());
+        self.out.drain()?;
+        self.out.inner.flush()?;
+        Ok(self.out.inner)
+    }
+}
+
+impl<W: Write> Write for GzEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.crc = crc32(self.crc, buf);
+        self.size = self.size.wrapping_add(buf.len() as u32);
+        self.pending.extend_from_slice(buf);
+        while self.pending.len() >= BLOCK {
+            let rest = self.pending.split_off(BLOCK);
+            let block = std::mem::replace(&mut self.pending, rest);
+            self.block(&block, false)?;
+        }
+        Ok(buf.len())
+    }
+
+    /// Flushes the compressed blocks written so far. Data still waiting to
+    /// fill a block is only written by `finish`
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.inner.flush()
+    }
+}
+
+/// `bytes` compressed into a gzip stream at `level`
+pub fn gzip(bytes: &[u8], level: u32) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(vec![], level);
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn gunzip(bytes: &[u8]) -> Result<Vec<u8>, io::Error> {
+        let mut out = vec![];
+        GzDecoder::new(bytes).read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    fn gzip_error(bytes: &[u8]) -> GzipError {
+        let err = gunzip(bytes).unwrap_err();
+        *err.into_inner().unwrap().downcast::<GzipError>().unwrap()
+    }
+
+    #[test]
+    fn every_level_round_trips() {
+        let text: Vec<u8> = (0..20_000u32)
+            .flat_map(|i| format!("{} {}\n", i % 97, i % 13).into_bytes())
+            .collect();
+        for level in 0..=MAX_LEVEL {
+            let compressed = gzip(&text, level).unwrap();
+            assert_eq!(gunzip(&compressed).unwrap(), text, "level {}", level);
+            if level > 0 {
+                assert!(compressed.len() < text.len(), "level {}", level);
+            }
+        }
+        assert_eq!(gunzip(&gzip(b"", 6).unwrap()).unwrap(), b"");
+    }
+
+    #[test]
+    fn concatenated_members_read_as_one_stream() {
+        let mut bytes = gzip(b"1 2\n", 6).unwrap();
+        bytes.extend(gzip(b"3 4\n", 0).unwrap());
+        assert_eq!(gunzip(&bytes).unwrap(), b"1 2\n3 4\n");
+    }
+
+    #[test]
+    fn damaged_streams_are_errors() {
+        assert_eq!(gzip_error(b"1 2\n3 4\n"), GzipError::NotGzip);
+        let good = gzip(b"1 2\n3 4\n", 6).unwrap();
+        assert_eq!(gzip_error(&good[..good.len() - 3]), GzipError::Truncated);
+        let mut method = good.clone();
+        method[2] = 7;
+        assert_eq!(gzip_error(&method), GzipError::UnsupportedMethod(7));
+        let mut crc = good.clone();
+        let at = crc.len() - 8;
+        crc[at] ^= 1;
+        assert!(matches!(gzip_error(&crc), GzipError::Corrupt(_)));
+    }
+
+    #[test]
+    fn gz_extension_is_case_insensitive() {
+        assert!(is_gzip_path(Path::new("a.csv.gz")));
+        assert!(is_gzip_path(Path::new("A.TXT.GZ")));
+        assert!(!is_gzip_path(Path::new("a.csv")));
+        assert!(!is_gzip_path(Path::new("gz")));
+    }
+}
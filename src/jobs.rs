@@ -0,0 +1,448 @@
+use crate::{
+    backend::{default_threads, find_backend, is_multithreaded, DEFAULT_ENGINE, ENGINES},
+    cli::{read_matrix_file, CliError, ReadOptions},
+    export::{export_answer, WriteOptions},
+    files::FileFormat,
+    log::{self, Level},
+    matrix::{panic_message, Answer, MatrixError},
+    operation::Operation,
+    parse::FormatError,
+};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// One entry of a batch manifest
+#[derive(Debug, Clone, PartialEq)]
+pub struct Job {
+    pub name: String,
+    pub a: PathBuf,
+    pub b: PathBuf,
+    pub operation: Operation,
+    pub output: PathBuf,
+    /// Multiplication engine, for `multiply` jobs only
+    pub engine: Option<String>,
+    pub threads: Option<usize>,
+}
+
+/// A value in a manifest, which is a string or an integer
+enum Value {
+    String(String),
+    Integer(i64),
+}
+
+/// Parses a double-quoted string or an integer, with `#` comments after it
+fn parse_value(text: &str, line: usize, column: usize) -> Result<Value, FormatError> {
+    let error = |message: &str| FormatError::at(line, column, message);
+    let Some(rest) = text.strip_prefix('"') else {
+        let number = text.split('#').next().unwrap_or("").trim();
+        return number
+            .parse()
+            .map(Value::Integer)
+            .map_err(|_| error("expected a quoted string or an integer"));
+    };
+
+    let mut value = String::new();
+    let mut chars = rest.chars();
+    loop {
+        match chars.next() {
+            None => return Err(error("unterminated string")),
+            Some('"') => break,
+            Some('\\') => match chars.next() {
+                Some('"') => value.push('"'),
+                Some('\\') => value.push('\\'),
+                Some('t') => value.push('\t'),
+                _ => return Err(error("invalid escape in string")),
+            },
+            Some(c) => value.push(c),
+        }
+    }
+    let trailing = chars.as_str().trim();
+    if !trailing.is_empty() && !trailing.starts_with('#') {
+        return Err(error("unexpected text after the value"));
+    }
+    Ok(Value::String(value))
+}
+
+/// The fields of a `[[job]]` table read so far
+#[derive(Default)]
+struct PartialJob {
+    line: usize,
+    name: Option<String>,
+    a: Option<PathBuf>,
+    b: Option<PathBuf>,
+    operation: Option<Operation>,
+    output: Option<PathBuf>,
+    engine: Option<String>,
+    threads: Option<usize>,
+}
+
+impl PartialJob {
+    fn finish(self, index: usize) -> Result<Job, FormatError> {
+        let missing = |key: &str| {
+            FormatError::new(self.line, format!("job {} is missing {:?}", index + 1, key))
+        };
+        let operation = self.operation.unwrap_or(Operation::Multiply);
+        if operation != Operation::Multiply && (self.engine.is_some() || self.threads.is_some()) {
+            return Err(FormatError::new(
+                self.line,
+                format!(
+                    "job {}: engine and threads only apply to multiply, not {}",
+                    index + 1,
+                    operation.name()
+                ),
+            ));
+        }
+        let engine = self.engine.as_deref().unwrap_or(DEFAULT_ENGINE);
+        if self.threads.is_some() && !is_multithreaded(engine) {
+            return Err(FormatError::new(
+                self.line,
+                format!(
+                    "job {}: threads only apply to the threaded engine, not {}",
+                    index + 1,
+                    engine
+                ),
+            ));
+        }
+        Ok(Job {
+            name: self.name.unwrap_or_else(|| format!("job {}", index + 1)),
+            a: self.a.ok_or_else(|| missing("a"))?,
+            b: self.b.ok_or_else(|| missing("b"))?,
+            operation,
+            output: self.output.ok_or_else(|| missing("output"))?,
+            engine: self.engine,
+            threads: self.threads,
+        })
+    }
+}
+
+/// Parses a manifest of `[[job]]` tables, a small subset of TOML:
+///
+/// ```toml
+/// [[job]]
+/// name = "nightly"        # optional, defaults to "job N"
+/// a = "a.txt"
+/// b = "b.csv"
+/// operation = "multiply"  # optional: multiply, add, subtract or hadamard
+/// output = "ab.mtx"
+/// engine = "threaded"     # optional, multiply only
+/// threads = 4             # optional, multiply only
+/// ```
+///
+/// Relative paths are resolved against `base`, the manifest's directory
+pub fn parse_manifest(text: &str, base: &Path) -> Result<Vec<Job>, FormatError> {
+    let mut jobs: Vec<PartialJob> = vec![];
+    for (i, raw) in text.lines().enumerate() {
+        let line = i + 1;
+        let trimmed = raw.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if trimmed.starts_with('[') {
+            if trimmed.split('#').next().unwrap_or("").trim() != "[[job]]" {
+                return Err(FormatError::new(
+                    line,
+                    format!("unsupported table {}, expected [[job]]", trimmed),
+                ));
+            }
+            jobs.push(PartialJob {
+                line,
+                ..PartialJob::default()
+            });
+            continue;
+        }
+
+        let Some((key, value)) = trimmed.split_once('=') else {
+            return Err(FormatError::new(line, "expected `key = value`"));
+        };
+        let key = key.trim();
+        let value_text = value.trim();
+        let column = raw.len() - raw.trim_start().len() + trimmed.len() - value_text.len() + 1;
+        let value = parse_value(value_text, line, column)?;
+        let Some(job) = jobs.last_mut() else {
+            return Err(FormatError::new(
+                line,
+                "expected [[job]] before the first key",
+            ));
+        };
+        let error = |message: String| FormatError::at(line, column, message);
+        match (key, value) {
+            ("name", Value::String(s)) => job.name = Some(s),
+            ("a", Value::String(s)) => job.a = Some(base.join(s)),
+            ("b", Value::String(s)) => job.b = Some(base.join(s)),
+            ("output", Value::String(s)) => job.output = Some(base.join(s)),
+            ("operation", Value::String(s)) => {
+                job.operation = Some(
+                    Operation::from_name(&s)
+                        .ok_or_else(|| error(format!("unknown operation {:?}", s)))?,
+                )
+            }
+            ("engine", Value::String(s)) => {
+                if !ENGINES.contains(&s.as_str()) {
+                    return Err(error(format!(
+                        "unknown engine {:?}, expected one of {}",
+                        s,
+                        ENGINES.join(", ")
+                    )));
+                }
+                job.engine = Some(s);
+            }
+            ("threads", Value::Integer(n)) if n > 0 => job.threads = Some(n as usize),
+            ("threads", _) => return Err(error(String::from("expected a positive integer"))),
+            ("name" | "a" | "b" | "output" | "operation" | "engine", _) => {
+                return Err(error(format!("{} must be a string", key)))
+            }
+            _ => return Err(FormatError::new(line, format!("unknown key {:?}", key))),
+        }
+    }
+    jobs.into_iter()
+        .enumerate()
+        .map(|(i, job)| job.finish(i))
+        .collect()
+}
+
+/// The outcome of one job
+#[derive(Debug)]
+pub struct JobReport {
+    pub name: String,
+    pub output: PathBuf,
+    pub elapsed: Duration,
+    /// The format the result was written in, or why the job failed
+    pub result: Result<FileFormat, CliError>,
+}
+
+/// Reads the inputs of `job`, computes its operation and writes the result
+pub fn run_job(
+    job: &Job,
+    read: &ReadOptions,
+    write: &WriteOptions,
+) -> Result<FileFormat, CliError> {
+    let _span = log::span(Level::Info, "job", format_args!("name={:?}", job.name));
+    let a = read_matrix_file(&job.a, read)?;
+    let b = read_matrix_file(&job.b, read)?;
+    let result = match job.operation {
+        Operation::Multiply => {
+            let name = job.engine.as_deref().unwrap_or(DEFAULT_ENGINE);
+            let threads = job.threads.unwrap_or_else(default_threads);
+            find_backend(name, threads)
+                .ok_or_else(|| CliError::Usage(format!("unknown engine {:?}", name)))?
+                .multiply(&a, &b)?
+        }
+        operation => operation.apply(&a, &b)?,
+    };
+    Ok(export_answer(
+        &job.output,
+        Some(&Answer::Int(result)),
+        write,
+    )?)
+}
+
+/// Runs every job on up to `parallel` threads, carrying on past failures,
+/// including a job that panics. Reports are returned in manifest order
+pub fn run_jobs(
+    jobs: &[Job],
+    parallel: usize,
+    read: &ReadOptions,
+    write: &WriteOptions,
+) -> Vec<JobReport> {
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..parallel.clamp(1, jobs.len().max(1)) {
+            let tx = tx.clone();
+            let next = &next;
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(job) = jobs.get(index) else {
+                    break;
+                };
+                let start = Instant::now();
+                let result = panic::catch_unwind(AssertUnwindSafe(|| run_job(job, read, write)))
+                    .unwrap_or_else(|payload| {
+                        Err(MatrixError::ComputationFailed(panic_message(payload)).into())
+                    });
+                let report = JobReport {
+                    name: job.name.clone(),
+                    output: job.output.clone(),
+                    elapsed: start.elapsed(),
+                    result,
+                };
+                if tx.send((index, report)).is_err() {
+                    break;
+                }
+            });
+        }
+    });
+    drop(tx);
+    let mut reports: Vec<(usize, JobReport)> = rx.iter().collect();
+    reports.sort_by_key(|(index, _)| *index);
+    reports.into_iter().map(|(_, report)| report).collect()
+}
+
+/// One line per job with its status and time, then a count of the
+/// successful jobs
+pub fn format_job_summary(reports: &[JobReport]) -> String {
+    let width = reports.iter().map(|r| r.name.len()).max().unwrap_or(0);
+    let mut text = String::new();
+    for report in reports {
+        let (status, detail) = match &report.result {
+            Ok(format) => (
+                "ok",
+                format!("wrote {} as {}", report.output.display(), format.name()),
+            ),
+            Err(err) => ("FAILED", err.to_string()),
+        };
+        text.push_str(&format!(
+            "{:<6}  {:<width$}  {:>9.3}s  {}\n",
+            status,
+            report.name,
+            report.elapsed.as_secs_f64(),
+            detail,
+            width = width
+        ));
+    }
+    let succeeded = reports.iter().filter(|r| r.result.is_ok()).count();
+    text.push_str(&format!(
+        "{} of {} jobs succeeded\n",
+        succeeded,
+        reports.len()
+    ));
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("matrixmult-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn run_jobs_carries_on_past_failing_jobs() {
+        let dir = temp_dir("jobs");
+        fs::write(dir.join("a.txt"), "1 2\n3 4\n").unwrap();
+        fs::write(dir.join("big.txt"), "2147483647 2147483647 2147483647\n").unwrap();
+        fs::write(dir.join("tall.txt"), "2147483647\n".repeat(3)).unwrap();
+        let manifest = "[[job]]\na = \"big.txt\"\nb = \"tall.txt\"\noutput = \"big.txt.out\"\n\
+                        [[job]]\na = \"a.txt\"\nb = \"a.txt\"\noutput = \"aa.csv\"\n\
+                        [[job]]\na = \"missing.txt\"\nb = \"a.txt\"\noutput = \"m.csv\"\n";
+        let jobs = parse_manifest(manifest, &dir).unwrap();
+        let write = WriteOptions {
+            overwrite: true,
+            ..WriteOptions::default()
+        };
+        let reports = run_jobs(&jobs, 2, &ReadOptions::default(), &write);
+
+        assert_eq!(reports.len(), 3);
+        assert!(matches!(
+            reports[0].result,
+            Err(CliError::Matrix(MatrixError::Overflow { bits: 64 }))
+        ));
+        assert!(matches!(reports[1].result, Ok(FileFormat::Csv)));
+        assert!(reports[2].result.is_err());
+        assert_eq!(
+            fs::read_to_string(dir.join("aa.csv")).unwrap(),
+            "7,10\n15,22\n"
+        );
+        assert!(format_job_summary(&reports).ends_with("1 of 3 jobs succeeded\n"));
+    }
+
+    #[test]
+    fn manifests_list_jobs_relative_to_their_directory() {
+        let text = "# nightly runs\n\
+                    [[job]]\n\
+                    name = \"first \\\"one\\\"\"  # comment\n\
+                    a = \"a.txt\"\n\
+                    b = \"b.csv\"\n\
+                    output = \"ab.mtx\"\n\
+                    engine = \"threaded\"\n\
+                    threads = 4\n\
+                    \n\
+                    [[job]]\n\
+                    a = \"/abs/a.txt\"\n\
+                    b = \"b.txt\"\n\
+                    operation = \"add\"\n\
+                    output = \"sum.txt\"\n";
+        let jobs = parse_manifest(text, Path::new("/work")).unwrap();
+        assert_eq!(
+            jobs,
+            vec![
+                Job {
+                    name: String::from("first \"one\""),
+                    a: PathBuf::from("/work/a.txt"),
+                    b: PathBuf::from("/work/b.csv"),
+                    operation: Operation::Multiply,
+                    output: PathBuf::from("/work/ab.mtx"),
+                    engine: Some(String::from("threaded")),
+                    threads: Some(4),
+                },
+                Job {
+                    name: String::from("job 2"),
+                    a: PathBuf::from("/abs/a.txt"),
+                    b: PathBuf::from("/work/b.txt"),
+                    operation: Operation::Add,
+                    output: PathBuf::from("/work/sum.txt"),
+                    engine: None,
+                    threads: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn manifest_errors_give_the_line_and_column() {
+        let job = "[[job]]\na = \"a\"\nb = \"b\"\noutput = \"c\"\n";
+        let cases = [
+            ("a = \"a\"\n", 1, None, "expected [[job]]"),
+            ("[jobs]\n", 1, None, "unsupported table"),
+            ("[[job]]\na \"a\"\n", 2, None, "key = value"),
+            ("[[job]]\na = \"a\n", 2, Some(5), "unterminated"),
+            ("[[job]]\na = a.txt\n", 2, Some(5), "quoted string"),
+            ("[[job]]\na = \"a\" b\n", 2, Some(5), "unexpected text"),
+            ("[[job]]\n  threads = 0\n", 2, Some(13), "positive integer"),
+            ("[[job]]\nengine = \"gpu\"\n", 2, Some(10), "unknown engine"),
+            (
+                "[[job]]\noperation = \"divide\"\n",
+                2,
+                Some(13),
+                "unknown operation",
+            ),
+            ("[[job]]\ncolour = \"red\"\n", 2, None, "unknown key"),
+            (
+                "[[job]]\na = \"a\"\nb = \"b\"\n",
+                1,
+                None,
+                "missing \"output\"",
+            ),
+        ];
+        for (text, line, column, message) in cases {
+            let err = parse_manifest(text, Path::new(".")).unwrap_err();
+            assert_eq!(
+                (err.line, err.column),
+                (line, column),
+                "{:?}: {}",
+                text,
+                err
+            );
+            assert!(err.message.contains(message), "{:?}: {}", text, err);
+        }
+
+        let threaded_only = format!("{}operation = \"add\"\nthreads = 2\n", job);
+        let err = parse_manifest(&threaded_only, Path::new(".")).unwrap_err();
+        assert!(err.message.contains("only apply to multiply"), "{}", err);
+        let narrow_threads = format!("{}threads = 2\n", job);
+        let err = parse_manifest(&narrow_threads, Path::new(".")).unwrap_err();
+        assert!(err.message.contains("not narrow"), "{}", err);
+    }
+}
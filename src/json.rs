@@ -0,0 +1,414 @@
+use crate::{
+    matrix::{Answer, Matrix},
+    parse::FormatError,
+};
+use std::{iter::Peekable, str::Chars};
+
+/// A parsed JSON value. Numbers keep their source text so integers too
+/// large for an f64 can still be checked against the element type
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+/// A JSON value and the line and column it starts at
+#[derive(Debug, Clone, PartialEq)]
+pub struct Json {
+    pub value: JsonValue,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Json {
+    fn error(&self, path: &str, message: impl std::fmt::Display) -> FormatError {
+        FormatError::at(self.line, self.column, format!("{}: {}", path, message))
+    }
+
+    /// Name of the value's type for error messages
+    fn kind(&self) -> &'static str {
+        match self.value {
+            JsonValue::Null => "null",
+            JsonValue::Bool(_) => "a boolean",
+            JsonValue::Number(_) => "a number",
+            JsonValue::String(_) => "a string",
+            JsonValue::Array(_) => "an array",
+            JsonValue::Object(_) => "an object",
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match &self.value {
+            JsonValue::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl Parser<'_> {
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, FormatError> {
+        Err(FormatError::at(self.line, self.column, message))
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.bump();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), FormatError> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some(&c) if c == expected => {
+                self.bump();
+                Ok(())
+            }
+            Some(&c) => self.error(format!("expected {:?}, found {:?}", expected, c)),
+            None => self.error(format!(
+                "expected {:?}, found the end of the input",
+                expected
+            )),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, FormatError> {
+        self.skip_whitespace();
+        let (line, column) = (self.line, self.column);
+        let value = match self.chars.peek() {
+            None => return self.error("unexpected end of the input"),
+            Some('{') => self.object()?,
+            Some('[') => self.array()?,
+            Some('"') => JsonValue::String(self.string()?),
+            Some('-' | '0'..='9') => self.number()?,
+            Some(_) => self.literal()?,
+        };
+        Ok(Json {
+            value,
+            line,
+            column,
+        })
+    }
+
+    fn object(&mut self) -> Result<JsonValue, FormatError> {
+        self.bump();
+        let mut fields = vec![];
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&'}') {
+            self.bump();
+            return Ok(JsonValue::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.chars.peek() != Some(&'"') {
+                return self.error("expected a string key");
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => continue,
+                Some('}') => return Ok(JsonValue::Object(fields)),
+                _ => return self.error("expected ',' or '}' after an object field"),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<JsonValue, FormatError> {
+        self.bump();
+        let mut items = vec![];
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&']') {
+            self.bump();
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => continue,
+                Some(']') => return Ok(JsonValue::Array(items)),
+                _ => return self.error("expected ',' or ']' after an array element"),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, FormatError> {
+        self.bump();
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                None => return self.error("unterminated string"),
+                Some('"') => return Ok(s),
+                Some('\\') => match self.bump() {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('/') => s.push('/'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('u') => {
+                        let hex: String = (0..4).filter_map(|_| self.bump()).collect();
+                        let c = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+                        match c {
+                            Some(c) => s.push(c),
+                            None => return self.error(format!("invalid escape \\u{}", hex)),
+                        }
+                    }
+                    _ => return self.error("invalid escape in string"),
+                },
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<JsonValue, FormatError> {
+        let mut text = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                break;
+            }
+            text.push(c);
+            self.bump();
+        }
+        if text.parse::<f64>().is_err() {
+            return self.error(format!("invalid number {:?}", text));
+        }
+        Ok(JsonValue::Number(text))
+    }
+
+    fn literal(&mut self) -> Result<JsonValue, FormatError> {
+        let mut word = String::new();
+        while self.chars.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            word.push(self.bump().unwrap());
+        }
+        match word.as_str() {
+            "null" => Ok(JsonValue::Null),
+            "true" => Ok(JsonValue::Bool(true)),
+            "false" => Ok(JsonValue::Bool(false)),
+            "" => {
+                let c = self.chars.peek().copied();
+                self.error(format!("unexpected {:?}", c.unwrap_or_default()))
+            }
+            _ => self.error(format!("unexpected {:?}", word)),
+        }
+    }
+}
+
+/// `text` as a JSON string literal, quoted and escaped
+pub fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Parses a complete JSON document
+pub fn parse_json(text: &str) -> Result<Json, FormatError> {
+    let mut parser = Parser {
+        chars: text.chars().peekable(),
+        line: 1,
+        column: 1,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.chars.peek().is_some() {
+        return parser.error("unexpected data after the JSON value");
+    }
+    Ok(value)
+}
+
+fn dimension(root: &Json, key: &str) -> Result<usize, FormatError> {
+    let value = root
+        .get(key)
+        .ok_or_else(|| root.error("$", format!("missing {:?}", key)))?;
+    match &value.value {
+        JsonValue::Number(n) => n
+            .parse()
+            .map_err(|_| value.error(key, format!("{} is not a valid dimension", n))),
+        _ => Err(value.error(key, format!("expected a number, found {}", value.kind()))),
+    }
+}
+
+/// Parses a matrix stored as `{"rows": r, "cols": c, "data": [[...], ...]}`.
+/// `data` must match the declared dimensions and every element must be an
+/// integer that fits in an i64. Errors name the offending element, e.g.
+/// `data[1][2]`
+pub fn parse_json_matrix(text: &str) -> Result<Matrix, FormatError> {
+    let root = parse_json(text)?;
+    if !matches!(root.value, JsonValue::Object(_)) {
+        return Err(root.error("$", format!("expected an object, found {}", root.kind())));
+    }
+    let rows = dimension(&root, "rows")?;
+    let cols = dimension(&root, "cols")?;
+    let data = root
+        .get("data")
+        .ok_or_else(|| root.error("$", "missing \"data\""))?;
+
+    let JsonValue::Array(data_rows) = &data.value else {
+        return Err(data.error("data", format!("expected an array, found {}", data.kind())));
+    };
+    if data_rows.len() != rows {
+        return Err(data.error(
+            "data",
+            format!("expected {} rows, found {}", rows, data_rows.len()),
+        ));
+    }
+
+    integer_rows(data_rows, "data", cols)
+}
+
+/// Parses a matrix written as a JSON array of rows, e.g. `[[1, 2], [3, 4]]`.
+/// Every row must be as long as the first and every element an i64
+pub fn parse_json_rows(text: &str) -> Result<Matrix, FormatError> {
+    let root = parse_json(text)?;
+    let JsonValue::Array(rows) = &root.value else {
+        return Err(root.error("$", format!("expected an array, found {}", root.kind())));
+    };
+    let cols = match rows.first().map(|row| &row.value) {
+        Some(JsonValue::Array(first)) => first.len(),
+        _ => 0,
+    };
+    integer_rows(rows, "$", cols)
+}
+
+/// The elements of `rows`, which must be arrays of `cols` integers. `path`
+/// names the array of rows in errors
+fn integer_rows(rows: &[Json], path: &str, cols: usize) -> Result<Matrix, FormatError> {
+    let mut m = Vec::with_capacity(rows.len());
+    for (i, row) in rows.iter().enumerate() {
+        let path = format!("{}[{}]", path, i);
+        let JsonValue::Array(elements) = &row.value else {
+            return Err(row.error(&path, format!("expected an array, found {}", row.kind())));
+        };
+        if elements.len() != cols {
+            return Err(row.error(
+                &path,
+                format!("expected {} columns, found {}", cols, elements.len()),
+            ));
+        }
+        let row = elements
+            .iter()
+            .enumerate()
+            .map(|(j, x)| {
+                let path = format!("{}[{}]", path, j);
+                match &x.value {
+                    JsonValue::Number(n) => n
+                        .parse::<i64>()
+                        .map_err(|_| x.error(&path, format!("{} is not an i64", n))),
+                    _ => Err(x.error(&path, format!("expected a number, found {}", x.kind()))),
+                }
+            })
+            .collect::<Result<Vec<i64>, FormatError>>()?;
+        m.push(row);
+    }
+    Ok(m)
+}
+
+/// Formats a result as `{"rows": r, "cols": c, "data": [[...], ...]}`, or
+/// `None` if it holds a NaN or infinity, which JSON can't represent
+pub fn format_json(answer: &Answer) -> Option<String> {
+    let cells: Vec<Vec<String>> = match answer {
+        Answer::Int(m) => m
+            .iter()
+            .map(|row| row.iter().map(|x| x.to_string()).collect())
+            .collect(),
+        Answer::Float(m) => m
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|x| x.is_finite().then(|| format!("{:?}", x)))
+                    .collect::<Option<Vec<String>>>()
+            })
+            .collect::<Option<_>>()?,
+    };
+    let (rows, cols) = answer.dims();
+    let data: Vec<String> = cells
+        .iter()
+        .map(|row| format!("[{}]", row.join(", ")))
+        .collect();
+    Some(format!(
+        "{{\"rows\": {}, \"cols\": {}, \"data\": [{}]}}\n",
+        rows,
+        cols,
+        data.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_matrices_round_trip() {
+        let m = vec![vec![1, -2, 3], vec![i64::MIN, 0, i64::MAX]];
+        let text = format_json(&Answer::Int(m.clone())).unwrap();
+        assert_eq!(parse_json_matrix(&text).unwrap(), m);
+        assert_eq!(
+            parse_json_rows("[[1, 2], [3, 4]]").unwrap(),
+            vec![vec![1, 2], vec![3, 4]]
+        );
+        assert_eq!(format_json(&Answer::Float(vec![vec![1.0, f64::NAN]])), None);
+    }
+
+    #[test]
+    fn json_errors_name_the_offending_element() {
+        let cases = [
+            ("[1, 2]", "expected an object"),
+            ("{\"rows\": 1, \"data\": [[1]]}", "missing \"cols\""),
+            (
+                "{\"rows\": 2, \"cols\": 1, \"data\": [[1]]}",
+                "expected 2 rows",
+            ),
+            (
+                "{\"rows\": 1, \"cols\": 2, \"data\": [[1, \"x\"]]}",
+                "data[0][1]",
+            ),
+            (
+                "{\"rows\": 1, \"cols\": 1, \"data\": [[1.5]]}",
+                "not an i64",
+            ),
+            (
+                "{\"rows\": 1, \"cols\": 1, \"data\": [[1]]} 2",
+                "unexpected data",
+            ),
+        ];
+        for (text, expected) in cases {
+            let err = parse_json_matrix(text).unwrap_err().to_string();
+            assert!(err.contains(expected), "{:?}: {}", text, err);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use crate::{format::answer_cells, matrix::Answer};
+
+/// What to do with results wider than `LatexOptions::max_cols`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WideMatrix {
+    /// Keep the first `max_cols` columns
+    Truncate,
+    /// Transpose the result if that makes it fit, truncating otherwise
+    Transpose,
+}
+
+/// How results are written as LaTeX
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatexOptions {
+    /// The amsmath environment, e.g. `bmatrix`, `pmatrix` or `matrix`
+    pub environment: String,
+    /// Wrap the environment in `\left[ ... \right]`, for plain `matrix`
+    pub brackets: bool,
+    pub max_cols: Option<usize>,
+    pub wide: WideMatrix,
+}
+
+impl Default for LatexOptions {
+    fn default() -> LatexOptions {
+        LatexOptions {
+            environment: String::from("bmatrix"),
+            brackets: false,
+            max_cols: None,
+            wide: WideMatrix::Truncate,
+        }
+    }
+}
+
+/// A result cell as LaTeX math, spelling out infinities and NaN
+fn latex_cell(cell: String) -> String {
+    match cell.as_str() {
+        "inf" => String::from("\\infty"),
+        "-inf" => String::from("-\\infty"),
+        "NaN" => String::from("\\text{NaN}"),
+        _ => cell,
+    }
+}
+
+/// Formats a result as a LaTeX matrix environment with `&` between columns
+/// and `\\` between rows. Results wider than `max_cols` are transposed or
+/// truncated as `options` says, with a `%` comment noting it
+pub fn format_latex(answer: &Answer, options: &LatexOptions) -> String {
+    let mut cells = answer_cells(answer);
+    let mut notes = vec![];
+    let (rows, cols) = answer.dims();
+
+    if let Some(max) = options.max_cols {
+        if cols > max && options.wide == WideMatrix::Transpose && rows < cols {
+            cells = transpose_strings(&cells);
+            notes.push(format!(
+                "% transposed: {}x{} shown as {}x{}",
+                rows, cols, cols, rows
+            ));
+        }
+        let shown = cells.first().map_or(0, |row| row.len());
+        if shown > max {
+            cells.iter_mut().for_each(|row| row.truncate(max));
+            notes.push(format!("% truncated: showing {} of {} columns", max, shown));
+        }
+    }
+
+    let mut text = String::new();
+    for note in notes {
+        text.push_str(&note);
+        text.push('\n');
+    }
+    if options.brackets {
+        text.push_str("\\left[\n");
+    }
+    text.push_str(&format!("\\begin{{{}}}\n", options.environment));
+    let lines: Vec<String> = cells
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(latex_cell)
+                .collect::<Vec<_>>()
+                .join(" & ")
+        })
+        .collect();
+    text.push_str(&lines.join(" \\\\\n"));
+    if !lines.is_empty() {
+        text.push('\n');
+    }
+    text.push_str(&format!("\\end{{{}}}\n", options.environment));
+    if options.brackets {
+        text.push_str("\\right]\n");
+    }
+    text
+}
+
+fn transpose_strings(cells: &[Vec<String>]) -> Vec<Vec<String>> {
+    let cols = cells.first().map_or(0, |row| row.len());
+    (0..cols)
+        .map(|j| cells.iter().map(|row| row[j].clone()).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latex_uses_the_chosen_environment() {
+        let answer = Answer::Int(vec![vec![1, 2], vec![3, 4]]);
+        assert_eq!(
+            format_latex(&answer, &LatexOptions::default()),
+            "\\begin{bmatrix}\n1 & 2 \\\\\n3 & 4\n\\end{bmatrix}\n"
+        );
+        let options = LatexOptions {
+            environment: String::from("matrix"),
+            brackets: true,
+            ..LatexOptions::default()
+        };
+        assert_eq!(
+            format_latex(&answer, &options),
+            "\\left[\n\\begin{matrix}\n1 & 2 \\\\\n3 & 4\n\\end{matrix}\n\\right]\n"
+        );
+    }
+
+    #[test]
+    fn latex_spells_out_infinities() {
+        let answer = Answer::Float(vec![vec![f64::INFINITY, f64::NEG_INFINITY, f64::NAN]]);
+        let text = format_latex(&answer, &LatexOptions::default());
+        assert!(
+            text.contains("\\infty & -\\infty & \\text{NaN}\n"),
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    fn wide_results_are_transposed_or_truncated() {
+        let wide = Answer::Int(vec![vec![1, 2, 3, 4]]);
+        let truncate = LatexOptions {
+            max_cols: Some(2),
+            ..LatexOptions::default()
+        };
+        assert_eq!(
+            format_latex(&wide, &truncate),
+            "% truncated: showing 2 of 4 columns\n\\begin{bmatrix}\n1 & 2\n\\end{bmatrix}\n"
+        );
+        let transpose = LatexOptions {
+            wide: WideMatrix::Transpose,
+            ..truncate
+        };
+        assert_eq!(
+            format_latex(&wide, &transpose),
+            "% transposed: 1x4 shown as 4x1\n\\begin{bmatrix}\n1 \\\\\n2 \\\\\n3 \\\\\n4\n\\end{bmatrix}\n"
+        );
+    }
+}
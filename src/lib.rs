@@ -0,0 +1,38 @@
+#![allow(clippy::needless_range_loop)]
+
+pub mod backend;
+pub mod bench;
+pub mod binary;
+pub mod cache;
+pub mod cli;
+pub mod commands;
+pub mod completions;
+pub mod csv;
+pub mod envelope;
+pub mod export;
+pub mod files;
+pub mod fixed;
+pub mod format;
+pub mod gzip;
+pub mod jobs;
+pub mod json;
+pub mod latex;
+pub mod linalg;
+pub mod log;
+pub mod manpage;
+pub mod markdown;
+pub mod market;
+pub mod matrix;
+pub mod npy;
+pub mod operation;
+pub mod out_of_core;
+pub mod parse;
+pub mod pretty;
+pub mod properties;
+pub mod random;
+pub mod repl;
+pub mod session;
+pub mod sha256;
+pub mod sparse;
+pub mod typed;
+pub mod watch;
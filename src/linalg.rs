@@ -0,0 +1,1506 @@
+use crate::matrix::{
+    dims, identity_matrix, multiply_f64, to_f64, transpose, Matrix, MatrixError, MatrixF64,
+};
+use std::f64::consts::FRAC_PI_2;
+
+/// A projected vector whose norm falls below this fraction of the original
+/// vector's norm is treated as zero (i.e. linearly dependent)
+const DEPENDENCE_TOL: f64 = 1e-10;
+
+fn column(m: &MatrixF64, j: usize) -> Vec<f64> {
+    m.iter().map(|row| row[j]).collect()
+}
+
+fn from_columns(cols: &[Vec<f64>], rows: usize) -> MatrixF64 {
+    (0..rows)
+        .map(|i| cols.iter().map(|c| c[i]).collect())
+        .collect()
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn norm(a: &[f64]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+fn column_count(m: &MatrixF64) -> usize {
+    m.first().map_or(0, |row| row.len())
+}
+
+/// Normalizes `v` in place, failing if it was reduced to (almost) nothing
+/// compared to the `original` vector it was projected from
+fn normalize(v: &mut [f64], original: &[f64], col: usize) -> Result<(), MatrixError> {
+    let len = norm(v);
+    if len == 0.0 || len <= DEPENDENCE_TOL * norm(original) {
+        return Err(MatrixError::LinearlyDependent { col });
+    }
+    v.iter_mut().for_each(|x| *x /= len);
+    Ok(())
+}
+
+/// Orthonormalizes the columns of `columns` using classical Gram-Schmidt.
+/// Each column is projected against the *original* column, which loses
+/// orthogonality quickly when the inputs are nearly dependent
+pub fn gram_schmidt_orthogonalize(columns: &MatrixF64) -> Result<MatrixF64, MatrixError> {
+    let rows = columns.len();
+    let mut q: Vec<Vec<f64>> = vec![];
+
+    for j in 0..column_count(columns) {
+        let a = column(columns, j);
+        let mut v = a.clone();
+        for qi in &q {
+            let r = dot(qi, &a);
+            v.iter_mut().zip(qi).for_each(|(x, y)| *x -= r * y);
+        }
+        normalize(&mut v, &a, j)?;
+        q.push(v);
+    }
+
+    Ok(from_columns(&q, rows))
+}
+
+/// Orthonormalizes the columns of `columns` using modified Gram-Schmidt.
+/// Each projection is removed from the partially orthogonalized vector,
+/// which keeps the result much closer to orthogonal than the classical variant
+pub fn gram_schmidt_modified(columns: &MatrixF64) -> Result<MatrixF64, MatrixError> {
+    let rows = columns.len();
+    let mut q: Vec<Vec<f64>> = vec![];
+
+    for j in 0..column_count(columns) {
+        let a = column(columns, j);
+        let mut v = a.clone();
+        for qi in &q {
+            let r = dot(qi, &v);
+            v.iter_mut().zip(qi).for_each(|(x, y)| *x -= r * y);
+        }
+        normalize(&mut v, &a, j)?;
+        q.push(v);
+    }
+
+    Ok(from_columns(&q, rows))
+}
+
+/// QR decomposition via modified Gram-Schmidt, returning `(Q, R)` where `Q`
+/// has orthonormal columns and `R` is upper triangular
+pub fn qr_decompose(m: &MatrixF64) -> Result<(MatrixF64, MatrixF64), MatrixError> {
+    let q = gram_schmidt_modified(m)?;
+    let cols = column_count(m);
+    let mut r = vec![vec![0.0; cols]; cols];
+
+    for (i, row) in r.iter_mut().enumerate() {
+        let qi = column(&q, i);
+        for (j, entry) in row.iter_mut().enumerate().skip(i) {
+            *entry = dot(&qi, &column(m, j));
+        }
+    }
+
+    Ok((q, r))
+}
+
+/// Sign of the determinant (+1, 0 or -1) from an LU factorization with
+/// partial pivoting. Only the pivot signs and the number of row swaps are
+/// tracked, so this never overflows the way the full determinant can
+pub fn determinant_sign(m: &MatrixF64) -> Result<i32, MatrixError> {
+    let n = m.len();
+    if m.iter().any(|row| row.len() != n) {
+        return Err(MatrixError::NotSquare {
+            rows: n,
+            cols: column_count(m),
+        });
+    }
+
+    let scale = m.iter().flatten().fold(0.0_f64, |acc, x| acc.max(x.abs()));
+    let tol = f64::EPSILON * n as f64 * scale;
+    let mut lu = m.clone();
+    let mut sign = 1;
+
+    for k in 0..n {
+        let pivot = (k..n)
+            .max_by(|&a, &b| lu[a][k].abs().total_cmp(&lu[b][k].abs()))
+            .unwrap();
+        if lu[pivot][k].abs() <= tol {
+            return Ok(0);
+        }
+        if pivot != k {
+            lu.swap(pivot, k);
+            sign = -sign;
+        }
+        if lu[k][k] < 0.0 {
+            sign = -sign;
+        }
+        for i in k + 1..n {
+            let factor = lu[i][k] / lu[k][k];
+            for j in k..n {
+                lu[i][j] -= factor * lu[k][j];
+            }
+        }
+    }
+
+    Ok(sign)
+}
+
+/// Orthonormal basis for the column space of `m`, skipping any column that is
+/// dependent on the ones before it
+pub fn orthonormal_basis(m: &MatrixF64) -> MatrixF64 {
+    let rows = m.len();
+    let mut q: Vec<Vec<f64>> = vec![];
+
+    for j in 0..column_count(m) {
+        let a = column(m, j);
+        let mut v = a.clone();
+        for qi in &q {
+            let r = dot(qi, &v);
+            v.iter_mut().zip(qi).for_each(|(x, y)| *x -= r * y);
+        }
+        if normalize(&mut v, &a, j).is_ok() {
+            q.push(v);
+        }
+    }
+
+    from_columns(&q, rows)
+}
+
+/// Singular values of `m` in descending order, computed with one-sided
+/// Jacobi rotations
+pub fn singular_values(m: &MatrixF64) -> Vec<f64> {
+    let (rows, cols) = dims(m);
+    let mut cols_of: Vec<Vec<f64>> = (0..cols).map(|j| column(m, j)).collect();
+
+    for _sweep in 0..100 {
+        let mut rotated = false;
+        for p in 0..cols {
+            for q in p + 1..cols {
+                let alpha = dot(&cols_of[p], &cols_of[p]);
+                let beta = dot(&cols_of[q], &cols_of[q]);
+                let gamma = dot(&cols_of[p], &cols_of[q]);
+                if gamma.abs() <= f64::EPSILON * (alpha * beta).sqrt() {
+                    continue;
+                }
+                rotated = true;
+                let zeta = (beta - alpha) / (2.0 * gamma);
+                let t = zeta.signum() / (zeta.abs() + (1.0 + zeta * zeta).sqrt());
+                let c = 1.0 / (1.0 + t * t).sqrt();
+                let s = c * t;
+                for i in 0..rows {
+                    let x = cols_of[p][i];
+                    let y = cols_of[q][i];
+                    cols_of[p][i] = c * x - s * y;
+                    cols_of[q][i] = s * x + c * y;
+                }
+            }
+        }
+        if !rotated {
+            break;
+        }
+    }
+
+    let mut values: Vec<f64> = cols_of.iter().map(|c| norm(c)).collect();
+    values.sort_by(|a, b| b.total_cmp(a));
+    values.truncate(rows.min(cols));
+    values
+}
+
+/// Largest principal angle (in radians) between the column spaces of `a`
+/// and `b`: the arccos of the smallest singular value of `QaᵀQb`
+pub fn matrix_angle(a: &MatrixF64, b: &MatrixF64) -> Result<f64, MatrixError> {
+    if a.len() != b.len() {
+        return Err(MatrixError::DimensionMismatch {
+            left: dims(a),
+            right: dims(b),
+        });
+    }
+
+    let qa = orthonormal_basis(a);
+    let qb = orthonormal_basis(b);
+    if column_count(&qa) == 0 || column_count(&qb) == 0 {
+        return Err(MatrixError::Empty);
+    }
+
+    let cosines = singular_values(&multiply_f64(&transpose(&qa), &qb));
+    let smallest = cosines.last().copied().unwrap_or(0.0);
+    if smallest <= 0.0 {
+        return Ok(FRAC_PI_2);
+    }
+    Ok(smallest.min(1.0).acos())
+}
+
+pub fn frobenius_norm(m: &MatrixF64) -> f64 {
+    m.iter().flatten().map(|x| x * x).sum::<f64>().sqrt()
+}
+
+/// Smallest `k` with `‖mᵏ‖_F <= tol`, searching up to `k = n` since a
+/// nilpotent n×n matrix always has index at most n (Cayley-Hamilton)
+fn nilpotency_index_within(m: &Matrix, tol: f64) -> Option<usize> {
+    let n = m.len();
+    if n == 0 || m.iter().any(|row| row.len() != n) {
+        return None;
+    }
+
+    let base = to_f64(m);
+    let mut power = base.clone();
+    for k in 1..=n {
+        if frobenius_norm(&power) <= tol {
+            return Some(k);
+        }
+        power = multiply_f64(&power, &base);
+    }
+    None
+}
+
+/// Index of nilpotency of `m`: the smallest `k` with `mᵏ = 0`, or `None` if
+/// `m` isn't nilpotent
+pub fn nilpotency_index(m: &Matrix) -> Option<usize> {
+    nilpotency_index_within(m, 0.0)
+}
+
+/// Whether some power of `m` has a Frobenius norm of at most `tol`
+pub fn is_nilpotent(m: &Matrix, tol: f64) -> bool {
+    nilpotency_index_within(m, tol).is_some()
+}
+
+/// Reduces a square matrix to upper Hessenberg form in place using
+/// Householder reflections `A ← P A P`, also applying each reflection to the
+/// columns of `q` if given (`Q ← Q P`)
+fn reduce_to_hessenberg(a: &mut MatrixF64, mut q: Option<&mut MatrixF64>) {
+    let n = a.len();
+    for k in 0..n.saturating_sub(2) {
+        let mut v: Vec<f64> = (k + 1..n).map(|i| a[i][k]).collect();
+        let alpha = -v[0].signum() * norm(&v);
+        v[0] -= alpha;
+        let vv = dot(&v, &v);
+        if vv == 0.0 {
+            continue;
+        }
+
+        for j in 0..n {
+            let s: f64 = (0..v.len()).map(|i| v[i] * a[k + 1 + i][j]).sum();
+            for i in 0..v.len() {
+                a[k + 1 + i][j] -= 2.0 * s * v[i] / vv;
+            }
+        }
+        let reflect_columns = |m: &mut MatrixF64| {
+            for row in m.iter_mut() {
+                let s: f64 = (0..v.len()).map(|j| row[k + 1 + j] * v[j]).sum();
+                for j in 0..v.len() {
+                    row[k + 1 + j] -= 2.0 * s * v[j] / vv;
+                }
+            }
+        };
+        reflect_columns(a);
+        if let Some(q) = q.as_deref_mut() {
+            reflect_columns(q);
+        }
+        // the reflection zeroes these exactly; don't leave rounding noise
+        for row in a.iter_mut().skip(k + 2) {
+            row[k] = 0.0;
+        }
+    }
+}
+
+/// Upper Hessenberg form of a square matrix: `(H, Q)` with `Q` orthogonal,
+/// `H` zero below its first subdiagonal and `m = Q H Qᵀ`
+pub fn hessenberg_form(m: &MatrixF64) -> Result<(MatrixF64, MatrixF64), MatrixError> {
+    let n = m.len();
+    if m.iter().any(|row| row.len() != n) {
+        return Err(MatrixError::NotSquare {
+            rows: n,
+            cols: column_count(m),
+        });
+    }
+
+    let mut h = m.clone();
+    let mut q = to_f64(&identity_matrix(n));
+    reduce_to_hessenberg(&mut h, Some(&mut q));
+    Ok((h, q))
+}
+
+/// Swaps rows and columns `j` and `m` of `a`, touching only the parts that
+/// can be nonzero while `balance` is isolating eigenvalues
+fn exchange(a: &mut MatrixF64, j: usize, m: usize, low: usize, high: usize) {
+    if j == m {
+        return;
+    }
+    for row in a.iter_mut().take(high + 1) {
+        row.swap(j, m);
+    }
+    for i in low..a.len() {
+        let tmp = a[j][i];
+        a[j][i] = a[m][i];
+        a[m][i] = tmp;
+    }
+}
+
+/// Balances a square matrix before computing its eigenvalues, like LAPACK's
+/// `dgebal`. Rows and columns that isolate an eigenvalue are first permuted to
+/// the bottom and top, then the remaining block is scaled by powers of two
+/// (so no rounding is introduced) until each row and column have similar
+/// norms. The result is similar to `m`, so it has the same eigenvalues.
+///
+/// For each index `j`, the returned vector holds the index `j` was swapped
+/// with if it was isolated, and otherwise the factor `dⱼ` of the diagonal
+/// scaling `D⁻¹ A D`, which is what back-transforming eigenvectors needs
+pub fn balance(m: &MatrixF64) -> (MatrixF64, Vec<f64>) {
+    const RADIX: f64 = 2.0;
+    let mut a = m.clone();
+    let n = a.len();
+    let mut scale = vec![1.0; n];
+    if n == 0 {
+        return (a, scale);
+    }
+
+    let mut low = 0;
+    let mut high = n - 1;
+
+    // rows with no off-diagonal entries in the active block isolate an
+    // eigenvalue; move them to the bottom
+    while let Some(j) = (0..=high)
+        .rev()
+        .find(|&j| (0..=high).all(|i| i == j || a[j][i] == 0.0))
+    {
+        scale[high] = j as f64;
+        exchange(&mut a, j, high, low, high);
+        if high == 0 {
+            return (a, scale);
+        }
+        high -= 1;
+    }
+
+    // likewise columns, moved to the top
+    while let Some(j) = (low..=high).find(|&j| (low..=high).all(|i| i == j || a[i][j] == 0.0)) {
+        scale[low] = j as f64;
+        exchange(&mut a, j, low, low, high);
+        low += 1;
+    }
+
+    loop {
+        let mut converged = true;
+        for i in low..=high {
+            let mut c: f64 = (low..=high)
+                .filter(|&j| j != i)
+                .map(|j| a[j][i].abs())
+                .sum();
+            let r: f64 = (low..=high)
+                .filter(|&j| j != i)
+                .map(|j| a[i][j].abs())
+                .sum();
+            if c == 0.0 || r == 0.0 {
+                continue;
+            }
+
+            let s = c + r;
+            let mut f = 1.0;
+            while c < r / RADIX {
+                f *= RADIX;
+                c *= RADIX * RADIX;
+            }
+            while c >= r * RADIX {
+                f /= RADIX;
+                c /= RADIX * RADIX;
+            }
+
+            if (c + r) / f < 0.95 * s {
+                converged = false;
+                scale[i] *= f;
+                for j in low..n {
+                    a[i][j] /= f;
+                }
+                for row in a.iter_mut().take(high + 1) {
+                    row[i] *= f;
+                }
+            }
+        }
+        if converged {
+            return (a, scale);
+        }
+    }
+}
+
+/// Maximum Francis iterations spent on any one eigenvalue
+const MAX_FRANCIS_ITERATIONS: usize = 60;
+
+/// Eigenvalues of an upper Hessenberg matrix using the Francis double-shift
+/// QR algorithm (after `hqr` from Numerical Recipes), as `(re, im)` pairs,
+/// spending at most `max_iterations` iterations on any one eigenvalue
+fn francis_eigenvalues(
+    h: &MatrixF64,
+    max_iterations: usize,
+) -> Result<Vec<(f64, f64)>, MatrixError> {
+    let n = h.len();
+    // 1-based copy so the indexing matches the textbook formulation
+    let mut a = vec![vec![0.0; n + 1]; n + 1];
+    for i in 0..n {
+        a[i + 1][1..].copy_from_slice(&h[i]);
+    }
+    let mut eig = vec![(0.0, 0.0); n + 1];
+
+    let mut anorm = 0.0;
+    for i in 1..=n {
+        for j in (i - 1).max(1)..=n {
+            anorm += a[i][j].abs();
+        }
+    }
+
+    let mut nn = n;
+    let mut t = 0.0;
+    while nn >= 1 {
+        let mut its = 0;
+        loop {
+            let mut l = nn;
+            while l >= 2 {
+                let mut s = a[l - 1][l - 1].abs() + a[l][l].abs();
+                if s == 0.0 {
+                    s = anorm;
+                }
+                if a[l][l - 1].abs() + s == s {
+                    a[l][l - 1] = 0.0;
+                    break;
+                }
+                l -= 1;
+            }
+
+            let mut x = a[nn][nn];
+            if l == nn {
+                // one root found
+                eig[nn] = (x + t, 0.0);
+                nn -= 1;
+            } else {
+                let mut y = a[nn - 1][nn - 1];
+                let mut w = a[nn][nn - 1] * a[nn - 1][nn];
+                if l == nn - 1 {
+                    // two roots found
+                    let p = 0.5 * (y - x);
+                    let q = p * p + w;
+                    let z = q.abs().sqrt();
+                    x += t;
+                    if q >= 0.0 {
+                        let z = p + z.copysign(p);
+                        eig[nn - 1] = (x + z, 0.0);
+                        eig[nn] = (if z != 0.0 { x - w / z } else { x + z }, 0.0);
+                    } else {
+                        eig[nn - 1] = (x + p, -z);
+                        eig[nn] = (x + p, z);
+                    }
+                    nn -= 2;
+                } else {
+                    if its == max_iterations {
+                        return Err(MatrixError::DidNotConverge { iterations: its });
+                    }
+                    if its == 10 || its == 20 {
+                        // exceptional shift
+                        t += x;
+                        for i in 1..=nn {
+                            a[i][i] -= x;
+                        }
+                        let s = a[nn][nn - 1].abs() + a[nn - 1][nn - 2].abs();
+                        x = 0.75 * s;
+                        y = x;
+                        w = -0.4375 * s * s;
+                    }
+                    its += 1;
+
+                    // look for two consecutive small subdiagonal elements
+                    let mut m = nn - 2;
+                    let (mut p, mut q, mut r);
+                    loop {
+                        let z = a[m][m];
+                        r = x - z;
+                        let s = y - z;
+                        p = (r * s - w) / a[m + 1][m] + a[m][m + 1];
+                        q = a[m + 1][m + 1] - z - r - s;
+                        r = a[m + 2][m + 1];
+                        let s = p.abs() + q.abs() + r.abs();
+                        p /= s;
+                        q /= s;
+                        r /= s;
+                        if m == l {
+                            break;
+                        }
+                        let u = a[m][m - 1].abs() * (q.abs() + r.abs());
+                        let v = p.abs() * (a[m - 1][m - 1].abs() + z.abs() + a[m + 1][m + 1].abs());
+                        if u + v == v {
+                            break;
+                        }
+                        m -= 1;
+                    }
+                    for i in m + 2..=nn {
+                        a[i][i - 2] = 0.0;
+                        if i != m + 2 {
+                            a[i][i - 3] = 0.0;
+                        }
+                    }
+
+                    // double QR step on rows l..nn and columns m..nn
+                    for k in m..nn {
+                        if k != m {
+                            p = a[k][k - 1];
+                            q = a[k + 1][k - 1];
+                            r = if k != nn - 1 { a[k + 2][k - 1] } else { 0.0 };
+                            x = p.abs() + q.abs() + r.abs();
+                            if x != 0.0 {
+                                p /= x;
+                                q /= x;
+                                r /= x;
+                            }
+                        }
+                        let s = (p * p + q * q + r * r).sqrt().copysign(p);
+                        if s == 0.0 {
+                            continue;
+                        }
+                        if k == m {
+                            if l != m {
+                                a[k][k - 1] = -a[k][k - 1];
+                            }
+                        } else {
+                            a[k][k - 1] = -s * x;
+                        }
+                        p += s;
+                        x = p / s;
+                        y = q / s;
+                        let z = r / s;
+                        q /= p;
+                        r /= p;
+                        for j in k..=nn {
+                            let mut p = a[k][j] + q * a[k + 1][j];
+                            if k != nn - 1 {
+                                p += r * a[k + 2][j];
+                                a[k + 2][j] -= p * z;
+                            }
+                            a[k + 1][j] -= p * y;
+                            a[k][j] -= p * x;
+                        }
+                        for i in l..=nn.min(k + 3) {
+                            let mut p = x * a[i][k] + y * a[i][k + 1];
+                            if k != nn - 1 {
+                                p += z * a[i][k + 2];
+                                a[i][k + 2] -= p * r;
+                            }
+                            a[i][k + 1] -= p * q;
+                            a[i][k] -= p;
+                        }
+                    }
+                }
+            }
+            if nn < 2 || l + 1 >= nn {
+                break;
+            }
+        }
+    }
+
+    Ok(eig.split_off(1))
+}
+
+/// All eigenvalues of a square matrix as `(re, im)` pairs, in no particular
+/// order. Complex eigenvalues come in conjugate pairs
+pub fn eigenvalues(m: &MatrixF64) -> Result<Vec<(f64, f64)>, MatrixError> {
+    let n = m.len();
+    if m.iter().any(|row| row.len() != n) {
+        return Err(MatrixError::NotSquare {
+            rows: n,
+            cols: column_count(m),
+        });
+    }
+
+    let (mut h, _) = balance(m);
+    reduce_to_hessenberg(&mut h, None);
+    francis_eigenvalues(&h, MAX_FRANCIS_ITERATIONS)
+}
+
+/// Shift strategy for `qr_iteration`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QrShift {
+    /// Plain `A = QR; A ← RQ`, which converges linearly at a rate set by the
+    /// ratios of eigenvalue magnitudes
+    Unshifted,
+    /// Francis implicit double shifts, which converge quadratically
+    FrancisDouble,
+}
+
+/// Eigenvalues of the 2×2 block `[[a, b], [c, d]]` as `(re, im)` pairs
+fn block_eigenvalues(a: f64, b: f64, c: f64, d: f64) -> [(f64, f64); 2] {
+    let mean = 0.5 * (a + d);
+    let disc = 0.25 * (a - d) * (a - d) + b * c;
+    if disc >= 0.0 {
+        let root = disc.sqrt();
+        [(mean + root, 0.0), (mean - root, 0.0)]
+    } else {
+        let root = (-disc).sqrt();
+        [(mean, -root), (mean, root)]
+    }
+}
+
+/// One unshifted QR step `H ← RQ` on an upper Hessenberg matrix, using Givens
+/// rotations so it stays Hessenberg and works for singular matrices too
+fn hessenberg_qr_step(h: &mut MatrixF64) {
+    let n = h.len();
+    let mut rotations = Vec::with_capacity(n.saturating_sub(1));
+    for k in 0..n.saturating_sub(1) {
+        let (x, y) = (h[k][k], h[k + 1][k]);
+        let r = x.hypot(y);
+        let (c, s) = if r == 0.0 { (1.0, 0.0) } else { (x / r, y / r) };
+        for j in k..n {
+            let (p, q) = (h[k][j], h[k + 1][j]);
+            h[k][j] = c * p + s * q;
+            h[k + 1][j] = -s * p + c * q;
+        }
+        rotations.push((c, s));
+    }
+    for (k, &(c, s)) in rotations.iter().enumerate() {
+        for row in h.iter_mut().take((k + 2).min(n)) {
+            let (p, q) = (row[k], row[k + 1]);
+            row[k] = c * p + s * q;
+            row[k + 1] = -s * p + c * q;
+        }
+    }
+}
+
+/// All eigenvalues of a square matrix by QR iteration on its Hessenberg
+/// form, as `(re, im)` pairs. With `QrShift::Unshifted` the iteration stops
+/// once every subdiagonal element next to another non-negligible one is below
+/// `tol` relative to its diagonal neighbours, leaving 1×1 and 2×2 diagonal
+/// blocks whose eigenvalues are read off directly. `QrShift::FrancisDouble`
+/// deflates at machine precision instead, and `max_iter` bounds the
+/// iterations spent on each eigenvalue
+pub fn qr_iteration(
+    m: &MatrixF64,
+    max_iter: usize,
+    tol: f64,
+    shift: QrShift,
+) -> Result<Vec<(f64, f64)>, MatrixError> {
+    let (mut h, _) = hessenberg_form(m)?;
+    let n = h.len();
+    if shift == QrShift::FrancisDouble {
+        return francis_eigenvalues(&h, max_iter);
+    }
+
+    let negligible = |h: &MatrixF64, k: usize| {
+        h[k + 1][k].abs() <= tol * (h[k][k].abs() + h[k + 1][k + 1].abs())
+    };
+    let converged =
+        |h: &MatrixF64| (1..n.saturating_sub(1)).all(|k| negligible(h, k - 1) || negligible(h, k));
+
+    let mut iterations = 0;
+    while !converged(&h) {
+        if iterations == max_iter {
+            return Err(MatrixError::DidNotConverge {
+                iterations: max_iter,
+            });
+        }
+        hessenberg_qr_step(&mut h);
+        iterations += 1;
+    }
+
+    let mut eig = Vec::with_capacity(n);
+    let mut i = 0;
+    while i < n {
+        if i + 1 < n && !negligible(&h, i) {
+            eig.extend(block_eigenvalues(
+                h[i][i],
+                h[i][i + 1],
+                h[i + 1][i],
+                h[i + 1][i + 1],
+            ));
+            i += 2;
+        } else {
+            eig.push((h[i][i], 0.0));
+            i += 1;
+        }
+    }
+    Ok(eig)
+}
+
+/// Iterations and relative residual `power_iteration` uses when called by
+/// `spectral_radius` and `spectral_norm`
+const POWER_ITERATIONS: usize = 1000;
+const POWER_TOL: f64 = 1e-10;
+
+/// Dominant eigenvalue of a square matrix and a unit eigenvector for it,
+/// by power iteration. The eigenvalue is the Rayleigh quotient `xᵀAx`, and
+/// the iteration stops once `‖Ax - λx‖ <= tol·‖Ax‖`. It doesn't converge
+/// when several eigenvalues share the largest modulus (`±λ` or a complex
+/// pair), which is reported as `DidNotConverge`
+pub fn power_iteration(
+    m: &MatrixF64,
+    max_iterations: usize,
+    tol: f64,
+) -> Result<(f64, Vec<f64>), MatrixError> {
+    let n = m.len();
+    if n == 0 {
+        return Err(MatrixError::Empty);
+    }
+    if m.iter().any(|row| row.len() != n) {
+        return Err(MatrixError::NotSquare {
+            rows: n,
+            cols: column_count(m),
+        });
+    }
+
+    // any fixed start can be orthogonal to the dominant eigenvector, but
+    // an uneven one rarely is for the structured matrices people type in
+    let mut x: Vec<f64> = (0..n).map(|i| 1.0 / (i + 1) as f64).collect();
+    let len = norm(&x);
+    x.iter_mut().for_each(|v| *v /= len);
+
+    for _ in 0..max_iterations {
+        let ax: Vec<f64> = m.iter().map(|row| dot(row, &x)).collect();
+        let lambda = dot(&x, &ax);
+        let ax_norm = norm(&ax);
+        let residual: Vec<f64> = ax.iter().zip(&x).map(|(a, b)| a - lambda * b).collect();
+        if norm(&residual) <= tol * ax_norm || ax_norm == 0.0 {
+            return Ok((lambda, x));
+        }
+        x = ax.iter().map(|a| a / ax_norm).collect();
+    }
+    Err(MatrixError::DidNotConverge {
+        iterations: max_iterations,
+    })
+}
+
+/// Spectral radius `max |λᵢ|` of a square matrix, from power iteration when
+/// it converges and from the full eigenvalue computation when it doesn't
+pub fn spectral_radius(m: &MatrixF64) -> Result<f64, MatrixError> {
+    match power_iteration(m, POWER_ITERATIONS, POWER_TOL) {
+        Ok((lambda, _)) => Ok(lambda.abs()),
+        Err(MatrixError::DidNotConverge { .. }) => Ok(eigenvalues(m)?
+            .iter()
+            .map(|&(re, im)| re.hypot(im))
+            .fold(0.0, f64::max)),
+        Err(err) => Err(err),
+    }
+}
+
+/// Spectral norm (operator 2-norm) of `m`: its largest singular value, the
+/// square root of the dominant eigenvalue of `mᵀm`
+pub fn spectral_norm(m: &MatrixF64) -> f64 {
+    if column_count(m) == 0 {
+        return 0.0;
+    }
+    let gram = multiply_f64(&transpose(m), m);
+    match power_iteration(&gram, POWER_ITERATIONS, POWER_TOL) {
+        Ok((lambda, _)) => lambda.max(0.0).sqrt(),
+        Err(_) => singular_values(m).first().copied().unwrap_or(0.0),
+    }
+}
+
+/// Companion matrix of the polynomial with coefficients `coeffs` in
+/// ascending order (`coeffs[i]` multiplies `xⁱ`). The polynomial is made
+/// monic by dividing through by its leading nonzero coefficient; the result
+/// has ones on the subdiagonal and the negated remaining coefficients in the
+/// last column, so its eigenvalues are the polynomial's roots
+pub fn companion_matrix(coeffs: &[f64]) -> MatrixF64 {
+    let degree = match coeffs.iter().rposition(|&c| c != 0.0) {
+        Some(d) => d,
+        None => return vec![],
+    };
+    let lead = coeffs[degree];
+    let mut m = vec![vec![0.0; degree]; degree];
+
+    for i in 0..degree {
+        if i > 0 {
+            m[i][i - 1] = 1.0;
+        }
+        m[i][degree - 1] = -coeffs[i] / lead;
+    }
+    m
+}
+
+/// Real roots of the polynomial with ascending coefficients `coeffs`, in
+/// ascending order, found as the eigenvalues of its companion matrix
+pub fn poly_roots(coeffs: &[f64]) -> Vec<f64> {
+    let companion = companion_matrix(coeffs);
+    let scale = 1.0 + frobenius_norm(&companion);
+    let mut roots: Vec<f64> = eigenvalues(&companion)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|&(_, im)| im.abs() <= 1e-9 * scale)
+        .map(|(re, _)| re)
+        .collect();
+    roots.sort_by(|a, b| a.total_cmp(b));
+    roots
+}
+
+/// Characteristic polynomial `det(λI - m)` of a square matrix using
+/// Berkowitz's division-free algorithm. The `n + 1` coefficients are in
+/// ascending order like `companion_matrix` takes them, so the last one is 1
+pub fn characteristic_polynomial(m: &MatrixF64) -> Vec<f64> {
+    let n = m.len();
+    // coefficients of the leading principal submatrix, highest degree first
+    let mut poly = vec![1.0];
+
+    for i in 0..n {
+        let row = &m[i][..i];
+        let mut col: Vec<f64> = (0..i).map(|k| m[k][i]).collect();
+        let mut toeplitz = vec![1.0, -m[i][i]];
+        for _ in 0..i {
+            toeplitz.push(-dot(row, &col));
+            col = (0..i).map(|k| dot(&m[k][..i], &col)).collect();
+        }
+
+        poly = (0..i + 2)
+            .map(|r| {
+                (0..poly.len())
+                    .filter(|&c| c <= r)
+                    .map(|c| toeplitz[r - c] * poly[c])
+                    .sum()
+            })
+            .collect();
+    }
+
+    poly.reverse();
+    poly
+}
+
+/// Evaluates the polynomial with ascending coefficients `coeffs` at the
+/// square matrix `m` using Horner's scheme
+pub fn evaluate_polynomial(coeffs: &[f64], m: &MatrixF64) -> MatrixF64 {
+    let n = m.len();
+    let mut result = vec![vec![0.0; n]; n];
+
+    for &c in coeffs.iter().rev() {
+        result = multiply_f64(&result, m);
+        for i in 0..n {
+            result[i][i] += c;
+        }
+    }
+    result
+}
+
+/// Eigenvalues closer than this, relative to the largest element of the
+/// matrix, are taken to be one repeated eigenvalue
+const EIGENVALUE_TOL: f64 = 1e-8;
+/// How far `rootⁿ` may be from the matrix, relative to its Frobenius norm,
+/// before `matrix_root` reports that it couldn't find a root
+const ROOT_TOL: f64 = 1e-6;
+
+/// "square root", "cube root", "4th root", ...
+fn root_name(n: u32) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    match n {
+        2 => String::from("square root"),
+        3 => String::from("cube root"),
+        _ => format!("{}{} root", n, suffix),
+    }
+}
+
+/// The principal `n`th root of a diagonalizable matrix with real
+/// eigenvalues: the matrix `r` with `rⁿ = m` whose eigenvalues are the real
+/// `n`th roots of those of `m`. It's Sylvester's formula, the sum over the
+/// distinct eigenvalues `λᵢ` of `λᵢ^(1/n) · ∏ⱼ≠ᵢ (m - λⱼI) / (λᵢ - λⱼ)`.
+///
+/// Fails if `m` has complex eigenvalues, a negative one when `n` is even,
+/// or isn't diagonalizable (which shows as `rⁿ` not matching `m`)
+pub fn matrix_root(m: &MatrixF64, n: u32) -> Result<MatrixF64, MatrixError> {
+    if n == 0 {
+        return Err(MatrixError::InvalidArgument(String::from(
+            "the root must be at least 1",
+        )));
+    }
+    let size = m.len();
+    if size == 0 || column_count(m) == 0 {
+        return Err(MatrixError::Empty);
+    }
+    let name = root_name(n);
+    let scale = m
+        .iter()
+        .flatten()
+        .fold(0.0f64, |max, x| max.max(x.abs()))
+        .max(f64::MIN_POSITIVE);
+    let tol = EIGENVALUE_TOL * scale;
+
+    let mut eigs = vec![];
+    for (re, im) in eigenvalues(m)? {
+        if im.abs() > tol {
+            return Err(MatrixError::InvalidArgument(format!(
+                "the matrix has complex eigenvalues, so its real {} isn't computed",
+                name
+            )));
+        }
+        eigs.push(re);
+    }
+    eigs.sort_by(|a, b| a.total_cmp(b));
+    let mut distinct: Vec<(f64, usize)> = vec![];
+    for x in eigs {
+        match distinct.last_mut() {
+            Some((sum, count)) if (x - *sum / *count as f64).abs() <= tol => {
+                *sum += x;
+                *count += 1;
+            }
+            _ => distinct.push((x, 1)),
+        }
+    }
+    let distinct: Vec<f64> = distinct
+        .iter()
+        .map(|&(sum, count)| sum / count as f64)
+        .map(|x| if x.abs() <= tol { 0.0 } else { x })
+        .collect();
+
+    let mut root = vec![vec![0.0; size]; size];
+    for (i, &x) in distinct.iter().enumerate() {
+        let r = if x >= 0.0 {
+            x.powf(1.0 / n as f64)
+        } else if n % 2 == 1 {
+            -(-x).powf(1.0 / n as f64)
+        } else {
+            return Err(MatrixError::InvalidArgument(format!(
+                "the matrix has the negative eigenvalue {}, so it has no real {}",
+                x, name
+            )));
+        };
+        let mut term = vec![vec![0.0; size]; size];
+        for (k, row) in term.iter_mut().enumerate() {
+            row[k] = r;
+        }
+        for (j, &y) in distinct.iter().enumerate() {
+            if j == i {
+                continue;
+            }
+            let mut factor = m.clone();
+            for (k, row) in factor.iter_mut().enumerate() {
+                row[k] -= y;
+                row.iter_mut().for_each(|e| *e /= x - y);
+            }
+            term = multiply_f64(&term, &factor);
+        }
+        for (sum, row) in root.iter_mut().zip(term) {
+            sum.iter_mut().zip(row).for_each(|(s, t)| *s += t);
+        }
+    }
+
+    let mut power = root.clone();
+    for _ in 1..n {
+        power = multiply_f64(&power, &root);
+    }
+    let residual: MatrixF64 = power
+        .iter()
+        .zip(m)
+        .map(|(p, a)| p.iter().zip(a).map(|(x, y)| x - y).collect())
+        .collect();
+    if frobenius_norm(&residual) > ROOT_TOL * frobenius_norm(m).max(1.0) {
+        return Err(MatrixError::InvalidArgument(format!(
+            "the matrix isn't diagonalizable, or its eigenvalues are too close to compute its {}",
+            name
+        )));
+    }
+    Ok(root)
+}
+
+/// How far `m[i][j]` and `m[j][i]` may differ, relative to the largest
+/// element, for `cholesky` to treat `m` as symmetric
+const SYMMETRY_TOL: f64 = 1e-10;
+
+/// The Cholesky factor of a symmetric matrix: the lower triangular `l` with
+/// a positive diagonal and `l·lᵀ = m`, which exists exactly when `m` is
+/// positive definite. `None` if it isn't, an error if `m` isn't square or
+/// symmetric
+pub fn cholesky(m: &MatrixF64) -> Result<Option<MatrixF64>, MatrixError> {
+    let (rows, cols) = dims(m);
+    if rows == 0 || cols == 0 {
+        return Err(MatrixError::Empty);
+    }
+    if rows != cols {
+        return Err(MatrixError::NotSquare { rows, cols });
+    }
+    let scale = m.iter().flatten().fold(0.0f64, |max, x| max.max(x.abs()));
+    for i in 0..rows {
+        for j in 0..i {
+            let diff = (m[i][j] - m[j][i]).abs();
+            if diff > SYMMETRY_TOL * scale || diff.is_nan() {
+                return Err(MatrixError::InvalidArgument(format!(
+                    "the matrix isn't symmetric (m[{}][{}] = {}, m[{}][{}] = {}), \
+                     so positive definiteness isn't checked",
+                    i, j, m[i][j], j, i, m[j][i]
+                )));
+            }
+        }
+    }
+
+    let mut l = vec![vec![0.0; rows]; rows];
+    for j in 0..rows {
+        let pivot = m[j][j] - dot(&l[j][..j], &l[j][..j]);
+        if pivot <= 0.0 || pivot.is_nan() {
+            return Ok(None);
+        }
+        l[j][j] = pivot.sqrt();
+        for i in j + 1..rows {
+            l[i][j] = (m[i][j] - dot(&l[i][..j], &l[j][..j])) / l[j][j];
+        }
+    }
+    Ok(Some(l))
+}
+
+/// Whether `m` is symmetric and positive definite, that is `xᵀ·m·x > 0` for
+/// every nonzero `x`
+pub fn is_positive_definite(m: &Matrix) -> bool {
+    matches!(cholesky(&to_f64(m)), Ok(Some(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64, tol: f64) -> bool {
+        (a - b).abs() <= tol
+    }
+
+    fn matrices_close(a: &MatrixF64, b: &MatrixF64, tol: f64) -> bool {
+        dims(a) == dims(b)
+            && a.iter()
+                .flatten()
+                .zip(b.iter().flatten())
+                .all(|(x, y)| close(*x, *y, tol))
+    }
+
+    /// The largest `|QᵀQ - I|` element
+    fn orthogonality_error(q: &MatrixF64) -> f64 {
+        let gram = multiply_f64(&transpose(q), q);
+        let identity = identity_matrix(gram.len());
+        gram.iter()
+            .flatten()
+            .zip(identity.iter().flatten())
+            .map(|(x, &y)| (x - y as f64).abs())
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn gram_schmidt_gives_orthonormal_columns() {
+        let m = vec![vec![3.0, 1.0], vec![4.0, 2.0], vec![0.0, 5.0]];
+        for q in [
+            gram_schmidt_orthogonalize(&m).unwrap(),
+            gram_schmidt_modified(&m).unwrap(),
+        ] {
+            assert_eq!(dims(&q), (3, 2));
+            assert!(orthogonality_error(&q) < 1e-12);
+            // the first column is the first input column normalized
+            assert!(close(q[0][0], 0.6, 1e-12) && close(q[1][0], 0.8, 1e-12));
+        }
+    }
+
+    #[test]
+    fn gram_schmidt_rejects_dependent_columns() {
+        let m = vec![vec![1.0, 2.0, 0.0], vec![2.0, 4.0, 1.0]];
+        assert_eq!(
+            gram_schmidt_orthogonalize(&m),
+            Err(MatrixError::LinearlyDependent { col: 1 })
+        );
+        assert_eq!(
+            gram_schmidt_modified(&m),
+            Err(MatrixError::LinearlyDependent { col: 1 })
+        );
+    }
+
+    #[test]
+    fn modified_gram_schmidt_stays_orthogonal_on_nearly_dependent_columns() {
+        // the Läuchli matrix, on which classical Gram-Schmidt loses orthogonality
+        let e = 1e-8;
+        let m = vec![
+            vec![1.0, 1.0, 1.0],
+            vec![e, 0.0, 0.0],
+            vec![0.0, e, 0.0],
+            vec![0.0, 0.0, e],
+        ];
+        let classical = orthogonality_error(&gram_schmidt_orthogonalize(&m).unwrap());
+        let modified = orthogonality_error(&gram_schmidt_modified(&m).unwrap());
+        assert!(modified < 1e-7, "{}", modified);
+        assert!(classical > 0.1, "{}", classical);
+    }
+
+    #[test]
+    fn qr_decompose_reproduces_the_matrix() {
+        let m = vec![vec![1.0, 2.0], vec![3.0, 4.0], vec![5.0, 7.0]];
+        let (q, r) = qr_decompose(&m).unwrap();
+        assert!(matrices_close(&multiply_f64(&q, &r), &m, 1e-12));
+        assert!(close(r[1][0], 0.0, 0.0));
+    }
+
+    /// The exact determinant of a small integer matrix, by cofactor
+    /// expansion along the first row
+    fn cofactor_determinant(m: &Matrix) -> i128 {
+        if m.is_empty() {
+            return 1;
+        }
+        (0..m.len())
+            .map(|j| {
+                let minor: Matrix = m[1..]
+                    .iter()
+                    .map(|row| [&row[..j], &row[j + 1..]].concat())
+                    .collect();
+                let term = m[0][j] as i128 * cofactor_determinant(&minor);
+                if j % 2 == 0 {
+                    term
+                } else {
+                    -term
+                }
+            })
+            .sum()
+    }
+
+    #[test]
+    fn determinant_sign_follows_the_determinant() {
+        let mut cases: Vec<Matrix> = vec![
+            vec![vec![2, 1], vec![1, 3]],
+            // a row swap of the identity, and a singular matrix
+            vec![vec![0, 1], vec![1, 0]],
+            vec![vec![1, 2], vec![2, 4]],
+            vec![vec![-3]],
+            // the third row is the sum of the first two
+            vec![vec![1, 2, 3], vec![4, 5, 6], vec![5, 7, 9]],
+            vec![vec![0, 0, 0], vec![1, 2, 3], vec![4, 5, 6]],
+            vec![vec![0, 2, 1], vec![3, 0, 0], vec![1, 1, -1]],
+        ];
+        for n in 1..=5 {
+            for seed in 0..6 {
+                cases.push(
+                    crate::random::random_int_matrix(
+                        n,
+                        n,
+                        (-2, 2),
+                        crate::random::MatrixKind::Dense,
+                        seed,
+                    )
+                    .unwrap(),
+                );
+            }
+        }
+        let mut signs = [0; 3];
+        for m in &cases {
+            let expected = cofactor_determinant(m).signum() as i32;
+            signs[(expected + 1) as usize] += 1;
+            assert_eq!(determinant_sign(&to_f64(m)), Ok(expected), "{:?}", m);
+            // an enormous determinant has the same sign, as scaling by a
+            // positive number doesn't change it
+            let scaled: MatrixF64 = to_f64(m)
+                .iter()
+                .map(|row| row.iter().map(|x| x * 1e200).collect())
+                .collect();
+            assert_eq!(determinant_sign(&scaled), Ok(expected), "{:?}", m);
+        }
+        assert!(signs.iter().all(|&count| count > 0), "{:?}", signs);
+    }
+
+    #[test]
+    fn determinant_sign_needs_a_square_matrix() {
+        assert_eq!(
+            determinant_sign(&vec![vec![1.0, 2.0]]),
+            Err(MatrixError::NotSquare { rows: 1, cols: 2 })
+        );
+    }
+
+    #[test]
+    fn matrix_angle_between_column_spaces() {
+        let x = vec![vec![1.0], vec![0.0], vec![0.0]];
+        let y = vec![vec![0.0], vec![1.0], vec![0.0]];
+        let diagonal = vec![vec![1.0], vec![1.0], vec![0.0]];
+        assert!(close(matrix_angle(&x, &x).unwrap(), 0.0, 1e-12));
+        assert!(close(matrix_angle(&x, &y).unwrap(), FRAC_PI_2, 1e-12));
+        assert!(close(
+            matrix_angle(&x, &diagonal).unwrap(),
+            std::f64::consts::FRAC_PI_4,
+            1e-12
+        ));
+        // the span doesn't depend on the basis
+        let scaled = vec![vec![2.0, 1.0], vec![0.0, 3.0], vec![0.0, 0.0]];
+        let plane = vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![0.0, 0.0]];
+        assert!(close(matrix_angle(&scaled, &plane).unwrap(), 0.0, 1e-7));
+    }
+
+    #[test]
+    fn matrix_angle_needs_matching_nonzero_operands() {
+        let x = vec![vec![1.0], vec![0.0]];
+        assert!(matches!(
+            matrix_angle(&x, &vec![vec![1.0]]),
+            Err(MatrixError::DimensionMismatch { .. })
+        ));
+        assert_eq!(
+            matrix_angle(&x, &vec![vec![0.0], vec![0.0]]),
+            Err(MatrixError::Empty)
+        );
+    }
+
+    #[test]
+    fn nilpotency_index_of_shift_matrices() {
+        let shift = vec![vec![0, 1, 0], vec![0, 0, 1], vec![0, 0, 0]];
+        assert_eq!(nilpotency_index(&shift), Some(3));
+        assert!(is_nilpotent(&shift, 0.0));
+        assert_eq!(nilpotency_index(&vec![vec![0, 0], vec![0, 0]]), Some(1));
+        assert_eq!(nilpotency_index(&vec![vec![0, 1], vec![0, 1]]), None);
+        assert!(!is_nilpotent(&identity_matrix(2), 1e-9));
+    }
+
+    #[test]
+    fn companion_matrix_layout_and_roots() {
+        // 2x² - 6x + 4 = 2(x - 1)(x - 2)
+        let coeffs = [4.0, -6.0, 2.0];
+        assert_eq!(
+            companion_matrix(&coeffs),
+            vec![vec![0.0, -2.0], vec![1.0, 3.0]]
+        );
+        let roots = poly_roots(&coeffs);
+        assert_eq!(roots.len(), 2);
+        assert!(close(roots[0], 1.0, 1e-9) && close(roots[1], 2.0, 1e-9));
+        // trailing zeros don't raise the degree
+        assert_eq!(companion_matrix(&[4.0, -6.0, 2.0, 0.0]).len(), 2);
+        assert!(companion_matrix(&[0.0, 0.0]).is_empty());
+        // x² + 1 has no real roots
+        assert!(poly_roots(&[1.0, 0.0, 1.0]).is_empty());
+    }
+
+    #[test]
+    fn characteristic_polynomial_coefficients() {
+        // λ² - 5λ - 2 for [[1, 2], [3, 4]]
+        assert_eq!(
+            characteristic_polynomial(&vec![vec![1.0, 2.0], vec![3.0, 4.0]]),
+            vec![-2.0, -5.0, 1.0]
+        );
+        // (λ - 2)(λ - 3)(λ - 5) for a triangular matrix
+        let m = vec![
+            vec![2.0, 7.0, 1.0],
+            vec![0.0, 3.0, 4.0],
+            vec![0.0, 0.0, 5.0],
+        ];
+        assert_eq!(characteristic_polynomial(&m), vec![-30.0, 31.0, -10.0, 1.0]);
+        assert_eq!(characteristic_polynomial(&vec![]), vec![1.0]);
+    }
+
+    #[test]
+    fn matrices_satisfy_their_characteristic_polynomial() {
+        let m = vec![
+            vec![2.0, -1.0, 0.5],
+            vec![3.0, 0.0, 1.0],
+            vec![-1.0, 4.0, 2.0],
+        ];
+        let zero = evaluate_polynomial(&characteristic_polynomial(&m), &m);
+        assert!(frobenius_norm(&zero) < 1e-9, "{:?}", zero);
+    }
+
+    #[test]
+    fn spectral_radius_is_the_largest_eigenvalue_modulus() {
+        let m = vec![vec![2.0, 0.0], vec![0.0, -5.0]];
+        assert!(close(spectral_radius(&m).unwrap(), 5.0, 1e-9));
+        // a rotation has no dominant real eigenvalue for power iteration
+        let rotation = vec![vec![0.0, -3.0], vec![3.0, 0.0]];
+        assert!(close(spectral_radius(&rotation).unwrap(), 3.0, 1e-9));
+        assert!(matches!(
+            spectral_radius(&vec![vec![1.0, 2.0]]),
+            Err(MatrixError::NotSquare { .. })
+        ));
+    }
+
+    #[test]
+    fn balancing_scales_by_powers_of_two_without_rounding() {
+        let m = vec![
+            vec![1.0, 4096.0, 2048.0],
+            vec![1.0 / 1024.0, 2.0, 8.0],
+            vec![1.0 / 512.0, 0.5, 3.0],
+        ];
+        let (b, scale) = balance(&m);
+        for &d in &scale {
+            assert_eq!(d.log2().fract(), 0.0, "{:?}", scale);
+        }
+        // b = D⁻¹ m D exactly
+        for i in 0..3 {
+            for j in 0..3 {
+                assert_eq!(b[i][j], m[i][j] * scale[j] / scale[i]);
+            }
+        }
+        let off = |x: &MatrixF64, i: usize| -> (f64, f64) {
+            let row = (0..3).filter(|&j| j != i).map(|j| x[i][j].abs()).sum();
+            let col = (0..3).filter(|&j| j != i).map(|j| x[j][i].abs()).sum();
+            (row, col)
+        };
+        let (row, col) = off(&m, 0);
+        assert!(row / col > 1e5);
+        let (row, col) = off(&b, 0);
+        assert!(row / col < 16.0 && col / row < 16.0, "{:?}", b);
+    }
+
+    #[test]
+    fn balancing_moves_isolated_eigenvalues_aside() {
+        // the last row has no off-diagonal entries
+        let m = vec![
+            vec![1.0, 2.0, 3.0],
+            vec![4.0, 5.0, 6.0],
+            vec![0.0, 0.0, 9.0],
+        ];
+        let (b, scale) = balance(&m);
+        assert_eq!(scale[2], 2.0);
+        assert_eq!(b[2], vec![0.0, 0.0, 9.0]);
+        assert_eq!(balance(&vec![]), (vec![], vec![]));
+    }
+
+    #[test]
+    fn hessenberg_form_is_similar_to_the_input() {
+        let m = vec![
+            vec![4.0, 1.0, -2.0, 2.0],
+            vec![1.0, 2.0, 0.0, 1.0],
+            vec![-2.0, 0.0, 3.0, -2.0],
+            vec![2.0, 1.0, -2.0, -1.0],
+        ];
+        let (h, q) = hessenberg_form(&m).unwrap();
+        for (i, row) in h.iter().enumerate() {
+            assert!(
+                row[..i.saturating_sub(1)].iter().all(|&x| x == 0.0),
+                "{:?}",
+                h
+            );
+        }
+        assert!(orthogonality_error(&q) < 1e-12);
+        let back = multiply_f64(&multiply_f64(&q, &h), &transpose(&q));
+        assert!(matrices_close(&back, &m, 1e-12), "{:?}", back);
+        assert!(matches!(
+            hessenberg_form(&vec![vec![1.0, 2.0]]),
+            Err(MatrixError::NotSquare { .. })
+        ));
+    }
+
+    fn sorted_eigenvalues(mut eig: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+        eig.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        eig
+    }
+
+    #[test]
+    fn qr_iteration_finds_real_and_complex_eigenvalues() {
+        // eigenvalues 1, 2, 3 and ±2i
+        let m = vec![
+            vec![2.0, 1.0, 0.0, 0.0, 0.0],
+            vec![0.0, 1.0, 5.0, 0.0, 0.0],
+            vec![0.0, 0.0, 3.0, 1.0, 0.0],
+            vec![0.0, 0.0, 0.0, 0.0, -2.0],
+            vec![0.0, 0.0, 0.0, 2.0, 0.0],
+        ];
+        let expected = [(0.0, -2.0), (0.0, 2.0), (1.0, 0.0), (2.0, 0.0), (3.0, 0.0)];
+        for shift in [QrShift::Unshifted, QrShift::FrancisDouble] {
+            let eig = sorted_eigenvalues(qr_iteration(&m, 10_000, 1e-12, shift).unwrap());
+            assert_eq!(eig.len(), 5);
+            for (&(re, im), &(x, y)) in eig.iter().zip(&expected) {
+                assert!(
+                    close(re, x, 1e-8) && close(im, y, 1e-8),
+                    "{:?} {:?}",
+                    shift,
+                    eig
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn qr_iteration_reports_when_it_runs_out_of_iterations() {
+        let m = vec![
+            vec![1.0, 2.0, 3.0],
+            vec![4.0, 5.0, 6.0],
+            vec![7.0, 8.0, 10.0],
+        ];
+        assert_eq!(
+            qr_iteration(&m, 1, 1e-15, QrShift::Unshifted),
+            Err(MatrixError::DidNotConverge { iterations: 1 })
+        );
+    }
+
+    fn root_error(m: &MatrixF64, n: u32) -> String {
+        match matrix_root(m, n) {
+            Err(MatrixError::InvalidArgument(msg)) => msg,
+            other => panic!("expected an invalid argument, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn roots_raised_to_their_power_give_the_matrix_back() {
+        // eigenvalues 1 and 9
+        let m = vec![vec![5.0, 4.0], vec![4.0, 5.0]];
+        let root = matrix_root(&m, 2).unwrap();
+        assert!(matrices_close(
+            &root,
+            &vec![vec![2.0, 1.0], vec![1.0, 2.0]],
+            1e-9
+        ));
+
+        let m = vec![
+            vec![-8.0, 0.0, 0.0],
+            vec![0.0, 27.0, 0.0],
+            vec![1.0, 0.0, 1.0],
+        ];
+        let root = matrix_root(&m, 3).unwrap();
+        let cube = multiply_f64(&multiply_f64(&root, &root), &root);
+        assert!(matrices_close(&cube, &m, 1e-9));
+        assert!(close(root[0][0], -2.0, 1e-9) && close(root[1][1], 3.0, 1e-9));
+        // a repeated eigenvalue of a diagonalizable matrix
+        assert!(matrices_close(
+            &matrix_root(&vec![vec![4.0, 0.0], vec![0.0, 4.0]], 2).unwrap(),
+            &vec![vec![2.0, 0.0], vec![0.0, 2.0]],
+            1e-12
+        ));
+    }
+
+    #[test]
+    fn matrices_without_a_real_root_are_errors() {
+        assert_eq!(
+            root_error(&vec![vec![0.0, -1.0], vec![1.0, 0.0]], 2),
+            "the matrix has complex eigenvalues, so its real square root isn't computed"
+        );
+        assert_eq!(
+            root_error(&vec![vec![-4.0, 0.0], vec![0.0, 1.0]], 4),
+            "the matrix has the negative eigenvalue -4, so it has no real 4th root"
+        );
+        assert!(root_error(&vec![vec![1.0, 1.0], vec![0.0, 1.0]], 2)
+            .starts_with("the matrix isn't diagonalizable"));
+        assert_eq!(
+            root_error(&vec![vec![1.0]], 0),
+            "the root must be at least 1"
+        );
+        assert_eq!(matrix_root(&vec![], 2), Err(MatrixError::Empty));
+        assert_eq!(root_name(21), "21st root");
+        assert_eq!(root_name(112), "112th root");
+    }
+
+    #[test]
+    fn cholesky_factors_multiply_back_to_the_matrix() {
+        let m = vec![
+            vec![4.0, 12.0, -16.0],
+            vec![12.0, 37.0, -43.0],
+            vec![-16.0, -43.0, 98.0],
+        ];
+        let l = cholesky(&m).unwrap().unwrap();
+        assert!(matrices_close(
+            &l,
+            &vec![
+                vec![2.0, 0.0, 0.0],
+                vec![6.0, 1.0, 0.0],
+                vec![-8.0, 5.0, 3.0]
+            ],
+            1e-12
+        ));
+        assert!(matrices_close(&multiply_f64(&l, &transpose(&l)), &m, 1e-12));
+
+        assert!(is_positive_definite(&vec![vec![2, -1], vec![-1, 2]]));
+        // positive semidefinite and indefinite matrices aren't
+        assert!(!is_positive_definite(&vec![vec![1, 1], vec![1, 1]]));
+        assert!(!is_positive_definite(&vec![vec![1, 2], vec![2, 1]]));
+        assert!(!is_positive_definite(&vec![vec![1, 2], vec![0, 1]]));
+    }
+
+    #[test]
+    fn cholesky_needs_a_square_symmetric_matrix() {
+        assert_eq!(cholesky(&vec![]), Err(MatrixError::Empty));
+        assert_eq!(
+            cholesky(&vec![vec![1.0, 0.0]]),
+            Err(MatrixError::NotSquare { rows: 1, cols: 2 })
+        );
+        assert_eq!(
+            cholesky(&vec![vec![1.0, 2.0], vec![0.0, 1.0]]),
+            Err(MatrixError::InvalidArgument(String::from(
+                "the matrix isn't symmetric (m[1][0] = 0, m[0][1] = 2), \
+                 so positive definiteness isn't checked"
+            )))
+        );
+        // rounding below the tolerance still counts as symmetric
+        assert!(cholesky(&vec![vec![2.0, 1.0], vec![1.0 + 1e-14, 2.0]])
+            .unwrap()
+            .is_some());
+    }
+}
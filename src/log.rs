@@ -0,0 +1,195 @@
+use std::{
+    fmt,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, OnceLock,
+    },
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Environment variable holding the least severe level written to the log
+pub const LOG_ENV: &str = "MATRIXMULT_LOG";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub fn from_name(name: &str) -> Option<Level> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "error" => Level::Error,
+            "warn" => Level::Warn,
+            "info" => Level::Info,
+            "debug" => Level::Debug,
+            "trace" => Level::Trace,
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+struct Logger {
+    file: Mutex<File>,
+    level: Level,
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Appends log lines at `level` or more severe to the file at `path`. Logs
+/// only ever go to the file, never to stdout or stderr, so logging is safe
+/// while the TUI owns the terminal. Only the first call has any effect
+pub fn init(path: &Path, level: Level) -> io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let _ = LOGGER.set(Logger {
+        file: Mutex::new(file),
+        level,
+    });
+    Ok(())
+}
+
+/// Set while the TUI owns the terminal
+static TERMINAL_OWNED: AtomicBool = AtomicBool::new(false);
+
+/// Records whether the TUI owns the terminal, in raw mode on the alternate
+/// screen, where anything else written to stdout garbles its panels
+pub fn set_terminal_owned(owned: bool) {
+    TERMINAL_OWNED.store(owned, Ordering::Relaxed);
+}
+
+/// Records that the TUI is giving the terminal back, returning whether it
+/// owned it, so only the first of several callers restores it
+pub fn release_terminal() -> bool {
+    TERMINAL_OWNED.swap(false, Ordering::Relaxed)
+}
+
+/// Fails a debug build if the TUI owns the terminal. Called before writing
+/// to stdout, so a print reachable from the TUI's computations is caught
+/// instead of smearing the screen; diagnostics belong in the log
+pub fn debug_assert_stdout_free() {
+    debug_assert!(
+        !TERMINAL_OWNED.load(Ordering::Relaxed),
+        "stdout written while the TUI owns the terminal"
+    );
+}
+
+pub fn enabled(level: Level) -> bool {
+    LOGGER.get().is_some_and(|logger| level <= logger.level)
+}
+
+/// Writes `message` as a line `<unix millis> <LEVEL> <target>: <message>`
+pub fn log(level: Level, target: &str, message: impl fmt::Display) {
+    let Some(logger) = LOGGER.get().filter(|logger| level <= logger.level) else {
+        return;
+    };
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis());
+    let line = format!("{} {} {}: {}\n", millis, level.name(), target, message);
+    if let Ok(mut file) = logger.file.lock() {
+        // a failing log write mustn't fail the computation it describes
+        let _ = file.write_all(line.as_bytes());
+    }
+}
+
+/// A timed section of work, logged when it starts and, with its duration,
+/// when it's dropped
+pub struct Span {
+    level: Level,
+    name: &'static str,
+    fields: String,
+    start: Instant,
+}
+
+/// Starts a span, with `key=value` fields describing it
+pub fn span(level: Level, name: &'static str, fields: impl fmt::Display) -> Span {
+    let fields = if enabled(level) {
+        fields.to_string()
+    } else {
+        String::new()
+    };
+    log(level, "span", format_args!("{} started {}", name, fields));
+    Span {
+        level,
+        name,
+        fields,
+        start: Instant::now(),
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        log(
+            self.level,
+            "span",
+            format_args!(
+                "{} finished {} elapsed_ms={:.3}",
+                self.name,
+                self.fields,
+                self.start.elapsed().as_secs_f64() * 1000.0
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_parse_case_insensitively() {
+        assert_eq!(Level::from_name("WARN"), Some(Level::Warn));
+        assert_eq!(Level::from_name("trace"), Some(Level::Trace));
+        assert_eq!(Level::from_name("verbose"), None);
+        assert!(Level::Error < Level::Debug);
+    }
+
+    // the logger is set once per process, so this is the only test that sets it
+    #[test]
+    fn the_log_file_gets_lines_at_or_above_its_level() {
+        let path =
+            std::env::temp_dir().join(format!("matrixmult-{}-log-levels.txt", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        init(&path, Level::Debug).unwrap();
+        let other = path.with_extension("other");
+        init(&other, Level::Trace).unwrap();
+
+        log(Level::Info, "test", "kept");
+        log(Level::Trace, "test", "dropped");
+        drop(span(Level::Debug, "work", "size=3"));
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(
+            text.lines().any(|line| line.ends_with(" INFO test: kept")),
+            "{}",
+            text
+        );
+        assert!(!text.contains("dropped"), "{}", text);
+        assert!(text.contains("DEBUG span: work started size=3"), "{}", text);
+        assert!(
+            text.contains("DEBUG span: work finished size=3 elapsed_ms="),
+            "{}",
+            text
+        );
+        assert!(std::fs::read_to_string(&other).unwrap().is_empty());
+        for path in [path, other] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -5,35 +5,68 @@
 ///
 /// This is a very simple example:
 ///   * A input box always focused. Every character you type is registered
-///   here
+///     here
 ///   * Pressing Backspace erases a character
 ///   * Pressing Enter pushes the current input in the history of previous
-///   messages
+///     messages
 use crossterm::{
+    cursor,
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use matrixmult::{
+    cache::LruCache,
+    cli::{self, CliError, ErrorFormat},
+    export::{
+        autosave_result, export_answer, export_frames, write_atomic, ExportError, WriteOptions,
+    },
+    fixed::FixedWidth,
+    format::{
+        answer_cells, format_answer_localized, format_answer_zeros_as, format_heatmap,
+        matrix_to_text, NumberLocale, ZERO_PLACEHOLDER,
+    },
+    linalg::{cholesky, determinant_sign, matrix_root},
+    log::{self, Level},
+    matrix::{
+        center_columns, convolve, covariance, diff_cells, dims, flatten_column, flatten_row,
+        interpolate, interpolation_frames, matrix_vector_product, mean, min_max,
+        multiply_rows_until, outer_difference, partial_sums, replace_value, rotate_ccw, rotate_cw,
+        round_matrix, sort_rows, sum_all, to_f64, to_int_matrix, transpose, transpose_in_place,
+        vandermonde, Answer, ConvolveMode, Matrix, MatrixError, RoundMode, RowKey,
+    },
+    operation::{elementwise_max, elementwise_min, Operation},
+    parse::{parse_matrix, parse_matrix_f64},
+    properties::{max_asymmetry, scalar_multiple, trace_of_product, MatrixProperties},
+    session::{format_session, NamedMatrices, INPUT_NAMES},
+};
 use std::{
+    collections::BTreeMap,
     error::Error,
-    io,
+    io, panic,
+    path::{Path, PathBuf},
+    process,
     sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         mpsc::{self, Receiver},
         Arc,
     },
-    thread,
+    thread::{self, JoinHandle},
     time::{Duration, Instant},
 };
 use tui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Alignment, Constraint, Direction, Layout},
-    style::{Color, Style},
-    text::Span,
-    widgets::{Block, BorderType, Borders, Paragraph, Wrap},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans, Text},
+    widgets::{Block, BorderType, Borders, Clear, Paragraph, Wrap},
     Frame, Terminal,
 };
 
-type Matrix = Vec<Vec<i64>>;
+/// Compute requests this soon after the previous computation finished are
+/// dropped, so key repeat or presses queued up during a long computation
+/// don't each trigger another one
+const COMPUTE_DEBOUNCE: Duration = Duration::from_millis(250);
 
 // the name event was taken :(
 enum Ev<I> {
@@ -47,8 +80,166 @@ struct App {
     curr_matrix: i32,
     /// The text inside either matrix
     matrix_text: Vec<String>,
-    curr_string: String,
-    answer: Option<Matrix>,
+    /// Editing state of each matrix, kept while the other one is selected
+    panels: [PanelState; 2],
+    answer: Option<Answer>,
+    /// Operation computed from the two matrices when pressing `t`
+    operation: Operation,
+    /// Message shown in the status line
+    status: Option<String>,
+    /// Inputs of `t` that produced `answer`
+    answer_key: Option<ComputeInputs>,
+    /// Directory every computed result is written to, if autosave is on
+    autosave_dir: Option<PathBuf>,
+    /// Value being asked of the user, if any
+    prompt: Option<Prompt>,
+    /// Whether the info popup for the selected matrix is open
+    show_info: bool,
+    /// When the last computation finished, and its inputs
+    last_computed: Option<(Instant, ComputeInputs)>,
+    /// Result kept aside with `p` to compare later results against
+    pinned: Option<Answer>,
+    /// Whether the Result panel highlights cells that differ from `pinned`
+    show_diff: bool,
+    /// How numbers in the Result panel are formatted
+    locale: NumberLocale,
+    /// Existing file the user was warned about, which saving to again replaces
+    overwrite_path: Option<PathBuf>,
+    /// Every operation computed with `t`, oldest first
+    history: Vec<HistoryEntry>,
+    /// Entry highlighted in the history overlay, which is open while set
+    history_selected: Option<usize>,
+    /// Which matrices ignore edits, toggled with `k`
+    locked: [bool; 2],
+    /// Running product of the matrices multiplied on with `x`, and how many
+    /// there were
+    accumulator: Option<(Matrix, usize)>,
+    /// Which panels (the two matrices, then the result) are shown as
+    /// heatmaps, toggled with `H` for the selected matrix and `M` for the
+    /// result
+    heatmap: [bool; 3],
+    /// Whether zero elements of the panels are shown as `ZERO_PLACEHOLDER`,
+    /// toggled with `z`
+    blank_zeros: bool,
+    /// Width and overflow mode of integer computations, changed with `w`
+    /// and `W`
+    arithmetic: FixedWidth,
+    /// Matrices stored in the registers `a` to `z`
+    registers: BTreeMap<char, Matrix>,
+    /// What the next letter typed does with its register, while set
+    register_action: Option<RegisterAction>,
+    /// Recent results of `t`, by the inputs they were computed from
+    cache: LruCache<ComputeInputs, Answer>,
+    /// Matrices of the `--resume` session that aren't inputs or registers,
+    /// kept to be saved back
+    other_matrices: NamedMatrices,
+    /// Whether `--safe` turned off loading and saving files
+    safe: bool,
+    /// Field separator of CSV and TSV files loaded and saved, from
+    /// `--delimiter`
+    delimiter: Option<char>,
+    /// The product being computed in the background, if any
+    job: Option<Job>,
+    /// The matrices' text when `answer` was computed, or `None` if it
+    /// doesn't depend on them, like the running product of `x`
+    answer_inputs: Option<Vec<String>>,
+    /// Title and lines of the overlay showing how an element of the product
+    /// is summed, open while set
+    cell_sums: Option<(String, String)>,
+    /// Rows computed and total rows of the product, when the Result panel
+    /// holds the first rows of a product stopped with `C`
+    partial: Option<(usize, usize)>,
+}
+
+/// Where editing a matrix continues when it's selected
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct PanelState {
+    /// Text of the row being edited, the last one
+    line: String,
+}
+
+/// Products taking at least this many multiply-adds are computed on a
+/// background thread so they can be stopped
+const BACKGROUND_WORK: usize = 20_000_000;
+
+/// A product computed on a background thread, one row at a time
+struct Job {
+    stop: Arc<AtomicBool>,
+    rows_done: Arc<AtomicUsize>,
+    rows: usize,
+    /// Whether the rows computed so far are shown once it stops
+    keep_partial: bool,
+    /// Whether `t` was pressed again while it runs
+    requested_again: bool,
+    inputs: ComputeInputs,
+    handle: JoinHandle<Result<Matrix, MatrixError>>,
+}
+
+/// The inputs that determine the result of `t`: the matrices' text, the
+/// operation and the integer arithmetic
+type ComputeInputs = (Vec<String>, Operation, FixedWidth);
+
+/// Results of `t` kept by `App::cache`
+const COMPUTE_CACHE_SIZE: usize = 16;
+
+/// What is done with the register named by the next key
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RegisterAction {
+    /// Store the selected matrix in it
+    StoreSelected,
+    /// Store the result in it
+    StoreResult,
+    /// Replace the selected matrix with its contents
+    Recall,
+}
+
+/// A computed operation and the inputs it was computed from, enough to
+/// compute it again
+#[derive(Clone, PartialEq)]
+struct HistoryEntry {
+    operation: Operation,
+    inputs: Vec<String>,
+}
+
+/// What a submitted prompt value is used for
+#[derive(Clone, Copy)]
+enum PromptAction {
+    /// The `t` to interpolate between the two matrices with
+    Interpolate,
+    /// The number of frames interpolating from Matrix 0 to Matrix 1 and the
+    /// path they're written to
+    Frames,
+    /// A file to load into the selected matrix
+    OpenFile,
+    /// A file to save the result to
+    SaveFile,
+    /// Whether to flatten the selected matrix into a row or a column
+    Flatten,
+    /// The key and direction to sort the rows of the selected matrix by
+    SortRows,
+    /// The tolerance to check the symmetry of the selected matrix with
+    Symmetry,
+    /// The value to replace in the selected matrix and its replacement
+    Replace,
+    /// The degree of the Vandermonde matrix of the selected vector
+    Vandermonde,
+    /// How to round the elements of a float result
+    Round,
+    /// Which root of the selected matrix to take
+    Root,
+    /// The size of the Hilbert matrix to put in the selected slot
+    Hilbert,
+    /// Whether to keep the valid or the full convolution of the matrices
+    Convolve,
+    /// The element of the product whose partial sums are shown
+    PartialSums,
+}
+
+/// A single-line input box asking the user for a value
+struct Prompt {
+    label: &'static str,
+    input: String,
+    action: PromptAction,
 }
 
 impl Default for App {
@@ -56,47 +247,245 @@ impl Default for App {
         App {
             curr_matrix: 0,
             matrix_text: vec![String::from(""); 2],
-            curr_string: String::from(""),
+            panels: Default::default(),
             answer: None,
+            operation: Operation::Multiply,
+            status: None,
+            answer_key: None,
+            autosave_dir: None,
+            prompt: None,
+            show_info: false,
+            last_computed: None,
+            pinned: None,
+            show_diff: false,
+            locale: NumberLocale::default(),
+            overwrite_path: None,
+            history: vec![],
+            history_selected: None,
+            locked: [false; 2],
+            accumulator: None,
+            heatmap: [false; 3],
+            blank_zeros: false,
+            arithmetic: FixedWidth::default(),
+            registers: BTreeMap::new(),
+            register_action: None,
+            cache: LruCache::new(COMPUTE_CACHE_SIZE),
+            other_matrices: NamedMatrices::new(),
+            safe: false,
+            delimiter: None,
+            job: None,
+            answer_inputs: None,
+            cell_sums: None,
+            partial: None,
         }
     }
 }
 
 impl App {
     fn next(&mut self) {
-        self.curr_string = String::from("");
         self.curr_matrix = (self.curr_matrix + 1) % 2;
     }
-}
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let (tx, rx) = mpsc::channel(); // create mpsc channel to handle inputs in separate thread
-    let tick_rate = Duration::from_millis(1000); // wait 1000 ms for event
-    thread::spawn(move || {
-        let mut last_tick = Instant::now();
-        loop {
-            let timeout = tick_rate
-                .checked_sub(last_tick.elapsed())
-                .unwrap_or_else(|| Duration::from_secs(0)); // set timeout to tick_rate - (time since last_tick)
+    /// Editing state of the selected matrix
+    fn panel(&mut self) -> &mut PanelState {
+        &mut self.panels[self.curr_matrix as usize]
+    }
 
-            // if we got an event
-            if event::poll(timeout).unwrap() {
-                // if the event is a keypress
-                if let Event::Key(key) = event::read().unwrap() {
-                    tx.send(Ev::Input(key)).unwrap();
-                }
+    /// Fills the input matrices and registers from a session file's
+    /// matrices, keeping the others to save back with the session
+    fn restore_session(&mut self, mut matrices: NamedMatrices) {
+        for (index, name) in INPUT_NAMES.iter().enumerate() {
+            if let Some(m) = matrices.remove(*name) {
+                self.set_matrix(index, &m);
+            }
+        }
+        for register in 'a'..='z' {
+            if let Some(m) = matrices.remove(&register.to_string()) {
+                self.registers.insert(register, m);
             }
+        }
+        self.other_matrices = matrices;
+    }
 
-            // if more than tick_rate time has passed since last_tick was created
-            if last_tick.elapsed() >= tick_rate {
-                if let Ok(_) = tx.send(Ev::Tick) {
-                    last_tick = Instant::now(); // reset last tick
+    /// The matrices saved in a session file: the input matrices that parse
+    /// and aren't empty, the registers and the session's other matrices
+    fn session_matrices(&self) -> NamedMatrices {
+        let mut matrices = self.other_matrices.clone();
+        for (text, name) in self.matrix_text.iter().zip(INPUT_NAMES) {
+            match parse_matrix(text) {
+                Ok(m) if !m.is_empty() => {
+                    matrices.insert(String::from(name), m);
                 }
+                _ => {}
             }
         }
-    });
+        for (register, m) in &self.registers {
+            matrices.insert(register.to_string(), m.clone());
+        }
+        matrices
+    }
+
+    /// Waits for the letter of the register `action` applies to
+    fn choose_register(&mut self, action: RegisterAction) {
+        let what = match action {
+            RegisterAction::StoreSelected => format!("Store Matrix {} in", self.curr_matrix),
+            RegisterAction::StoreResult => String::from("Store the result in"),
+            RegisterAction::Recall => format!("Recall into Matrix {}", self.curr_matrix),
+        };
+        self.status = Some(format!("{} register: press a-z (Esc cancels)", what));
+        self.register_action = Some(action);
+    }
+
+    fn ask(&mut self, label: &'static str, action: PromptAction) {
+        self.prompt = Some(Prompt {
+            label,
+            input: String::new(),
+            action,
+        });
+    }
+
+    /// Stores a freshly computed answer, writing it out if autosave is on
+    fn set_answer(&mut self, operation: &str, answer: Answer) {
+        self.status = None;
+        if let Some(dir) = &self.autosave_dir {
+            if let Err(err) = autosave_result(dir, operation, &answer) {
+                self.status = Some(format!("autosave failed: {}", err));
+            }
+        }
+        self.answer = Some(answer);
+        self.answer_key = None;
+        self.answer_inputs = Some(self.matrix_text.clone());
+        self.partial = None;
+    }
+
+    /// Whether a matrix was edited since `answer` was computed from them,
+    /// however it was edited
+    fn answer_is_stale(&self) -> bool {
+        self.answer.is_some()
+            && self
+                .answer_inputs
+                .as_ref()
+                .is_some_and(|inputs| *inputs != self.matrix_text)
+    }
+
+    /// Whether files can be loaded and saved, telling the user why not
+    fn files_allowed(&mut self) -> bool {
+        if self.safe {
+            self.status = Some(String::from("file access is disabled in safe mode"));
+        }
+        !self.safe
+    }
+
+    /// Whether the selected matrix can be edited, telling the user why not
+    fn selected_unlocked(&mut self) -> bool {
+        let index = self.curr_matrix as usize;
+        if self.locked[index] {
+            self.status = Some(format!("Matrix {} is locked (k to unlock)", index));
+        }
+        !self.locked[index]
+    }
+
+    /// Replaces the selected matrix with `f` applied to it
+    fn transform_selected(&mut self, f: impl Fn(&Matrix) -> Matrix) {
+        if !self.selected_unlocked() {
+            return;
+        }
+        let index = self.curr_matrix as usize;
+        match parse_matrix(&self.matrix_text[index]) {
+            Ok(m) => self.set_matrix(index, &f(&m)),
+            Err(err) => self.status = Some(err.to_string()),
+        }
+    }
+
+    /// Replaces the text of matrix `index`, continuing editing on its last row
+    fn set_matrix(&mut self, index: usize, m: &Matrix) {
+        self.matrix_text[index] = matrix_to_text(m);
+        self.sync_panel(index);
+    }
+
+    /// Resumes editing matrix `index` on its last row, after its text was
+    /// replaced
+    fn sync_panel(&mut self, index: usize) {
+        self.panels[index].line = self.matrix_text[index]
+            .rsplit('\n')
+            .next()
+            .unwrap_or("")
+            .to_string();
+    }
+
+    /// Deletes the last character of the selected matrix. Deleting a row
+    /// break joins the last two rows, and editing continues on the joined row
+    fn backspace(&mut self) {
+        let index = self.curr_matrix as usize;
+        if self.matrix_text[index].pop() == Some('\n') {
+            self.sync_panel(index);
+        } else {
+            self.panel().line.pop();
+        }
+    }
+
+    /// Everything the answer depends on, used to skip recomputing when
+    /// nothing changed since the last computation
+    fn compute_inputs(&self) -> ComputeInputs {
+        (self.matrix_text.clone(), self.operation, self.arithmetic)
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let (error_format, args) = match cli::split_error_format(std::env::args().skip(1)) {
+        Ok(split) => split,
+        Err(err) => {
+            eprintln!("{}", cli::format_error(&err, ErrorFormat::Text));
+            process::exit(err.exit_code());
+        }
+    };
+    let fail = |err: CliError| -> ! {
+        eprintln!("{}", cli::format_error(&err, error_format));
+        process::exit(err.exit_code());
+    };
+    let options = cli::parse_args(args).unwrap_or_else(|err| fail(err));
+
+    let level = std::env::var(log::LOG_ENV).ok();
+    if let Err(err) = cli::init_log(&options, level.as_deref()) {
+        fail(err);
+    }
+
+    if options.is_batch() {
+        if let Err(err) = cli::run(&options, &mut io::stdout()) {
+            fail(err);
+        }
+        return Ok(());
+    }
+
+    let mut app = App {
+        autosave_dir: options.autosave_dir,
+        locale: options.locale,
+        safe: options.safe,
+        delimiter: options.read.delimiter,
+        ..App::default()
+    };
+    // a session file that doesn't exist yet is created when quitting
+    if let Some(path) = options.resume.as_deref().filter(|path| path.exists()) {
+        let matrices = cli::read_session_file(path).unwrap_or_else(|err| fail(err));
+        app.restore_session(matrices);
+    }
+
+    let (tx, rx) = mpsc::channel(); // create mpsc channel to handle inputs in separate thread
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let input = spawn_input_thread(tx, shutdown.clone());
+
+    // a panic in the TUI gives the terminal back before its message is
+    // printed, and the TUI quits once it sees it no longer owns the terminal
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if panic_restores_terminal(&thread::current()) {
+            restore_terminal().ok();
+        }
+        default_hook(info);
+    }));
 
     // setup terminal
+    log::set_terminal_owned(true);
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
@@ -104,70 +493,439 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut terminal = Terminal::new(backend)?;
     terminal.clear()?;
     terminal.hide_cursor()?;
-    // create app and run it
-    let app = App::default();
+    // run the app
     let res = run_app(&mut terminal, app, rx);
 
-    // restore terminal
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
+    // stop reading keys before handing the terminal back
+    shutdown.store(true, Ordering::Relaxed);
+    input.join().ok();
+
+    restore_terminal()?;
     terminal.show_cursor()?;
 
-    if let Err(err) = res {
-        println!("{:?}", err)
+    match res {
+        Ok(app) => {
+            if let Some(path) = &options.resume {
+                let session = format_session(&app.session_matrices());
+                if let Err(err) = write_atomic(path, session.as_bytes(), true) {
+                    fail(CliError::from(err));
+                }
+            }
+        }
+        Err(err) => println!("{:?}", err),
     }
 
     Ok(())
 }
 
+/// Whether a panic on `thread` should give the terminal back. Only the main
+/// thread runs the TUI: panics in workers are caught and reported by the
+/// TUI, which keeps running
+fn panic_restores_terminal(thread: &thread::Thread) -> bool {
+    thread.name() == Some("main")
+}
+
+/// Set once `restore_terminal` has given the terminal back
+static TERMINAL_RESTORED: AtomicBool = AtomicBool::new(false);
+
+/// Takes the terminal out of raw mode and off the alternate screen, with
+/// mouse capture off and the cursor shown. Only the first call does
+/// anything, so `main` and the panic hook can both call it
+fn restore_terminal() -> io::Result<()> {
+    if !log::release_terminal() {
+        return Ok(());
+    }
+    TERMINAL_RESTORED.store(true, Ordering::Relaxed);
+    disable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        cursor::Show
+    )
+}
+
+/// Longest the input thread waits for a key before checking whether it
+/// should stop, which bounds how long quitting waits for it
+const INPUT_POLL: Duration = Duration::from_millis(100);
+
+/// Reads keys on a thread of its own and sends them to `tx`, with a tick
+/// every second. It stops once `shutdown` is set, the receiver is gone or
+/// the terminal can't be read
+fn spawn_input_thread(tx: mpsc::Sender<Ev<KeyEvent>>, shutdown: Arc<AtomicBool>) -> JoinHandle<()> {
+    let tick_rate = Duration::from_millis(1000); // wait 1000 ms for event
+    thread::spawn(move || {
+        let mut last_tick = Instant::now();
+        while !shutdown.load(Ordering::Relaxed) {
+            let timeout = tick_rate
+                .checked_sub(last_tick.elapsed())
+                .unwrap_or_else(|| Duration::from_secs(0)) // set timeout to tick_rate - (time since last_tick)
+                .min(INPUT_POLL);
+
+            // if we got an event
+            match event::poll(timeout) {
+                Ok(true) => match event::read() {
+                    // if the event is a keypress
+                    Ok(Event::Key(key)) => {
+                        if tx.send(Ev::Input(key)).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(_) => return,
+                },
+                Ok(false) => {}
+                Err(_) => return,
+            }
+
+            // if more than tick_rate time has passed since last_tick was created
+            if last_tick.elapsed() >= tick_rate {
+                if tx.send(Ev::Tick).is_err() {
+                    return;
+                }
+                last_tick = Instant::now(); // reset last tick
+            }
+        }
+    })
+}
+
 fn run_app<B: Backend>(
     terminal: &mut Terminal<B>,
     mut app: App,
     rx: Receiver<Ev<KeyEvent>>,
-) -> io::Result<()> {
+) -> io::Result<App> {
     loop {
+        // a panic hook restored the terminal, which drawing would garble
+        if TERMINAL_RESTORED.load(Ordering::Relaxed) {
+            return Ok(app);
+        }
         terminal.draw(|f| ui(f, &app))?;
 
-        match rx.recv().unwrap() {
+        // the input thread only stops early if the terminal can't be read
+        let Ok(event) = rx.recv() else {
+            return Ok(app);
+        };
+        match event {
+            Ev::Input(key) if app.job.is_some() => {
+                if handle_job_key(&mut app, key) {
+                    return Ok(app);
+                }
+            }
+            Ev::Input(key) if app.prompt.is_some() => handle_prompt_key(&mut app, key),
+            Ev::Input(_) if app.cell_sums.is_some() => app.cell_sums = None,
+            Ev::Input(key) if app.history_selected.is_some() => handle_history_key(&mut app, key),
+            Ev::Input(key) if app.register_action.is_some() => handle_register_key(&mut app, key),
             Ev::Input(key) => match key.code {
                 KeyCode::Tab => {
                     app.next();
                 }
                 KeyCode::Char('q') => {
-                    return Ok(());
+                    return Ok(app);
                 }
                 KeyCode::Char(c) => match c {
-                    '0'..='9' => {
+                    '0'..='9' if app.selected_unlocked() => {
                         app.matrix_text[app.curr_matrix as usize].push(c);
-                        app.curr_string.push(c);
+                        app.panel().line.push(c);
+                    }
+                    '/' if app.selected_unlocked() => {
+                        app.matrix_text[app.curr_matrix as usize].push('/');
+                        app.panel().line.push('/');
                     }
-                    ' ' => {
+                    ' ' if app.selected_unlocked() => {
                         app.matrix_text[app.curr_matrix as usize].push('_');
-                        app.curr_string.push('_');
+                        app.panel().line.push('_');
+                    }
+                    'k' => {
+                        let index = app.curr_matrix as usize;
+                        app.locked[index] = !app.locked[index];
+                    }
+                    'H' => {
+                        let index = app.curr_matrix as usize;
+                        app.heatmap[index] = !app.heatmap[index];
+                    }
+                    'M' => {
+                        app.heatmap[2] = !app.heatmap[2];
+                    }
+                    'z' => {
+                        app.blank_zeros = !app.blank_zeros;
+                        app.status = Some(if app.blank_zeros {
+                            format!("Zeros shown as {} (z to show them)", ZERO_PLACEHOLDER)
+                        } else {
+                            String::from("Zeros shown as 0")
+                        });
                     }
                     't' => {
                         parse_matrices(&mut app);
                     }
+                    'd' => {
+                        report_determinant_sign(&mut app);
+                    }
+                    'o' => {
+                        app.operation = app.operation.next();
+                    }
+                    'w' => {
+                        app.arithmetic.width = app.arithmetic.width.next();
+                    }
+                    'W' => {
+                        app.arithmetic.mode = app.arithmetic.mode.next();
+                    }
+                    'e' => {
+                        explain_operation(&mut app);
+                    }
+                    'a' => {
+                        report_sum_and_mean(&mut app);
+                    }
+                    'x' => {
+                        accumulate_selected(&mut app);
+                    }
+                    'X' => {
+                        app.accumulator = None;
+                        app.status = Some(String::from("cleared the running product"));
+                    }
+                    'm' => {
+                        report_min_max(&mut app);
+                    }
+                    'i' => {
+                        app.show_info = !app.show_info;
+                    }
+                    'h' => {
+                        open_history(&mut app);
+                    }
+                    'r' => {
+                        app.transform_selected(|m| rotate_cw(m));
+                    }
+                    'R' => {
+                        app.transform_selected(|m| rotate_ccw(m));
+                    }
+                    'J' => {
+                        transpose_selected(&mut app);
+                    }
+                    'p' => {
+                        pin_answer(&mut app);
+                    }
+                    'D' => {
+                        toggle_diff(&mut app);
+                    }
+                    'f' => {
+                        app.ask("Flatten into a (r)ow or (c)olumn", PromptAction::Flatten);
+                    }
+                    'c' => {
+                        center_selected(&mut app);
+                    }
+                    'F' => {
+                        app.ask("Replace every (old new)", PromptAction::Replace);
+                    }
+                    'y' => {
+                        app.ask(
+                            "Symmetry tolerance (empty for exact)",
+                            PromptAction::Symmetry,
+                        );
+                    }
+                    'v' => {
+                        covariance_selected(&mut app);
+                    }
+                    'K' => {
+                        report_scalar_multiple(&mut app);
+                    }
+                    'T' => {
+                        report_trace_of_product(&mut app);
+                    }
+                    'O' => {
+                        combine_matrices(&mut app, "outer-difference", outer_difference);
+                    }
+                    '>' => {
+                        combine_matrices(&mut app, "max", elementwise_max);
+                    }
+                    '<' => {
+                        combine_matrices(&mut app, "min", elementwise_min);
+                    }
+                    'V' => {
+                        app.ask(
+                            "Vandermonde matrix of the selected vector: degree",
+                            PromptAction::Vandermonde,
+                        );
+                    }
+                    'S' => {
+                        app.ask(
+                            "Sort rows by (f)irst element or (s)um, then (a)sc or (d)esc",
+                            PromptAction::SortRows,
+                        );
+                    }
+                    's' if app.files_allowed() => {
+                        app.ask(
+                            "Save result as (.csv, .tsv, .mtx, .json, .npy, .mmb, .tex, .md or text)",
+                            PromptAction::SaveFile,
+                        );
+                    }
+                    'L' if app.files_allowed() => {
+                        app.ask(
+                            "Open file (.csv, .tsv, .mtx, .json, .npy, .mmb or text)",
+                            PromptAction::OpenFile,
+                        );
+                    }
+                    'l' => {
+                        app.ask("Interpolate: t in [0, 1]", PromptAction::Interpolate);
+                    }
+                    'N' => {
+                        app.ask(
+                            "Animation frames: count and path, e.g. 10 frames/anim.csv",
+                            PromptAction::Frames,
+                        );
+                    }
+                    'b' => {
+                        app.choose_register(RegisterAction::StoreSelected);
+                    }
+                    'B' => {
+                        app.choose_register(RegisterAction::StoreResult);
+                    }
+                    'g' => {
+                        app.choose_register(RegisterAction::Recall);
+                    }
+                    'n' => {
+                        app.ask(
+                            "Round the result: (r)ound, (f)loor, (c)eil or (t)runcate, then i for integers",
+                            PromptAction::Round,
+                        );
+                    }
+                    'A' => {
+                        app.ask(
+                            "Partial sums of the product's element: row col",
+                            PromptAction::PartialSums,
+                        );
+                    }
+                    'u' => {
+                        app.ask(
+                            "Convolve Matrix 0 with the kernel Matrix 1: (v)alid or (f)ull",
+                            PromptAction::Convolve,
+                        );
+                    }
+                    'I' => {
+                        app.ask("Hilbert matrix: size n", PromptAction::Hilbert);
+                    }
+                    'E' => report_positive_definite(&mut app),
+                    'P' => {
+                        app.ask(
+                            "Root of the selected matrix: n (2 for the square root)",
+                            PromptAction::Root,
+                        );
+                    }
                     _ => {}
                 },
-                KeyCode::Enter => {
+                // a second Enter in a row would start an empty row
+                KeyCode::Enter if app.selected_unlocked() && !app.panel().line.is_empty() => {
                     app.matrix_text[app.curr_matrix as usize].push('\n');
-                    app.curr_string = String::from("");
+                    app.panel().line.clear();
                 }
 
-                KeyCode::Backspace => {
-                    app.matrix_text[app.curr_matrix as usize].pop();
-                    app.curr_string.pop();
+                KeyCode::Backspace if app.selected_unlocked() => {
+                    app.backspace();
                 }
                 _ => {}
             },
             Ev::Tick => {}
         }
+        poll_job(&mut app);
+    }
+}
+
+/// Keys while a product is computed in the background: `Esc` stops it,
+/// `C` stops it and keeps the rows computed so far. Returns whether to quit
+fn handle_job_key(app: &mut App, key: KeyEvent) -> bool {
+    let Some(job) = &mut app.job else {
+        return false;
+    };
+    match key.code {
+        KeyCode::Char('t') => job.requested_again = true,
+        KeyCode::Esc => job.stop.store(true, Ordering::Relaxed),
+        KeyCode::Char('C') => {
+            job.keep_partial = true;
+            job.stop.store(true, Ordering::Relaxed);
+        }
+        KeyCode::Char('q') => {
+            job.stop.store(true, Ordering::Relaxed);
+            return true;
+        }
+        _ => {}
     }
+    false
+}
+
+/// Shows the progress of the background product, or its result once it's
+/// done
+fn poll_job(app: &mut App) {
+    let Some(job) = &app.job else {
+        return;
+    };
+    let done = job.rows_done.load(Ordering::Relaxed);
+    if !job.handle.is_finished() {
+        let stopping = if job.stop.load(Ordering::Relaxed) {
+            " (stopping)"
+        } else {
+            " (Esc cancels, C stops and keeps the rows so far)"
+        };
+        let computing = if job.requested_again {
+            "already computing"
+        } else {
+            "multiplying"
+        };
+        app.status = Some(format!(
+            "{}: {} of {} rows{}",
+            computing, done, job.rows, stopping
+        ));
+        return;
+    }
+    let Some(job) = app.job.take() else {
+        return;
+    };
+    app.last_computed = Some((Instant::now(), job.inputs.clone()));
+    let result = job.handle.join().unwrap_or_else(|_| {
+        Err(MatrixError::ComputationFailed(String::from(
+            "the multiplying thread panicked",
+        )))
+    });
+    match result {
+        Ok(m) if m.len() == job.rows => finish_computation(app, job.inputs, Answer::Int(m)),
+        Ok(m) if job.keep_partial => {
+            let done = m.len();
+            app.set_answer("partial", Answer::Int(m));
+            app.partial = Some((done, job.rows));
+            app.status = Some(format!("stopped after {} of {} rows", done, job.rows));
+        }
+        Ok(_) => app.status = Some(String::from("cancelled")),
+        Err(err) => app.status = Some(err.to_string()),
+    }
+}
+
+/// Starts multiplying `a` and `b` on a background thread
+fn start_job(app: &mut App, a: Matrix, b: Matrix, inputs: ComputeInputs) {
+    let stop = Arc::new(AtomicBool::new(false));
+    let rows_done = Arc::new(AtomicUsize::new(0));
+    let rows = a.len();
+    let handle = {
+        let (stop, rows_done) = (stop.clone(), rows_done.clone());
+        thread::spawn(move || multiply_rows_until(&a, &b, &stop, &rows_done))
+    };
+    app.job = Some(Job {
+        stop,
+        rows_done,
+        rows,
+        keep_partial: false,
+        requested_again: false,
+        inputs,
+        handle,
+    });
+    poll_job(app);
+}
+
+/// Shows a computed result and records it in the cache and the history
+fn finish_computation(app: &mut App, inputs: ComputeInputs, answer: Answer) {
+    app.set_answer(app.operation.name(), answer.clone());
+    app.answer_key = Some(inputs.clone());
+    app.cache.insert(inputs, answer);
+    app.history.push(HistoryEntry {
+        operation: app.operation,
+        inputs: app.matrix_text.clone(),
+    });
 }
 
 fn ui<B: Backend>(f: &mut Frame<B>, app: &App) {
@@ -202,44 +960,145 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &App) {
             .direction(Direction::Vertical)
             .margin(1)
             .constraints([Constraint::Percentage(45), Constraint::Percentage(25)])
-            .split(matrices[i as usize])
+            .split(matrices[i])
     };
 
+    let header = format!(
+        "Operation: {} (o to change)   Integers: {} {} (w / W to change)",
+        app.operation.name(),
+        app.arithmetic.width.name(),
+        app.arithmetic.mode.name()
+    );
+    f.render_widget(Paragraph::new(header), chunks[0]);
+
+    if let Some(status) = &app.status {
+        let status = Paragraph::new(status.as_str()).wrap(Wrap { trim: true });
+        f.render_widget(status, chunks[3]);
+    }
+
     for i in 0..3 {
-        let para = render_grid(i, app.curr_matrix);
+        let locked = app.locked.get(i as usize).copied().unwrap_or(false);
+        let para = render_grid(
+            i,
+            app.curr_matrix,
+            locked,
+            app.partial,
+            app.answer_is_stale(),
+        );
         f.render_widget(para, matrices[i as usize]);
     }
 
     for i in 0..app.matrix_text.len() {
-        let a = Paragraph::new(app.matrix_text[i].as_ref())
+        // a panel that doesn't parse yet is shown as typed
+        let typed = &app.matrix_text[i];
+        let shown = if app.heatmap[i] {
+            parse_matrix_f64(typed).ok().map(|m| format_heatmap(&m))
+        } else if app.blank_zeros {
+            parse_matrix(typed)
+                .map(Answer::Int)
+                .or_else(|_| parse_matrix_f64(typed).map(Answer::Float))
+                .ok()
+                .map(|m| format_answer_zeros_as(&m, &NumberLocale::default(), ZERO_PLACEHOLDER))
+        } else {
+            None
+        };
+        let a = Paragraph::new(shown.unwrap_or_else(|| typed.clone()))
             .alignment(Alignment::Center)
             .wrap(Wrap { trim: false });
         f.render_widget(a, text(i)[1]);
     }
 
-    if let Some(x) = &app.answer {
-        let text2: Vec<Vec<String>> = x
-            .iter()
-            .map(|a| a.iter().map(|b| b.to_string()).collect())
-            .collect();
+    if let Some(prompt) = &app.prompt {
+        let input = Paragraph::new(prompt.input.as_str()).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Span::raw(prompt.label)),
+        );
+        f.render_widget(input, chunks[1]);
+    }
 
-        let mut str: String = String::from("");
-        for i in text2 {
-            for j in i {
-                str.push_str(format!("{} ", j).as_str());
+    if let Some(x) = &app.answer {
+        let area = text(2)[1];
+        let text_of = match (&app.pinned, app.show_diff) {
+            _ if app.heatmap[2] => Text::from(format_heatmap(&x.to_f64())),
+            (Some(pinned), true) => diff_text(pinned, x, &app.locale),
+            _ if x.dims() == (1, 1) => scalar_text(&format_answer_localized(x, &app.locale), area),
+            _ if app.blank_zeros => {
+                Text::from(format_answer_zeros_as(x, &app.locale, ZERO_PLACEHOLDER))
             }
-            str.push_str("\n");
+            _ => Text::from(format_answer_localized(x, &app.locale)),
+        };
+
+        let style = if app.answer_is_stale() {
+            Style::default().fg(Color::DarkGray)
+        } else {
+            Style::default()
+        };
+        // wrapping would split numbers across lines, so wide rows are cut
+        // after their last whole cell instead
+        let (mut text_of, clipped) = clip_lines(text_of, area.width as usize);
+        if clipped {
+            text_of.lines.push(Spans::from(Span::styled(
+                "too wide, s saves it all",
+                Style::default().fg(Color::DarkGray),
+            )));
         }
+        let a = Paragraph::new(text_of)
+            .style(style)
+            .alignment(Alignment::Center);
+        f.render_widget(a, area);
+    }
 
-        let a = Paragraph::new(str)
-            .alignment(Alignment::Center)
-            .wrap(Wrap { trim: false });
-        f.render_widget(a, text(2)[1]);
+    if app.show_info {
+        let area = centered_rect(50, 50, f.size());
+        let index = app.curr_matrix as usize;
+        let info = Paragraph::new(matrix_info(&app.matrix_text[index])).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Span::raw(format!("Matrix {} info", index))),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(info, area);
+    }
+
+    if let Some((title, lines)) = &app.cell_sums {
+        let area = centered_rect(60, 60, f.size());
+        let sums = Paragraph::new(lines.as_str()).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Span::raw(title.as_str())),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(sums, area);
+    }
+
+    if let Some(selected) = app.history_selected {
+        let area = centered_rect(60, 50, f.size());
+        let lines: Vec<Spans> = app
+            .history
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let style = if i == selected {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default()
+                };
+                Spans::from(Span::styled(history_line(i, entry), style))
+            })
+            .collect();
+        let history = Paragraph::new(lines)
+            .scroll((selected.saturating_sub(area.height as usize / 2) as u16, 0))
+            .block(Block::default().borders(Borders::ALL).title(Span::raw(
+                "History: Enter reruns on the current inputs, u on its own inputs",
+            )));
+        f.render_widget(Clear, area);
+        f.render_widget(history, area);
     }
 
     // let x = matrices[app.curr_matrix as usize].x;
     // let half_width = matrices[app.curr_matrix as usize].width / 2;
-    // let len = app.curr_string.len() as u16;
+    // let len = app.panels[app.curr_matrix as usize].line.len() as u16;
     // let mut y_cal = matrices[app.curr_matrix as usize].y
     //     + matrices[app.curr_matrix as usize].height / 2
     //     + app.matrix_text[app.curr_matrix as usize]
@@ -262,7 +1121,135 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &App) {
     // );
 }
 
-fn render_grid<'a>(index: i32, curr_matrix: i32) -> Paragraph<'a> {
+/// The result with the cells that differ from `pinned` in red, or the plain
+/// result if the two shapes differ
+fn diff_text<'a>(pinned: &Answer, answer: &Answer, locale: &NumberLocale) -> Text<'a> {
+    let Ok(changed) = diff_cells(pinned, answer) else {
+        return Text::from(format_answer_localized(answer, locale));
+    };
+    let lines: Vec<Spans> = answer_cells(answer)
+        .into_iter()
+        .zip(changed)
+        .map(|(row, changed)| {
+            let mut spans = vec![];
+            for (j, (cell, changed)) in row.into_iter().zip(changed).enumerate() {
+                let cell = locale.localize(&cell);
+                if j > 0 {
+                    spans.push(Span::raw(" "));
+                }
+                spans.push(if changed {
+                    Span::styled(cell, Style::default().fg(Color::Red))
+                } else {
+                    Span::raw(cell)
+                });
+            }
+            Spans::from(spans)
+        })
+        .collect();
+    Text::from(lines)
+}
+
+/// A 1x1 result, formatted as `value`, shown as a scalar: bold in the middle
+/// of `area` under a `scalar` label
+fn scalar_text<'a>(value: &str, area: Rect) -> Text<'a> {
+    let mut lines = vec![Spans::default(); (area.height as usize).saturating_sub(2) / 2];
+    lines.push(Spans::from(Span::styled(
+        "scalar",
+        Style::default().fg(Color::DarkGray),
+    )));
+    lines.push(Spans::from(Span::styled(
+        String::from(value.trim()),
+        Style::default().add_modifier(Modifier::BOLD),
+    )));
+    Text::from(lines)
+}
+
+/// `text` with every line longer than `width` characters cut after the last
+/// cell that fits along with a `…`, so no number is split, and whether any
+/// line was cut. A line whose first cell doesn't fit is just `…`
+fn clip_lines(text: Text<'_>, width: usize) -> (Text<'_>, bool) {
+    let mut clipped = false;
+    let lines = text
+        .lines
+        .into_iter()
+        .map(|line| {
+            let chars: Vec<char> = line
+                .0
+                .iter()
+                .flat_map(|span| span.content.chars())
+                .collect();
+            if chars.len() <= width {
+                return line;
+            }
+            clipped = true;
+            // cells end where a space follows a non-space
+            let budget = width.saturating_sub(1);
+            let mut cut = (1..=budget)
+                .rev()
+                .find(|&p| p < chars.len() && chars[p] == ' ' && chars[p - 1] != ' ')
+                .unwrap_or(0);
+            let mut spans = vec![];
+            for span in line.0 {
+                if cut == 0 {
+                    break;
+                }
+                let taken: String = span.content.chars().take(cut).collect();
+                cut -= taken.chars().count();
+                spans.push(Span::styled(taken, span.style));
+            }
+            spans.push(Span::raw("…"));
+            Spans::from(spans)
+        })
+        .collect::<Vec<_>>();
+    (Text::from(lines), clipped)
+}
+
+/// A `percent_x` by `percent_y` rectangle in the middle of `area`
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+/// The lines of the info popup for a matrix panel
+fn matrix_info(text: &str) -> String {
+    let properties = match parse_matrix(text) {
+        Ok(m) => MatrixProperties::compute(&m),
+        Err(err) => return err.to_string(),
+    };
+
+    let mut info = format!("dimensions: {}x{}\n", properties.rows, properties.cols);
+    for (name, value) in properties.flags() {
+        info.push_str(&format!("{}: {}\n", name, if value { "yes" } else { "no" }));
+    }
+    info
+}
+
+fn render_grid<'a>(
+    index: i32,
+    curr_matrix: i32,
+    locked: bool,
+    partial: Option<(usize, usize)>,
+    stale: bool,
+) -> Paragraph<'a> {
+    let stale = if stale {
+        " (stale — press t to recompute)"
+    } else {
+        ""
+    };
     Paragraph::new("")
         .style(Style::default().fg(Color::White))
         .block(
@@ -277,103 +1264,1535 @@ fn render_grid<'a>(index: i32, curr_matrix: i32) -> Paragraph<'a> {
                 })
                 .border_type(BorderType::Plain)
                 .title(Span::raw(if index != 2 {
-                    format!("Matrix {}", index)
+                    format!("Matrix {}{}", index, if locked { " (locked)" } else { "" })
+                } else if let Some((done, rows)) = partial {
+                    format!("Result: partial (rows 0..{} of {}){}", done, rows, stale)
                 } else {
-                    String::from("Result")
+                    format!("Result{}", stale)
                 })),
         )
 }
 
-fn multiply_matrices(m1: &Matrix, m2: &Matrix) -> Matrix {
-    let mut result = vec![vec![0; m2[0].len()]; m1.len()];
-
-    for i in 0..m1.len() {
-        // rows of the first matrix
-        for j in 0..m2[0].len() {
-            // columns of the second matrix
-            for k in 0..m2.len() {
-                // rows of the second matrix
-                result[i][j] += m1[i][k] * m2[k][j]
-            }
-        }
+fn parse_matrices(app: &mut App) {
+    // a repeat of the request that was just handled, dropped quietly. Once
+    // the inputs change it's a new request
+    let inputs = app.compute_inputs();
+    if app
+        .last_computed
+        .as_ref()
+        .is_some_and(|(t, last)| *last == inputs && t.elapsed() < COMPUTE_DEBOUNCE)
+    {
+        return;
+    }
+    // blank panels would parse as matrices without rows. What's typed in
+    // the other panel is kept for when both are filled in
+    let empty: Vec<usize> = (0..app.matrix_text.len())
+        .filter(|&index| app.matrix_text[index].trim().is_empty())
+        .collect();
+    if !empty.is_empty() {
+        let which = match empty[..] {
+            [index] => format!("Matrix {} is empty", index),
+            _ => String::from("both are empty"),
+        };
+        app.status = Some(format!("enter both matrices before computing ({})", which));
+        return;
     }
 
-    result
-}
-
-fn multiply_matrices_threaded(m1: &Matrix, m2: &Matrix, thread_count: usize) -> Matrix {
-    let mut threads = vec![];
-    let (tx, rx) = mpsc::channel();
-
-    let m1 = Arc::new(m1.clone());
-    let m2 = Arc::new(m2.clone());
+    if app.answer.is_some() && app.answer_key.as_ref() == Some(&inputs) {
+        app.status = Some(String::from("cached"));
+        return;
+    }
 
-    for th in 0..thread_count {
-        let tx = tx.clone();
-        let m1 = Arc::new(m1.clone());
-        let m2 = Arc::new(m2.clone());
-        threads.push(thread::spawn(move || {
-            println!("thread {} started", th);
+    let _span = log::span(
+        Level::Info,
+        "computation",
+        format_args!("operation={}", app.operation.name()),
+    );
+    if let Some(answer) = app.cache.get(&inputs).cloned() {
+        app.set_answer(app.operation.name(), answer);
+        app.answer_key = Some(inputs);
+        app.status = Some(format!(
+            "from the cache ({} hits, {} misses)",
+            app.cache.hits(),
+            app.cache.misses()
+        ));
+        app.history.push(HistoryEntry {
+            operation: app.operation,
+            inputs: app.matrix_text.clone(),
+        });
+        return;
+    }
 
-            let mut curr_result = vec![vec![]; m1.len()];
-            let start_row = (th * m1.len()) / thread_count;
-            let end_row = ((th + 1) * m1.len()) / thread_count;
-            // rows of the first matrix
-            if start_row == end_row {
+    let fractions = app.matrix_text.iter().any(|text| text.contains('/'));
+    // fractions make the computation a float one, which has no width
+    if fractions && app.arithmetic != FixedWidth::default() {
+        app.status = Some(format!(
+            "fractions are computed as floats, not in {} {} arithmetic (w and W go back to i64 check)",
+            app.arithmetic.width.name(),
+            app.arithmetic.mode.name()
+        ));
+        return;
+    }
+    if app.operation == Operation::Multiply && app.arithmetic == FixedWidth::default() && !fractions
+    {
+        if let (Ok(a), Ok(b)) = (
+            parse_matrix(&app.matrix_text[0]),
+            parse_matrix(&app.matrix_text[1]),
+        ) {
+            if dims(&b).1 == 1 {
+                app.last_computed = Some((Instant::now(), inputs.clone()));
+                match matrix_vector_product(&a, &b) {
+                    Ok(product) => {
+                        let rows = product.len();
+                        finish_computation(app, inputs, Answer::Int(product));
+                        // keep an autosave error set by set_answer
+                        app.status
+                            .get_or_insert(format!("matrix-vector product A·x, {}x1", rows));
+                    }
+                    Err(err) => app.status = Some(err.to_string()),
+                }
                 return;
             }
-            for i in start_row..end_row {
-                // columns of the second matrix
-                for j in 0..m2[0].len() {
-                    // rows of the second matrix
-                    let mut cur = 0;
-                    for k in 0..m2.len() {
-                        cur += m1[i][k] * m2[k][j]
-                    }
-                    curr_result[i].push(cur);
-                }
+            let work = a.len().saturating_mul(b.len()).saturating_mul(dims(&b).1);
+            if work >= BACKGROUND_WORK {
+                return start_job(app, a, b, inputs);
             }
-            tx.send((start_row, end_row, curr_result)).unwrap();
-        }));
+        }
     }
 
-    for i in threads {
-        i.join().unwrap();
+    let result = if fractions {
+        parse_matrix_f64(&app.matrix_text[0])
+            .and_then(|m1| Ok((m1, parse_matrix_f64(&app.matrix_text[1])?)))
+            .and_then(|(m1, m2)| app.operation.apply_f64(&m1, &m2))
+            .map(Answer::Float)
+    } else {
+        parse_matrix(&app.matrix_text[0])
+            .and_then(|m1| Ok((m1, parse_matrix(&app.matrix_text[1])?)))
+            .and_then(|(m1, m2)| {
+                if app.arithmetic == FixedWidth::default() {
+                    app.operation.apply(&m1, &m2)
+                } else {
+                    app.arithmetic.apply(app.operation, &m1, &m2)
+                }
+            })
+            .map(Answer::Int)
+    };
+    app.last_computed = Some((Instant::now(), inputs.clone()));
+    match result {
+        Ok(answer) => finish_computation(app, inputs, answer),
+        Err(err) => app.status = Some(err.to_string()),
     }
+}
 
-    let mut result = vec![vec![]; m1.len()];
-    for j in rx.iter().take(thread_count / 2) {
-        let (start, end, m) = j;
-        for i in start..end {
-            result[i].extend(&m[i]);
+/// Multiplies the selected matrix onto the running product, starting it
+/// with the selected matrix if there is none, and clears the matrix for the
+/// next one unless it's locked. On an error the running product is kept
+fn accumulate_selected(app: &mut App) {
+    let index = app.curr_matrix as usize;
+    let m = match parse_matrix(&app.matrix_text[index]) {
+        Ok(m) if m.is_empty() => {
+            app.status = Some(format!("Matrix {}: {}", index, MatrixError::Empty));
+            return;
         }
+        Ok(m) => m,
+        Err(err) => {
+            app.status = Some(format!("Matrix {}: {}", index, err));
+            return;
+        }
+    };
+    let (product, count) = match &app.accumulator {
+        None => (m, 1),
+        Some((acc, count)) => match Operation::Multiply.apply(acc, &m) {
+            Ok(product) => (product, count + 1),
+            Err(err) => {
+                app.status = Some(format!("running product unchanged: {}", err));
+                return;
+            }
+        },
+    };
+    app.set_answer("accumulate", Answer::Int(product.clone()));
+    app.answer_inputs = None;
+    let (rows, cols) = dims(&product);
+    // keep an autosave error set by set_answer
+    app.status.get_or_insert(format!(
+        "running product of {} matrices, {}x{} (X to clear)",
+        count, rows, cols
+    ));
+    app.accumulator = Some((product, count));
+    if !app.locked[index] {
+        app.matrix_text[index].clear();
+        app.panels[index].line.clear();
     }
+}
 
-    result
+/// Keeps the current result aside to diff later results against
+fn pin_answer(app: &mut App) {
+    app.status = Some(match &app.answer {
+        Some(answer) => {
+            app.pinned = Some(answer.clone());
+            String::from("pinned the result")
+        }
+        None => String::from("no result to pin"),
+    });
 }
 
-fn parse_matrices(app: &mut App) {
-    let mut a = app.matrix_text[0].split("\n").collect::<Vec<_>>();
-    let mut m1 = vec![vec![]; a.len()];
-    for i in 0..a.len() {
-        m1[i] = a[i].split("_").collect::<Vec<&str>>();
+/// Turns highlighting of cells that differ from the pinned result on or off
+fn toggle_diff(app: &mut App) {
+    if app.show_diff {
+        app.show_diff = false;
+        app.status = None;
+        return;
     }
+    let (Some(pinned), Some(answer)) = (&app.pinned, &app.answer) else {
+        app.status = Some(String::from("pin a result with p to diff against"));
+        return;
+    };
+    app.status = Some(match diff_cells(pinned, answer) {
+        Ok(changed) => {
+            app.show_diff = true;
+            let count = changed.iter().flatten().filter(|&&c| c).count();
+            format!("{} cells differ from the pinned result", count)
+        }
+        Err(_) => String::from("shapes differ"),
+    });
+}
 
-    a = app.matrix_text[1].split("\n").collect::<Vec<_>>();
-    let mut m2 = vec![vec![]; a.len()];
-    for i in 0..a.len() {
-        m2[i] = a[i].split("_").collect::<Vec<&str>>();
+/// Describes what the selected operation does with the current inputs
+fn explain_operation(app: &mut App) {
+    let shapes = parse_matrix(&app.matrix_text[0])
+        .and_then(|m1| Ok((dims(&m1), dims(&parse_matrix(&app.matrix_text[1])?))));
+
+    app.status = Some(match shapes {
+        Ok((left, right)) => app.operation.explain(left, right),
+        Err(err) => err.to_string(),
+    });
+}
+
+/// Shows the selected matrix with its column means subtracted in the Result panel
+fn center_selected(app: &mut App) {
+    let index = app.curr_matrix as usize;
+    match parse_matrix(&app.matrix_text[index]).and_then(|m| center_columns(&to_f64(&m))) {
+        Ok(centered) => app.set_answer("center", Answer::Float(centered)),
+        Err(err) => app.status = Some(format!("Matrix {}: {}", index, err)),
     }
+}
 
-    let m1: Matrix = m1
+/// Replaces every `old` in the selected matrix with `new`, reporting how
+/// many cells changed
+fn replace_in_selected(app: &mut App, old: i64, new: i64) {
+    if !app.selected_unlocked() {
+        return;
+    }
+    let index = app.curr_matrix as usize;
+    match parse_matrix(&app.matrix_text[index]) {
+        Ok(m) => {
+            let (replaced, count) = replace_value(&m, old, new);
+            app.set_matrix(index, &replaced);
+            app.status = Some(format!(
+                "Matrix {}: replaced {} cell{}",
+                index,
+                count,
+                if count == 1 { "" } else { "s" }
+            ));
+        }
+        Err(err) => app.status = Some(format!("Matrix {}: {}", index, err)),
+    }
+}
+
+/// Replaces the selected matrix, a row or column vector of nodes, with its
+/// Vandermonde matrix of the given degree
+fn vandermonde_selected(app: &mut App, degree: usize) {
+    if !app.selected_unlocked() {
+        return;
+    }
+    let index = app.curr_matrix as usize;
+    let result = parse_matrix(&app.matrix_text[index]).and_then(|m| match dims(&m) {
+        (1, _) | (_, 1) => vandermonde(&flatten_row(&m)[0], degree),
+        (rows, cols) => Err(MatrixError::InvalidArgument(format!(
+            "expected a row or column vector of nodes, not a {}x{} matrix",
+            rows, cols
+        ))),
+    });
+    match result {
+        Ok(v) => {
+            app.set_matrix(index, &v);
+            app.status = Some(format!(
+                "Matrix {}: Vandermonde matrix of degree {}",
+                index, degree
+            ));
+        }
+        Err(err) => app.status = Some(format!("Matrix {}: {}", index, err)),
+    }
+}
+
+/// Shows the covariance matrix of the columns of the selected matrix
+fn covariance_selected(app: &mut App) {
+    let index = app.curr_matrix as usize;
+    match parse_matrix(&app.matrix_text[index]).and_then(|m| covariance(&to_f64(&m))) {
+        Ok(cov) => app.set_answer("covariance", Answer::Float(cov)),
+        Err(err) => app.status = Some(format!("Matrix {}: {}", index, err)),
+    }
+}
+
+/// Reports the smallest and largest elements of the selected matrix and
+/// where they are
+fn report_min_max(app: &mut App) {
+    let index = app.curr_matrix as usize;
+    app.status = Some(match parse_matrix(&app.matrix_text[index]) {
+        Ok(m) => match min_max(&m) {
+            Some(((min, (i, j)), (max, (k, l)))) => format!(
+                "Matrix {}: min {} at ({},{}), max {} at ({},{})",
+                index, min, i, j, max, k, l
+            ),
+            None => format!("Matrix {}: no elements", index),
+        },
+        Err(err) => format!("Matrix {}: {}", index, err),
+    });
+}
+
+/// Reports the sum and mean of the elements of the selected matrix
+fn report_sum_and_mean(app: &mut App) {
+    let index = app.curr_matrix as usize;
+    app.status = Some(match parse_matrix(&app.matrix_text[index]) {
+        Ok(m) => format!(
+            "Matrix {}: sum {}, mean {}",
+            index,
+            sum_all(&m).map_or_else(
+                |err| format!("unavailable ({})", err),
+                |sum| sum.to_string()
+            ),
+            mean(&m).map_or(String::from("—"), |x| x.to_string())
+        ),
+        Err(err) => format!("Matrix {}: {}", index, err),
+    });
+}
+
+/// Reports the orientation (sign of the determinant) of the selected matrix
+fn report_determinant_sign(app: &mut App) {
+    let index = app.curr_matrix as usize;
+    let sign = parse_matrix(&app.matrix_text[index]).and_then(|m| determinant_sign(&to_f64(&m)));
+
+    app.status = Some(match sign {
+        Ok(1) => format!("Matrix {}: positive orientation", index),
+        Ok(-1) => format!("Matrix {}: negative orientation", index),
+        Ok(_) => format!("Matrix {}: zero orientation", index),
+        Err(err) => format!("Matrix {}: {}", index, err),
+    });
+}
+
+/// Reports whether the selected matrix equals its transpose within `tol`,
+/// and the largest difference between mirrored elements
+fn report_symmetry(app: &mut App, tol: f64) {
+    let index = app.curr_matrix as usize;
+    app.status = Some(match parse_matrix(&app.matrix_text[index]) {
+        Ok(m) => match max_asymmetry(&to_f64(&m)) {
+            Some((max, _)) if max <= tol => format!(
+                "Matrix {}: symmetric within {} (max asymmetry {})",
+                index, tol, max
+            ),
+            Some((max, (i, j))) => format!(
+                "Matrix {}: not symmetric within {}, max |M[i][j] - M[j][i]| is {} at row {}, column {}",
+                index, tol, max, i, j
+            ),
+            None => format!("Matrix {}: {}x{} isn't square", index, dims(&m).0, dims(&m).1),
+        },
+        Err(err) => format!("Matrix {}: {}", index, err),
+    });
+}
+
+/// Reports whether the selected matrix is positive definite, showing its
+/// Cholesky factor in the Result panel if it is
+fn report_positive_definite(app: &mut App) {
+    let index = app.curr_matrix as usize;
+    match parse_matrix_f64(&app.matrix_text[index]).and_then(|m| cholesky(&m)) {
+        Ok(Some(l)) => {
+            app.set_answer("cholesky", Answer::Float(l));
+            app.status = Some(format!(
+                "Matrix {}: positive definite, its Cholesky factor L (M = L·Lᵀ) is the result",
+                index
+            ));
+        }
+        Ok(None) => app.status = Some(format!("Matrix {}: not positive definite", index)),
+        Err(err) => app.status = Some(format!("Matrix {}: {}", index, err)),
+    }
+}
+
+/// Shows `f` applied to the two input matrices, e.g. `elementwise_max`, in
+/// the Result panel
+fn combine_matrices(
+    app: &mut App,
+    name: &str,
+    f: fn(&Matrix, &Matrix) -> Result<Matrix, MatrixError>,
+) {
+    let result = parse_matrix(&app.matrix_text[0])
+        .and_then(|a| Ok((a, parse_matrix(&app.matrix_text[1])?)))
+        .and_then(|(a, b)| f(&a, &b));
+    match result {
+        Ok(m) => app.set_answer(name, Answer::Int(m)),
+        Err(err) => app.status = Some(err.to_string()),
+    }
+}
+
+/// Opens an overlay listing the products `m0[row][k]·m1[k][col]` that add
+/// up to element `(row, col)` of the product, with their running sums
+fn show_partial_sums(app: &mut App, row: usize, col: usize) {
+    let result = parse_matrix(&app.matrix_text[0])
+        .and_then(|a| Ok((a, parse_matrix(&app.matrix_text[1])?)))
+        .and_then(|(a, b)| partial_sums(&a, &b, row, col));
+    let terms = match result {
+        Ok(terms) => terms,
+        Err(err) => return app.status = Some(err.to_string()),
+    };
+    let mut lines: Vec<String> = terms
         .iter()
-        .map(|a| a.iter().map(|b| b.parse::<i64>().unwrap()).collect())
+        .enumerate()
+        .map(|(k, term)| {
+            format!(
+                "k = {}: {} · {} = {}, sum {}",
+                k, term.left, term.right, term.product, term.sum
+            )
+        })
         .collect();
+    lines.push(String::new());
+    lines.push(String::from("any key closes"));
+    app.cell_sums = Some((
+        format!(
+            "Product element ({}, {}) = {}",
+            row,
+            col,
+            terms.last().map_or(0, |term| term.sum)
+        ),
+        lines.join("\n"),
+    ));
+}
 
-    let m2: Matrix = m2
+/// Shows the convolution of Matrix 0 with the kernel in Matrix 1 in the
+/// Result panel
+fn convolve_matrices(app: &mut App, mode: ConvolveMode) {
+    let result = parse_matrix(&app.matrix_text[0])
+        .and_then(|m| Ok((m, parse_matrix(&app.matrix_text[1])?)))
+        .and_then(|(m, kernel)| convolve(&m, &kernel, mode));
+    match result {
+        Ok(m) => app.set_answer("convolve", Answer::Int(m)),
+        Err(err) => app.status = Some(err.to_string()),
+    }
+}
+
+/// Shows `tr(Matrix 0 · Matrix 1)`, computed without the product, in the
+/// status line
+fn report_trace_of_product(app: &mut App) {
+    let result = parse_matrix(&app.matrix_text[0])
+        .and_then(|a| Ok((a, parse_matrix(&app.matrix_text[1])?)))
+        .and_then(|(a, b)| trace_of_product(&a, &b));
+    app.status = Some(match result {
+        Ok(trace) => format!("tr(Matrix 0 · Matrix 1) = {}", trace),
+        Err(err) => err.to_string(),
+    });
+}
+
+/// Reports whether Matrix 1 is a scalar multiple of Matrix 0, and by what
+fn report_scalar_multiple(app: &mut App) {
+    let result = parse_matrix(&app.matrix_text[0])
+        .and_then(|a| Ok((a, parse_matrix(&app.matrix_text[1])?)))
+        .and_then(|(a, b)| scalar_multiple(&a, &b));
+    app.status = Some(match result {
+        Ok(Some((num, 1))) => format!("Matrix 1 = {} · Matrix 0", num),
+        Ok(Some((num, den))) => format!("Matrix 1 = {}/{} · Matrix 0", num, den),
+        Ok(None) => String::from("Matrix 1 is not a scalar multiple of Matrix 0"),
+        Err(err) => err.to_string(),
+    });
+}
+
+/// A history entry as one line of the overlay, with its inputs' rows
+/// separated by `;`
+fn history_line(index: usize, entry: &HistoryEntry) -> String {
+    let inputs: Vec<String> = entry
+        .inputs
         .iter()
-        .map(|a| a.iter().map(|b| b.parse::<i64>().unwrap()).collect())
+        .map(|text| {
+            text.lines()
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join("; ")
+        })
         .collect();
+    format!(
+        "{}. {}: {}",
+        index + 1,
+        entry.operation.name(),
+        inputs.join(" | ")
+    )
+}
 
-    app.answer = Some(multiply_matrices(&m1, &m2));
+fn open_history(app: &mut App) {
+    if app.history.is_empty() {
+        app.status = Some(String::from("nothing computed yet"));
+    } else {
+        app.history_selected = Some(app.history.len() - 1);
+    }
+}
+
+fn handle_history_key(app: &mut App, key: KeyEvent) {
+    let Some(selected) = app.history_selected else {
+        return;
+    };
+    match key.code {
+        KeyCode::Up => app.history_selected = Some(selected.saturating_sub(1)),
+        KeyCode::Down => app.history_selected = Some((selected + 1).min(app.history.len() - 1)),
+        KeyCode::Enter => replay(app, selected, false),
+        KeyCode::Char('u') => replay(app, selected, true),
+        KeyCode::Esc | KeyCode::Char('h') => app.history_selected = None,
+        _ => {}
+    }
+}
+
+fn handle_register_key(app: &mut App, key: KeyEvent) {
+    let Some(action) = app.register_action.take() else {
+        return;
+    };
+    let register = match key.code {
+        KeyCode::Char(c @ 'a'..='z') => c,
+        KeyCode::Esc => {
+            app.status = None;
+            return;
+        }
+        _ => {
+            app.status = Some(String::from("registers are named a to z"));
+            return;
+        }
+    };
+    let index = app.curr_matrix as usize;
+    app.status = Some(match action {
+        RegisterAction::StoreSelected => match parse_matrix(&app.matrix_text[index]) {
+            Ok(m) => {
+                app.registers.insert(register, m);
+                format!("stored Matrix {} in register {}", index, register)
+            }
+            Err(err) => format!("Matrix {}: {}", index, err),
+        },
+        RegisterAction::StoreResult => match &app.answer {
+            Some(Answer::Int(m)) => {
+                app.registers.insert(register, m.clone());
+                format!("stored the result in register {}", register)
+            }
+            Some(Answer::Float(_)) => String::from(
+                "registers hold integer matrices, round the result first (n, then e.g. ri)",
+            ),
+            None => String::from("no result to store"),
+        },
+        RegisterAction::Recall => {
+            let Some(m) = app.registers.get(&register).cloned() else {
+                app.status = Some(format!("register {} is empty", register));
+                return;
+            };
+            if !app.selected_unlocked() {
+                return;
+            }
+            app.set_matrix(index, &m);
+            format!("recalled register {} into Matrix {}", register, index)
+        }
+    });
+}
+
+/// Computes history entry `index` again, on the current inputs or, with
+/// `restore_inputs`, on the inputs it was computed from
+fn replay(app: &mut App, index: usize, restore_inputs: bool) {
+    app.history_selected = None;
+    let entry = app.history[index].clone();
+    if restore_inputs && app.locked.contains(&true) {
+        app.status = Some(String::from(
+            "unlock the matrices (k) to restore the inputs of this entry",
+        ));
+        return;
+    }
+    app.operation = entry.operation;
+    if restore_inputs {
+        app.matrix_text = entry.inputs;
+        for index in 0..app.panels.len() {
+            app.sync_panel(index);
+        }
+    }
+    parse_matrices(app);
+}
+
+fn handle_prompt_key(app: &mut App, key: KeyEvent) {
+    let Some(prompt) = app.prompt.as_mut() else {
+        return;
+    };
+    match key.code {
+        KeyCode::Char(c) => prompt.input.push(c),
+        KeyCode::Backspace => {
+            prompt.input.pop();
+        }
+        KeyCode::Esc => app.prompt = None,
+        KeyCode::Enter => {
+            if let Some(prompt) = app.prompt.take() {
+                submit_prompt(app, prompt.action, prompt.input.trim());
+            }
+        }
+        _ => {}
+    }
+}
+
+fn submit_prompt(app: &mut App, action: PromptAction, input: &str) {
+    match action {
+        PromptAction::Interpolate => match input.parse::<f64>() {
+            Ok(t) => interpolate_matrices(app, t),
+            Err(_) => app.status = Some(format!("invalid t {:?}", input)),
+        },
+        PromptAction::Frames => match input.split_once(' ') {
+            Some((count, path)) => match count.parse::<usize>() {
+                Ok(count) => export_animation(app, count, Path::new(path.trim())),
+                Err(_) => app.status = Some(format!("invalid frame count {:?}", count)),
+            },
+            None => {
+                app.status = Some(format!(
+                    "expected a frame count and a path, got {:?}",
+                    input
+                ))
+            }
+        },
+        PromptAction::OpenFile => open_file(app, Path::new(input)),
+        PromptAction::SaveFile => save_file(app, Path::new(input)),
+        PromptAction::Replace => match input.split_whitespace().collect::<Vec<_>>()[..] {
+            [old, new] => match (old.parse::<i64>(), new.parse::<i64>()) {
+                (Ok(old), Ok(new)) => replace_in_selected(app, old, new),
+                _ => app.status = Some(format!("expected two integers, got {:?}", input)),
+            },
+            _ => app.status = Some(format!("expected an old and a new value, got {:?}", input)),
+        },
+        PromptAction::Vandermonde => match input.parse::<usize>() {
+            Ok(degree) => vandermonde_selected(app, degree),
+            Err(_) => app.status = Some(format!("invalid degree {:?}", input)),
+        },
+        PromptAction::Symmetry => match input {
+            "" => report_symmetry(app, 0.0),
+            _ => match input.parse::<f64>() {
+                Ok(tol) if tol >= 0.0 => report_symmetry(app, tol),
+                _ => app.status = Some(format!("invalid tolerance {:?}", input)),
+            },
+        },
+        PromptAction::Flatten => match input {
+            "r" | "row" => app.transform_selected(|m| flatten_row(m)),
+            "c" | "column" => app.transform_selected(|m| flatten_column(m)),
+            _ => app.status = Some(format!("expected r or c, got {:?}", input)),
+        },
+        PromptAction::SortRows => sort_selected_rows(app, input),
+        PromptAction::Round => round_answer(app, input),
+        PromptAction::PartialSums => match input.split_whitespace().collect::<Vec<_>>()[..] {
+            [row, col] => match (row.parse::<usize>(), col.parse::<usize>()) {
+                (Ok(row), Ok(col)) => show_partial_sums(app, row, col),
+                _ => app.status = Some(format!("expected a row and a column, got {:?}", input)),
+            },
+            _ => app.status = Some(format!("expected a row and a column, got {:?}", input)),
+        },
+        PromptAction::Convolve => match input {
+            "v" | "valid" => convolve_matrices(app, ConvolveMode::Valid),
+            "f" | "full" => convolve_matrices(app, ConvolveMode::Full),
+            _ => app.status = Some(format!("expected v or f, got {:?}", input)),
+        },
+        PromptAction::Hilbert => match input.parse::<usize>() {
+            Ok(n) if n > 0 => hilbert_selected(app, n),
+            _ => app.status = Some(format!("invalid size {:?}", input)),
+        },
+        PromptAction::Root => match input.parse::<u32>() {
+            Ok(n) if n > 0 => root_of_selected(app, n),
+            _ => app.status = Some(format!("invalid root {:?}", input)),
+        },
+    }
+}
+
+/// Sorts the rows of the selected matrix as described by `input`: `f` (first
+/// element) or `s` (row sum), optionally followed by `d` for descending
+fn sort_selected_rows(app: &mut App, input: &str) {
+    let mut words = input.split_whitespace();
+    let key = match words.next() {
+        Some("f" | "first") => RowKey::First,
+        Some("s" | "sum") => RowKey::Sum,
+        _ => {
+            app.status = Some(format!("expected f or s, got {:?}", input));
+            return;
+        }
+    };
+    let descending = match words.next() {
+        None | Some("a" | "asc") => false,
+        Some("d" | "desc") => true,
+        Some(other) => {
+            app.status = Some(format!("expected a or d, got {:?}", other));
+            return;
+        }
+    };
+    app.transform_selected(|m| sort_rows(m, key, descending));
+}
+
+/// Writes the result to a file in the format its extension names
+fn save_file(app: &mut App, path: &Path) {
+    if !app.files_allowed() {
+        return;
+    }
+    if path.as_os_str().is_empty() {
+        app.status = Some(String::from("enter the path of a file to save to"));
+        return;
+    }
+    let options = WriteOptions {
+        overwrite: app.overwrite_path.take().as_deref() == Some(path),
+        delimiter: app.delimiter,
+        ..WriteOptions::default()
+    };
+    app.status = Some(match export_answer(path, app.answer.as_ref(), &options) {
+        Ok(format) => format!("saved {} as {}", path.display(), format.name()),
+        Err(ExportError::Exists(path)) => {
+            let msg = format!(
+                "{} already exists, save to it again to replace it",
+                path.display()
+            );
+            app.overwrite_path = Some(path);
+            msg
+        }
+        Err(err) => err.to_string(),
+    });
+}
+
+/// Writes `count` frames interpolating from Matrix 0 to Matrix 1 to files
+/// numbered after `path`, in its format
+fn export_animation(app: &mut App, count: usize, path: &Path) {
+    if !app.files_allowed() {
+        return;
+    }
+    let frames = parse_matrix(&app.matrix_text[0])
+        .and_then(|a| Ok((a, parse_matrix(&app.matrix_text[1])?)))
+        .and_then(|(a, b)| interpolation_frames(&to_f64(&a), &to_f64(&b), count));
+    let frames: Vec<Answer> = match frames {
+        Ok(frames) => frames.into_iter().map(Answer::Float).collect(),
+        Err(err) => return app.status = Some(err.to_string()),
+    };
+    let options = WriteOptions {
+        overwrite: app.overwrite_path.take().as_deref() == Some(path),
+        delimiter: app.delimiter,
+        ..WriteOptions::default()
+    };
+    app.status = Some(match export_frames(path, &frames, &options) {
+        Ok(paths) => format!(
+            "wrote {} frames, {} to {}",
+            paths.len(),
+            paths[0].display(),
+            paths[paths.len() - 1].display()
+        ),
+        Err(ExportError::Exists(existing)) => {
+            app.overwrite_path = Some(path.to_path_buf());
+            format!(
+                "{} already exists, export again to replace the frames",
+                existing.display()
+            )
+        }
+        Err(err) => err.to_string(),
+    });
+}
+
+/// Loads a matrix file into the selected matrix
+fn open_file(app: &mut App, path: &Path) {
+    if !app.files_allowed() {
+        return;
+    }
+    if path.as_os_str().is_empty() || path == Path::new("-") {
+        app.status = Some(String::from("enter the path of a file to open"));
+        return;
+    }
+    if !app.selected_unlocked() {
+        return;
+    }
+    let options = cli::ReadOptions {
+        delimiter: app.delimiter,
+        ..cli::ReadOptions::default()
+    };
+    match cli::read_matrix_file(path, &options) {
+        Ok(m) => {
+            app.set_matrix(app.curr_matrix as usize, &m);
+            app.status = Some(format!("loaded {}", path.display()));
+        }
+        Err(err) => app.status = Some(err.to_string()),
+    }
+}
+
+/// Rounds every element of a float result as described by `input`: `r`
+/// (round), `f` (floor), `c` (ceil) or `t` (truncate), followed by `i` to
+/// turn the result into an integer matrix
+fn round_answer(app: &mut App, input: &str) {
+    let Some(Answer::Float(m)) = &app.answer else {
+        app.status = Some(String::from("round needs a float result"));
+        return;
+    };
+    let (mode, integer) = match input.strip_suffix('i') {
+        Some(mode) => (mode.trim(), true),
+        None => (input, false),
+    };
+    let mode = match mode {
+        "r" | "round" => RoundMode::Round,
+        "f" | "floor" => RoundMode::Floor,
+        "c" | "ceil" => RoundMode::Ceil,
+        "t" | "truncate" => RoundMode::Truncate,
+        _ => {
+            app.status = Some(format!("expected r, f, c or t, got {:?}", input));
+            return;
+        }
+    };
+    let rounded = round_matrix(m, mode);
+    let rounded = if integer {
+        match to_int_matrix(&rounded) {
+            Ok(m) => Answer::Int(m),
+            Err(err) => return app.status = Some(err.to_string()),
+        }
+    } else {
+        Answer::Float(rounded)
+    };
+    // the rounded result is as stale as the result it was rounded from
+    let inputs = app.answer_inputs.take();
+    app.set_answer(mode.name(), rounded);
+    app.answer_inputs = inputs;
+}
+
+/// Replaces the selected matrix with the n×n Hilbert matrix, written with
+/// fractions like `1/3` so its elements are exact until it's computed with
+fn hilbert_selected(app: &mut App, n: usize) {
+    if !app.selected_unlocked() {
+        return;
+    }
+    let index = app.curr_matrix as usize;
+    app.matrix_text[index] = (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| match i + j {
+                    0 => String::from("1"),
+                    k => format!("1/{}", k + 1),
+                })
+                .collect::<Vec<_>>()
+                .join("_")
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    app.sync_panel(index);
+    app.status = Some(format!("Matrix {}: {}x{} Hilbert matrix", index, n, n));
+}
+
+/// Transposes the selected matrix, in place when it's square
+fn transpose_selected(app: &mut App) {
+    if !app.selected_unlocked() {
+        return;
+    }
+    let index = app.curr_matrix as usize;
+    match parse_matrix(&app.matrix_text[index]) {
+        Ok(mut m) => {
+            if transpose_in_place(&mut m).is_err() {
+                m = transpose(&m);
+            }
+            app.set_matrix(index, &m);
+        }
+        Err(err) => app.status = Some(err.to_string()),
+    }
+}
+
+/// Shows the principal `n`th root of the selected matrix in the Result panel
+fn root_of_selected(app: &mut App, n: u32) {
+    let index = app.curr_matrix as usize;
+    match parse_matrix_f64(&app.matrix_text[index]).and_then(|m| matrix_root(&m, n)) {
+        Ok(m) => app.set_answer("root", Answer::Float(m)),
+        Err(err) => app.status = Some(format!("Matrix {}: {}", index, err)),
+    }
+}
+
+/// Shows `(1 - t)·A + t·B` for the two input matrices in the Result panel
+fn interpolate_matrices(app: &mut App, t: f64) {
+    let result = parse_matrix(&app.matrix_text[0])
+        .and_then(|a| Ok((a, parse_matrix(&app.matrix_text[1])?)))
+        .and_then(|(a, b)| interpolate(&to_f64(&a), &to_f64(&b), t));
+
+    match result {
+        Ok(m) => app.set_answer("interpolate", Answer::Float(m)),
+        Err(err) => app.status = Some(err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+    use tui::backend::TestBackend;
+
+    /// Runs the TUI on a test backend, pressing `keys` in order: a character
+    /// per key, with `\n` for Enter, `\t` for Tab, `\x08` for Backspace and
+    /// `\x1b` for Esc. It stops after the last key, as when the input thread
+    /// stops
+    fn press(app: App, keys: &str) -> App {
+        let (tx, rx) = mpsc::channel();
+        for c in keys.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                '\t' => KeyCode::Tab,
+                '\x08' => KeyCode::Backspace,
+                '\x1b' => KeyCode::Esc,
+                c => KeyCode::Char(c),
+            };
+            tx.send(Ev::Input(KeyEvent::new(code, KeyModifiers::NONE)))
+                .unwrap();
+        }
+        drop(tx);
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        run_app(&mut terminal, app, rx).unwrap()
+    }
+
+    /// An app with the two matrices typed in, rows separated by `\n`
+    fn app_with(m0: &str, m1: &str) -> App {
+        press(App::default(), &format!("{}\t{}\t", m0, m1))
+    }
+
+    /// What the TUI shows for `app` on a `width`×`height` screen, a line per row
+    fn screen(app: &App, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| ui(f, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| buffer.get(x, y).symbol.as_str())
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn int_answer(app: &App) -> Option<&Matrix> {
+        match &app.answer {
+            Some(Answer::Int(m)) => Some(m),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn computing_again_with_the_same_inputs_is_cached() {
+        let mut app = press(app_with("1 2\n3 4", "5 6\n7 8"), "t");
+        assert_eq!(int_answer(&app), Some(&vec![vec![19, 22], vec![43, 50]]));
+        app.last_computed = None;
+        let mut app = press(app, "t");
+        assert_eq!(app.status.as_deref(), Some("cached"));
+        // a change to the inputs computes again
+        app.last_computed = None;
+        let app = press(app, "1t");
+        assert_eq!(int_answer(&app), Some(&vec![vec![19, 22], vec![302, 346]]));
+    }
+
+    #[test]
+    fn changing_the_operation_or_arithmetic_computes_again() {
+        let app = press(app_with("100", "2"), "t");
+        assert_eq!(int_answer(&app), Some(&vec![vec![200]]));
+        let app = press(app, "ot");
+        assert_eq!(app.status, None);
+        assert_eq!(int_answer(&app), Some(&vec![vec![102]]));
+
+        // back to multiplying is a cache hit, on the exact inputs
+        let app = press(app, "ooot");
+        assert_eq!(
+            app.status.as_deref(),
+            Some("from the cache (1 hits, 2 misses)")
+        );
+
+        // saturating gives the same elements as checking, but is computed
+        let app = press(app, "Wt");
+        assert_eq!(app.status, None);
+        assert_eq!(int_answer(&app), Some(&vec![vec![200]]));
+        let app = press(app, "wt");
+        assert_eq!(int_answer(&app), Some(&vec![vec![127]]));
+    }
+
+    #[test]
+    fn fractions_arent_computed_at_a_fixed_width() {
+        let app = press(app_with("1/2 1", "2\n4"), "wt");
+        assert_eq!(
+            app.status.as_deref(),
+            Some("fractions are computed as floats, not in i8 check arithmetic (w and W go back to i64 check)")
+        );
+        assert!(app.answer.is_none());
+        // back at i64 they are
+        let app = press(app, "wwwt");
+        assert_eq!(app.answer, Some(Answer::Float(vec![vec![5.0]])));
+    }
+
+    #[test]
+    fn the_result_panel_shows_the_answer() {
+        let app = press(app_with("1 2\n3 4", "5 6\n7 8"), "t");
+        let screen = screen(&app, 120, 40).join("\n");
+        assert!(screen.contains("19 22"), "{}", screen);
+        assert!(screen.contains("43 50"), "{}", screen);
+    }
+
+    #[test]
+    fn e_explains_the_operation_for_the_current_shapes() {
+        let app = press(app_with("1 2 3\n4 5 6", "1\n2\n3"), "e");
+        let status = app.status.clone().unwrap();
+        assert!(status.contains("2×3 by a 3×1"), "{}", status);
+        assert!(status.contains("produces a 2×1"), "{}", status);
+        assert!(app.answer.is_none());
+
+        let app = press(app, "oe");
+        let status = app.status.unwrap();
+        assert!(status.contains("same shape"), "{}", status);
+    }
+
+    /// Makes the last computation of `app` look like it just finished,
+    /// however long the test took to get here
+    fn just_computed(app: &mut App) {
+        let (_, inputs) = app.last_computed.take().unwrap();
+        app.last_computed = Some((Instant::now() + Duration::from_secs(3600), inputs));
+    }
+
+    #[test]
+    fn a_second_t_straight_after_a_compute_is_dropped() {
+        let mut app = press(app_with("1 2\n3 4", "5 6\n7 8"), "t");
+        let status = app.status.clone();
+        just_computed(&mut app);
+        let mut app = press(app, "t");
+        assert_eq!(app.status, status);
+
+        // as if the debounce had passed
+        let (_, inputs) = app.last_computed.take().unwrap();
+        app.last_computed = Some((Instant::now() - COMPUTE_DEBOUNCE, inputs));
+        let app = press(app, "t");
+        assert_eq!(app.status.as_deref(), Some("cached"));
+    }
+
+    #[test]
+    fn t_straight_after_an_edit_computes_the_new_inputs() {
+        let mut app = press(app_with("1 2\n3 4", "5 6\n7 8"), "t");
+        just_computed(&mut app);
+        let app = press(app, "1t");
+        assert_eq!(int_answer(&app), Some(&vec![vec![19, 22], vec![302, 346]]));
+        assert!(!app.answer_is_stale());
+    }
+
+    #[test]
+    fn a_reports_the_sum_and_mean() {
+        let app = press(app_with("1 2\n3 4", "5"), "a");
+        assert_eq!(app.status.as_deref(), Some("Matrix 0: sum 10, mean 2.5"));
+        let app = press(app_with("9223372036854775807 1", "5"), "a");
+        let status = app.status.unwrap();
+        assert!(
+            status.starts_with("Matrix 0: sum unavailable ("),
+            "{}",
+            status
+        );
+        assert!(status.ends_with("mean 4611686018427388000"), "{}", status);
+    }
+
+    #[test]
+    fn history_replays_entries_on_their_own_inputs() {
+        let mut app = press(app_with("1 2\n3 4", "5 6\n7 8"), "t");
+        app.last_computed = None;
+        let mut app = press(app, "1t");
+        assert_eq!(app.history.len(), 2);
+        // spaces are typed as `_`
+        assert_eq!(
+            history_line(0, &app.history[0]),
+            "1. multiply: 1_2; 3_4 | 5_6; 7_8"
+        );
+        let first_inputs = app.history[0].inputs.clone();
+
+        // h opens the overlay on the latest entry, Up picks the first, u
+        // restores its inputs and computes it again
+        app.last_computed = None;
+        let app = press(app, "h");
+        assert_eq!(app.history_selected, Some(1));
+        let app = press(app, "\x1b");
+        assert_eq!(app.history_selected, None);
+        let app = press(app, "h");
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        let (tx, rx) = mpsc::channel();
+        for code in [KeyCode::Up, KeyCode::Char('u')] {
+            tx.send(Ev::Input(KeyEvent::new(code, KeyModifiers::NONE)))
+                .unwrap();
+        }
+        drop(tx);
+        let app = run_app(&mut terminal, app, rx).unwrap();
+        assert_eq!(app.matrix_text, first_inputs);
+        assert_eq!(int_answer(&app), Some(&vec![vec![19, 22], vec![43, 50]]));
+    }
+
+    #[test]
+    fn history_is_empty_until_something_is_computed() {
+        let app = press(app_with("1", "2"), "h");
+        assert_eq!(app.history_selected, None);
+        assert_eq!(app.status.as_deref(), Some("nothing computed yet"));
+    }
+
+    #[test]
+    fn locked_matrices_ignore_edits_until_unlocked() {
+        let app = app_with("1 2", "3 4");
+        let typed = app.matrix_text.clone();
+        let app = press(app, "k5r\x08");
+        assert_eq!(app.locked, [true, false]);
+        assert_eq!(app.matrix_text, typed);
+        assert_eq!(
+            app.status.as_deref(),
+            Some("Matrix 0 is locked (k to unlock)")
+        );
+
+        let app = press(app, "k5");
+        assert_eq!(app.locked, [false, false]);
+        assert_ne!(app.matrix_text, typed);
+    }
+
+    #[test]
+    fn x_multiplies_matrices_onto_a_running_product() {
+        let app = press(app_with("1 2\n3 4", "1"), "x");
+        assert_eq!(app.accumulator, Some((vec![vec![1, 2], vec![3, 4]], 1)));
+        assert!(app.matrix_text[0].is_empty());
+
+        let app = press(app, "2 0\n0 2x");
+        assert_eq!(app.accumulator, Some((vec![vec![2, 4], vec![6, 8]], 2)));
+        assert_eq!(int_answer(&app), Some(&vec![vec![2, 4], vec![6, 8]]));
+        assert_eq!(
+            app.status.as_deref(),
+            Some("running product of 2 matrices, 2x2 (X to clear)")
+        );
+
+        let app = press(app, "1 2 3x");
+        assert_eq!(app.accumulator.as_ref().unwrap().1, 2);
+        assert!(app
+            .status
+            .as_deref()
+            .unwrap()
+            .starts_with("running product unchanged"));
+
+        let app = press(app, "X");
+        assert_eq!(app.accumulator, None);
+    }
+
+    #[test]
+    fn registers_store_and_recall_matrices() {
+        let app = press(app_with("1 2\n3 4", "5 6\n7 8"), "ba\tga");
+        assert_eq!(
+            app.status.as_deref(),
+            Some("recalled register a into Matrix 1")
+        );
+        assert_eq!(
+            parse_matrix(&app.matrix_text[1]),
+            Ok(vec![vec![1, 2], vec![3, 4]])
+        );
+
+        let app = press(app, "tBz\tgz");
+        assert_eq!(app.registers[&'z'], vec![vec![7, 10], vec![15, 22]]);
+        assert_eq!(
+            parse_matrix(&app.matrix_text[0]),
+            Ok(vec![vec![7, 10], vec![15, 22]])
+        );
+
+        let app = press(app, "gq");
+        assert_eq!(app.status.as_deref(), Some("register q is empty"));
+        let app = press(app, "b1");
+        assert_eq!(app.status.as_deref(), Some("registers are named a to z"));
+        let app = press(app, "b\x1b");
+        assert!(app.status.is_none() && app.register_action.is_none());
+        assert_eq!(app.registers.len(), 2);
+    }
+
+    #[test]
+    fn sessions_restore_inputs_registers_and_other_matrices() {
+        let session = NamedMatrices::from([
+            (String::from("m1"), vec![vec![5, 6]]),
+            (String::from("c"), vec![vec![1]]),
+            (String::from("kept"), vec![vec![2, 3]]),
+        ]);
+        let mut app = App::default();
+        app.restore_session(session.clone());
+        assert_eq!(parse_matrix(&app.matrix_text[1]), Ok(vec![vec![5, 6]]));
+        assert_eq!(app.registers[&'c'], vec![vec![1]]);
+        // the empty Matrix 0 isn't saved
+        assert_eq!(app.session_matrices(), session);
+    }
+
+    #[test]
+    fn safe_mode_refuses_to_open_or_save_files() {
+        let safe = || App {
+            safe: true,
+            ..App::default()
+        };
+        for key in ["s", "L"] {
+            let app = press(press(safe(), "1\t2\tt"), key);
+            assert_eq!(
+                app.status.as_deref(),
+                Some("file access is disabled in safe mode"),
+                "{}",
+                key
+            );
+            assert!(app.prompt.is_none(), "{}", key);
+        }
+        let mut app = safe();
+        save_file(&mut app, Path::new("never-written.csv"));
+        assert!(!Path::new("never-written.csv").exists());
+    }
+
+    #[test]
+    fn z_shows_zeros_as_dots_in_every_panel() {
+        let app = press(app_with("1 0\n0 1", "2 0\n0 3"), "tz");
+        let shown = screen(&app, 120, 40).join("\n");
+        for line in ["1 .", ". 1", "2 .", ". 3"] {
+            assert!(shown.contains(line), "{:?} in\n{}", line, shown);
+        }
+        // the typed text is unchanged
+        assert_eq!(app.matrix_text[0], "1_0\n0_1");
+        let app = press(app, "z");
+        assert_eq!(app.status.as_deref(), Some("Zeros shown as 0"));
+        assert!(!screen(&app, 120, 40).join("\n").contains("2 ."));
+    }
+
+    #[test]
+    fn empty_panels_are_reported_instead_of_computed() {
+        let app = press(App::default(), "\t1 2\tt");
+        assert_eq!(
+            app.status.as_deref(),
+            Some("enter both matrices before computing (Matrix 0 is empty)")
+        );
+        assert!(app.answer.is_none());
+        let app = press(App::default(), "1 2\t\tt");
+        assert_eq!(
+            app.status.as_deref(),
+            Some("enter both matrices before computing (Matrix 1 is empty)")
+        );
+    }
+
+    #[test]
+    fn j_transposes_the_selected_matrix_of_any_shape() {
+        let app = press(app_with("1 2 3\n4 5 6", "1 2\n3 4"), "J");
+        assert_eq!(
+            parse_matrix(&app.matrix_text[0]),
+            Ok(vec![vec![1, 4], vec![2, 5], vec![3, 6]])
+        );
+        let app = press(app, "\tJ");
+        assert_eq!(
+            parse_matrix(&app.matrix_text[1]),
+            Ok(vec![vec![1, 3], vec![2, 4]])
+        );
+    }
+
+    /// A background job that already stopped with `rows` of a `total`-row
+    /// product
+    fn stopped_job(app: &App, rows: Matrix, total: usize) -> Job {
+        let done = rows.len();
+        Job {
+            stop: Arc::new(AtomicBool::new(false)),
+            rows_done: Arc::new(AtomicUsize::new(done)),
+            rows: total,
+            keep_partial: false,
+            requested_again: false,
+            inputs: app.compute_inputs(),
+            handle: thread::spawn(move || Ok(rows)),
+        }
+    }
+
+    #[test]
+    fn c_stops_a_background_product_and_keeps_its_rows() {
+        let mut app = app_with("1 2\n3 4", "5 6\n7 8");
+        app.job = Some(stopped_job(&app, vec![vec![19, 22]], 2));
+        assert!(!handle_job_key(
+            &mut app,
+            KeyEvent::new(KeyCode::Char('C'), KeyModifiers::NONE)
+        ));
+        let job = app.job.as_ref().unwrap();
+        assert!(job.keep_partial && job.stop.load(Ordering::Relaxed));
+        while !app.job.as_ref().unwrap().handle.is_finished() {
+            thread::yield_now();
+        }
+        poll_job(&mut app);
+        assert_eq!(app.status.as_deref(), Some("stopped after 1 of 2 rows"));
+        assert_eq!(int_answer(&app), Some(&vec![vec![19, 22]]));
+        assert_eq!(app.partial, Some((1, 2)));
+
+        // Esc drops the rows instead
+        let mut app = app_with("1 2\n3 4", "5 6\n7 8");
+        app.job = Some(stopped_job(&app, vec![vec![19, 22]], 2));
+        handle_job_key(&mut app, KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        while !app.job.as_ref().unwrap().handle.is_finished() {
+            thread::yield_now();
+        }
+        poll_job(&mut app);
+        assert_eq!(app.status.as_deref(), Some("cancelled"));
+        assert!(app.answer.is_none() && app.job.is_none());
+    }
+
+    #[test]
+    fn backspacing_over_a_row_break_edits_the_joined_row() {
+        let app = press(App::default(), "1 2\n3\x08\x08");
+        assert_eq!(app.matrix_text[0], "1_2");
+        assert_eq!(app.panels[0].line, "1_2");
+        let app = press(app, " 9\n4 5 6");
+        assert_eq!(
+            parse_matrix(&app.matrix_text[0]),
+            Ok(vec![vec![1, 2, 9], vec![4, 5, 6]])
+        );
+        // backspacing an empty matrix does nothing
+        let app = press(App::default(), "\x08\x081");
+        assert_eq!(
+            (app.matrix_text[0].as_str(), app.panels[0].line.as_str()),
+            ("1", "1")
+        );
+    }
+
+    #[test]
+    fn v_replaces_a_vector_of_nodes_with_its_vandermonde_matrix() {
+        let expected = vec![vec![1, 1, 1], vec![1, 2, 4], vec![1, 3, 9]];
+        for nodes in ["1 2 3", "1\n2\n3"] {
+            let app = press(app_with(nodes, "1"), "V2\n");
+            assert_eq!(parse_matrix(&app.matrix_text[0]), Ok(expected.clone()));
+            assert_eq!(
+                app.status.as_deref(),
+                Some("Matrix 0: Vandermonde matrix of degree 2")
+            );
+        }
+
+        // the selected matrix is left as it was if it can't be done
+        let app = press(app_with("1 2\n3 4", "1"), "V2\n");
+        assert_eq!(
+            app.status.as_deref(),
+            Some("Matrix 0: expected a row or column vector of nodes, not a 2x2 matrix")
+        );
+        assert_eq!(app.matrix_text[0], "1_2\n3_4");
+        let app = press(app_with("3037000500", "1"), "V2\n");
+        assert_eq!(
+            app.status,
+            Some(format!("Matrix 0: {}", MatrixError::Overflow { bits: 64 }))
+        );
+        assert_eq!(app.matrix_text[0], "3037000500");
+        let app = press(app, "Vtwo\n");
+        assert_eq!(app.status.as_deref(), Some("invalid degree \"two\""));
+    }
+
+    #[test]
+    fn i_types_a_hilbert_matrix_with_fractions() {
+        let app = press(App::default(), "I3\n");
+        assert_eq!(app.matrix_text[0], "1_1/2_1/3\n1/2_1/3_1/4\n1/3_1/4_1/5");
+        assert_eq!(
+            parse_matrix_f64(&app.matrix_text[0]),
+            Ok(matrixmult::matrix::hilbert_matrix(3))
+        );
+        assert_eq!(app.status.as_deref(), Some("Matrix 0: 3x3 Hilbert matrix"));
+        let app = press(app, "I0\n");
+        assert_eq!(app.status.as_deref(), Some("invalid size \"0\""));
+    }
+
+    #[test]
+    fn editing_a_matrix_marks_the_result_stale() {
+        let app = press(app_with("1 2\n3 4", "5 6\n7 8"), "t");
+        assert!(!app.answer_is_stale());
+        assert!(!screen(&app, 120, 40).join("\n").contains("stale"));
+        let app = press(app, "9");
+        assert!(app.answer_is_stale());
+        let shown = screen(&app, 120, 40).join("\n");
+        assert!(
+            shown.contains("Result (stale — press t to recompute)"),
+            "{}",
+            shown
+        );
+        // undoing the edit makes it current again
+        let mut app = press(app, "\x08");
+        assert!(!app.answer_is_stale());
+        app.last_computed = None;
+        let app = press(app, "9t");
+        assert!(!app.answer_is_stale());
+    }
+
+    #[test]
+    fn a_column_vector_is_multiplied_as_a_matrix_vector_product() {
+        let app = press(app_with("1 2\n3 4", "5\n6"), "t");
+        assert_eq!(int_answer(&app), Some(&vec![vec![17], vec![39]]));
+        assert_eq!(
+            app.status.as_deref(),
+            Some("matrix-vector product A·x, 2x1")
+        );
+        assert_eq!(app.history.len(), 1);
+    }
+
+    #[test]
+    fn tab_keeps_each_matrix_mid_row() {
+        let app = press(App::default(), "1 2\n3\t5 6\n7\t 4\t 8");
+        assert_eq!(app.matrix_text, vec!["1_2\n3_4", "5_6\n7_8"]);
+        assert_eq!(app.panels[0].line, "3_4");
+        assert_eq!(app.panels[1].line, "7_8");
+        let app = press(app, "\t\x08\x08\x08\x08");
+        assert_eq!(app.matrix_text[0], "1_2");
+        assert_eq!(app.panels[0].line, "1_2");
+        let app = press(app, "\n");
+        assert_eq!(app.matrix_text[0], "1_2\n");
+        assert_eq!(app.panels[0].line, "");
+    }
+
+    #[test]
+    fn the_input_thread_stops_soon_after_shutdown() {
+        let (tx, _rx) = mpsc::channel();
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let input = spawn_input_thread(tx, shutdown.clone());
+        shutdown.store(true, Ordering::Relaxed);
+        let start = Instant::now();
+        input.join().unwrap();
+        assert!(start.elapsed() < INPUT_POLL * 5, "{:?}", start.elapsed());
+    }
+
+    #[test]
+    fn the_tui_returns_once_its_events_end() {
+        let (tx, rx) = mpsc::channel();
+        tx.send(Ev::Tick).unwrap();
+        drop(tx);
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        assert!(run_app(&mut terminal, App::default(), rx).is_ok());
+    }
+
+    #[test]
+    fn a_lists_the_terms_of_a_product_element() {
+        let app = press(app_with("1 2\n3 4", "5 6\n7 8"), "A1 0\n");
+        let (title, body) = app.cell_sums.as_ref().unwrap();
+        assert_eq!(title, "Product element (1, 0) = 43");
+        assert!(
+            body.starts_with("k = 0: 3 · 5 = 15, sum 15\nk = 1: 4 · 7 = 28, sum 43\n"),
+            "{}",
+            body
+        );
+        // any key closes the overlay
+        let app = press(app, "x");
+        assert!(app.cell_sums.is_none());
+        let app = press(app, "A1\n");
+        assert_eq!(
+            app.status.as_deref(),
+            Some("expected a row and a column, got \"1\"")
+        );
+    }
+
+    // restoring for real would end every other test's `run_app` loop, so only
+    // the call that finds the terminal already given back is tested
+    #[test]
+    fn restoring_a_terminal_the_tui_doesnt_own_does_nothing() {
+        assert!(restore_terminal().is_ok());
+        assert!(!TERMINAL_RESTORED.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn a_worker_panic_leaves_the_tui_running() {
+        let worker = thread::Builder::new()
+            .name(String::from("worker"))
+            .spawn(|| panic_restores_terminal(&thread::current()))
+            .unwrap();
+        assert!(!worker.join().unwrap());
+
+        let mut app = app_with("1 2\n3 4", "5 6\n7 8");
+        let mut job = stopped_job(&app, vec![], 2);
+        job.handle = thread::spawn(|| panic!("worker panic"));
+        app.job = Some(job);
+        while !app.job.as_ref().unwrap().handle.is_finished() {
+            thread::yield_now();
+        }
+        let (tx, rx) = mpsc::channel();
+        tx.send(Ev::Tick).unwrap();
+        drop(tx);
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        let app = run_app(&mut terminal, app, rx).unwrap();
+        assert!(!TERMINAL_RESTORED.load(Ordering::Relaxed));
+        assert_eq!(
+            app.status.as_deref(),
+            Some("computation failed: the multiplying thread panicked")
+        );
+
+        // and still takes keys
+        let app = press(app, "z");
+        assert!(app.blank_zeros);
+    }
+
+    #[test]
+    fn computing_with_an_empty_panel_keeps_what_was_typed() {
+        let app = press(App::default(), "t");
+        assert_eq!(
+            app.status.as_deref(),
+            Some("enter both matrices before computing (both are empty)")
+        );
+        let app = press(App::default(), "1 2\n3 4\tt");
+        assert_eq!(app.matrix_text, vec!["1_2\n3_4", ""]);
+        assert_eq!(app.curr_matrix, 1);
+        // filling in the empty panel is enough to compute
+        let app = press(app, "1\n0t");
+        assert_eq!(int_answer(&app), Some(&vec![vec![1], vec![3]]));
+    }
+
+    fn plain(text: &Text<'_>) -> Vec<String> {
+        text.lines
+            .iter()
+            .map(|line| line.0.iter().map(|span| span.content.as_ref()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn clipped_lines_end_after_a_whole_cell() {
+        let text = Text::from("12 345 6789\n1 2\n123456789");
+        let (clipped, any) = clip_lines(text.clone(), 9);
+        assert!(any);
+        assert_eq!(plain(&clipped), vec!["12 345…", "1 2", "123456789"]);
+        // a first cell that doesn't fit leaves only the ellipsis
+        assert_eq!(
+            plain(&clip_lines(text.clone(), 5).0),
+            vec!["12…", "1 2", "…"]
+        );
+        let (unchanged, any) = clip_lines(text.clone(), 11);
+        assert!(!any);
+        assert_eq!(plain(&unchanged), plain(&text));
+
+        // styles survive the cut
+        let styled = Text::from(Spans::from(vec![
+            Span::styled("10 ", Style::default().fg(Color::Red)),
+            Span::raw("20 30"),
+        ]));
+        let (clipped, _) = clip_lines(styled, 7);
+        assert_eq!(plain(&clipped), vec!["10 20…"]);
+        assert_eq!(clipped.lines[0].0[0].style.fg, Some(Color::Red));
+    }
+
+    #[test]
+    fn wide_results_are_cut_instead_of_wrapped() {
+        let row = ["123456"; 12].join(" ");
+        let app = press(app_with("1", &row), "t");
+        let shown = screen(&app, 100, 40).join("\n");
+        assert!(shown.contains("too wide, s saves it all"), "{}", shown);
+        // the row stops at a whole cell
+        assert!(shown.contains(" 123456…"), "{}", shown);
+    }
+
+    #[test]
+    fn a_1x1_result_is_a_bold_scalar() {
+        let text = scalar_text("42\n", Rect::new(0, 0, 20, 10));
+        assert_eq!(text.lines.len(), 6);
+        assert_eq!(plain(&text)[4..], ["scalar", "42"]);
+        assert!(text.lines[5].0[0]
+            .style
+            .add_modifier
+            .contains(Modifier::BOLD));
+
+        let app = press(app_with("1 2", "3\n4"), "t");
+        let shown = screen(&app, 120, 40).join("\n");
+        assert!(shown.contains("scalar"), "{}", shown);
+        assert!(shown.contains("11"), "{}", shown);
+        // other shapes are shown as matrices
+        let app = press(app_with("1 2\n3 4", "5\n6"), "t");
+        assert!(!screen(&app, 120, 40).join("\n").contains("scalar"));
+    }
+
+    #[test]
+    fn enter_on_an_empty_row_is_ignored() {
+        let app = press(App::default(), "\n1 2\n\n\n3 4");
+        assert_eq!(app.matrix_text[0], "1_2\n3_4");
+        let app = press(app_with("1 2\n3 4", "5 6\n7 8"), "t");
+        assert!(int_answer(&app).is_some());
+
+        // a blank row can still come from a session or a file
+        let mut app = App {
+            matrix_text: vec![String::from("1_2\n\n3_4"), String::from("1\n1")],
+            ..App::default()
+        };
+        parse_matrices(&mut app);
+        assert_eq!(
+            app.status.as_deref(),
+            Some("row 1 is empty, remove the blank line or fill it in")
+        );
+    }
 }
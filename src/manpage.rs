@@ -0,0 +1,104 @@
+use crate::commands::{Command, MAIN, SUBCOMMANDS};
+
+/// Escapes text for roff, where backslashes start escapes, `-` is a hyphen
+/// rather than a minus and lines starting with `.` or `'` are requests
+fn roff(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+    if escaped.starts_with('.') || escaped.starts_with('\'') {
+        format!("\\&{}", escaped)
+    } else {
+        escaped
+    }
+}
+
+fn flags(command: &Command) -> String {
+    let mut text = String::new();
+    for flag in command.flags {
+        text.push_str(".TP\n");
+        text.push_str(&format!("\\fB\\-\\-{}\\fR", roff(flag.name)));
+        if let Some(value) = flag.value {
+            text.push_str(&format!(" \\fI{}\\fR", roff(value)));
+        }
+        text.push('\n');
+        text.push_str(&roff(flag.help));
+        if !flag.choices.is_empty() {
+            text.push_str(&format!(", one of {}", roff(&flag.choices.join(", "))));
+        }
+        text.push('\n');
+    }
+    text
+}
+
+fn synopsis(command: &Command, subcommand: bool) -> String {
+    let mut parts = vec![];
+    if subcommand {
+        parts.push(format!("\\fB{}\\fR", roff(command.name)));
+    }
+    if !command.flags.is_empty() {
+        parts.push(String::from("[\\fIOPTIONS\\fR]"));
+    }
+    if !command.args.is_empty() {
+        parts.push(format!("\\fI{}\\fR", roff(command.args)));
+    }
+    format!(".B matrixmult\n{}\n.br\n", parts.join(" "))
+}
+
+/// The man page, in roff, made from the flag definitions in `commands`
+pub fn format_manpage() -> String {
+    let mut text = String::from(".TH MATRIXMULT 1\n.SH NAME\n");
+    text.push_str(&format!("matrixmult \\- {}\n", roff(MAIN.about)));
+
+    text.push_str(".SH SYNOPSIS\n");
+    text.push_str(&synopsis(&MAIN, false));
+    for sub in &SUBCOMMANDS {
+        text.push_str(&synopsis(sub, true));
+    }
+
+    text.push_str(".SH DESCRIPTION\n");
+    text.push_str(
+        "Without input files, matrixmult starts an interactive TUI with two matrix panels and \
+         a result panel.\nWith two input files, or \\fB\\-\\-stdin\\fR, it multiplies them and \
+         prints the product.\nAn input file of \\fB\\-\\fR is read from stdin.\n",
+    );
+
+    text.push_str(".SH OPTIONS\n");
+    text.push_str(&flags(&MAIN));
+
+    text.push_str(".SH COMMANDS\n");
+    for sub in &SUBCOMMANDS {
+        text.push_str(&format!(".SS {}\n{}\n", roff(sub.name), roff(sub.about)));
+        text.push_str(&flags(sub));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roff_escapes_hyphens_backslashes_and_requests() {
+        assert_eq!(roff("--out-of-core"), "\\-\\-out\\-of\\-core");
+        assert_eq!(roff("a\\b"), "a\\eb");
+        assert_eq!(roff(".5 or 'x'"), "\\&.5 or 'x'");
+    }
+
+    #[test]
+    fn the_man_page_documents_every_flag() {
+        let page = format_manpage();
+        assert!(page.starts_with(".TH MATRIXMULT 1\n"));
+        for command in std::iter::once(&MAIN).chain(&SUBCOMMANDS) {
+            for flag in command.flags {
+                let entry = format!("\\fB\\-\\-{}\\fR", roff(flag.name));
+                assert!(page.contains(&entry), "missing {}", flag.name);
+            }
+        }
+        for sub in &SUBCOMMANDS {
+            assert!(
+                page.contains(&format!("\\fB{}\\fR", roff(sub.name))),
+                "missing {}",
+                sub.name
+            );
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{format::answer_cells, matrix::Answer};
+
+/// How results are written as Markdown tables
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MarkdownOptions {
+    pub max_rows: Option<usize>,
+    pub max_cols: Option<usize>,
+}
+
+/// Escapes the characters that would end a cell or be read as markup
+fn escape_cell(cell: &str) -> String {
+    let mut escaped = String::with_capacity(cell.len());
+    for c in cell.chars() {
+        if matches!(c, '|' | '\\' | '*' | '_' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn table_row(cells: &[String]) -> String {
+    format!("| {} |\n", cells.join(" | "))
+}
+
+/// Formats a result as a GitHub-flavored Markdown table whose header holds
+/// the (1-based) column numbers, with right-aligned columns. Rows and columns
+/// past the caps in `options` are left out and a note says so
+pub fn format_markdown(answer: &Answer, options: &MarkdownOptions) -> String {
+    let (rows, cols) = answer.dims();
+    let shown_rows = options.max_rows.map_or(rows, |max| rows.min(max));
+    let shown_cols = options.max_cols.map_or(cols, |max| cols.min(max));
+
+    let header: Vec<String> = (1..=shown_cols).map(|j| j.to_string()).collect();
+    let mut text = table_row(&header);
+    text.push_str(&table_row(&vec![String::from("---:"); shown_cols]));
+    for row in answer_cells(answer).iter().take(shown_rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .take(shown_cols)
+            .map(|x| escape_cell(x))
+            .collect();
+        text.push_str(&table_row(&cells));
+    }
+
+    if shown_rows < rows || shown_cols < cols {
+        text.push_str(&format!(
+            "\n_Showing {} of {} rows and {} of {} columns._\n",
+            shown_rows, rows, shown_cols, cols
+        ));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_tables_number_and_right_align_the_columns() {
+        let answer = Answer::Int(vec![vec![1, -2], vec![30, 4]]);
+        assert_eq!(
+            format_markdown(&answer, &MarkdownOptions::default()),
+            "| 1 | 2 |\n| ---: | ---: |\n| 1 | -2 |\n| 30 | 4 |\n"
+        );
+    }
+
+    #[test]
+    fn markdown_tables_note_what_the_caps_left_out() {
+        let answer = Answer::Int(vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]]);
+        let options = MarkdownOptions {
+            max_rows: Some(1),
+            max_cols: Some(2),
+        };
+        assert_eq!(
+            format_markdown(&answer, &options),
+            "| 1 | 2 |\n| ---: | ---: |\n| 1 | 2 |\n\n_Showing 1 of 3 rows and 2 of 3 columns._\n"
+        );
+    }
+
+    #[test]
+    fn markdown_cells_escape_markup() {
+        assert_eq!(escape_cell("a|b*c_d`e\\"), "a\\|b\\*c\\_d\\`e\\\\");
+    }
+}
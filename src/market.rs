@@ -0,0 +1,333 @@
+use crate::{
+    matrix::{Answer, Matrix},
+    parse::FormatError,
+    sparse::SparseMatrix,
+};
+
+/// A matrix read from a MatrixMarket file: coordinate files become sparse
+/// matrices and array files dense ones
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarketMatrix {
+    Dense(Matrix),
+    Sparse(SparseMatrix),
+}
+
+impl MarketMatrix {
+    pub fn into_dense(self) -> Matrix {
+        match self {
+            MarketMatrix::Dense(m) => m,
+            MarketMatrix::Sparse(m) => m.to_dense(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Symmetry {
+    General,
+    Symmetric,
+    SkewSymmetric,
+}
+
+/// Reads a MatrixMarket file in the `coordinate` or `array` format with
+/// `real`, `integer` or (coordinate only) `pattern` elements. Coordinate files
+/// may also be `symmetric` or `skew-symmetric`, listing only the lower
+/// triangle. Real values must be integral since the matrices hold integers
+pub fn read_matrix_market(text: &str) -> Result<MarketMatrix, FormatError> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()));
+
+    let (_, header) = lines
+        .next()
+        .ok_or_else(|| FormatError::new(1, "empty file"))?;
+    let fields: Vec<String> = header.split_whitespace().map(str::to_lowercase).collect();
+    if fields.len() != 5 || fields[0] != "%%matrixmarket" || fields[1] != "matrix" {
+        return Err(FormatError::new(
+            1,
+            "expected a `%%MatrixMarket matrix <format> <field> <symmetry>` header",
+        ));
+    }
+    let coordinate = match fields[2].as_str() {
+        "coordinate" => true,
+        "array" => false,
+        other => return Err(FormatError::new(1, format!("unknown format {:?}", other))),
+    };
+    let pattern = match fields[3].as_str() {
+        "real" | "integer" => false,
+        "pattern" if coordinate => true,
+        other => {
+            return Err(FormatError::new(
+                1,
+                format!("unsupported field type {:?}", other),
+            ))
+        }
+    };
+    let symmetry = match fields[4].as_str() {
+        "general" => Symmetry::General,
+        "symmetric" if coordinate => Symmetry::Symmetric,
+        "skew-symmetric" if coordinate => Symmetry::SkewSymmetric,
+        other => {
+            return Err(FormatError::new(
+                1,
+                format!("unsupported symmetry {:?}", other),
+            ))
+        }
+    };
+
+    let mut data = lines.filter(|(_, line)| !line.is_empty() && !line.starts_with('%'));
+    let (size_line, size) = data
+        .next()
+        .ok_or_else(|| FormatError::new(1, "missing size line"))?;
+    let size: Vec<usize> = size
+        .split_whitespace()
+        .map(|x| x.parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| FormatError::new(size_line, "invalid size line"))?;
+    let (rows, cols, entries) = match (coordinate, size.as_slice()) {
+        (true, &[rows, cols, entries]) => (rows, cols, entries),
+        (false, &[rows, cols]) => (rows, cols, rows * cols),
+        _ => return Err(FormatError::new(size_line, "wrong number of sizes")),
+    };
+    if symmetry != Symmetry::General && rows != cols {
+        return Err(FormatError::new(
+            size_line,
+            "a symmetric matrix must be square",
+        ));
+    }
+
+    let mut triplets = Vec::with_capacity(entries);
+    let mut count = 0;
+    for (line, entry) in data {
+        if count == entries {
+            return Err(FormatError::new(
+                line,
+                format!("more than the {} declared entries", entries),
+            ));
+        }
+        let tokens: Vec<&str> = entry.split_whitespace().collect();
+        let value = |x: &str| {
+            parse_integral(x)
+                .ok_or_else(|| FormatError::new(line, format!("{:?} is not an integer value", x)))
+        };
+        if coordinate {
+            let (i, j, x) = match (pattern, tokens.as_slice()) {
+                (false, [i, j, x]) => (*i, *j, value(x)?),
+                (true, [i, j]) => (*i, *j, 1),
+                (false, _) => return Err(FormatError::new(line, "expected `row col value`")),
+                (true, _) => return Err(FormatError::new(line, "expected `row col`")),
+            };
+            let index = |x: &str, max: usize| match x.parse::<usize>() {
+                Ok(k) if (1..=max).contains(&k) => Ok(k - 1),
+                _ => Err(FormatError::new(
+                    line,
+                    format!("index {:?} out of range 1..={}", x, max),
+                )),
+            };
+            let (i, j) = (index(i, rows)?, index(j, cols)?);
+            if symmetry != Symmetry::General && j > i {
+                return Err(FormatError::new(
+                    line,
+                    "symmetric files only list the lower triangle",
+                ));
+            }
+            triplets.push((i, j, x));
+            match symmetry {
+                Symmetry::General => {}
+                _ if i == j && symmetry == Symmetry::SkewSymmetric => {
+                    return Err(FormatError::new(
+                        line,
+                        "a skew-symmetric matrix has a zero diagonal",
+                    ))
+                }
+                _ if i == j => {}
+                Symmetry::Symmetric => triplets.push((j, i, x)),
+                Symmetry::SkewSymmetric => triplets.push((j, i, -x)),
+            }
+        } else {
+            let [x] = tokens.as_slice() else {
+                return Err(FormatError::new(line, "expected a single value"));
+            };
+            // array entries are listed in column-major order
+            triplets.push((count % rows, count / rows, value(x)?));
+        }
+        count += 1;
+    }
+
+    if count != entries {
+        return Err(FormatError::new(
+            text.lines().count(),
+            format!("expected {} entries, found {}", entries, count),
+        ));
+    }
+    let sparse = SparseMatrix::from_triplets(rows, cols, triplets);
+    Ok(if coordinate {
+        MarketMatrix::Sparse(sparse)
+    } else {
+        MarketMatrix::Dense(sparse.to_dense())
+    })
+}
+
+/// Parses a MatrixMarket file (see `read_matrix_market`) into a dense matrix
+pub fn parse_matrix_market(text: &str) -> Result<Matrix, FormatError> {
+    read_matrix_market(text).map(MarketMatrix::into_dense)
+}
+
+/// Parses an integer, or a real with no fractional part
+fn parse_integral(value: &str) -> Option<i64> {
+    value.parse::<i64>().ok().or_else(|| {
+        let x = value.parse::<f64>().ok()?;
+        (x.fract() == 0.0 && x.abs() < i64::MAX as f64).then_some(x as i64)
+    })
+}
+
+/// Formats a sparse matrix in the `coordinate integer general` format
+pub fn format_coordinate(m: &SparseMatrix) -> String {
+    let mut text = String::from("%%MatrixMarket matrix coordinate integer general\n");
+    text.push_str(&format!("{} {} {}\n", m.rows, m.cols, m.nnz()));
+    for &(i, j, x) in m.entries() {
+        text.push_str(&format!("{} {} {}\n", i + 1, j + 1, x));
+    }
+    text
+}
+
+/// Formats a result as MatrixMarket: coordinate format if at most half of
+/// its elements are nonzero and array format otherwise. Returns `None` if a
+/// float result holds a NaN or infinity
+pub fn format_matrix_market(answer: &Answer) -> Option<String> {
+    let (rows, cols) = answer.dims();
+    let (field, cells): (_, Vec<Vec<String>>) = match answer {
+        Answer::Int(m) => {
+            let sparse = SparseMatrix::from_dense(m);
+            if sparse.density() <= 0.5 {
+                return Some(format_coordinate(&sparse));
+            }
+            let cells = m
+                .iter()
+                .map(|row| row.iter().map(|x| x.to_string()).collect())
+                .collect();
+            ("integer", cells)
+        }
+        Answer::Float(m) => {
+            let cells = m
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|x| x.is_finite().then(|| format!("{:?}", x)))
+                        .collect::<Option<Vec<String>>>()
+                })
+                .collect::<Option<_>>()?;
+            ("real", cells)
+        }
+    };
+
+    let mut text = format!("%%MatrixMarket matrix array {} general\n", field);
+    text.push_str(&format!("{} {}\n", rows, cols));
+    for j in 0..cols {
+        for row in &cells {
+            text.push_str(&row[j]);
+            text.push('\n');
+        }
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coordinate_and_array_files_read_as_dense_matrices() {
+        let coordinate = "%%MatrixMarket matrix coordinate integer general\n\
+                          % a comment\n\
+                          2 3 2\n\
+                          1 1 5\n\
+                          2 3 -7\n";
+        assert_eq!(
+            parse_matrix_market(coordinate).unwrap(),
+            vec![vec![5, 0, 0], vec![0, 0, -7]]
+        );
+        // column-major
+        let array = "%%MatrixMarket matrix array real general\n2 2\n1\n2\n3.0\n4\n";
+        assert_eq!(
+            parse_matrix_market(array).unwrap(),
+            vec![vec![1, 3], vec![2, 4]]
+        );
+    }
+
+    #[test]
+    fn symmetric_files_fill_in_the_upper_triangle() {
+        let symmetric = "%%MatrixMarket matrix coordinate integer symmetric\n2 2 2\n1 1 3\n2 1 4\n";
+        assert_eq!(
+            parse_matrix_market(symmetric).unwrap(),
+            vec![vec![3, 4], vec![4, 0]]
+        );
+        let skew = "%%MatrixMarket matrix coordinate integer skew-symmetric\n2 2 1\n2 1 4\n";
+        assert_eq!(
+            parse_matrix_market(skew).unwrap(),
+            vec![vec![0, -4], vec![4, 0]]
+        );
+        let pattern = "%%MatrixMarket matrix coordinate pattern general\n1 2 1\n1 2\n";
+        assert_eq!(parse_matrix_market(pattern).unwrap(), vec![vec![0, 1]]);
+    }
+
+    #[test]
+    fn malformed_files_report_the_line() {
+        let cases = [
+            ("", 1),
+            (
+                "%%MatrixMarket matrix coordinate complex general\n1 1 1\n1 1 1\n",
+                1,
+            ),
+            (
+                "%%MatrixMarket matrix coordinate integer general\n2 2 1\n3 1 1\n",
+                3,
+            ),
+            (
+                "%%MatrixMarket matrix coordinate integer general\n2 2 1\n1 1 1.5\n",
+                3,
+            ),
+            (
+                "%%MatrixMarket matrix coordinate integer general\n2 2 1\n1 1 1\n2 2 2\n",
+                4,
+            ),
+            (
+                "%%MatrixMarket matrix array integer general\n2 2\n1\n2\n",
+                4,
+            ),
+            (
+                "%%MatrixMarket matrix coordinate integer symmetric\n2 2 1\n1 2 1\n",
+                3,
+            ),
+        ];
+        for (text, line) in cases {
+            assert_eq!(
+                parse_matrix_market(text).unwrap_err().line,
+                line,
+                "{:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn mostly_zero_results_are_written_as_coordinates() {
+        let sparse = vec![vec![0, 0, 3], vec![0, -1, 0]];
+        let text = format_matrix_market(&Answer::Int(sparse.clone())).unwrap();
+        assert!(text.starts_with("%%MatrixMarket matrix coordinate integer general\n2 3 2\n"));
+        assert_eq!(parse_matrix_market(&text).unwrap(), sparse);
+
+        let dense = vec![vec![1, 2], vec![3, 0]];
+        let text = format_matrix_market(&Answer::Int(dense.clone())).unwrap();
+        assert_eq!(
+            text,
+            "%%MatrixMarket matrix array integer general\n2 2\n1\n3\n2\n0\n"
+        );
+        assert_eq!(parse_matrix_market(&text).unwrap(), dense);
+
+        assert_eq!(
+            format_matrix_market(&Answer::Float(vec![vec![f64::INFINITY]])),
+            None
+        );
+    }
+}
@@ -0,0 +1,1318 @@
+use crate::{
+    backend::check_multiply_dims,
+    log::{self, Level},
+};
+use std::{
+    any::Any,
+    error::Error,
+    fmt, mem,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    thread,
+};
+
+pub type Matrix = Vec<Vec<i64>>;
+pub type MatrixF64 = Vec<Vec<f64>>;
+
+/// The result of a computation, which is either integral or floating point
+#[derive(Debug, Clone, PartialEq)]
+pub enum Answer {
+    Int(Matrix),
+    Float(MatrixF64),
+}
+
+impl Answer {
+    pub fn dims(&self) -> (usize, usize) {
+        match self {
+            Answer::Int(m) => dims(m),
+            Answer::Float(m) => dims(m),
+        }
+    }
+
+    pub fn to_f64(&self) -> MatrixF64 {
+        match self {
+            Answer::Int(m) => to_f64(m),
+            Answer::Float(m) => m.clone(),
+        }
+    }
+}
+
+/// Which cells differ between two results of the same shape. Integer and
+/// float results are compared by value, so `2` and `2.0` are equal
+pub fn diff_cells(a: &Answer, b: &Answer) -> Result<Vec<Vec<bool>>, MatrixError> {
+    if a.dims() != b.dims() {
+        return Err(MatrixError::DimensionMismatch {
+            left: a.dims(),
+            right: b.dims(),
+        });
+    }
+    let (a, b) = (a.to_f64(), b.to_f64());
+    Ok(a.iter()
+        .zip(&b)
+        .map(|(x, y)| x.iter().zip(y).map(|(x, y)| x != y).collect())
+        .collect())
+}
+
+/// Errors produced by the matrix operations
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatrixError {
+    /// The column at `col` lies in the span of the columns before it
+    LinearlyDependent { col: usize },
+    /// The operation needs a square matrix
+    NotSquare { rows: usize, cols: usize },
+    /// The shapes of the two operands aren't compatible for the operation
+    DimensionMismatch {
+        left: (usize, usize),
+        right: (usize, usize),
+    },
+    /// The operation needs a non-empty matrix
+    Empty,
+    /// Row `row` has a different length than the first row
+    Ragged { row: usize },
+    /// Row `row` of a matrix panel is blank, as a repeated Enter or a
+    /// pasted blank line leaves it
+    EmptyRow { row: usize },
+    /// An iterative algorithm didn't converge within its iteration budget
+    DidNotConverge { iterations: usize },
+    /// A parameter of the operation is outside its valid range
+    InvalidArgument(String),
+    /// A token in the input text isn't a number
+    Parse {
+        row: usize,
+        col: usize,
+        token: String,
+    },
+    /// A fraction in the input text has a zero denominator
+    ZeroDenominator {
+        row: usize,
+        col: usize,
+        token: String,
+    },
+    /// A worker thread panicked, with the panic message if it had one
+    ComputationFailed(String),
+    /// An element of the result doesn't fit in a `bits` wide integer
+    Overflow { bits: u32 },
+}
+
+impl MatrixError {
+    /// Name of the variant, for machine-readable error output
+    pub fn name(&self) -> &'static str {
+        match self {
+            MatrixError::LinearlyDependent { .. } => "LinearlyDependent",
+            MatrixError::NotSquare { .. } => "NotSquare",
+            MatrixError::DimensionMismatch { .. } => "DimensionMismatch",
+            MatrixError::Empty => "Empty",
+            MatrixError::Ragged { .. } => "Ragged",
+            MatrixError::EmptyRow { .. } => "EmptyRow",
+            MatrixError::DidNotConverge { .. } => "DidNotConverge",
+            MatrixError::InvalidArgument(_) => "InvalidArgument",
+            MatrixError::Parse { .. } => "Parse",
+            MatrixError::ZeroDenominator { .. } => "ZeroDenominator",
+            MatrixError::ComputationFailed(_) => "ComputationFailed",
+            MatrixError::Overflow { .. } => "Overflow",
+        }
+    }
+}
+
+impl fmt::Display for MatrixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatrixError::LinearlyDependent { col } => {
+                write!(
+                    f,
+                    "column {} is linearly dependent on the previous columns",
+                    col
+                )
+            }
+            MatrixError::NotSquare { rows, cols } => {
+                write!(f, "expected a square matrix, got {}x{}", rows, cols)
+            }
+            MatrixError::DimensionMismatch { left, right } => write!(
+                f,
+                "incompatible dimensions {}x{} and {}x{}",
+                left.0, left.1, right.0, right.1
+            ),
+            MatrixError::Empty => write!(f, "matrix is empty"),
+            MatrixError::Ragged { row } => write!(
+                f,
+                "row {} has a different number of columns than the first row",
+                row
+            ),
+            MatrixError::EmptyRow { row } => write!(
+                f,
+                "row {} is empty, remove the blank line or fill it in",
+                row
+            ),
+            MatrixError::DidNotConverge { iterations } => {
+                write!(f, "did not converge after {} iterations", iterations)
+            }
+            MatrixError::InvalidArgument(msg) => write!(f, "{}", msg),
+            MatrixError::Parse { row, col, token } => {
+                write!(
+                    f,
+                    "invalid number {:?} at row {}, column {}",
+                    token, row, col
+                )
+            }
+            MatrixError::ZeroDenominator { row, col, token } => write!(
+                f,
+                "zero denominator in {:?} at row {}, column {}",
+                token, row, col
+            ),
+            MatrixError::ComputationFailed(msg) => write!(f, "computation failed: {}", msg),
+            MatrixError::Overflow { bits } => {
+                write!(f, "an element of the result overflows i{}", bits)
+            }
+        }
+    }
+}
+
+impl Error for MatrixError {}
+
+pub fn to_f64(m: &Matrix) -> MatrixF64 {
+    m.iter()
+        .map(|row| row.iter().map(|&x| x as f64).collect())
+        .collect()
+}
+
+/// Returns `(rows, cols)`, taking the column count from the first row
+pub fn dims<T>(m: &[Vec<T>]) -> (usize, usize) {
+    (m.len(), m.first().map_or(0, |row| row.len()))
+}
+
+pub fn identity_matrix(n: usize) -> Matrix {
+    (0..n)
+        .map(|i| (0..n).map(|j| (i == j) as i64).collect())
+        .collect()
+}
+
+pub fn transpose<T: Copy>(m: &[Vec<T>]) -> Vec<Vec<T>> {
+    let (rows, cols) = dims(m);
+    (0..cols)
+        .map(|j| (0..rows).map(|i| m[i][j]).collect())
+        .collect()
+}
+
+/// Transposes a square matrix without allocating, by swapping each element
+/// above the diagonal with its mirror below it
+pub fn transpose_in_place<T>(m: &mut [Vec<T>]) -> Result<(), MatrixError> {
+    let n = m.len();
+    if let Some(row) = m.iter().find(|row| row.len() != n) {
+        return Err(MatrixError::NotSquare {
+            rows: n,
+            cols: row.len(),
+        });
+    }
+    for i in 0..n {
+        let (upper, lower) = m.split_at_mut(i + 1);
+        for (j, row) in (i + 1..n).zip(lower) {
+            mem::swap(&mut upper[i][j], &mut row[i]);
+        }
+    }
+    Ok(())
+}
+
+/// Rotates a matrix 90° clockwise: an r×c matrix becomes c×r
+pub fn rotate_cw<T: Copy>(m: &[Vec<T>]) -> Vec<Vec<T>> {
+    let mut rotated = transpose(m);
+    rotated.iter_mut().for_each(|row| row.reverse());
+    rotated
+}
+
+/// Rotates a matrix 90° counter-clockwise: an r×c matrix becomes c×r
+pub fn rotate_ccw<T: Copy>(m: &[Vec<T>]) -> Vec<Vec<T>> {
+    let mut rotated = transpose(m);
+    rotated.reverse();
+    rotated
+}
+
+/// Flattens a matrix into a 1×(r·c) row vector in row-major order
+pub fn flatten_row<T: Copy>(m: &[Vec<T>]) -> Vec<Vec<T>> {
+    vec![m.iter().flatten().copied().collect()]
+}
+
+/// Flattens a matrix into an (r·c)×1 column vector in row-major order
+pub fn flatten_column<T: Copy>(m: &[Vec<T>]) -> Vec<Vec<T>> {
+    m.iter().flatten().map(|&x| vec![x]).collect()
+}
+
+/// `m` with every element equal to `old` replaced by `new`, and how many
+/// were replaced
+pub fn replace_value(m: &Matrix, old: i64, new: i64) -> (Matrix, usize) {
+    let mut count = 0;
+    let replaced = m
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&x| {
+                    if x == old {
+                        count += 1;
+                        new
+                    } else {
+                        x
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    (replaced, count)
+}
+
+/// Largest degree `vandermonde` accepts. Powers of nodes other than -1, 0
+/// and 1 overflow i64 long before it, so only a mistyped degree reaches it
+pub const MAX_VANDERMONDE_DEGREE: usize = 1000;
+
+/// The Vandermonde matrix of `nodes`, with one row per node and element
+/// `(i, j)` equal to `nodes[i]^j` for `j` in `0..=degree`. Degrees above
+/// `MAX_VANDERMONDE_DEGREE` are rejected
+pub fn vandermonde(nodes: &[i64], degree: usize) -> Result<Matrix, MatrixError> {
+    if degree > MAX_VANDERMONDE_DEGREE {
+        return Err(MatrixError::InvalidArgument(format!(
+            "the degree can be at most {}, got {}",
+            MAX_VANDERMONDE_DEGREE, degree
+        )));
+    }
+    nodes
+        .iter()
+        .map(|&node| {
+            let mut power: i64 = 1;
+            let mut row = Vec::with_capacity(degree + 1);
+            for j in 0..=degree {
+                if j > 0 {
+                    power = power
+                        .checked_mul(node)
+                        .ok_or(MatrixError::Overflow { bits: 64 })?;
+                }
+                row.push(power);
+            }
+            Ok(row)
+        })
+        .collect()
+}
+
+/// The n×n Hilbert matrix, with element `(i, j)` equal to `1 / (i + j + 1)`.
+/// It's famously ill-conditioned, which makes it a test case for inverses
+pub fn hilbert_matrix(n: usize) -> MatrixF64 {
+    (0..n)
+        .map(|i| (0..n).map(|j| 1.0 / (i + j + 1) as f64).collect())
+        .collect()
+}
+
+/// The outer difference of a column vector `a` and a row vector `b`: the
+/// m×n matrix with element `(i, j)` equal to `a[i] - b[j]`
+pub fn outer_difference(a: &Matrix, b: &Matrix) -> Result<Matrix, MatrixError> {
+    if a.is_empty() || b.is_empty() {
+        return Err(MatrixError::Empty);
+    }
+    let (shape_a, shape_b) = (dims(a), dims(b));
+    if shape_a.1 != 1 || shape_b.0 != 1 {
+        return Err(MatrixError::InvalidArgument(format!(
+            "expected a column vector and a row vector, not {}x{} and {}x{}",
+            shape_a.0, shape_a.1, shape_b.0, shape_b.1
+        )));
+    }
+    a.iter()
+        .map(|row| {
+            b[0].iter()
+                .map(|&y| {
+                    row[0]
+                        .checked_sub(y)
+                        .ok_or(MatrixError::Overflow { bits: 64 })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Which part of a convolution `convolve` returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConvolveMode {
+    /// Only where the kernel lies entirely inside the matrix: (r - kr + 1)
+    /// × (c - kc + 1)
+    Valid,
+    /// Wherever the kernel overlaps the matrix at all: (r + kr - 1) ×
+    /// (c + kc - 1)
+    Full,
+}
+
+/// The 2D discrete convolution of `m` with `kernel`, which is flipped in
+/// both directions as it slides over `m`: element `(i, j)` of the full
+/// convolution is the sum of `m[i - p][j - q] · kernel[p][q]` over the
+/// elements of `m` that exist
+pub fn convolve(m: &Matrix, kernel: &Matrix, mode: ConvolveMode) -> Result<Matrix, MatrixError> {
+    let ((rows, cols), (krows, kcols)) = (dims(m), dims(kernel));
+    if rows == 0 || cols == 0 || krows == 0 || kcols == 0 {
+        return Err(MatrixError::Empty);
+    }
+    let (out_rows, out_cols, offset) = match mode {
+        ConvolveMode::Full => (rows + krows - 1, cols + kcols - 1, (0, 0)),
+        ConvolveMode::Valid if krows <= rows && kcols <= cols => {
+            (rows - krows + 1, cols - kcols + 1, (krows - 1, kcols - 1))
+        }
+        ConvolveMode::Valid => {
+            return Err(MatrixError::InvalidArgument(format!(
+                "a {}x{} kernel doesn't fit inside a {}x{} matrix for a valid convolution",
+                krows, kcols, rows, cols
+            )))
+        }
+    };
+    let overflow = MatrixError::Overflow { bits: 64 };
+    let mut result = vec![vec![0i64; out_cols]; out_rows];
+    for (i, out) in result.iter_mut().enumerate() {
+        let i = i + offset.0;
+        for (j, cell) in out.iter_mut().enumerate() {
+            let j = j + offset.1;
+            let mut sum = 0i64;
+            for p in i.saturating_sub(rows - 1)..=i.min(krows - 1) {
+                for q in j.saturating_sub(cols - 1)..=j.min(kcols - 1) {
+                    sum = m[i - p][j - q]
+                        .checked_mul(kernel[p][q])
+                        .and_then(|x| sum.checked_add(x))
+                        .ok_or(overflow.clone())?;
+                }
+            }
+            *cell = sum;
+        }
+    }
+    Ok(result)
+}
+
+/// What `sort_rows` orders rows by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowKey {
+    /// The element in the first column
+    First,
+    /// The sum of the row's elements
+    Sum,
+}
+
+/// Reorders the rows of `m` by `key`. The sort is stable, so rows with equal
+/// keys keep their order in either direction
+pub fn sort_rows(m: &Matrix, key: RowKey, descending: bool) -> Matrix {
+    let key_of = |row: &Vec<i64>| match key {
+        RowKey::First => row.first().map_or(i128::MIN, |&x| x as i128),
+        RowKey::Sum => row.iter().map(|&x| x as i128).sum(),
+    };
+    let mut sorted = m.clone();
+    if descending {
+        sorted.sort_by_key(|row| std::cmp::Reverse(key_of(row)));
+    } else {
+        sorted.sort_by_key(key_of);
+    }
+    sorted
+}
+
+/// How `round_matrix` picks the integer for an element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundMode {
+    /// The nearest integer, halves rounding up (2.5 to 3, -2.5 to -2)
+    Round,
+    Floor,
+    Ceil,
+    /// Toward zero
+    Truncate,
+}
+
+impl RoundMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            RoundMode::Round => "round",
+            RoundMode::Floor => "floor",
+            RoundMode::Ceil => "ceil",
+            RoundMode::Truncate => "truncate",
+        }
+    }
+
+    pub fn apply(self, x: f64) -> f64 {
+        match self {
+            RoundMode::Round => {
+                let floor = x.floor();
+                if x - floor >= 0.5 {
+                    floor + 1.0
+                } else {
+                    floor
+                }
+            }
+            RoundMode::Floor => x.floor(),
+            RoundMode::Ceil => x.ceil(),
+            RoundMode::Truncate => x.trunc(),
+        }
+    }
+}
+
+/// Every element of `m` rounded by `mode`
+pub fn round_matrix(m: &MatrixF64, mode: RoundMode) -> MatrixF64 {
+    m.iter()
+        .map(|row| row.iter().map(|&x| mode.apply(x)).collect())
+        .collect()
+}
+
+/// `m` as an integer matrix. Every element must be integral and fit in an
+/// i64, as after `round_matrix`
+pub fn to_int_matrix(m: &MatrixF64) -> Result<Matrix, MatrixError> {
+    m.iter()
+        .map(|row| {
+            row.iter()
+                .map(|&x| {
+                    let limit = -(i64::MIN as f64);
+                    if !x.is_finite() || x.fract() != 0.0 {
+                        return Err(MatrixError::InvalidArgument(format!(
+                            "{} is not an integer",
+                            x
+                        )));
+                    }
+                    if !(-limit..limit).contains(&x) {
+                        return Err(MatrixError::Overflow { bits: 64 });
+                    }
+                    Ok(x as i64)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+pub fn multiply_f64(m1: &MatrixF64, m2: &MatrixF64) -> MatrixF64 {
+    let (rows, inner) = dims(m1);
+    let cols = dims(m2).1;
+    let mut result = vec![vec![0.0; cols]; rows];
+
+    for i in 0..rows {
+        for k in 0..inner {
+            for j in 0..cols {
+                result[i][j] += m1[i][k] * m2[k][j];
+            }
+        }
+    }
+
+    result
+}
+
+/// Sum of every element of `m`, summed in i128 so only a sum that doesn't
+/// fit in i64 fails, with `MatrixError::Overflow`
+pub fn sum_all(m: &Matrix) -> Result<i64, MatrixError> {
+    let sum: i128 = m.iter().flatten().map(|&x| x as i128).sum();
+    i64::try_from(sum).map_err(|_| MatrixError::Overflow { bits: 64 })
+}
+
+/// Mean of the elements of `m`, or `None` if it has none
+pub fn mean(m: &Matrix) -> Option<f64> {
+    let count = m.iter().map(|row| row.len()).sum::<usize>();
+    (count > 0).then(|| m.iter().flatten().map(|&x| x as f64).sum::<f64>() / count as f64)
+}
+
+/// An element and its `(row, col)`
+pub type Located = (i64, (usize, usize));
+
+/// The smallest and largest elements of `m` with their positions, the first
+/// in row-major order on ties, or `None` if `m` has no elements
+pub fn min_max(m: &Matrix) -> Option<(Located, Located)> {
+    let mut elements = m
+        .iter()
+        .enumerate()
+        .flat_map(|(i, row)| row.iter().enumerate().map(move |(j, &x)| (x, (i, j))));
+    let first = elements.next()?;
+    Some(elements.fold((first, first), |(min, max), e| {
+        (
+            if e.0 < min.0 { e } else { min },
+            if e.0 > max.0 { e } else { max },
+        )
+    }))
+}
+
+/// Subtracts each column's mean from the elements of that column, so every
+/// column of the result sums to (about) zero
+pub fn center_columns(m: &MatrixF64) -> Result<MatrixF64, MatrixError> {
+    let (rows, cols) = dims(m);
+    if rows == 0 || cols == 0 {
+        return Err(MatrixError::Empty);
+    }
+    if let Some(row) = m.iter().position(|row| row.len() != cols) {
+        return Err(MatrixError::Ragged { row });
+    }
+
+    let means: Vec<f64> = (0..cols)
+        .map(|j| m.iter().map(|row| row[j]).sum::<f64>() / rows as f64)
+        .collect();
+    Ok(m.iter()
+        .map(|row| row.iter().zip(&means).map(|(x, mean)| x - mean).collect())
+        .collect())
+}
+
+/// Sample covariance of the columns of `m`, treating each row as an
+/// observation: `CᵀC / (n - 1)` where `C` is `m` with its columns centered
+pub fn covariance(m: &MatrixF64) -> Result<MatrixF64, MatrixError> {
+    if m.len() < 2 {
+        return Err(MatrixError::InvalidArgument(format!(
+            "covariance needs at least two rows, got {}",
+            m.len()
+        )));
+    }
+    let centered = center_columns(m)?;
+    let n = (m.len() - 1) as f64;
+    let mut cov = multiply_f64(&transpose(&centered), &centered);
+    cov.iter_mut().flatten().for_each(|x| *x /= n);
+    Ok(cov)
+}
+
+/// Linear interpolation `(1 - t)·a + t·b` between two equally shaped matrices
+pub fn interpolate(a: &MatrixF64, b: &MatrixF64, t: f64) -> Result<MatrixF64, MatrixError> {
+    if dims(a) != dims(b) || a.iter().zip(b).any(|(x, y)| x.len() != y.len()) {
+        return Err(MatrixError::DimensionMismatch {
+            left: dims(a),
+            right: dims(b),
+        });
+    }
+    if !(0.0..=1.0).contains(&t) {
+        return Err(MatrixError::InvalidArgument(format!(
+            "t must be in [0, 1], got {}",
+            t
+        )));
+    }
+
+    Ok(a.iter()
+        .zip(b)
+        .map(|(x, y)| {
+            x.iter()
+                .zip(y)
+                .map(|(p, q)| (1.0 - t) * p + t * q)
+                .collect()
+        })
+        .collect())
+}
+
+/// `count` matrices interpolating evenly from `a` to `b`, for `t` from 0 to 1.
+/// The first and last are `a` and `b`, so there must be at least two
+pub fn interpolation_frames(
+    a: &MatrixF64,
+    b: &MatrixF64,
+    count: usize,
+) -> Result<Vec<MatrixF64>, MatrixError> {
+    if count < 2 {
+        return Err(MatrixError::InvalidArgument(format!(
+            "an animation needs at least 2 frames, got {}",
+            count
+        )));
+    }
+    (0..count)
+        .map(|i| interpolate(a, b, i as f64 / (count - 1) as f64))
+        .collect()
+}
+
+/// The textbook triple loop. Fails with `MatrixError::Empty` if either
+/// operand has no rows or no columns and with `MatrixError::DimensionMismatch`
+/// if their shapes don't multiply, rather than indexing into them, and with
+/// `MatrixError::Overflow` if a product or partial sum overflows i64
+pub fn multiply_matrices(m1: &Matrix, m2: &Matrix) -> Result<Matrix, MatrixError> {
+    check_multiply_dims(m1, m2)?;
+    checked_multiply(m1, m2).ok_or(MatrixError::Overflow { bits: 64 })
+}
+
+/// The product of two nonempty matrices, or `None` if their shapes don't
+/// multiply or any product or partial sum overflows i64
+pub fn checked_multiply(m1: &Matrix, m2: &Matrix) -> Option<Matrix> {
+    check_multiply_dims(m1, m2).ok()?;
+    let cols = dims(m2).1;
+    let mut result = vec![vec![0i64; cols]; m1.len()];
+
+    for i in 0..m1.len() {
+        for j in 0..cols {
+            for k in 0..m2.len() {
+                result[i][j] = result[i][j].checked_add(m1[i][k].checked_mul(m2[k][j])?)?;
+            }
+        }
+    }
+
+    Some(result)
+}
+
+/// Multiplies one row of the product at a time, checking `stop` between
+/// rows and counting the finished ones in `rows_done`. Returns the rows
+/// finished by the time `stop` was set, which are rows `0..k` of the
+/// product, all of them if it wasn't
+pub fn multiply_rows_until(
+    m1: &Matrix,
+    m2: &Matrix,
+    stop: &AtomicBool,
+    rows_done: &AtomicUsize,
+) -> Result<Matrix, MatrixError> {
+    check_multiply_dims(m1, m2)?;
+    let cols = dims(m2).1;
+    let mut result = Vec::with_capacity(m1.len());
+    for row in m1 {
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        let mut out = Vec::with_capacity(cols);
+        for j in 0..cols {
+            let mut cur = 0i64;
+            for (k, x) in row.iter().enumerate() {
+                cur = x
+                    .checked_mul(m2[k][j])
+                    .and_then(|p| cur.checked_add(p))
+                    .ok_or(MatrixError::Overflow { bits: 64 })?;
+            }
+            out.push(cur);
+        }
+        result.push(out);
+        rows_done.store(result.len(), Ordering::Relaxed);
+    }
+    Ok(result)
+}
+
+/// One term of the sum that gives an element of a product
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialSum {
+    /// `m1[row][k]`
+    pub left: i64,
+    /// `m2[k][col]`
+    pub right: i64,
+    pub product: i64,
+    /// The sum of the products up to and including this one
+    pub sum: i64,
+}
+
+/// The terms `m1[row][k]·m2[k][col]` that element `(row, col)` of `m1·m2`
+/// is the sum of, each with the running sum so far. The last running sum
+/// is the element
+pub fn partial_sums(
+    m1: &Matrix,
+    m2: &Matrix,
+    row: usize,
+    col: usize,
+) -> Result<Vec<PartialSum>, MatrixError> {
+    check_multiply_dims(m1, m2)?;
+    let (rows, cols) = (m1.len(), dims(m2).1);
+    if row >= rows || col >= cols {
+        return Err(MatrixError::InvalidArgument(format!(
+            "the product is {}x{}, so it has no element ({}, {})",
+            rows, cols, row, col
+        )));
+    }
+    let overflow = MatrixError::Overflow { bits: 64 };
+    let mut sum = 0i64;
+    let mut terms = vec![];
+    for (k, &left) in m1[row].iter().enumerate() {
+        let right = m2[k][col];
+        let product = left.checked_mul(right).ok_or(overflow.clone())?;
+        sum = sum.checked_add(product).ok_or(overflow.clone())?;
+        terms.push(PartialSum {
+            left,
+            right,
+            product,
+            sum,
+        });
+    }
+    Ok(terms)
+}
+
+/// The matrix-vector product `a·x` of an m×n matrix and an n×1 column
+/// vector, an m×1 column vector. Each element is one dot product, so this
+/// skips the column loop of a general product
+pub fn matrix_vector_product(a: &Matrix, x: &Matrix) -> Result<Matrix, MatrixError> {
+    check_multiply_dims(a, x)?;
+    if dims(x).1 != 1 {
+        return Err(MatrixError::InvalidArgument(format!(
+            "expected a column vector, not a {}x{} matrix",
+            x.len(),
+            dims(x).1
+        )));
+    }
+    a.iter()
+        .map(|row| {
+            row.iter()
+                .zip(x)
+                .try_fold(0i64, |sum, (a, x)| sum.checked_add(a.checked_mul(x[0])?))
+                .map(|sum| vec![sum])
+                .ok_or(MatrixError::Overflow { bits: 64 })
+        })
+        .collect()
+}
+
+/// Message of a panic caught by `JoinHandle::join` or `catch_unwind`
+pub fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        String::from("worker thread panicked")
+    }
+}
+
+/// Multiplies on `thread_count` threads, each computing a band of rows. A
+/// panic in a worker is returned as `ComputationFailed` instead of
+/// propagating, and an element overflowing i64 as `Overflow`
+pub fn multiply_matrices_threaded(
+    m1: &Matrix,
+    m2: &Matrix,
+    thread_count: usize,
+) -> Result<Matrix, MatrixError> {
+    check_multiply_dims(m1, m2)?;
+    multiply_bands(m1, m2, thread_count)
+}
+
+/// The work of `multiply_matrices_threaded` once the shapes are known to
+/// multiply
+fn multiply_bands(m1: &Matrix, m2: &Matrix, thread_count: usize) -> Result<Matrix, MatrixError> {
+    let thread_count = thread_count.max(1);
+    let rows = m1.len();
+    let cols = dims(m2).1;
+    let mut result: Matrix = vec![vec![]; rows];
+
+    // every worker writes its own band of `result`, so nothing waits on a
+    // message from a worker whose band is empty, as with more threads than
+    // rows
+    let outcomes: Vec<Result<(), MatrixError>> = thread::scope(|scope| {
+        let mut rest = result.as_mut_slice();
+        let mut workers = vec![];
+        for th in 0..thread_count {
+            let start_row = (th * rows) / thread_count;
+            let end_row = ((th + 1) * rows) / thread_count;
+            let (band, tail) = mem::take(&mut rest).split_at_mut(end_row - start_row);
+            rest = tail;
+            workers.push(scope.spawn(move || {
+                log::log(
+                    Level::Trace,
+                    "worker",
+                    format_args!("worker {} started", th),
+                );
+
+                // rows of the first matrix
+                for (i, out) in (start_row..end_row).zip(band.iter_mut()) {
+                    // columns of the second matrix
+                    for j in 0..cols {
+                        // rows of the second matrix
+                        let mut cur = 0i64;
+                        for k in 0..m2.len() {
+                            cur = m1[i][k]
+                                .checked_mul(m2[k][j])
+                                .and_then(|x| cur.checked_add(x))
+                                .ok_or(MatrixError::Overflow { bits: 64 })?;
+                        }
+                        out.push(cur);
+                    }
+                }
+                log::log(
+                    Level::Trace,
+                    "worker",
+                    format_args!("worker {} finished rows {}..{}", th, start_row, end_row),
+                );
+                Ok(())
+            }));
+        }
+        // join every worker before reporting, so none is left running
+        workers
+            .into_iter()
+            .map(|worker| {
+                worker
+                    .join()
+                    .map_err(|payload| MatrixError::ComputationFailed(panic_message(payload)))?
+            })
+            .collect()
+    });
+    outcomes.into_iter().collect::<Result<(), _>>()?;
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vandermonde_rows_are_powers_of_the_nodes() {
+        assert_eq!(
+            vandermonde(&[2, -1, 0], 3),
+            Ok(vec![vec![1, 2, 4, 8], vec![1, -1, 1, -1], vec![1, 0, 0, 0]])
+        );
+        assert_eq!(
+            vandermonde(&[3], 40),
+            Err(MatrixError::Overflow { bits: 64 })
+        );
+    }
+
+    #[test]
+    fn vandermonde_of_one_two_three_to_degree_two() {
+        assert_eq!(
+            vandermonde(&[1, 2, 3], 2),
+            Ok(vec![vec![1, 1, 1], vec![1, 2, 4], vec![1, 3, 9]])
+        );
+        assert_eq!(vandermonde(&[5, -4], 0), Ok(vec![vec![1], vec![1]]));
+    }
+
+    #[test]
+    fn vandermonde_rejects_huge_degrees() {
+        assert!(vandermonde(&[1], MAX_VANDERMONDE_DEGREE).is_ok());
+        assert!(matches!(
+            vandermonde(&[1], usize::MAX),
+            Err(MatrixError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn sum_all_and_mean() {
+        let m = vec![vec![1, 2], vec![3, 6]];
+        assert_eq!(sum_all(&m), Ok(12));
+        assert_eq!(mean(&m), Some(3.0));
+        assert_eq!(mean(&vec![]), None);
+    }
+
+    #[test]
+    fn sum_all_reports_overflow() {
+        assert_eq!(
+            sum_all(&vec![vec![i64::MAX, 1]]),
+            Err(MatrixError::Overflow { bits: 64 })
+        );
+        // partial sums may leave the range as long as the total is in it
+        assert_eq!(sum_all(&vec![vec![i64::MAX, 1, -2]]), Ok(i64::MAX - 1));
+    }
+
+    #[test]
+    fn interpolate_blends_equally_shaped_matrices() {
+        let a = vec![vec![0.0, 2.0], vec![4.0, -2.0]];
+        let b = vec![vec![2.0, 2.0], vec![0.0, 6.0]];
+        assert_eq!(interpolate(&a, &b, 0.0).unwrap(), a);
+        assert_eq!(interpolate(&a, &b, 1.0).unwrap(), b);
+        assert_eq!(
+            interpolate(&a, &b, 0.5).unwrap(),
+            vec![vec![1.0, 2.0], vec![2.0, 2.0]]
+        );
+    }
+
+    #[test]
+    fn interpolate_rejects_bad_arguments() {
+        let a = vec![vec![1.0, 2.0]];
+        assert!(matches!(
+            interpolate(&a, &vec![vec![1.0], vec![2.0]], 0.5),
+            Err(MatrixError::DimensionMismatch { .. })
+        ));
+        assert!(matches!(
+            interpolate(&a, &a, 1.5),
+            Err(MatrixError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            interpolate(&a, &a, f64::NAN),
+            Err(MatrixError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn rotating_a_rectangle_swaps_its_dimensions() {
+        let m = vec![vec![1, 2, 3], vec![4, 5, 6]];
+        assert_eq!(rotate_cw(&m), vec![vec![4, 1], vec![5, 2], vec![6, 3]]);
+        assert_eq!(rotate_ccw(&m), vec![vec![3, 6], vec![2, 5], vec![1, 4]]);
+        assert_eq!(rotate_ccw(&rotate_cw(&m)), m);
+        assert_eq!(rotate_cw(&rotate_cw(&rotate_cw(&rotate_cw(&m)))), m);
+    }
+
+    #[test]
+    fn diff_cells_marks_changed_cells_by_value() {
+        let a = Answer::Int(vec![vec![1, 2], vec![3, 4]]);
+        let b = Answer::Float(vec![vec![1.0, 2.5], vec![3.0, -4.0]]);
+        assert_eq!(
+            diff_cells(&a, &b).unwrap(),
+            vec![vec![false, true], vec![false, true]]
+        );
+        assert_eq!(diff_cells(&a, &a).unwrap(), vec![vec![false; 2]; 2]);
+        assert!(matches!(
+            diff_cells(&a, &Answer::Int(vec![vec![1, 2]])),
+            Err(MatrixError::DimensionMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn flattening_reads_rows_in_order() {
+        let m = vec![vec![1, 2, 3], vec![4, 5, 6]];
+        assert_eq!(flatten_row(&m), vec![vec![1, 2, 3, 4, 5, 6]]);
+        assert_eq!(
+            flatten_column(&m),
+            vec![vec![1], vec![2], vec![3], vec![4], vec![5], vec![6]]
+        );
+    }
+
+    #[test]
+    fn a_panicking_worker_is_reported_not_propagated() {
+        // the short second row makes its worker index out of bounds, which
+        // the shape check would have caught before any worker started
+        let ragged = vec![vec![1, 2], vec![3]];
+        let m2 = vec![vec![1, 0], vec![0, 1]];
+        match multiply_bands(&ragged, &m2, 2) {
+            Err(MatrixError::ComputationFailed(msg)) => {
+                assert!(msg.contains("index out of bounds"), "{}", msg)
+            }
+            other => panic!("expected ComputationFailed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn panic_message_reads_str_and_string_payloads() {
+        assert_eq!(panic_message(Box::new("static")), "static");
+        assert_eq!(panic_message(Box::new(String::from("owned"))), "owned");
+        assert_eq!(panic_message(Box::new(7)), "worker thread panicked");
+    }
+
+    #[test]
+    fn sort_rows_is_stable_in_both_directions() {
+        let m = vec![
+            vec![2, 0],
+            vec![1, 9],
+            vec![2, -5],
+            vec![i64::MAX, i64::MAX],
+        ];
+        assert_eq!(
+            sort_rows(&m, RowKey::First, false),
+            vec![
+                vec![1, 9],
+                vec![2, 0],
+                vec![2, -5],
+                vec![i64::MAX, i64::MAX]
+            ]
+        );
+        assert_eq!(
+            sort_rows(&m, RowKey::First, true),
+            vec![
+                vec![i64::MAX, i64::MAX],
+                vec![2, 0],
+                vec![2, -5],
+                vec![1, 9]
+            ]
+        );
+        // row sums don't overflow
+        assert_eq!(
+            sort_rows(&m, RowKey::Sum, false),
+            vec![
+                vec![2, -5],
+                vec![2, 0],
+                vec![1, 9],
+                vec![i64::MAX, i64::MAX]
+            ]
+        );
+    }
+
+    #[test]
+    fn centering_subtracts_the_column_means() {
+        let m = vec![vec![1.0, 10.0], vec![3.0, 20.0], vec![5.0, 60.0]];
+        assert_eq!(
+            center_columns(&m).unwrap(),
+            vec![vec![-2.0, -20.0], vec![0.0, -10.0], vec![2.0, 30.0]]
+        );
+        assert_eq!(center_columns(&vec![]), Err(MatrixError::Empty));
+        assert_eq!(
+            center_columns(&vec![vec![1.0, 2.0], vec![3.0]]),
+            Err(MatrixError::Ragged { row: 1 })
+        );
+    }
+
+    #[test]
+    fn covariance_of_the_columns() {
+        let m = vec![vec![1.0, 2.0], vec![3.0, 6.0], vec![5.0, 1.0]];
+        assert_eq!(
+            covariance(&m).unwrap(),
+            vec![vec![4.0, -1.0], vec![-1.0, 7.0]]
+        );
+        assert!(matches!(
+            covariance(&vec![vec![1.0, 2.0]]),
+            Err(MatrixError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn min_max_report_the_first_position_on_ties() {
+        let m = vec![vec![3, -1, 7], vec![7, -1, 0]];
+        assert_eq!(min_max(&m), Some(((-1, (0, 1)), (7, (0, 2)))));
+        assert_eq!(min_max(&vec![vec![5]]), Some(((5, (0, 0)), (5, (0, 0)))));
+        assert_eq!(min_max(&vec![vec![]]), None);
+    }
+
+    #[test]
+    fn replace_value_counts_the_replacements() {
+        let m = vec![vec![1, 0, 1], vec![0, 1, 2]];
+        assert_eq!(
+            replace_value(&m, 1, -7),
+            (vec![vec![-7, 0, -7], vec![0, -7, 2]], 3)
+        );
+        assert_eq!(replace_value(&m, 5, 9), (m.clone(), 0));
+    }
+
+    #[test]
+    fn rounding_modes() {
+        let m = vec![vec![2.5, -2.5, 1.2], vec![-1.7, 0.0, 3.0]];
+        assert_eq!(
+            round_matrix(&m, RoundMode::Round),
+            vec![vec![3.0, -2.0, 1.0], vec![-2.0, 0.0, 3.0]]
+        );
+        assert_eq!(
+            round_matrix(&m, RoundMode::Floor),
+            vec![vec![2.0, -3.0, 1.0], vec![-2.0, 0.0, 3.0]]
+        );
+        assert_eq!(
+            round_matrix(&m, RoundMode::Ceil),
+            vec![vec![3.0, -2.0, 2.0], vec![-1.0, 0.0, 3.0]]
+        );
+        assert_eq!(
+            round_matrix(&m, RoundMode::Truncate),
+            vec![vec![2.0, -2.0, 1.0], vec![-1.0, 0.0, 3.0]]
+        );
+    }
+
+    #[test]
+    fn only_integral_floats_in_range_become_integers() {
+        let m = round_matrix(&vec![vec![2.5, -7.2]], RoundMode::Round);
+        assert_eq!(to_int_matrix(&m), Ok(vec![vec![3, -7]]));
+        assert_eq!(
+            to_int_matrix(&vec![vec![i64::MIN as f64]]),
+            Ok(vec![vec![i64::MIN]])
+        );
+        // 2^63 rounds to no i64
+        assert_eq!(
+            to_int_matrix(&vec![vec![i64::MAX as f64]]),
+            Err(MatrixError::Overflow { bits: 64 })
+        );
+        assert!(matches!(
+            to_int_matrix(&vec![vec![0.5]]),
+            Err(MatrixError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            to_int_matrix(&vec![vec![f64::NAN]]),
+            Err(MatrixError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn outer_difference_of_a_column_and_a_row() {
+        let a = vec![vec![1], vec![5]];
+        let b = vec![vec![0, 2, -3]];
+        assert_eq!(
+            outer_difference(&a, &b),
+            Ok(vec![vec![1, -1, 4], vec![5, 3, 8]])
+        );
+        assert_eq!(
+            outer_difference(&vec![vec![i64::MIN]], &vec![vec![1]]),
+            Err(MatrixError::Overflow { bits: 64 })
+        );
+        assert_eq!(
+            outer_difference(&b, &a),
+            Err(MatrixError::InvalidArgument(String::from(
+                "expected a column vector and a row vector, not 1x3 and 2x1"
+            )))
+        );
+        assert_eq!(outer_difference(&vec![], &b), Err(MatrixError::Empty));
+    }
+
+    #[test]
+    fn more_threads_than_rows_still_finish() {
+        let m1 = vec![vec![1, 2], vec![3, 4], vec![5, 6]];
+        let m2 = vec![vec![1, 0, 2], vec![0, 1, 3]];
+        let expected = multiply_matrices(&m1, &m2).unwrap();
+        for threads in [0, 1, 2, 3, 4, 16] {
+            assert_eq!(
+                multiply_matrices_threaded(&m1, &m2, threads),
+                Ok(expected.clone()),
+                "{} threads",
+                threads
+            );
+        }
+        assert_eq!(
+            multiply_matrices_threaded(&vec![vec![i64::MAX]], &vec![vec![2]], 4),
+            Err(MatrixError::Overflow { bits: 64 })
+        );
+    }
+
+    #[test]
+    fn square_matrices_transpose_in_place() {
+        let mut m = vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]];
+        transpose_in_place(&mut m).unwrap();
+        assert_eq!(m, vec![vec![1, 4, 7], vec![2, 5, 8], vec![3, 6, 9]]);
+        let mut empty: Matrix = vec![];
+        assert_eq!(transpose_in_place(&mut empty), Ok(()));
+        let mut wide = vec![vec![1, 2, 3], vec![4, 5, 6]];
+        assert_eq!(
+            transpose_in_place(&mut wide),
+            Err(MatrixError::NotSquare { rows: 2, cols: 3 })
+        );
+        assert_eq!(wide, vec![vec![1, 2, 3], vec![4, 5, 6]]);
+    }
+
+    #[test]
+    fn stopped_products_keep_the_rows_already_finished() {
+        use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+        let m1: Matrix = (0..300)
+            .map(|i| (0..60).map(|j| (i + j) % 7).collect())
+            .collect();
+        let m2: Matrix = (0..60)
+            .map(|i| (0..60).map(|j| (i * j) % 5).collect())
+            .collect();
+        let full = multiply_matrices(&m1, &m2).unwrap();
+        let (stop, rows_done) = (AtomicBool::new(false), AtomicUsize::new(0));
+        assert_eq!(
+            multiply_rows_until(&m1, &m2, &stop, &rows_done),
+            Ok(full.clone())
+        );
+        assert_eq!(rows_done.load(Ordering::Relaxed), 300);
+
+        let (stop, rows_done) = (AtomicBool::new(false), AtomicUsize::new(0));
+        let partial = thread::scope(|scope| {
+            let worker = scope.spawn(|| multiply_rows_until(&m1, &m2, &stop, &rows_done));
+            while rows_done.load(Ordering::Relaxed) == 0 && !worker.is_finished() {
+                thread::yield_now();
+            }
+            stop.store(true, Ordering::Relaxed);
+            worker.join().unwrap().unwrap()
+        });
+        assert!(!partial.is_empty());
+        assert_eq!(partial.len(), rows_done.load(Ordering::Relaxed));
+        assert_eq!(partial[..], full[..partial.len()]);
+
+        let stop = AtomicBool::new(true);
+        assert_eq!(
+            multiply_rows_until(&m1, &m2, &stop, &AtomicUsize::new(0)),
+            Ok(vec![])
+        );
+        assert!(multiply_rows_until(&m1, &m1, &stop, &AtomicUsize::new(0)).is_err());
+    }
+
+    #[test]
+    fn hilbert_elements_are_reciprocals() {
+        let h = hilbert_matrix(3);
+        assert_eq!(h[0], vec![1.0, 0.5, 1.0 / 3.0]);
+        assert_eq!(h[2], vec![1.0 / 3.0, 0.25, 0.2]);
+        assert_eq!(h, transpose(&h));
+        assert!(hilbert_matrix(0).is_empty());
+    }
+
+    #[test]
+    fn matrix_vector_products_match_the_general_product() {
+        let a = vec![vec![1, 2, 3], vec![-4, 5, 6]];
+        let x = vec![vec![7], vec![0], vec![-1]];
+        assert_eq!(matrix_vector_product(&a, &x), multiply_matrices(&a, &x));
+        assert_eq!(matrix_vector_product(&a, &x), Ok(vec![vec![4], vec![-34]]));
+        assert_eq!(
+            matrix_vector_product(&vec![vec![i64::MAX, 1]], &vec![vec![1], vec![1]]),
+            Err(MatrixError::Overflow { bits: 64 })
+        );
+        assert_eq!(
+            matrix_vector_product(&a, &vec![vec![1, 2], vec![3, 4], vec![5, 6]]),
+            Err(MatrixError::InvalidArgument(String::from(
+                "expected a column vector, not a 3x2 matrix"
+            )))
+        );
+        assert!(matches!(
+            matrix_vector_product(&a, &vec![vec![1]]),
+            Err(MatrixError::DimensionMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn full_and_valid_convolutions() {
+        let m = vec![vec![1, 2], vec![3, 4]];
+        assert_eq!(
+            convolve(&m, &vec![vec![1, 1]], ConvolveMode::Full),
+            Ok(vec![vec![1, 3, 2], vec![3, 7, 4]])
+        );
+        // the kernel is flipped, so this is m[i][j] - m[i-1][j-1]
+        let m = vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]];
+        let kernel = vec![vec![1, 0], vec![0, -1]];
+        assert_eq!(
+            convolve(&m, &kernel, ConvolveMode::Valid),
+            Ok(vec![vec![4, 4], vec![4, 4]])
+        );
+        assert_eq!(
+            convolve(&m, &vec![vec![2]], ConvolveMode::Valid),
+            Ok(vec![vec![2, 4, 6], vec![8, 10, 12], vec![14, 16, 18]])
+        );
+        assert_eq!(
+            convolve(&kernel, &m, ConvolveMode::Valid),
+            Err(MatrixError::InvalidArgument(String::from(
+                "a 3x3 kernel doesn't fit inside a 2x2 matrix for a valid convolution"
+            )))
+        );
+        assert_eq!(
+            convolve(&vec![vec![i64::MAX]], &vec![vec![2]], ConvolveMode::Full),
+            Err(MatrixError::Overflow { bits: 64 })
+        );
+        assert_eq!(
+            convolve(&vec![], &m, ConvolveMode::Full),
+            Err(MatrixError::Empty)
+        );
+    }
+
+    #[test]
+    fn partial_sums_end_at_the_product_element() {
+        let a = vec![vec![1, 2], vec![3, 4]];
+        let b = vec![vec![5, 6], vec![7, 8]];
+        let terms = partial_sums(&a, &b, 1, 0).unwrap();
+        assert_eq!(
+            terms,
+            vec![
+                PartialSum {
+                    left: 3,
+                    right: 5,
+                    product: 15,
+                    sum: 15
+                },
+                PartialSum {
+                    left: 4,
+                    right: 7,
+                    product: 28,
+                    sum: 43
+                },
+            ]
+        );
+        assert_eq!(
+            terms.last().unwrap().sum,
+            multiply_matrices(&a, &b).unwrap()[1][0]
+        );
+        assert_eq!(
+            partial_sums(&a, &b, 0, 2),
+            Err(MatrixError::InvalidArgument(String::from(
+                "the product is 2x2, so it has no element (0, 2)"
+            )))
+        );
+        assert_eq!(
+            partial_sums(&vec![vec![i64::MAX, 1]], &vec![vec![1], vec![1]], 0, 0),
+            Err(MatrixError::Overflow { bits: 64 })
+        );
+    }
+
+    #[test]
+    fn interpolation_frames_start_and_end_at_the_matrices() {
+        let a = vec![vec![0.0, 10.0]];
+        let b = vec![vec![4.0, -10.0]];
+        let frames = interpolation_frames(&a, &b, 5).unwrap();
+        assert_eq!(frames.len(), 5);
+        assert_eq!((&frames[0], &frames[4]), (&a, &b));
+        assert_eq!(frames[1], vec![vec![1.0, 5.0]]);
+        assert_eq!(
+            interpolation_frames(&a, &b, 1),
+            Err(MatrixError::InvalidArgument(String::from(
+                "an animation needs at least 2 frames, got 1"
+            )))
+        );
+        assert!(interpolation_frames(&a, &vec![vec![1.0]], 3).is_err());
+    }
+
+    #[test]
+    fn operands_whose_shapes_dont_multiply_are_rejected() {
+        // more columns than the other has rows would drop the extra column,
+        // fewer would index past the rows there are
+        let (one_by_three, one_by_two) = (vec![vec![1, 2, 3]], vec![vec![1, 2]]);
+        let two_by_one = vec![vec![1], vec![2]];
+        let three_by_one = vec![vec![1], vec![2], vec![3]];
+        let ragged = vec![vec![1, 2], vec![3]];
+        for (m1, m2) in [
+            (&one_by_three, &two_by_one),
+            (&one_by_two, &three_by_one),
+            (&ragged, &two_by_one),
+        ] {
+            let mismatch = Err(MatrixError::DimensionMismatch {
+                left: dims(m1),
+                right: dims(m2),
+            });
+            assert_eq!(multiply_matrices(m1, m2), mismatch);
+            assert_eq!(checked_multiply(m1, m2), None);
+            for threads in [1, 4] {
+                assert_eq!(multiply_matrices_threaded(m1, m2, threads), mismatch);
+            }
+        }
+    }
+}
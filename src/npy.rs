@@ -0,0 +1,324 @@
+use crate::matrix::{dims, Answer, Matrix, MatrixError};
+use std::{error::Error, fmt};
+
+/// NumPy `.npy` version 1.0 files:
+///
+/// | bytes        | field                                    |
+/// |--------------|------------------------------------------|
+/// | 0..6         | magic `\x93NUMPY`                        |
+/// | 6, 7         | major and minor version, 1 and 0         |
+/// | 8..10        | header length, u16 LE                    |
+/// | 10..         | header, a Python dict literal            |
+/// | after header | elements in C (row-major) order          |
+///
+/// The header is padded with spaces and a final newline so the elements
+/// start on a multiple of 64 bytes. Only 2-D little-endian int64 (`<i8`) and
+/// float64 (`<f8`) arrays are supported
+pub const MAGIC: [u8; 6] = *b"\x93NUMPY";
+const PREAMBLE_LEN: usize = 10;
+const ALIGNMENT: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NpyError {
+    /// The file doesn't start with the magic bytes
+    BadMagic,
+    UnsupportedVersion(u8, u8),
+    /// The header dict is malformed or missing a key
+    BadHeader(String),
+    UnsupportedDtype(String),
+    /// The array isn't 2-D
+    UnsupportedShape(String),
+    FortranOrder,
+    /// A float64 element read as an operand has a fractional part
+    NotIntegral {
+        row: usize,
+        col: usize,
+        value: f64,
+    },
+    /// The file is shorter than its header says
+    Truncated {
+        expected: usize,
+        actual: usize,
+    },
+    Matrix(MatrixError),
+}
+
+impl fmt::Display for NpyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NpyError::BadMagic => write!(f, "not a .npy file (bad magic bytes)"),
+            NpyError::UnsupportedVersion(major, minor) => {
+                write!(f, "unsupported .npy version {}.{}", major, minor)
+            }
+            NpyError::BadHeader(msg) => write!(f, "malformed .npy header: {}", msg),
+            NpyError::UnsupportedDtype(descr) => write!(
+                f,
+                "unsupported dtype {:?}, expected little-endian int64 ('<i8') or float64 ('<f8')",
+                descr
+            ),
+            NpyError::UnsupportedShape(shape) => {
+                write!(f, "unsupported shape {}, expected a 2-D array", shape)
+            }
+            NpyError::FortranOrder => write!(f, "Fortran-ordered arrays aren't supported"),
+            NpyError::NotIntegral { row, col, value } => write!(
+                f,
+                "element at row {}, column {} is {}, which isn't an integer",
+                row, col, value
+            ),
+            NpyError::Truncated { expected, actual } => write!(
+                f,
+                "file is truncated: expected {} bytes, found {}",
+                expected, actual
+            ),
+            NpyError::Matrix(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for NpyError {}
+
+impl From<MatrixError> for NpyError {
+    fn from(err: MatrixError) -> NpyError {
+        NpyError::Matrix(err)
+    }
+}
+
+/// The value of `key` in a header dict literal, up to the next comma or
+/// closing brace outside parentheses
+fn header_value<'a>(header: &'a str, key: &str) -> Result<&'a str, NpyError> {
+    let quoted = format!("'{}'", key);
+    let start = header
+        .find(&quoted)
+        .and_then(|i| header[i + quoted.len()..].trim_start().strip_prefix(':'))
+        .ok_or_else(|| NpyError::BadHeader(format!("missing {:?}", key)))?;
+
+    let mut depth = 0;
+    for (i, c) in start.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' | '}' if depth == 0 => return Ok(start[..i].trim()),
+            _ => {}
+        }
+    }
+    Err(NpyError::BadHeader(format!(
+        "unterminated value of {:?}",
+        key
+    )))
+}
+
+/// Reads a `.npy` file into an integer or float result
+pub fn parse_npy(bytes: &[u8]) -> Result<Answer, NpyError> {
+    if bytes.len() < PREAMBLE_LEN || bytes[0..6] != MAGIC {
+        return Err(NpyError::BadMagic);
+    }
+    if (bytes[6], bytes[7]) != (1, 0) {
+        return Err(NpyError::UnsupportedVersion(bytes[6], bytes[7]));
+    }
+    let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+    let data_start = PREAMBLE_LEN + header_len;
+    if bytes.len() < data_start {
+        return Err(NpyError::Truncated {
+            expected: data_start,
+            actual: bytes.len(),
+        });
+    }
+    let header = std::str::from_utf8(&bytes[PREAMBLE_LEN..data_start])
+        .map_err(|_| NpyError::BadHeader(String::from("not ASCII")))?;
+
+    let descr = header_value(header, "descr")?.trim_matches(|c| c == '\'' || c == '"');
+    let float = match descr {
+        "<i8" => false,
+        "<f8" => true,
+        _ => return Err(NpyError::UnsupportedDtype(descr.to_string())),
+    };
+    match header_value(header, "fortran_order")? {
+        "False" => {}
+        "True" => return Err(NpyError::FortranOrder),
+        other => return Err(NpyError::BadHeader(format!("fortran_order is {}", other))),
+    }
+    let shape_text = header_value(header, "shape")?;
+    let shape: Vec<usize> = shape_text
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(|x| x.parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| NpyError::BadHeader(format!("invalid shape {}", shape_text)))?;
+    let [rows, cols] = shape[..] else {
+        return Err(NpyError::UnsupportedShape(shape_text.to_string()));
+    };
+
+    let expected = rows
+        .checked_mul(cols)
+        .and_then(|n| n.checked_mul(8))
+        .and_then(|n| n.checked_add(data_start))
+        .ok_or_else(|| NpyError::BadHeader(format!("shape {} is too large", shape_text)))?;
+    if bytes.len() < expected {
+        return Err(NpyError::Truncated {
+            expected,
+            actual: bytes.len(),
+        });
+    }
+    let element = |k: usize| -> [u8; 8] {
+        let at = data_start + k * 8;
+        bytes[at..at + 8].try_into().unwrap()
+    };
+    Ok(if float {
+        Answer::Float(
+            (0..rows)
+                .map(|i| {
+                    (0..cols)
+                        .map(|j| f64::from_le_bytes(element(i * cols + j)))
+                        .collect()
+                })
+                .collect(),
+        )
+    } else {
+        Answer::Int(
+            (0..rows)
+                .map(|i| {
+                    (0..cols)
+                        .map(|j| i64::from_le_bytes(element(i * cols + j)))
+                        .collect()
+                })
+                .collect(),
+        )
+    })
+}
+
+/// Reads a `.npy` file as an integer matrix. Float64 arrays are accepted if
+/// every element is integral, like real MatrixMarket files
+pub fn parse_npy_matrix(bytes: &[u8]) -> Result<Matrix, NpyError> {
+    match parse_npy(bytes)? {
+        Answer::Int(m) => Ok(m),
+        Answer::Float(m) => m
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(j, &x)| {
+                        if x.fract() == 0.0 && x.abs() < i64::MAX as f64 {
+                            Ok(x as i64)
+                        } else {
+                            Err(NpyError::NotIntegral {
+                                row: i,
+                                col: j,
+                                value: x,
+                            })
+                        }
+                    })
+                    .collect()
+            })
+            .collect(),
+    }
+}
+
+/// Writes a result as a version 1.0 `.npy` file
+pub fn format_npy(answer: &Answer) -> Result<Vec<u8>, NpyError> {
+    let ((rows, cols), descr) = match answer {
+        Answer::Int(m) => (dims(m), "<i8"),
+        Answer::Float(m) => (dims(m), "<f8"),
+    };
+    let ragged = match answer {
+        Answer::Int(m) => m.iter().position(|row| row.len() != cols),
+        Answer::Float(m) => m.iter().position(|row| row.len() != cols),
+    };
+    if let Some(row) = ragged {
+        return Err(MatrixError::Ragged { row }.into());
+    }
+
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': ({}, {}), }}",
+        descr, rows, cols
+    );
+    let unpadded = PREAMBLE_LEN + header.len() + 1;
+    header.push_str(&" ".repeat((ALIGNMENT - unpadded % ALIGNMENT) % ALIGNMENT));
+    header.push('\n');
+
+    let mut bytes = Vec::with_capacity(PREAMBLE_LEN + header.len() + rows * cols * 8);
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&[1, 0]);
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    match answer {
+        Answer::Int(m) => m
+            .iter()
+            .flatten()
+            .for_each(|x| bytes.extend_from_slice(&x.to_le_bytes())),
+        Answer::Float(m) => m
+            .iter()
+            .flatten()
+            .for_each(|x| bytes.extend_from_slice(&x.to_le_bytes())),
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn npy_files_round_trip_with_aligned_data() {
+        for answer in [
+            Answer::Int(vec![vec![1, -2, 3], vec![i64::MIN, 0, i64::MAX]]),
+            Answer::Float(vec![vec![0.5, -1.25], vec![f64::INFINITY, 3.0]]),
+        ] {
+            let bytes = format_npy(&answer).unwrap();
+            let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+            assert_eq!((PREAMBLE_LEN + header_len) % ALIGNMENT, 0);
+            assert_eq!(bytes[PREAMBLE_LEN + header_len - 1], b'\n');
+            assert_eq!(parse_npy(&bytes).unwrap(), answer);
+        }
+    }
+
+    #[test]
+    fn npy_operands_must_be_integral() {
+        let bytes = format_npy(&Answer::Float(vec![vec![2.0, 4.0]])).unwrap();
+        assert_eq!(parse_npy_matrix(&bytes).unwrap(), vec![vec![2, 4]]);
+        let bytes = format_npy(&Answer::Float(vec![vec![2.0, 4.5]])).unwrap();
+        assert_eq!(
+            parse_npy_matrix(&bytes),
+            Err(NpyError::NotIntegral {
+                row: 0,
+                col: 1,
+                value: 4.5
+            })
+        );
+    }
+
+    #[test]
+    fn malformed_npy_files_are_rejected() {
+        let bytes = format_npy(&Answer::Int(vec![vec![1, 2], vec![3, 4]])).unwrap();
+        let patched = |from: &str, to: &str| {
+            let text = String::from_utf8_lossy(&bytes).replace(from, to);
+            let mut patched: Vec<u8> = bytes[..PREAMBLE_LEN].to_vec();
+            patched.extend_from_slice(&text.as_bytes()[PREAMBLE_LEN..]);
+            patched
+        };
+        assert_eq!(parse_npy(b"PK\x03\x04"), Err(NpyError::BadMagic));
+        assert!(matches!(
+            parse_npy(&bytes[..bytes.len() - 1]),
+            Err(NpyError::Truncated { .. })
+        ));
+        assert_eq!(
+            parse_npy(&patched("False", "True ")),
+            Err(NpyError::FortranOrder)
+        );
+        assert!(matches!(
+            parse_npy(&patched("<i8", ">i8")),
+            Err(NpyError::UnsupportedDtype(_))
+        ));
+        assert!(matches!(
+            parse_npy(&patched("(2, 2)", "(4,)  ")),
+            Err(NpyError::UnsupportedShape(_))
+        ));
+        assert!(matches!(
+            format_npy(&Answer::Int(vec![vec![1, 2], vec![3]])),
+            Err(NpyError::Matrix(MatrixError::Ragged { row: 1 }))
+        ));
+    }
+}
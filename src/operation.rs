@@ -0,0 +1,253 @@
+use crate::{
+    backend::{check_multiply_dims, MultiplyBackend, Narrow},
+    matrix::{dims, multiply_f64, Matrix, MatrixError, MatrixF64},
+};
+
+/// The binary operation computed from the two input matrices
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Multiply,
+    Add,
+    Subtract,
+    Hadamard,
+}
+
+impl Operation {
+    pub const ALL: [Operation; 4] = [
+        Operation::Multiply,
+        Operation::Add,
+        Operation::Subtract,
+        Operation::Hadamard,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Operation::Multiply => "multiply",
+            Operation::Add => "add",
+            Operation::Subtract => "subtract",
+            Operation::Hadamard => "hadamard",
+        }
+    }
+
+    /// The operation called `name`, as returned by `name()`
+    pub fn from_name(name: &str) -> Option<Operation> {
+        Operation::ALL.iter().copied().find(|op| op.name() == name)
+    }
+
+    /// The operation after this one, wrapping around
+    pub fn next(self) -> Operation {
+        let index = Operation::ALL.iter().position(|&op| op == self).unwrap();
+        Operation::ALL[(index + 1) % Operation::ALL.len()]
+    }
+
+    /// Computes the operation in i64, failing with `MatrixError::Overflow`
+    /// if an element doesn't fit
+    pub fn apply(self, a: &Matrix, b: &Matrix) -> Result<Matrix, MatrixError> {
+        let checked = |f: fn(i64, i64) -> Option<i64>| -> Result<Matrix, MatrixError> {
+            elementwise(a, b, f)?
+                .into_iter()
+                .map(|row| {
+                    row.into_iter()
+                        .map(|x| x.ok_or(MatrixError::Overflow { bits: 64 }))
+                        .collect()
+                })
+                .collect()
+        };
+        match self {
+            Operation::Multiply => Narrow.multiply(a, b),
+            Operation::Add => checked(i64::checked_add),
+            Operation::Subtract => checked(i64::checked_sub),
+            Operation::Hadamard => checked(i64::checked_mul),
+        }
+    }
+
+    /// Like `apply`, for float matrices
+    pub fn apply_f64(self, a: &MatrixF64, b: &MatrixF64) -> Result<MatrixF64, MatrixError> {
+        match self {
+            Operation::Multiply => {
+                check_multiply_dims(a, b)?;
+                Ok(multiply_f64(a, b))
+            }
+            Operation::Add => elementwise(a, b, |x, y| x + y),
+            Operation::Subtract => elementwise(a, b, |x, y| x - y),
+            Operation::Hadamard => elementwise(a, b, |x, y| x * y),
+        }
+    }
+
+    /// Plain-English description of what applying the operation to matrices
+    /// of the given shapes does
+    pub fn explain(self, left: (usize, usize), right: (usize, usize)) -> String {
+        let (r1, c1) = left;
+        let (r2, c2) = right;
+        match self {
+            Operation::Multiply if c1 == r2 => format!(
+                "Multiplying a {}×{} by a {}×{} matrix produces a {}×{} matrix; each output entry is \
+                 the dot product of a row of A with a column of B.",
+                r1, c1, r2, c2, r1, c2
+            ),
+            Operation::Multiply => format!(
+                "Multiplying a {}×{} by a {}×{} matrix is undefined: the number of columns of A ({}) \
+                 must equal the number of rows of B ({}).",
+                r1, c1, r2, c2, c1, r2
+            ),
+            _ if left != right => format!(
+                "{} needs both matrices to have the same shape, but A is {}×{} and B is {}×{}.",
+                self.verb(),
+                r1,
+                c1,
+                r2,
+                c2
+            ),
+            Operation::Add => format!(
+                "Adding two {}×{} matrices produces a {}×{} matrix; each output entry is the sum \
+                 of the entries at the same position in A and B.",
+                r1, c1, r1, c1
+            ),
+            Operation::Subtract => format!(
+                "Subtracting two {}×{} matrices produces a {}×{} matrix; each output entry is the \
+                 entry of A minus the entry at the same position in B.",
+                r1, c1, r1, c1
+            ),
+            Operation::Hadamard => format!(
+                "The Hadamard product of two {}×{} matrices is a {}×{} matrix; each output entry is \
+                 the product of the entries at the same position in A and B.",
+                r1, c1, r1, c1
+            ),
+        }
+    }
+
+    fn verb(self) -> &'static str {
+        match self {
+            Operation::Multiply => "Multiplying",
+            Operation::Add => "Adding",
+            Operation::Subtract => "Subtracting",
+            Operation::Hadamard => "The Hadamard product",
+        }
+    }
+}
+
+/// Applies `f` to each pair of entries at the same position
+pub fn elementwise<T: Copy, U>(
+    a: &[Vec<T>],
+    b: &[Vec<T>],
+    f: impl Fn(T, T) -> U,
+) -> Result<Vec<Vec<U>>, MatrixError> {
+    if dims(a) != dims(b) || a.iter().zip(b).any(|(x, y)| x.len() != y.len()) {
+        return Err(MatrixError::DimensionMismatch {
+            left: dims(a),
+            right: dims(b),
+        });
+    }
+
+    Ok(a.iter()
+        .zip(b)
+        .map(|(x, y)| x.iter().zip(y).map(|(&p, &q)| f(p, q)).collect())
+        .collect())
+}
+
+/// The larger of the two entries at each position. The matrices must have
+/// the same shape
+pub fn elementwise_max(m1: &Matrix, m2: &Matrix) -> Result<Matrix, MatrixError> {
+    elementwise(m1, m2, i64::max)
+}
+
+/// The smaller of the two entries at each position. The matrices must have
+/// the same shape
+pub fn elementwise_min(m1: &Matrix, m2: &Matrix) -> Result<Matrix, MatrixError> {
+    elementwise(m1, m2, i64::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_computes_each_operation() {
+        let a = vec![vec![1, 2], vec![3, 4]];
+        let b = vec![vec![5, 6], vec![7, 8]];
+        assert_eq!(
+            Operation::Multiply.apply(&a, &b),
+            Ok(vec![vec![19, 22], vec![43, 50]])
+        );
+        assert_eq!(
+            Operation::Add.apply(&a, &b),
+            Ok(vec![vec![6, 8], vec![10, 12]])
+        );
+        assert_eq!(
+            Operation::Subtract.apply(&a, &b),
+            Ok(vec![vec![-4, -4], vec![-4, -4]])
+        );
+        assert_eq!(
+            Operation::Hadamard.apply(&a, &b),
+            Ok(vec![vec![5, 12], vec![21, 32]])
+        );
+    }
+
+    #[test]
+    fn apply_reports_overflow_for_every_operation() {
+        let a = vec![vec![i64::MAX, i64::MIN]];
+        let b = vec![vec![2, 2]];
+        for operation in [Operation::Add, Operation::Subtract, Operation::Hadamard] {
+            assert_eq!(
+                operation.apply(&a, &b),
+                Err(MatrixError::Overflow { bits: 64 }),
+                "{}",
+                operation.name()
+            );
+        }
+        let column = vec![vec![2], vec![2]];
+        assert_eq!(
+            Operation::Multiply.apply(&a, &column),
+            Err(MatrixError::Overflow { bits: 64 })
+        );
+    }
+
+    #[test]
+    fn elementwise_needs_equal_shapes() {
+        let a = vec![vec![1, 2]];
+        let b = vec![vec![1], vec![2]];
+        assert_eq!(
+            Operation::Add.apply(&a, &b),
+            Err(MatrixError::DimensionMismatch {
+                left: (1, 2),
+                right: (2, 1)
+            })
+        );
+    }
+
+    #[test]
+    fn names_round_trip_and_next_cycles() {
+        for operation in Operation::ALL {
+            assert_eq!(Operation::from_name(operation.name()), Some(operation));
+        }
+        assert_eq!(Operation::Hadamard.next(), Operation::Multiply);
+    }
+
+    #[test]
+    fn explain_describes_undefined_products() {
+        assert!(Operation::Multiply
+            .explain((2, 3), (2, 3))
+            .contains("is undefined"));
+        assert!(Operation::Add.explain((2, 3), (2, 3)).contains("2×3"));
+    }
+
+    #[test]
+    fn elementwise_maximum_and_minimum() {
+        let a = vec![vec![1, -5], vec![i64::MIN, 4]];
+        let b = vec![vec![2, -6], vec![0, 4]];
+        assert_eq!(elementwise_max(&a, &b), Ok(vec![vec![2, -5], vec![0, 4]]));
+        assert_eq!(
+            elementwise_min(&a, &b),
+            Ok(vec![vec![1, -6], vec![i64::MIN, 4]])
+        );
+        assert_eq!(
+            elementwise_max(&a, &vec![vec![1, 2]]),
+            Err(MatrixError::DimensionMismatch {
+                left: (2, 2),
+                right: (1, 2)
+            })
+        );
+        // a ragged matrix with the same row count still doesn't match
+        assert!(elementwise_min(&a, &vec![vec![1, 2], vec![3]]).is_err());
+    }
+}
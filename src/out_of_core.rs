@@ -0,0 +1,257 @@
+use crate::{
+    binary::{open_binary, BinaryError, ElementType, Header, HEADER_LEN},
+    export::temp_path,
+    matrix::MatrixError,
+};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// Parses a memory limit such as `2G`, `512M`, `64K` or a plain byte count
+pub fn parse_memory_limit(text: &str) -> Option<usize> {
+    let text = text.trim();
+    let (digits, multiplier) = match text.chars().last()?.to_ascii_uppercase() {
+        'K' => (&text[..text.len() - 1], 1 << 10),
+        'M' => (&text[..text.len() - 1], 1 << 20),
+        'G' => (&text[..text.len() - 1], 1 << 30),
+        _ => (text, 1),
+    };
+    digits.parse::<usize>().ok()?.checked_mul(multiplier)
+}
+
+/// Side length of the square tiles used so that one tile of each operand and
+/// of the result fit in `memory_limit` bytes together
+pub fn tile_size(memory_limit: usize) -> usize {
+    let elements = memory_limit / (3 * std::mem::size_of::<i64>());
+    ((elements as f64).sqrt() as usize).max(1)
+}
+
+/// Reads the `rows`×`cols` block starting at `(row, col)` of a binary file
+fn read_tile(
+    file: &mut File,
+    header: Header,
+    (row, col): (usize, usize),
+    (rows, cols): (usize, usize),
+    tile: &mut Vec<i64>,
+) -> Result<(), BinaryError> {
+    let mut bytes = vec![0; cols * 8];
+    tile.clear();
+    for i in row..row + rows {
+        let offset = HEADER_LEN + ((i * header.cols + col) * 8) as u64;
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut bytes)?;
+        tile.extend(
+            bytes
+                .chunks_exact(8)
+                .map(|b| i64::from_le_bytes(b.try_into().unwrap())),
+        );
+    }
+    Ok(())
+}
+
+/// Multiplies two binary matrix files tile by tile, writing the product to
+/// `out` without ever holding more than about `memory_limit` bytes of
+/// matrix data in memory
+pub fn multiply_files(
+    a: &Path,
+    b: &Path,
+    out: &Path,
+    memory_limit: usize,
+) -> Result<(), BinaryError> {
+    let (mut file_a, header_a) = open_binary(a)?;
+    let (mut file_b, header_b) = open_binary(b)?;
+    if header_a.cols != header_b.rows {
+        return Err(MatrixError::DimensionMismatch {
+            left: (header_a.rows, header_a.cols),
+            right: (header_b.rows, header_b.cols),
+        }
+        .into());
+    }
+
+    let header = Header {
+        rows: header_a.rows,
+        cols: header_b.cols,
+        element: ElementType::I64,
+    };
+    // the product goes to a temporary file that's only renamed over `out`
+    // once it's complete, so a failure part way leaves `out` as it was and
+    // an output that's also an operand is still read whole
+    let tmp = temp_path(out);
+    let written = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&tmp)
+        .map_err(BinaryError::from)
+        .and_then(|file| {
+            write_product(
+                file,
+                (&mut file_a, header_a),
+                (&mut file_b, header_b),
+                header,
+                memory_limit,
+            )
+        })
+        .and_then(|()| Ok(fs::rename(&tmp, out)?));
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    written
+}
+
+/// Writes the product of the operands, each with its header, tile by tile
+fn write_product(
+    mut file_out: File,
+    (file_a, header_a): (&mut File, Header),
+    (file_b, header_b): (&mut File, Header),
+    header: Header,
+    memory_limit: usize,
+) -> Result<(), BinaryError> {
+    file_out.write_all(&header.to_bytes())?;
+    file_out.set_len(header.file_len())?;
+
+    let tile = tile_size(memory_limit);
+    let inner = header_a.cols;
+    let (mut tile_a, mut tile_b) = (vec![], vec![]);
+    let mut result = vec![];
+
+    for row in (0..header.rows).step_by(tile) {
+        let rows = tile.min(header.rows - row);
+        for col in (0..header.cols).step_by(tile) {
+            let cols = tile.min(header.cols - col);
+            result.clear();
+            result.resize(rows * cols, 0i64);
+
+            for k in (0..inner).step_by(tile) {
+                let depth = tile.min(inner - k);
+                read_tile(file_a, header_a, (row, k), (rows, depth), &mut tile_a)?;
+                read_tile(file_b, header_b, (k, col), (depth, cols), &mut tile_b)?;
+                for i in 0..rows {
+                    for p in 0..depth {
+                        let x = tile_a[i * depth + p];
+                        for j in 0..cols {
+                            let sum = x
+                                .checked_mul(tile_b[p * cols + j])
+                                .and_then(|y| result[i * cols + j].checked_add(y))
+                                .ok_or(MatrixError::Overflow { bits: 64 })?;
+                            result[i * cols + j] = sum;
+                        }
+                    }
+                }
+            }
+
+            for i in 0..rows {
+                let offset = HEADER_LEN + (((row + i) * header.cols + col) * 8) as u64;
+                file_out.seek(SeekFrom::Start(offset))?;
+                let bytes: Vec<u8> = result[i * cols..(i + 1) * cols]
+                    .iter()
+                    .flat_map(|x| x.to_le_bytes())
+                    .collect();
+                file_out.write_all(&bytes)?;
+            }
+        }
+    }
+
+    file_out.sync_all()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        binary::{read_binary, write_binary},
+        matrix::{multiply_matrices, Matrix},
+    };
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("matrixmult-{}-{}", std::process::id(), name))
+    }
+
+    fn counting(rows: usize, cols: usize, start: i64) -> Matrix {
+        (0..rows)
+            .map(|i| (0..cols).map(|j| start + (i * cols + j) as i64).collect())
+            .collect()
+    }
+
+    #[test]
+    fn parse_memory_limit_reads_suffixes() {
+        assert_eq!(parse_memory_limit("4096"), Some(4096));
+        assert_eq!(parse_memory_limit("64K"), Some(64 << 10));
+        assert_eq!(parse_memory_limit("512m"), Some(512 << 20));
+        assert_eq!(parse_memory_limit(" 2G "), Some(2 << 30));
+        assert_eq!(parse_memory_limit("G"), None);
+        assert_eq!(parse_memory_limit("lots"), None);
+        assert_eq!(parse_memory_limit(&format!("{}G", usize::MAX)), None);
+    }
+
+    #[test]
+    fn tiles_fit_in_the_memory_limit() {
+        assert_eq!(tile_size(0), 1);
+        assert_eq!(tile_size(3 * 8 * 4), 2);
+        assert_eq!(tile_size(3 * 8 * 100), 10);
+    }
+
+    #[test]
+    fn multiplying_files_in_tiles_matches_the_in_memory_product() {
+        let (a, b) = (counting(7, 5, -10), counting(5, 6, 3));
+        let (path_a, path_b, out) = (
+            temp_path("ooc-a.mmb"),
+            temp_path("ooc-b.mmb"),
+            temp_path("ooc-c.mmb"),
+        );
+        write_binary(&path_a, &a).unwrap();
+        write_binary(&path_b, &b).unwrap();
+
+        // 2×2 tiles that don't divide the dimensions evenly
+        multiply_files(&path_a, &path_b, &out, 3 * 8 * 4).unwrap();
+        assert_eq!(
+            read_binary(&out).unwrap(),
+            multiply_matrices(&a, &b).unwrap()
+        );
+
+        assert!(multiply_files(&path_a, &path_a, &out, 1 << 20).is_err());
+        for path in [path_a, path_b, out] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn a_failed_product_leaves_the_output_as_it_was() {
+        let big = vec![vec![i64::MAX, i64::MAX]];
+        let (path_a, path_b, out) = (
+            temp_path("ooc-fail-a.mmb"),
+            temp_path("ooc-fail-b.mmb"),
+            temp_path("ooc-fail-c.mmb"),
+        );
+        write_binary(&path_a, &big).unwrap();
+        write_binary(&path_b, &vec![vec![2], vec![2]]).unwrap();
+        write_binary(&out, &vec![vec![1]]).unwrap();
+
+        let err = multiply_files(&path_a, &path_b, &out, 1 << 20).unwrap_err();
+        assert!(matches!(
+            err,
+            BinaryError::Matrix(MatrixError::Overflow { .. })
+        ));
+        assert_eq!(read_binary(&out).unwrap(), vec![vec![1]]);
+        assert!(!crate::export::temp_path(&out).exists());
+        for path in [path_a, path_b, out] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn an_operand_can_be_replaced_by_its_own_product() {
+        let a = counting(3, 3, 1);
+        let path = temp_path("ooc-square.mmb");
+        write_binary(&path, &a).unwrap();
+        multiply_files(&path, &path, &path, 3 * 8 * 4).unwrap();
+        assert_eq!(
+            read_binary(&path).unwrap(),
+            multiply_matrices(&a, &a).unwrap()
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
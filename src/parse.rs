@@ -0,0 +1,202 @@
+use crate::matrix::{Matrix, MatrixError, MatrixF64};
+use std::{error::Error, fmt};
+
+/// Splits the text of a matrix panel into rows at newlines and elements at
+/// underscores, parsing each element with `parse`. Newlines at the end are
+/// ignored, but a blank row between others is an error
+fn parse_panel<T>(
+    text: &str,
+    parse: impl Fn(&str, usize, usize) -> Result<T, MatrixError>,
+) -> Result<Vec<Vec<T>>, MatrixError> {
+    if text.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    let m = text
+        .trim_end_matches('\n')
+        .split('\n')
+        .enumerate()
+        .map(|(row, line)| {
+            if line.is_empty() {
+                return Err(MatrixError::EmptyRow { row });
+            }
+            line.split('_')
+                .enumerate()
+                .map(|(col, token)| parse(token, row, col))
+                .collect()
+        })
+        .collect::<Result<Vec<Vec<T>>, MatrixError>>()?;
+
+    match m.iter().position(|row| row.len() != m[0].len()) {
+        Some(row) => Err(MatrixError::Ragged { row }),
+        None => Ok(m),
+    }
+}
+
+/// Parses the text of a matrix panel, where rows are separated by newlines
+/// and elements by underscores. Every row must have the same length, and an
+/// empty panel is an empty matrix
+pub fn parse_matrix(text: &str) -> Result<Matrix, MatrixError> {
+    parse_panel(text, |token, row, col| {
+        token.parse::<i64>().map_err(|_| MatrixError::Parse {
+            row,
+            col,
+            token: token.to_string(),
+        })
+    })
+}
+
+/// Like `parse_matrix`, but elements can also be fractions like `3/4`,
+/// which are evaluated to floats
+pub fn parse_matrix_f64(text: &str) -> Result<MatrixF64, MatrixError> {
+    parse_panel(text, |token, row, col| {
+        let invalid = || MatrixError::Parse {
+            row,
+            col,
+            token: token.to_string(),
+        };
+        let Some((numerator, denominator)) = token.split_once('/') else {
+            return token
+                .parse::<i64>()
+                .map(|x| x as f64)
+                .map_err(|_| invalid());
+        };
+        let numerator = numerator.parse::<i64>().map_err(|_| invalid())?;
+        match denominator.parse::<i64>().map_err(|_| invalid())? {
+            0 => Err(MatrixError::ZeroDenominator {
+                row,
+                col,
+                token: token.to_string(),
+            }),
+            denominator => Ok(numerator as f64 / denominator as f64),
+        }
+    })
+}
+
+/// Parses the contents of a matrix file: one row per line, with elements
+/// separated by commas if the text contains any and by whitespace otherwise.
+/// Blank lines are ignored
+pub fn parse_text_matrix(text: &str) -> Result<Matrix, MatrixError> {
+    let comma_separated = text.contains(',');
+    let m = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(row, line)| {
+            let tokens: Vec<&str> = if comma_separated {
+                line.split(',').map(str::trim).collect()
+            } else {
+                line.split_whitespace().collect()
+            };
+            tokens
+                .into_iter()
+                .enumerate()
+                .map(|(col, token)| {
+                    token.parse::<i64>().map_err(|_| MatrixError::Parse {
+                        row,
+                        col,
+                        token: token.to_string(),
+                    })
+                })
+                .collect()
+        })
+        .collect::<Result<Matrix, MatrixError>>()?;
+
+    match m.iter().position(|row| row.len() != m[0].len()) {
+        Some(row) => Err(MatrixError::Ragged { row }),
+        None => Ok(m),
+    }
+}
+
+/// A syntax error in a matrix file, with the 1-based line (and column, if
+/// known) it occurred on
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatError {
+    pub line: usize,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl FormatError {
+    pub fn new(line: usize, message: impl Into<String>) -> FormatError {
+        FormatError {
+            line,
+            column: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn at(line: usize, column: usize, message: impl Into<String>) -> FormatError {
+        FormatError {
+            line,
+            column: Some(column),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.column {
+            Some(column) => write!(f, "line {}, column {}: {}", self.line, column, self.message),
+            None => write!(f, "line {}: {}", self.line, self.message),
+        }
+    }
+}
+
+impl Error for FormatError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fractions_evaluate_to_floats() {
+        assert_eq!(
+            parse_matrix_f64("1/2_3\n-3/4_5/-1").unwrap(),
+            vec![vec![0.5, 3.0], vec![-0.75, -5.0]]
+        );
+    }
+
+    #[test]
+    fn bad_fractions_name_their_position() {
+        assert_eq!(
+            parse_matrix_f64("1_2\n3_4/0"),
+            Err(MatrixError::ZeroDenominator {
+                row: 1,
+                col: 1,
+                token: String::from("4/0")
+            })
+        );
+        for token in ["1/", "/2", "1/2/3", "a/2", "0.5"] {
+            assert_eq!(
+                parse_matrix_f64(token),
+                Err(MatrixError::Parse {
+                    row: 0,
+                    col: 0,
+                    token: token.to_string()
+                })
+            );
+        }
+        // the integer parser doesn't take fractions
+        assert!(matches!(
+            parse_matrix("1/2"),
+            Err(MatrixError::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn blank_rows_between_others_are_errors() {
+        assert_eq!(
+            parse_matrix("1_2\n3_4\n\n"),
+            Ok(vec![vec![1, 2], vec![3, 4]])
+        );
+        assert_eq!(
+            parse_matrix("1_2\n\n3_4"),
+            Err(MatrixError::EmptyRow { row: 1 })
+        );
+        assert_eq!(
+            MatrixError::EmptyRow { row: 1 }.to_string(),
+            "row 1 is empty, remove the blank line or fill it in"
+        );
+    }
+}
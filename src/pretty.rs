@@ -0,0 +1,192 @@
+/// How `--pretty` lays out results printed to stdout
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PrettyOptions {
+    /// Draw unicode box borders around and between the cells
+    pub borders: bool,
+    /// Label the rows and columns with their (1-based) numbers
+    pub headers: bool,
+    /// Widest line printed. Columns that don't fit are left out, and a note
+    /// says so
+    pub max_width: Option<usize>,
+}
+
+/// Shown in place of the columns left out by `max_width`
+const ELLIPSIS: &str = "…";
+
+fn width(cell: &str) -> usize {
+    cell.chars().count()
+}
+
+/// Width of a line whose columns are `widths` wide
+fn line_width(widths: &[usize], borders: bool) -> usize {
+    let gaps = widths.len().saturating_sub(1);
+    let cells: usize = widths.iter().sum();
+    if borders {
+        cells + 3 * gaps + 4
+    } else {
+        cells + gaps
+    }
+}
+
+/// A border line like `├───┼───┤`, with `left`, `middle` and `right` as
+/// the corners and junctions
+fn rule(widths: &[usize], left: char, middle: char, right: char) -> String {
+    let segments: Vec<String> = widths.iter().map(|&w| "─".repeat(w + 2)).collect();
+    format!("{}{}{}\n", left, segments.join(&middle.to_string()), right)
+}
+
+fn table_row(cells: &[String], widths: &[usize], borders: bool) -> String {
+    let padded: Vec<String> = cells
+        .iter()
+        .zip(widths)
+        .map(|(cell, &w)| format!("{}{}", " ".repeat(w - width(cell)), cell))
+        .collect();
+    if borders {
+        format!("│ {} │\n", padded.join(" │ "))
+    } else {
+        format!("{}\n", padded.join(" "))
+    }
+}
+
+/// Formats cells, e.g. from `format::answer_cells`, as a table with
+/// right-aligned columns, laid out as `options` says
+pub fn format_pretty(cells: &[Vec<String>], options: &PrettyOptions) -> String {
+    let cols = cells.first().map_or(0, |row| row.len());
+    if cols == 0 {
+        return String::new();
+    }
+    let column_widths: Vec<usize> = (0..cols)
+        .map(|j| {
+            let header = if options.headers {
+                width(&(j + 1).to_string())
+            } else {
+                0
+            };
+            cells
+                .iter()
+                .map(|row| width(&row[j]))
+                .fold(header, usize::max)
+        })
+        .collect();
+    let label_width = width(&cells.len().to_string());
+
+    // the widths of the printed columns when the first `shown` columns of
+    // the matrix are shown
+    let widths_showing = |shown: usize| {
+        let mut widths = vec![];
+        if options.headers {
+            widths.push(label_width);
+        }
+        widths.extend(&column_widths[..shown]);
+        if shown < cols {
+            widths.push(width(ELLIPSIS));
+        }
+        widths
+    };
+    let shown = match options.max_width {
+        Some(max) => (1..=cols)
+            .rev()
+            .find(|&shown| line_width(&widths_showing(shown), options.borders) <= max)
+            .unwrap_or(1),
+        None => cols,
+    };
+    let widths = widths_showing(shown);
+
+    let printed_row = |label: String, row: &[String]| {
+        let mut printed = vec![];
+        if options.headers {
+            printed.push(label);
+        }
+        printed.extend(row[..shown].iter().cloned());
+        if shown < cols {
+            printed.push(String::from(ELLIPSIS));
+        }
+        printed
+    };
+
+    let mut text = String::new();
+    if options.borders {
+        text.push_str(&rule(&widths, '┌', '┬', '┐'));
+    }
+    if options.headers {
+        let numbers: Vec<String> = (1..=cols).map(|j| j.to_string()).collect();
+        let header = printed_row(String::new(), &numbers);
+        text.push_str(&table_row(&header, &widths, options.borders));
+        if options.borders {
+            text.push_str(&rule(&widths, '├', '┼', '┤'));
+        }
+    }
+    for (i, row) in cells.iter().enumerate() {
+        text.push_str(&table_row(
+            &printed_row((i + 1).to_string(), row),
+            &widths,
+            options.borders,
+        ));
+    }
+    if options.borders {
+        text.push_str(&rule(&widths, '└', '┴', '┘'));
+    }
+
+    if shown < cols {
+        text.push_str(&format!(
+            "showing {} of {} columns to fit --max-width\n",
+            shown, cols
+        ));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn columns_are_right_aligned() {
+        let cells = cells(&[&["1", "-200"], &["30", "4"]]);
+        assert_eq!(
+            format_pretty(&cells, &PrettyOptions::default()),
+            " 1 -200\n30    4\n"
+        );
+        assert_eq!(format_pretty(&[], &PrettyOptions::default()), "");
+    }
+
+    #[test]
+    fn borders_and_headers_frame_the_table() {
+        let cells = cells(&[&["1", "-2"], &["30", "4"]]);
+        let options = PrettyOptions {
+            borders: true,
+            headers: true,
+            max_width: None,
+        };
+        assert_eq!(
+            format_pretty(&cells, &options),
+            "┌───┬────┬────┐\n\
+             │   │  1 │  2 │\n\
+             ├───┼────┼────┤\n\
+             │ 1 │  1 │ -2 │\n\
+             │ 2 │ 30 │  4 │\n\
+             └───┴────┴────┘\n"
+        );
+    }
+
+    #[test]
+    fn columns_past_the_max_width_are_left_out() {
+        let cells = cells(&[&["111", "222", "333", "444"]]);
+        let options = PrettyOptions {
+            max_width: Some(9),
+            ..PrettyOptions::default()
+        };
+        let text = format_pretty(&cells, &options);
+        assert_eq!(
+            text,
+            "111 222 …\nshowing 2 of 4 columns to fit --max-width\n"
+        );
+        assert!(text.lines().next().unwrap().chars().count() <= 9);
+    }
+}
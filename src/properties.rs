@@ -0,0 +1,407 @@
+use crate::{
+    backend::check_multiply_dims,
+    matrix::{
+        checked_multiply, dims, identity_matrix, multiply_f64, to_f64, Matrix, MatrixError,
+        MatrixF64,
+    },
+};
+
+/// Complex matrix with `(re, im)` elements
+pub type MatrixC64 = Vec<Vec<(f64, f64)>>;
+
+fn is_square<T>(m: &[Vec<T>]) -> bool {
+    !m.is_empty() && m.iter().all(|row| row.len() == m.len())
+}
+
+/// Whether `m` is its own inverse, i.e. `m² = I`. A square that overflows i64
+/// is treated as not involutory
+pub fn is_involutory(m: &Matrix) -> bool {
+    is_square(m) && checked_multiply(m, m) == Some(identity_matrix(m.len()))
+}
+
+/// Whether `m² = m` within `tol` elementwise, as for projection matrices
+pub fn is_idempotent(m: &MatrixF64, tol: f64) -> bool {
+    is_square(m)
+        && multiply_f64(m, m)
+            .iter()
+            .flatten()
+            .zip(m.iter().flatten())
+            .all(|(a, b)| (a - b).abs() <= tol)
+}
+
+fn is_rectangular<T>(m: &[Vec<T>]) -> bool {
+    let cols = dims(m).1;
+    !m.is_empty() && m.iter().all(|row| row.len() == cols)
+}
+
+/// Whether every element for which `outside(i, j)` holds is zero
+fn zero_outside<T>(
+    m: &[Vec<T>],
+    outside: impl Fn(usize, usize) -> bool,
+    is_zero: impl Fn(&T) -> bool,
+) -> bool {
+    is_rectangular(m)
+        && m.iter().enumerate().all(|(i, row)| {
+            row.iter()
+                .enumerate()
+                .all(|(j, x)| !outside(i, j) || is_zero(x))
+        })
+}
+
+/// Whether `related(m[i][j], m[j][i])` holds for every pair of positions,
+/// including the diagonal
+fn transpose_related<T>(m: &[Vec<T>], related: impl Fn(&T, &T) -> bool) -> bool {
+    is_square(m) && (0..m.len()).all(|i| (0..=i).all(|j| related(&m[i][j], &m[j][i])))
+}
+
+fn upper_outside(strict: bool) -> impl Fn(usize, usize) -> bool {
+    move |i, j| if strict { i >= j } else { i > j }
+}
+
+fn lower_outside(strict: bool) -> impl Fn(usize, usize) -> bool {
+    move |i, j| if strict { i <= j } else { i < j }
+}
+
+fn diagonal_outside(i: usize, j: usize) -> bool {
+    i != j
+}
+
+fn tridiagonal_outside(i: usize, j: usize) -> bool {
+    i.abs_diff(j) > 1
+}
+
+fn upper_bidiagonal_outside(i: usize, j: usize) -> bool {
+    j < i || j > i + 1
+}
+
+fn lower_bidiagonal_outside(i: usize, j: usize) -> bool {
+    i < j || i > j + 1
+}
+
+fn within(tol: f64) -> impl Fn(&f64) -> bool {
+    move |x| x.abs() <= tol
+}
+
+/// Whether every element below the diagonal is zero (and the diagonal too if
+/// `strict`)
+pub fn is_upper_triangular(m: &Matrix, strict: bool) -> bool {
+    zero_outside(m, upper_outside(strict), |&x| x == 0)
+}
+
+pub fn is_upper_triangular_f64(m: &MatrixF64, strict: bool, tol: f64) -> bool {
+    zero_outside(m, upper_outside(strict), within(tol))
+}
+
+/// Whether every element above the diagonal is zero (and the diagonal too if
+/// `strict`)
+pub fn is_lower_triangular(m: &Matrix, strict: bool) -> bool {
+    zero_outside(m, lower_outside(strict), |&x| x == 0)
+}
+
+pub fn is_lower_triangular_f64(m: &MatrixF64, strict: bool, tol: f64) -> bool {
+    zero_outside(m, lower_outside(strict), within(tol))
+}
+
+pub fn is_diagonal_matrix(m: &Matrix) -> bool {
+    zero_outside(m, diagonal_outside, |&x| x == 0)
+}
+
+pub fn is_diagonal_matrix_f64(m: &MatrixF64, tol: f64) -> bool {
+    zero_outside(m, diagonal_outside, within(tol))
+}
+
+/// Whether only the diagonal and the two diagonals next to it are nonzero
+pub fn is_tridiagonal(m: &Matrix) -> bool {
+    zero_outside(m, tridiagonal_outside, |&x| x == 0)
+}
+
+pub fn is_tridiagonal_f64(m: &MatrixF64, tol: f64) -> bool {
+    zero_outside(m, tridiagonal_outside, within(tol))
+}
+
+/// Whether only the diagonal and either the superdiagonal or the subdiagonal
+/// are nonzero
+pub fn is_bidiagonal(m: &Matrix) -> bool {
+    zero_outside(m, upper_bidiagonal_outside, |&x| x == 0)
+        || zero_outside(m, lower_bidiagonal_outside, |&x| x == 0)
+}
+
+pub fn is_bidiagonal_f64(m: &MatrixF64, tol: f64) -> bool {
+    zero_outside(m, upper_bidiagonal_outside, within(tol))
+        || zero_outside(m, lower_bidiagonal_outside, within(tol))
+}
+
+pub fn is_symmetric(m: &Matrix) -> bool {
+    transpose_related(m, |a, b| a == b)
+}
+
+pub fn is_symmetric_f64(m: &MatrixF64, tol: f64) -> bool {
+    transpose_related(m, |a, b| (a - b).abs() <= tol)
+}
+
+/// The largest `|m[i][j] - m[j][i]|` with its `(i, j)`, where `i > j`, or
+/// `None` if `m` isn't square. Exactly symmetric matrices give zero at
+/// `(0, 0)`
+pub fn max_asymmetry(m: &MatrixF64) -> Option<(f64, (usize, usize))> {
+    if !is_square(m) {
+        return None;
+    }
+    let mut max = (0.0, (0, 0));
+    for i in 0..m.len() {
+        for j in 0..i {
+            let diff = (m[i][j] - m[j][i]).abs();
+            if diff > max.0 || diff.is_nan() && !max.0.is_nan() {
+                max = (diff, (i, j));
+            }
+        }
+    }
+    Some(max)
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// The `k` with `b = k·a`, as a reduced fraction `(numerator, denominator)`
+/// with a positive denominator, or `None` if there is none. `a` and `b`
+/// must have the same shape. Every `k` works when both are all zeros, and
+/// `0` is returned
+pub fn scalar_multiple(a: &Matrix, b: &Matrix) -> Result<Option<(i128, i128)>, MatrixError> {
+    if dims(a) != dims(b) || a.iter().zip(b).any(|(x, y)| x.len() != y.len()) {
+        return Err(MatrixError::DimensionMismatch {
+            left: dims(a),
+            right: dims(b),
+        });
+    }
+    let pairs = || a.iter().flatten().zip(b.iter().flatten());
+    let (num, den) = match pairs().find(|(&x, _)| x != 0) {
+        Some((&x, &y)) => {
+            let (x, y) = (x as i128, y as i128);
+            let divisor = gcd(y, x) * x.signum();
+            (y / divisor, x / divisor)
+        }
+        None => (0, 1),
+    };
+    // a zero in `a` only matches a zero in `b`, which cross-multiplying
+    // checks too
+    let consistent = pairs().all(|(&x, &y)| y as i128 * den == x as i128 * num);
+    Ok(consistent.then_some((num, den)))
+}
+
+/// `tr(a·b)` without forming the product: the sum over `i` of row `i` of
+/// `a` times column `i` of `b`, which takes O(m·n) rather than O(m²·n).
+/// `a·b` must be defined and square
+pub fn trace_of_product(a: &Matrix, b: &Matrix) -> Result<i64, MatrixError> {
+    check_multiply_dims(a, b)?;
+    let (rows, cols) = (a.len(), dims(b).1);
+    if rows != cols {
+        return Err(MatrixError::NotSquare { rows, cols });
+    }
+    let overflow = MatrixError::Overflow { bits: 64 };
+    let mut trace: i128 = 0;
+    for (i, row) in a.iter().enumerate() {
+        for (k, &x) in row.iter().enumerate() {
+            trace = trace
+                .checked_add(x as i128 * b[k][i] as i128)
+                .ok_or(overflow.clone())?;
+        }
+    }
+    i64::try_from(trace).map_err(|_| overflow)
+}
+
+/// Whether `mᵀ = -m`, which forces a zero diagonal
+pub fn is_skew_symmetric(m: &Matrix) -> bool {
+    transpose_related(m, |&a, &b| Some(a) == b.checked_neg())
+}
+
+pub fn is_skew_symmetric_f64(m: &MatrixF64, tol: f64) -> bool {
+    transpose_related(m, |a, b| (a + b).abs() <= tol)
+}
+
+/// Whether `m` equals its conjugate transpose, which forces a real diagonal
+pub fn is_hermitian(m: &MatrixC64, tol: f64) -> bool {
+    transpose_related(m, |a, b| {
+        (a.0 - b.0).abs() <= tol && (a.1 + b.1).abs() <= tol
+    })
+}
+
+/// Structural properties of an integer matrix, as shown in the info popup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatrixProperties {
+    pub rows: usize,
+    pub cols: usize,
+    pub square: bool,
+    pub upper_triangular: bool,
+    pub lower_triangular: bool,
+    pub diagonal: bool,
+    pub tridiagonal: bool,
+    pub bidiagonal: bool,
+    pub symmetric: bool,
+    pub skew_symmetric: bool,
+    pub involutory: bool,
+    pub idempotent: bool,
+}
+
+impl MatrixProperties {
+    pub fn compute(m: &Matrix) -> MatrixProperties {
+        let (rows, cols) = dims(m);
+        MatrixProperties {
+            rows,
+            cols,
+            square: is_square(m),
+            upper_triangular: is_upper_triangular(m, false),
+            lower_triangular: is_lower_triangular(m, false),
+            diagonal: is_diagonal_matrix(m),
+            tridiagonal: is_tridiagonal(m),
+            bidiagonal: is_bidiagonal(m),
+            symmetric: is_symmetric(m),
+            skew_symmetric: is_skew_symmetric(m),
+            involutory: is_involutory(m),
+            idempotent: is_idempotent(&to_f64(m), 0.0),
+        }
+    }
+
+    /// The boolean properties with their display names
+    pub fn flags(&self) -> [(&'static str, bool); 10] {
+        [
+            ("square", self.square),
+            ("upper triangular", self.upper_triangular),
+            ("lower triangular", self.lower_triangular),
+            ("diagonal", self.diagonal),
+            ("tridiagonal", self.tridiagonal),
+            ("bidiagonal", self.bidiagonal),
+            ("symmetric", self.symmetric),
+            ("skew-symmetric", self.skew_symmetric),
+            ("involutory", self.involutory),
+            ("idempotent", self.idempotent),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn involutory_matrices_square_to_the_identity() {
+        assert!(is_involutory(&identity_matrix(3)));
+        assert!(is_involutory(&vec![vec![0, 1], vec![1, 0]]));
+        assert!(is_involutory(&vec![vec![1, 0], vec![5, -1]]));
+        assert!(!is_involutory(&vec![vec![1, 1], vec![0, 1]]));
+        assert!(!is_involutory(&vec![vec![1, 0, 0]]));
+        // the square overflows rather than wrapping round to I
+        assert!(!is_involutory(&vec![vec![i64::MAX, 0], vec![0, 1]]));
+    }
+
+    #[test]
+    fn triangular_and_banded_structure() {
+        let upper = vec![vec![1, 2, 3], vec![0, 4, 5], vec![0, 0, 6]];
+        assert!(is_upper_triangular(&upper, false));
+        assert!(!is_upper_triangular(&upper, true));
+        assert!(!is_lower_triangular(&upper, false));
+        let strict = vec![vec![0, 2], vec![0, 0]];
+        assert!(is_upper_triangular(&strict, true));
+        let lower = vec![vec![6, 0, 0], vec![5, 4, 0], vec![3, 2, 1]];
+        assert!(is_lower_triangular(&lower, false));
+        assert!(!is_upper_triangular(&lower, false));
+
+        let tridiagonal = vec![vec![1, 2, 0], vec![3, 4, 5], vec![0, 6, 7]];
+        assert!(is_tridiagonal(&tridiagonal));
+        assert!(!is_bidiagonal(&tridiagonal));
+        assert!(!is_tridiagonal(&vec![
+            vec![1, 0, 9],
+            vec![0, 1, 0],
+            vec![0, 0, 1]
+        ]));
+        assert!(is_bidiagonal(&vec![
+            vec![1, 2, 0],
+            vec![0, 3, 4],
+            vec![0, 0, 5]
+        ]));
+        assert!(is_diagonal_matrix(&identity_matrix(3)));
+        assert!(!is_diagonal_matrix(&upper));
+    }
+
+    #[test]
+    fn float_structure_checks_allow_for_rounding() {
+        let nearly = vec![vec![1.0, 2.0], vec![1e-12, 3.0]];
+        assert!(is_upper_triangular_f64(&nearly, false, 1e-9));
+        assert!(!is_upper_triangular_f64(&nearly, false, 0.0));
+        assert!(is_diagonal_matrix_f64(
+            &vec![vec![1.0, -1e-12], vec![0.0, 2.0]],
+            1e-9
+        ));
+    }
+
+    #[test]
+    fn symmetry_within_a_tolerance() {
+        let m = vec![
+            vec![1.0, 2.0, 3.0],
+            vec![2.0 + 1e-10, 5.0, 6.0],
+            vec![3.0, 6.5, 9.0],
+        ];
+        assert_eq!(max_asymmetry(&m), Some((0.5, (2, 1))));
+        assert!(!is_symmetric_f64(&m, 0.1));
+        assert!(is_symmetric_f64(&m, 0.5));
+        assert_eq!(max_asymmetry(&vec![vec![1.0, 2.0]]), None);
+        assert_eq!(max_asymmetry(&vec![vec![4.0]]), Some((0.0, (0, 0))));
+
+        let nan = vec![vec![0.0, f64::NAN], vec![1.0, 0.0]];
+        assert!(max_asymmetry(&nan).unwrap().0.is_nan());
+        assert!(!is_symmetric_f64(&nan, f64::INFINITY));
+    }
+
+    #[test]
+    fn scalar_multiples_as_reduced_fractions() {
+        let a = vec![vec![2, 0], vec![-4, 6]];
+        let b = vec![vec![3, 0], vec![-6, 9]];
+        assert_eq!(scalar_multiple(&a, &b), Ok(Some((3, 2))));
+        assert_eq!(scalar_multiple(&b, &a), Ok(Some((2, 3))));
+        let negated = vec![vec![-2, 0], vec![4, -6]];
+        assert_eq!(scalar_multiple(&a, &negated), Ok(Some((-1, 1))));
+        let zeros = vec![vec![0, 0], vec![0, 0]];
+        assert_eq!(scalar_multiple(&a, &zeros), Ok(Some((0, 1))));
+        assert_eq!(scalar_multiple(&zeros, &zeros), Ok(Some((0, 1))));
+        assert_eq!(scalar_multiple(&zeros, &a), Ok(None));
+        let off = vec![vec![3, 1], vec![-6, 9]];
+        assert_eq!(scalar_multiple(&a, &off), Ok(None));
+        let big = vec![vec![i64::MIN]];
+        assert_eq!(
+            scalar_multiple(&vec![vec![-1]], &big),
+            Ok(Some((1 << 63, 1)))
+        );
+        assert!(matches!(
+            scalar_multiple(&a, &vec![vec![1, 2]]),
+            Err(MatrixError::DimensionMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn trace_of_product_without_the_product() {
+        let a = vec![vec![1, 2, 3], vec![4, 5, 6]];
+        let b = vec![vec![7, 8], vec![9, 10], vec![11, 12]];
+        // a·b = [[58, 64], [139, 154]]
+        assert_eq!(trace_of_product(&a, &b), Ok(212));
+        // b·a is 3x3 with trace 39 + 68 + 105
+        assert_eq!(trace_of_product(&b, &a), Ok(212));
+        // the terms overflow an i64 but the trace doesn't
+        let big = vec![vec![i64::MAX, i64::MAX]];
+        assert_eq!(trace_of_product(&big, &vec![vec![2], vec![-2]]), Ok(0));
+        assert_eq!(
+            trace_of_product(&big, &vec![vec![2], vec![0]]),
+            Err(MatrixError::Overflow { bits: 64 })
+        );
+        assert_eq!(
+            trace_of_product(&a, &vec![vec![1], vec![2], vec![3]]),
+            Err(MatrixError::NotSquare { rows: 2, cols: 1 })
+        );
+        assert!(matches!(
+            trace_of_product(&a, &a),
+            Err(MatrixError::DimensionMismatch { .. })
+        ));
+    }
+}
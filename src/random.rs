@@ -0,0 +1,232 @@
+use crate::{
+    linalg::qr_decompose,
+    matrix::{multiply_f64, transpose, Matrix, MatrixError, MatrixF64},
+};
+use rand::{rngs::StdRng, RngExt, SeedableRng};
+use std::f64::consts::TAU;
+
+/// Standard normal sample via the Box-Muller transform
+fn gaussian(rng: &mut StdRng) -> f64 {
+    let u1: f64 = 1.0 - rng.random::<f64>();
+    let u2: f64 = rng.random();
+    (-2.0 * u1.ln()).sqrt() * (TAU * u2).cos()
+}
+
+fn orthogonal_from(rng: &mut StdRng, n: usize) -> MatrixF64 {
+    loop {
+        let g: MatrixF64 = (0..n)
+            .map(|_| (0..n).map(|_| gaussian(rng)).collect())
+            .collect();
+        // a Gaussian matrix is singular with probability zero, but retry anyway
+        let Ok((mut q, r)) = qr_decompose(&g) else {
+            continue;
+        };
+        // flip columns so R has a positive diagonal, which makes Q Haar distributed
+        for j in 0..n {
+            if r[j][j] < 0.0 {
+                q.iter_mut().for_each(|row| row[j] = -row[j]);
+            }
+        }
+        return q;
+    }
+}
+
+/// Uniformly random (Haar distributed) n×n orthogonal matrix
+pub fn random_orthogonal_matrix(n: usize, seed: u64) -> MatrixF64 {
+    orthogonal_from(&mut StdRng::seed_from_u64(seed), n)
+}
+
+/// Random symmetric positive definite n×n matrix `Q D Qᵀ` with a random
+/// orthogonal `Q` and eigenvalues drawn uniformly from `eigenvalue_range`,
+/// which must be positive
+pub fn random_symmetric_positive_definite(
+    n: usize,
+    eigenvalue_range: (f64, f64),
+    seed: u64,
+) -> MatrixF64 {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let q = orthogonal_from(&mut rng, n);
+    let (low, high) = eigenvalue_range;
+    let eigenvalues: Vec<f64> = (0..n)
+        .map(|_| low + (high - low) * rng.random::<f64>())
+        .collect();
+
+    let scaled: MatrixF64 = q
+        .iter()
+        .map(|row| row.iter().zip(&eigenvalues).map(|(x, d)| x * d).collect())
+        .collect();
+    let mut m = multiply_f64(&scaled, &transpose(&q));
+    // symmetrize away rounding noise
+    for i in 0..n {
+        for j in 0..i {
+            let mean = (m[i][j] + m[j][i]) / 2.0;
+            m[i][j] = mean;
+            m[j][i] = mean;
+        }
+    }
+    m
+}
+
+/// Structure of the matrices made by `random_int_matrix`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatrixKind {
+    /// Every element random
+    Dense,
+    Identity,
+    /// Random diagonal, zeros elsewhere
+    Diagonal,
+    /// Random lower triangle mirrored into the upper one
+    Symmetric,
+    /// Each element nonzero with probability `density`
+    Sparse {
+        density: f64,
+    },
+}
+
+impl MatrixKind {
+    /// Parses `dense`, `identity`, `diagonal`, `symmetric` or `sparse`, the
+    /// last with the given density
+    pub fn from_name(name: &str, density: f64) -> Option<MatrixKind> {
+        Some(match name {
+            "dense" => MatrixKind::Dense,
+            "identity" => MatrixKind::Identity,
+            "diagonal" => MatrixKind::Diagonal,
+            "symmetric" => MatrixKind::Symmetric,
+            "sparse" => MatrixKind::Sparse { density },
+            _ => return None,
+        })
+    }
+}
+
+/// Random `rows`×`cols` integer matrix of the given kind with elements drawn
+/// uniformly from `min..=max`. The same seed always gives the same matrix.
+/// Nonzero elements of sparse matrices are redrawn until nonzero, unless the
+/// range is just zero
+pub fn random_int_matrix(
+    rows: usize,
+    cols: usize,
+    (min, max): (i64, i64),
+    kind: MatrixKind,
+    seed: u64,
+) -> Result<Matrix, MatrixError> {
+    if min > max {
+        return Err(MatrixError::InvalidArgument(format!(
+            "the minimum {} is larger than the maximum {}",
+            min, max
+        )));
+    }
+    if matches!(
+        kind,
+        MatrixKind::Identity | MatrixKind::Diagonal | MatrixKind::Symmetric
+    ) && rows != cols
+    {
+        return Err(MatrixError::NotSquare { rows, cols });
+    }
+    if let MatrixKind::Sparse { density } = kind {
+        if !(0.0..=1.0).contains(&density) {
+            return Err(MatrixError::InvalidArgument(format!(
+                "density must be in [0, 1], got {}",
+                density
+            )));
+        }
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let element = |rng: &mut StdRng| rng.random_range(min..=max);
+    let mut m = vec![vec![0; cols]; rows];
+    for i in 0..rows {
+        for j in 0..cols {
+            m[i][j] = match kind {
+                MatrixKind::Dense => element(&mut rng),
+                MatrixKind::Identity => (i == j) as i64,
+                MatrixKind::Diagonal if i == j => element(&mut rng),
+                MatrixKind::Diagonal => 0,
+                MatrixKind::Symmetric if j <= i => element(&mut rng),
+                MatrixKind::Symmetric => continue,
+                MatrixKind::Sparse { density } => {
+                    if rng.random::<f64>() >= density {
+                        continue;
+                    }
+                    let mut x = element(&mut rng);
+                    while x == 0 && (min, max) != (0, 0) {
+                        x = element(&mut rng);
+                    }
+                    x
+                }
+            };
+        }
+    }
+    if kind == MatrixKind::Symmetric {
+        for i in 0..rows {
+            for j in i + 1..cols {
+                m[i][j] = m[j][i];
+            }
+        }
+    }
+    Ok(m)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::{identity_matrix, to_f64};
+
+    fn max_deviation(a: &MatrixF64, b: &MatrixF64) -> f64 {
+        a.iter()
+            .flatten()
+            .zip(b.iter().flatten())
+            .map(|(x, y)| (x - y).abs())
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn random_orthogonal_matrices_are_orthogonal_and_seeded() {
+        let q = random_orthogonal_matrix(6, 7);
+        let identity = to_f64(&identity_matrix(6));
+        assert!(max_deviation(&multiply_f64(&transpose(&q), &q), &identity) < 1e-12);
+        assert_eq!(random_orthogonal_matrix(6, 7), q);
+        assert_ne!(random_orthogonal_matrix(6, 8), q);
+        assert!(random_orthogonal_matrix(0, 7).is_empty());
+    }
+
+    #[test]
+    fn random_int_matrices_respect_the_range_and_kind() {
+        let dense = random_int_matrix(4, 6, (-3, 3), MatrixKind::Dense, 1).unwrap();
+        assert_eq!((dense.len(), dense[0].len()), (4, 6));
+        assert!(dense.iter().flatten().all(|x| (-3..=3).contains(x)));
+        assert_eq!(
+            random_int_matrix(4, 6, (-3, 3), MatrixKind::Dense, 1).unwrap(),
+            dense
+        );
+
+        let symmetric = random_int_matrix(5, 5, (-9, 9), MatrixKind::Symmetric, 2).unwrap();
+        assert_eq!(transpose(&symmetric), symmetric);
+        let diagonal = random_int_matrix(3, 3, (1, 9), MatrixKind::Diagonal, 3).unwrap();
+        assert!((0..3).all(|i| (0..3).all(|j| (diagonal[i][j] == 0) == (i != j))));
+        assert_eq!(
+            random_int_matrix(3, 3, (5, 9), MatrixKind::Identity, 4).unwrap(),
+            identity_matrix(3)
+        );
+
+        let sparse =
+            random_int_matrix(20, 20, (-1, 1), MatrixKind::Sparse { density: 0.1 }, 5).unwrap();
+        let nonzeros = sparse.iter().flatten().filter(|&&x| x != 0).count();
+        assert!((10..=80).contains(&nonzeros), "{}", nonzeros);
+    }
+
+    #[test]
+    fn random_int_matrices_reject_impossible_requests() {
+        assert!(matches!(
+            random_int_matrix(2, 2, (3, 1), MatrixKind::Dense, 0),
+            Err(MatrixError::InvalidArgument(_))
+        ));
+        assert_eq!(
+            random_int_matrix(2, 3, (0, 1), MatrixKind::Symmetric, 0),
+            Err(MatrixError::NotSquare { rows: 2, cols: 3 })
+        );
+        assert!(matches!(
+            random_int_matrix(2, 2, (0, 1), MatrixKind::Sparse { density: 1.5 }, 0),
+            Err(MatrixError::InvalidArgument(_))
+        ));
+    }
+}
@@ -0,0 +1,434 @@
+use crate::{
+    cli::{format_stdout, read_matrix_file, CliError, Options},
+    export::export_answer,
+    json::parse_json_rows,
+    log,
+    matrix::Answer,
+    operation::Operation,
+};
+use std::{
+    collections::BTreeMap,
+    io::{self, BufRead, IsTerminal, Write},
+    path::{Path, PathBuf},
+};
+
+const HELP: &str = "\
+load NAME PATH     read a matrix file into NAME
+set NAME EXPR      store EXPR in NAME, same as NAME = EXPR
+NAME = EXPR        store EXPR in NAME
+print EXPR         print EXPR
+export NAME PATH   write NAME to a file, in the format of its extension
+list               list the stored matrices and their sizes
+history            list the commands entered so far
+help               show this help
+quit               leave, as does end of input
+
+EXPR combines names, [[1, 2], [3, 4]] literals and parentheses with
+* (multiply), .* (hadamard), + and -. * and .* bind tighter than + and -
+";
+
+/// What the REPL does after a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Continue,
+    Quit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Name(String),
+    /// A `[[...]]` matrix literal, brackets included
+    Literal(String),
+    Operator(Operation),
+    Open,
+    Close,
+}
+
+fn is_name(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, CliError> {
+    let mut tokens = vec![];
+    let mut rest = text.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let len = match c {
+            '[' => {
+                let mut depth = 0;
+                let end = rest
+                    .char_indices()
+                    .find(|&(_, c)| {
+                        match c {
+                            '[' => depth += 1,
+                            ']' => depth -= 1,
+                            _ => {}
+                        }
+                        depth == 0
+                    })
+                    .map(|(i, _)| i + 1)
+                    .ok_or_else(|| CliError::Usage(String::from("unclosed [ in expression")))?;
+                tokens.push(Token::Literal(String::from(&rest[..end])));
+                end
+            }
+            '(' | ')' | '+' | '-' | '*' => {
+                tokens.push(match c {
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    '+' => Token::Operator(Operation::Add),
+                    '-' => Token::Operator(Operation::Subtract),
+                    _ => Token::Operator(Operation::Multiply),
+                });
+                1
+            }
+            '.' if rest.starts_with(".*") => {
+                tokens.push(Token::Operator(Operation::Hadamard));
+                2
+            }
+            _ if c.is_ascii_alphabetic() || c == '_' => {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                tokens.push(Token::Name(String::from(&rest[..end])));
+                end
+            }
+            _ => return Err(CliError::Usage(format!("unexpected {:?} in expression", c))),
+        };
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+/// `operation` on two results, in floating point if either is
+fn combine(operation: Operation, a: &Answer, b: &Answer) -> Result<Answer, CliError> {
+    Ok(match (a, b) {
+        (Answer::Int(a), Answer::Int(b)) => Answer::Int(operation.apply(a, b)?),
+        _ => Answer::Float(operation.apply_f64(&a.to_f64(), &b.to_f64())?),
+    })
+}
+
+/// A REPL session: the named matrices and the commands entered so far
+#[derive(Debug, Default)]
+pub struct Session {
+    matrices: BTreeMap<String, Answer>,
+    history: Vec<String>,
+}
+
+impl Session {
+    fn lookup(&self, name: &str) -> Result<&Answer, CliError> {
+        self.matrices
+            .get(name)
+            .ok_or_else(|| CliError::Usage(format!("no matrix named {:?}", name)))
+    }
+
+    /// Evaluates an expression like `(a + b) * [[1, 0], [0, 1]]`
+    pub fn evaluate(&self, text: &str) -> Result<Answer, CliError> {
+        let tokens = tokenize(text)?;
+        if tokens.is_empty() {
+            return Err(CliError::Usage(String::from("expected an expression")));
+        }
+        let mut position = 0;
+        let answer = self.sum(&tokens, &mut position)?;
+        match tokens.get(position) {
+            None => Ok(answer),
+            Some(Token::Close) => Err(CliError::Usage(String::from("unmatched ) in expression"))),
+            Some(_) => Err(CliError::Usage(String::from(
+                "expected an operator between operands",
+            ))),
+        }
+    }
+
+    fn sum(&self, tokens: &[Token], position: &mut usize) -> Result<Answer, CliError> {
+        let mut answer = self.product(tokens, position)?;
+        while let Some(&Token::Operator(operation @ (Operation::Add | Operation::Subtract))) =
+            tokens.get(*position)
+        {
+            *position += 1;
+            answer = combine(operation, &answer, &self.product(tokens, position)?)?;
+        }
+        Ok(answer)
+    }
+
+    fn product(&self, tokens: &[Token], position: &mut usize) -> Result<Answer, CliError> {
+        let mut answer = self.operand(tokens, position)?;
+        while let Some(&Token::Operator(operation @ (Operation::Multiply | Operation::Hadamard))) =
+            tokens.get(*position)
+        {
+            *position += 1;
+            answer = combine(operation, &answer, &self.operand(tokens, position)?)?;
+        }
+        Ok(answer)
+    }
+
+    fn operand(&self, tokens: &[Token], position: &mut usize) -> Result<Answer, CliError> {
+        let token = tokens.get(*position);
+        *position += 1;
+        match token {
+            Some(Token::Name(name)) => Ok(self.lookup(name)?.clone()),
+            Some(Token::Literal(text)) => {
+                parse_json_rows(text)
+                    .map(Answer::Int)
+                    .map_err(|err| CliError::Format {
+                        path: PathBuf::from("<literal>"),
+                        err,
+                    })
+            }
+            Some(Token::Open) => {
+                let answer = self.sum(tokens, position)?;
+                if tokens.get(*position) != Some(&Token::Close) {
+                    return Err(CliError::Usage(String::from("expected ) in expression")));
+                }
+                *position += 1;
+                Ok(answer)
+            }
+            _ => Err(CliError::Usage(String::from(
+                "expected a name, a [[...]] literal or (",
+            ))),
+        }
+    }
+
+    fn store(&mut self, name: &str, expression: &str) -> Result<(), CliError> {
+        if !is_name(name) {
+            return Err(CliError::Usage(format!("invalid matrix name {:?}", name)));
+        }
+        let answer = self.evaluate(expression)?;
+        self.matrices.insert(String::from(name), answer);
+        Ok(())
+    }
+
+    /// Runs one line of input, writing anything it prints to `out`
+    pub fn execute(
+        &mut self,
+        line: &str,
+        options: &Options,
+        out: &mut dyn Write,
+    ) -> Result<Control, CliError> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(Control::Continue);
+        }
+        self.history.push(String::from(line));
+        let write_out = |out: &mut dyn Write, text: &str| {
+            out.write_all(text.as_bytes()).map_err(|err| CliError::Io {
+                path: PathBuf::from("<stdout>"),
+                err,
+            })
+        };
+
+        if let Some((name, expression)) = line.split_once('=') {
+            if is_name(name.trim()) {
+                self.store(name.trim(), expression)?;
+                return Ok(Control::Continue);
+            }
+        }
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let name_and_rest = || {
+            rest.split_once(char::is_whitespace)
+                .map(|(name, rest)| (name, rest.trim()))
+                .ok_or_else(|| CliError::Usage(format!("usage: {} NAME ...", command)))
+        };
+        match command {
+            "load" => {
+                let (name, path) = name_and_rest()?;
+                if !is_name(name) {
+                    return Err(CliError::Usage(format!("invalid matrix name {:?}", name)));
+                }
+                let m = read_matrix_file(Path::new(path), &options.read)?;
+                self.matrices.insert(String::from(name), Answer::Int(m));
+            }
+            "set" => {
+                let (name, expression) = name_and_rest()?;
+                self.store(name, expression)?;
+            }
+            "print" => {
+                let answer = self.evaluate(rest)?;
+                write_out(out, &format_stdout(options, &answer))?;
+            }
+            "export" => {
+                let (name, path) = name_and_rest()?;
+                let format =
+                    export_answer(Path::new(path), Some(self.lookup(name)?), &options.write)?;
+                write_out(out, &format!("wrote {} as {}\n", path, format.name()))?;
+            }
+            "list" => {
+                for (name, answer) in &self.matrices {
+                    let (rows, cols) = answer.dims();
+                    write_out(out, &format!("{}  {}x{}\n", name, rows, cols))?;
+                }
+            }
+            "history" => {
+                for (i, entry) in self.history.iter().enumerate() {
+                    write_out(out, &format!("{:>4}  {}\n", i + 1, entry))?;
+                }
+            }
+            "help" => write_out(out, HELP)?,
+            "quit" | "exit" => return Ok(Control::Quit),
+            _ => {
+                return Err(CliError::Usage(format!(
+                    "unknown command {:?}, try help",
+                    command
+                )))
+            }
+        }
+        Ok(Control::Continue)
+    }
+}
+
+/// Reads commands from stdin until `quit` or end of input. Errors are
+/// printed and the session carries on. The prompt is only shown when stdin
+/// is a terminal, so scripts can be piped in
+pub fn run_repl(options: &Options, out: &mut impl Write) -> Result<(), CliError> {
+    let stdin = io::stdin();
+    let interactive = stdin.is_terminal();
+    let mut session = Session::default();
+    let mut lines = stdin.lock().lines();
+    loop {
+        if interactive {
+            log::debug_assert_stdout_free();
+            print!("> ");
+            io::stdout().flush().ok();
+        }
+        let Some(line) = lines.next() else {
+            break;
+        };
+        let line = line.map_err(|err| CliError::Io {
+            path: PathBuf::from("<stdin>"),
+            err,
+        })?;
+        match session.execute(&line, options, out) {
+            Ok(Control::Continue) => {}
+            Ok(Control::Quit) => break,
+            Err(err) => eprintln!("error: {}", err),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Runs `lines` in a fresh session, returning what they printed
+    fn run(session: &mut Session, lines: &[&str]) -> String {
+        let mut out = vec![];
+        for line in lines {
+            session
+                .execute(line, &Options::default(), &mut out)
+                .unwrap();
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    fn usage_message(session: &mut Session, line: &str) -> String {
+        match session.execute(line, &Options::default(), &mut vec![]) {
+            Err(CliError::Usage(msg)) => msg,
+            other => panic!("expected a usage error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn expressions_follow_precedence_and_parentheses() {
+        let mut session = Session::default();
+        let printed = run(
+            &mut session,
+            &[
+                "a = [[1, 2], [3, 4]]",
+                "set b [[0, 1], [1, 0]]",
+                "print a + a * b",
+                "print (a + a) * b",
+                "print a .* b - b",
+            ],
+        );
+        assert_eq!(printed, "3 3\n7 7\n4 2\n8 6\n0 1\n2 0\n");
+        assert_eq!(run(&mut session, &["list"]), "a  2x2\nb  2x2\n");
+    }
+
+    #[test]
+    fn history_skips_blank_lines_and_comments() {
+        let mut session = Session::default();
+        let printed = run(&mut session, &["", "# a comment", "a = [[1]]", "history"]);
+        assert_eq!(printed, "   1  a = [[1]]\n   2  history\n");
+        assert_eq!(
+            session
+                .execute("quit", &Options::default(), &mut vec![])
+                .unwrap(),
+            Control::Quit
+        );
+    }
+
+    #[test]
+    fn bad_commands_are_usage_errors() {
+        let mut session = Session::default();
+        assert_eq!(
+            usage_message(&mut session, "print x"),
+            "no matrix named \"x\""
+        );
+        assert_eq!(
+            usage_message(&mut session, "frobnicate"),
+            "unknown command \"frobnicate\", try help"
+        );
+        assert_eq!(
+            usage_message(&mut session, "set 1x [[1]]"),
+            "invalid matrix name \"1x\""
+        );
+        assert_eq!(
+            usage_message(&mut session, "print [[1]"),
+            "unclosed [ in expression"
+        );
+        assert_eq!(
+            usage_message(&mut session, "print ([[1]]"),
+            "expected ) in expression"
+        );
+        assert_eq!(
+            usage_message(&mut session, "print [[1]])"),
+            "unmatched ) in expression"
+        );
+        assert_eq!(
+            usage_message(&mut session, "print [[1]] [[2]]"),
+            "expected an operator between operands"
+        );
+        assert_eq!(
+            usage_message(&mut session, "print"),
+            "expected an expression"
+        );
+        assert_eq!(
+            usage_message(&mut session, "load a"),
+            "usage: load NAME ..."
+        );
+        assert!(matches!(
+            session.execute(
+                "print [[1, 2]] * [[3, 4]]",
+                &Options::default(),
+                &mut vec![]
+            ),
+            Err(CliError::Matrix(_))
+        ));
+    }
+
+    #[test]
+    fn load_and_export_files() {
+        let dir = std::env::temp_dir().join(format!("matrixmult-repl-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("in.txt");
+        let output = dir.join("out.csv");
+        let _ = fs::remove_file(&output);
+        fs::write(&input, "1 2\n3 4\n").unwrap();
+        let mut session = Session::default();
+        let printed = run(
+            &mut session,
+            &[
+                &format!("load m {}", input.display()),
+                "p = m * m",
+                &format!("export p {}", output.display()),
+            ],
+        );
+        assert_eq!(printed, format!("wrote {} as csv\n", output.display()));
+        assert_eq!(fs::read_to_string(&output).unwrap(), "7,10\n15,22\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
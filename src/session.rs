@@ -0,0 +1,228 @@
+use crate::{matrix::Matrix, parse::FormatError};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// Version written on the first line of session files
+pub const SESSION_VERSION: u32 = 1;
+
+/// Names of the TUI's two input matrices in a session file. Its registers
+/// are stored under their letters
+pub const INPUT_NAMES: [&str; 2] = ["m0", "m1"];
+
+/// Matrices by name, as a session file stores them
+pub type NamedMatrices = BTreeMap<String, Matrix>;
+
+/// Whether `name` can name a matrix in a session file: ASCII letters,
+/// digits, `_` and `-`
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// `text` made into a valid name by replacing the characters that can't be
+/// in one with `_`
+pub fn sanitize_name(text: &str) -> String {
+    let name: String = text
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() {
+        String::from("matrix")
+    } else {
+        name
+    }
+}
+
+/// `base`, or `base-2`, `base-3`, ... if `taken` says it's already used
+pub fn unique_name(base: &str, taken: impl Fn(&str) -> bool) -> String {
+    if !taken(base) {
+        return String::from(base);
+    }
+    (2..)
+        .map(|n| format!("{}-{}", base, n))
+        .find(|name| !taken(name))
+        .unwrap()
+}
+
+/// A path in `dir` named `name.extension`, numbered like `unique_name` to
+/// not collide with an existing file
+pub fn unique_path(dir: &Path, name: &str, extension: &str) -> PathBuf {
+    let path_of = |name: &str| dir.join(format!("{}.{}", name, extension));
+    path_of(&unique_name(name, |name| path_of(name).exists()))
+}
+
+/// Formats a session file:
+///
+/// ```text
+/// matrixmult-session 1
+/// matrix m0 2 2
+/// 1 2
+/// 3 4
+/// matrix a 1 3
+/// 5 6 7
+/// ```
+///
+/// Each matrix is a `matrix <name> <rows> <cols>` line followed by its rows.
+/// Matrices without columns are written as 0x0
+pub fn format_session(matrices: &NamedMatrices) -> String {
+    let mut text = format!("matrixmult-session {}\n", SESSION_VERSION);
+    for (name, m) in matrices {
+        let cols = m.first().map_or(0, |row| row.len());
+        let rows = if cols == 0 { 0 } else { m.len() };
+        text.push_str(&format!("matrix {} {} {}\n", name, rows, cols));
+        for row in &m[..rows] {
+            let row: Vec<String> = row.iter().map(|x| x.to_string()).collect();
+            text.push_str(&row.join(" "));
+            text.push('\n');
+        }
+    }
+    text
+}
+
+/// Parses a session file written by `format_session`. Blank lines and `#`
+/// comments between matrices are skipped
+pub fn parse_session(text: &str) -> Result<NamedMatrices, FormatError> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+    match lines.next() {
+        Some((line, header)) => {
+            let version = header
+                .strip_prefix("matrixmult-session ")
+                .ok_or_else(|| FormatError::new(line, "expected a matrixmult-session header"))?;
+            if version.trim() != SESSION_VERSION.to_string() {
+                return Err(FormatError::new(
+                    line,
+                    format!("unsupported session version {}", version.trim()),
+                ));
+            }
+        }
+        None => return Err(FormatError::new(1, "expected a matrixmult-session header")),
+    }
+
+    let mut matrices = NamedMatrices::new();
+    while let Some((line, header)) = lines.next() {
+        let fields: Vec<&str> = header.split_whitespace().collect();
+        let ["matrix", name, rows, cols] = fields[..] else {
+            return Err(FormatError::new(
+                line,
+                "expected `matrix <name> <rows> <cols>`",
+            ));
+        };
+        if !is_valid_name(name) {
+            return Err(FormatError::new(line, format!("invalid name {:?}", name)));
+        }
+        if matrices.contains_key(name) {
+            return Err(FormatError::new(line, format!("duplicate name {:?}", name)));
+        }
+        let (Ok(rows), Ok(cols)) = (rows.parse::<usize>(), cols.parse::<usize>()) else {
+            return Err(FormatError::new(line, "invalid matrix size"));
+        };
+
+        let mut m = vec![];
+        for _ in 0..rows {
+            let (line, text) = lines.next().ok_or_else(|| {
+                FormatError::new(line, format!("matrix {} is missing rows", name))
+            })?;
+            let row = text
+                .split_whitespace()
+                .map(|token| token.parse::<i64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| FormatError::new(line, "expected integer elements"))?;
+            if row.len() != cols {
+                return Err(FormatError::new(
+                    line,
+                    format!("expected {} elements, found {}", cols, row.len()),
+                ));
+            }
+            m.push(row);
+        }
+        matrices.insert(String::from(name), m);
+    }
+    Ok(matrices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(text: &str) -> (usize, String) {
+        let err = parse_session(text).unwrap_err();
+        (err.line, err.message)
+    }
+
+    #[test]
+    fn sessions_round_trip() {
+        let mut matrices = NamedMatrices::new();
+        matrices.insert(String::from("m0"), vec![vec![1, 2], vec![3, 4]]);
+        matrices.insert(String::from("a"), vec![vec![i64::MIN, 0, i64::MAX]]);
+        matrices.insert(String::from("empty"), vec![]);
+        let text = format_session(&matrices);
+        assert_eq!(
+            text,
+            "matrixmult-session 1\nmatrix a 1 3\n-9223372036854775808 0 9223372036854775807\n\
+             matrix empty 0 0\nmatrix m0 2 2\n1 2\n3 4\n"
+        );
+        assert_eq!(parse_session(&text), Ok(matrices));
+        assert_eq!(
+            parse_session("# saved\nmatrixmult-session 1\n\nmatrix b 1 1\n  7\n"),
+            Ok(NamedMatrices::from([(String::from("b"), vec![vec![7]])]))
+        );
+    }
+
+    #[test]
+    fn malformed_sessions_name_the_line() {
+        let header = "expected a matrixmult-session header";
+        assert_eq!(error(""), (1, String::from(header)));
+        assert_eq!(error("matrix a 1 1\n1\n"), (1, String::from(header)));
+        assert_eq!(
+            error("matrixmult-session 2\n"),
+            (1, String::from("unsupported session version 2"))
+        );
+        let session = |body: &str| format!("matrixmult-session 1\n{}", body);
+        for (body, line, message) in [
+            ("matrix a 1\n", 2, "expected `matrix <name> <rows> <cols>`"),
+            ("matrix a.b 1 1\n1\n", 2, "invalid name \"a.b\""),
+            (
+                "matrix a 1 1\n1\nmatrix a 1 1\n2\n",
+                4,
+                "duplicate name \"a\"",
+            ),
+            ("matrix a x 1\n", 2, "invalid matrix size"),
+            ("matrix a 2 1\n1\n", 2, "matrix a is missing rows"),
+            ("matrix a 1 2\n1 x\n", 3, "expected integer elements"),
+            ("matrix a 1 2\n1 2 3\n", 3, "expected 2 elements, found 3"),
+        ] {
+            assert_eq!(
+                error(&session(body)),
+                (line, String::from(message)),
+                "{}",
+                body
+            );
+        }
+    }
+
+    #[test]
+    fn names_are_made_valid_and_unique() {
+        assert_eq!(sanitize_name("my matrix.v2"), "my_matrix_v2");
+        assert_eq!(sanitize_name(""), "matrix");
+        assert!(is_valid_name(&sanitize_name("ä b")));
+        assert!(!is_valid_name(""));
+        let taken = ["a", "a-2"];
+        assert_eq!(unique_name("a", |name| taken.contains(&name)), "a-3");
+        assert_eq!(unique_name("b", |name| taken.contains(&name)), "b");
+    }
+}
@@ -0,0 +1,88 @@
+use crate::matrix::{dims, Matrix};
+
+/// A matrix stored as its nonzero entries `(row, col, value)`, sorted in
+/// row-major order with no duplicates
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseMatrix {
+    pub rows: usize,
+    pub cols: usize,
+    entries: Vec<(usize, usize, i64)>,
+}
+
+impl SparseMatrix {
+    /// Builds a sparse matrix from entries in any order. Entries for the same
+    /// cell are summed and zeros are dropped. Panics if an entry lies outside
+    /// `rows` x `cols`
+    pub fn from_triplets(
+        rows: usize,
+        cols: usize,
+        mut triplets: Vec<(usize, usize, i64)>,
+    ) -> SparseMatrix {
+        assert!(
+            triplets.iter().all(|&(i, j, _)| i < rows && j < cols),
+            "entry outside a {}x{} matrix",
+            rows,
+            cols
+        );
+        triplets.sort_by_key(|&(i, j, _)| (i, j));
+        let mut entries: Vec<(usize, usize, i64)> = Vec::with_capacity(triplets.len());
+        for (i, j, x) in triplets {
+            match entries.last_mut() {
+                Some(last) if (last.0, last.1) == (i, j) => last.2 += x,
+                _ => entries.push((i, j, x)),
+            }
+        }
+        entries.retain(|&(_, _, x)| x != 0);
+        SparseMatrix {
+            rows,
+            cols,
+            entries,
+        }
+    }
+
+    pub fn from_dense(m: &Matrix) -> SparseMatrix {
+        let (rows, cols) = dims(m);
+        let entries = m
+            .iter()
+            .enumerate()
+            .flat_map(|(i, row)| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, &x)| x != 0)
+                    .map(move |(j, &x)| (i, j, x))
+            })
+            .collect();
+        SparseMatrix {
+            rows,
+            cols,
+            entries,
+        }
+    }
+
+    pub fn to_dense(&self) -> Matrix {
+        let mut m = vec![vec![0; self.cols]; self.rows];
+        for &(i, j, x) in &self.entries {
+            m[i][j] = x;
+        }
+        m
+    }
+
+    /// The nonzero entries in row-major order
+    pub fn entries(&self) -> &[(usize, usize, i64)] {
+        &self.entries
+    }
+
+    /// Number of nonzero entries
+    pub fn nnz(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Fraction of the elements that are nonzero
+    pub fn density(&self) -> f64 {
+        let size = self.rows * self.cols;
+        if size == 0 {
+            return 0.0;
+        }
+        self.nnz() as f64 / size as f64
+    }
+}
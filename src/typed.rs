@@ -0,0 +1,96 @@
+use crate::matrix::{transpose, Matrix, MatrixError};
+
+pub type MatrixI32 = Vec<Vec<i32>>;
+
+/// `m` with i32 elements, or `None` if any element doesn't fit
+pub fn narrow(m: &Matrix) -> Option<MatrixI32> {
+    m.iter()
+        .map(|row| row.iter().map(|&x| i32::try_from(x).ok()).collect())
+        .collect()
+}
+
+/// Multiplies two i32 matrices, accumulating each dot product in i64. The
+/// second operand is transposed first so both inner loops walk contiguous
+/// rows, which lets the compiler vectorize the multiply-add. A product of
+/// two i32s always fits in i64, but their sum may not, which fails with
+/// `MatrixError::Overflow`
+pub fn multiply_i32(m1: &MatrixI32, m2: &MatrixI32) -> Result<Matrix, MatrixError> {
+    let m2t = transpose(m2);
+
+    m1.iter()
+        .map(|row| {
+            m2t.iter()
+                .map(|col| {
+                    row.iter()
+                        .zip(col)
+                        .try_fold(0i64, |sum, (&a, &b)| sum.checked_add(a as i64 * b as i64))
+                        .ok_or(MatrixError::Overflow { bits: 64 })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::multiply_matrices;
+    use crate::random::{random_int_matrix, MatrixKind};
+
+    #[test]
+    fn narrow_rejects_elements_outside_i32() {
+        assert_eq!(narrow(&vec![vec![1, -2]]), Some(vec![vec![1, -2]]));
+        assert_eq!(narrow(&vec![vec![1, i32::MAX as i64 + 1]]), None);
+    }
+
+    #[test]
+    fn multiply_i32_matches_the_naive_product() {
+        let a = vec![vec![1, 2], vec![3, 4]];
+        let b = vec![vec![5, 6], vec![7, 8]];
+        assert_eq!(multiply_i32(&a, &b), Ok(vec![vec![19, 22], vec![43, 50]]));
+    }
+
+    #[test]
+    fn multiply_i32_reports_sums_overflowing_i64() {
+        let a = vec![vec![i32::MAX; 3]];
+        let b = vec![vec![i32::MAX; 2]; 3];
+        assert_eq!(
+            multiply_i32(&a, &b),
+            Err(MatrixError::Overflow { bits: 64 })
+        );
+    }
+
+    #[test]
+    fn multiply_i32_matches_the_i64_kernel_across_the_i32_range() {
+        let range = (i32::MIN as i64, i32::MAX as i64);
+        // two products of i32s always fit in i64, while thirteen overflow it
+        // for some seeds, which both kernels report
+        let mut overflows = 0;
+        for inner in [2, 13] {
+            for seed in 0..8 {
+                let a = random_int_matrix(9, inner, range, MatrixKind::Dense, seed).unwrap();
+                let b = random_int_matrix(inner, 6, range, MatrixKind::Dense, seed + 100).unwrap();
+                let expected = multiply_matrices(&a, &b);
+                assert!(expected.is_ok() || inner == 13);
+                overflows += expected.is_err() as usize;
+                assert_eq!(
+                    multiply_i32(&narrow(&a).unwrap(), &narrow(&b).unwrap()),
+                    expected,
+                    "{} inner, seed {}",
+                    inner,
+                    seed
+                );
+            }
+        }
+        assert!(overflows > 0);
+
+        // the extremes, whose products only fit once widened:
+        // MIN·MIN + MAX·MIN = 2^62 - (2^62 - 2^31)
+        let a = vec![vec![i32::MIN as i64, i32::MAX as i64]];
+        let b = vec![vec![i32::MIN as i64], vec![i32::MIN as i64]];
+        assert_eq!(
+            multiply_i32(&narrow(&a).unwrap(), &narrow(&b).unwrap()),
+            Ok(vec![vec![1 << 31]])
+        );
+    }
+}
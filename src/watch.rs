@@ -0,0 +1,135 @@
+use std::{
+    fs,
+    path::Path,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// How often `watch_paths` looks at the files unless `--interval` is given
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(250);
+/// How long the files must stay unchanged before a change counts, unless
+/// `--debounce` is given
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Modification time and size of a file, `None` while it's missing
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// The current UTC time of day as `HH:MM:SS`
+pub fn time_of_day() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    format!(
+        "{:02}:{:02}:{:02}",
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Polls `paths` every `interval`, calling `changed` once at the start and
+/// again whenever any of them changes, until it returns false. A change only
+/// counts once the files have stayed the same for `debounce`, so a burst of
+/// writes, as editors do when saving, causes one call
+pub fn watch_paths(
+    paths: &[&Path],
+    interval: Duration,
+    debounce: Duration,
+    mut changed: impl FnMut() -> bool,
+) {
+    let stamps = || paths.iter().map(|path| stamp(path)).collect::<Vec<_>>();
+    let mut seen = stamps();
+    if !changed() {
+        return;
+    }
+    loop {
+        thread::sleep(interval);
+        let mut current = stamps();
+        if current == seen {
+            continue;
+        }
+        loop {
+            thread::sleep(debounce);
+            let settled = stamps();
+            if settled == current {
+                break;
+            }
+            current = settled;
+        }
+        seen = current;
+        if !changed() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("matrixmult-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn a_burst_of_writes_is_one_change() {
+        let path = temp_path("watch.txt");
+        fs::write(&path, "1\n").unwrap();
+        let mut writer = None;
+        let mut calls = 0;
+        watch_paths(
+            &[&path],
+            Duration::from_millis(5),
+            Duration::from_millis(100),
+            || {
+                calls += 1;
+                if calls == 1 {
+                    let path = path.clone();
+                    // growing the file changes its stamp even when the
+                    // clock is too coarse to
+                    writer = Some(thread::spawn(move || {
+                        for contents in ["1 2\n", "1 2 3\n", "1 2 3 4\n"] {
+                            thread::sleep(Duration::from_millis(20));
+                            fs::write(&path, contents).unwrap();
+                        }
+                    }));
+                    return true;
+                }
+                assert_eq!(fs::read_to_string(&path).unwrap(), "1 2 3 4\n");
+                false
+            },
+        );
+        writer.unwrap().join().unwrap();
+        assert_eq!(calls, 2);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn stopping_at_the_first_call_never_polls() {
+        let mut calls = 0;
+        watch_paths(
+            &[Path::new("missing")],
+            Duration::from_secs(60),
+            Duration::ZERO,
+            || {
+                calls += 1;
+                false
+            },
+        );
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn time_of_day_is_zero_padded() {
+        let time = time_of_day();
+        let parts: Vec<&str> = time.split(':').collect();
+        assert_eq!(parts.len(), 3);
+        assert!(parts.iter().all(|part| part.len() == 2));
+        assert!(parts[0].parse::<u32>().unwrap() < 24);
+    }
+}
@@ -0,0 +1,91 @@
+// Multiplies two sizable operand files through the binary, read on two
+// threads and on one, against a product worked out in closed form
+
+use std::{
+    fs,
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+const ROWS: usize = 600;
+const INNER: usize = 400;
+const COLS: usize = 100;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("matrixmult-{}-{}", std::process::id(), name))
+}
+
+/// `rows`×`cols` with element `(i, j)` equal to `f(i, j)`, one row per line
+/// with elements separated by `sep`
+fn write_matrix(
+    name: &str,
+    (rows, cols): (usize, usize),
+    sep: &str,
+    f: fn(i64, i64) -> i64,
+) -> PathBuf {
+    let text: String = (0..rows as i64)
+        .map(|i| {
+            let row: Vec<String> = (0..cols as i64).map(|j| f(i, j).to_string()).collect();
+            row.join(sep) + "\n"
+        })
+        .collect();
+    let path = temp_path(name);
+    fs::write(&path, text).unwrap();
+    path
+}
+
+fn multiply(threads: &str, a: &PathBuf, b: &PathBuf) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_matrixmult"))
+        .args(["--engine", "threaded", "--threads", threads])
+        .arg(a)
+        .arg(b)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn large_operands_read_in_parallel_multiply_correctly() {
+    // a = i + k and b = k - j, so the product is
+    // sum_k (i + k)(k - j) = S2 + (i - j)·S1 - i·j·n
+    let a = write_matrix("parallel-a.txt", (ROWS, INNER), " ", |i, k| i + k);
+    let b = write_matrix("parallel-b.csv", (INNER, COLS), ",", |k, j| k - j);
+    assert!(fs::metadata(&a).unwrap().len() > 500_000);
+
+    let n = INNER as i64;
+    let (s1, s2) = (n * (n - 1) / 2, (n - 1) * n * (2 * n - 1) / 6);
+    let expected: String = (0..ROWS as i64)
+        .map(|i| {
+            let row: Vec<String> = (0..COLS as i64)
+                .map(|j| (s2 + (i - j) * s1 - i * j * n).to_string())
+                .collect();
+            row.join(" ") + "\n"
+        })
+        .collect();
+
+    for threads in ["2", "1"] {
+        let output = multiply(threads, &a, &b);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert!(
+            String::from_utf8(output.stdout).unwrap() == expected,
+            "wrong product reading on {} threads",
+            threads
+        );
+    }
+
+    // an error in the second file is still put down to it
+    let mut bad = fs::read_to_string(&b).unwrap();
+    bad.push_str("1,x\n");
+    fs::write(&b, bad).unwrap();
+    let output = multiply("2", &a, &b);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("parallel-b.csv"), "{}", stderr);
+
+    for path in [a, b] {
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,26 @@
+// Whether the TUI owns the terminal is global to the process, so it's set
+// here, in a test binary of its own, rather than next to the unit tests that
+// write to stdout while it's clear
+
+use matrixmult::{log, matrix::multiply_matrices_threaded};
+use std::panic;
+
+#[test]
+fn stdout_is_only_free_while_the_tui_doesnt_own_the_terminal() {
+    log::debug_assert_stdout_free();
+
+    log::set_terminal_owned(true);
+    // workers only log, so computing while the TUI is up is fine
+    let m = vec![vec![1, 2], vec![3, 4]];
+    assert_eq!(
+        multiply_matrices_threaded(&m, &m, 4),
+        Ok(vec![vec![7, 10], vec![15, 22]])
+    );
+    let write = panic::catch_unwind(log::debug_assert_stdout_free);
+    assert_eq!(write.is_err(), cfg!(debug_assertions));
+
+    // only the first caller gets to restore the terminal
+    assert!(log::release_terminal());
+    assert!(!log::release_terminal());
+    log::debug_assert_stdout_free();
+}